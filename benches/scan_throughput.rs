@@ -0,0 +1,49 @@
+// the scans of all the pages by 1, 2, 4, 8 threads, with one latch and with 8 instances of the buffer pool.
+// the pool holds the whole table in the first round, a quarter of it in the second one.
+// run with `cargo bench --bench scan_throughput`
+use std::{fs, sync::Arc, thread, time::Instant};
+
+use rustdb::{buffer::buffer_pool_manager::BufferPoolManager, common::config::page_id_t, storage::page_based::{disk::{disk_manager::DiskManager, log_manager::LogManager}, page::page::COMMON_PAGE_HEADER_SIZE}};
+
+fn main() {
+    let num_pages = 1024;
+    let num_scans = 16;
+    for pool_size in [num_pages, num_pages / 4] {
+        for num_instances in [1, 8] {
+            let filename = format!("bench_scan_{}_{}.db", pool_size, num_instances);
+            let _ = fs::remove_file(&filename);
+            let disk_mgr = Arc::new(DiskManager::new(&filename).unwrap());
+            let bpm = Arc::new(BufferPoolManager::new_sharded(num_instances, pool_size, disk_mgr, 2, Arc::new(LogManager::new())));
+            for _ in 0..num_pages {
+                let mut guard = bpm.new_page_guarded().unwrap();
+                let pid = guard.get_pid().unwrap();
+                // the first bytes are the header, the pageLSN is there
+                guard.get_mut_as::<[u8; COMMON_PAGE_HEADER_SIZE + 1]>()[COMMON_PAGE_HEADER_SIZE] = pid as u8;
+            }
+            bpm.flush_all_pages();
+
+            for num_threads in [1, 2, 4, 8] {
+                let start = Instant::now();
+                let handlers: Vec<_> = (0..num_threads).map(|_| {
+                    let bpm = bpm.clone();
+                    thread::spawn(move || {
+                        for _ in 0..num_scans {
+                            for pid in 0..num_pages as page_id_t {
+                                let guard = bpm.fetch_page_read(pid).unwrap();
+                                assert_eq!(pid as u8, guard.get_as::<[u8; COMMON_PAGE_HEADER_SIZE + 1]>()[COMMON_PAGE_HEADER_SIZE]);
+                            }
+                        }
+                    })
+                }).collect();
+                for handler in handlers {
+                    handler.join().unwrap();
+                }
+                let elapsed = start.elapsed();
+                let pages = (num_threads * num_scans * num_pages) as f64;
+                println!("pool {:>4}, {} instance(s), {} thread(s): {:>10.0} pages/s",
+                    pool_size, num_instances, num_threads, pages / elapsed.as_secs_f64());
+            }
+            let _ = fs::remove_file(&filename);
+        }
+    }
+}
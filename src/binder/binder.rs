@@ -1,10 +1,10 @@
 #![allow(warnings)]
 
-use sqlparser::{ast::{self, CharacterLength, ColumnDef, DataType, Expr, GroupByExpr, Select, SelectItem, SetExpr, Statement, TableWithJoins, TableFactor, Insert, Query, Values, CreateTable}, dialect::Dialect, parser::{Parser, ParserError}};
+use sqlparser::{ast::{self, AssignmentTarget, FunctionArg, FunctionArgExpr, FunctionArguments, SequenceOptions, UnaryOperator, CharacterLength, ColumnDef, ColumnOption, DataType, Delete, Expr, FromTable, GroupByExpr, Insert, ObjectName, Query, ReferentialAction, Select, SelectItem, SetExpr, Statement, TableConstraint, TableFactor, TableWithJoins, Values, Value, CreateTable, SqlOption}, dialect::Dialect, parser::{Parser, ParserError}};
 
-use crate::{binder::{bound_table_ref::BoundTable, expression::{bound_alias::BoundAlias, bound_column_ref::BoundColumn, bound_constant::BoundConstant, bound_star::BoundStar}, table_ref::bound_base_table::BoundBaseTableRef}, catalog::{catalog::{CataLog, CataLogRef}, column::Column, schema::Schema}, typedef::{type_id::TypeId, value_factory::ValueFactory}};
+use crate::{binder::{bound_table_ref::BoundTable, expression::{bound_alias::BoundAlias, bound_column_ref::BoundColumn, bound_constant::BoundConstant, bound_func_call::BoundFuncCall, bound_star::BoundStar}, table_ref::bound_base_table::BoundBaseTableRef}, catalog::{catalog::{CataLog, CataLogRef}, column::Column, constraint::ForeignKeyAction, schema::Schema}, typedef::{type_id::TypeId, value_factory::ValueFactory}};
 
-use super::{bound_expression::BoundExpression, bound_statement::BoundStatement, bound_table_ref::BoundTableRef, expression::bound_binary_op::{BinaryOpType, BoundBinaryOp}, statement::{create_stmt::CreateStmt, insert_stmt::InsertStmt, select_stmt::SelectStmt}, table_ref::{bound_empty_table::BoundEmptyTable, bound_values_list_table::BoundValuesList}};
+use super::{bound_expression::BoundExpression, bound_statement::BoundStatement, bound_table_ref::BoundTableRef, expression::bound_binary_op::{BinaryOpType, BoundBinaryOp}, statement::{create_sequence_stmt::CreateSequenceStmt, create_stmt::{CheckDef, CreateStmt, ForeignKeyDef, UniqueDef}, delete_stmt::DeleteStmt, insert_stmt::InsertStmt, select_stmt::SelectStmt, update_stmt::UpdateStmt}, table_ref::{bound_empty_table::BoundEmptyTable, bound_values_list_table::BoundValuesList}};
 
 
 pub struct Binder {
@@ -40,12 +40,21 @@ impl Binder {
             Statement::CreateTable(_) => {
                 return Ok(BoundStatement::Create(*self.bind_create(stmt)?));
             },
+            Statement::CreateSequence { .. } => {
+                return Ok(BoundStatement::CreateSequence(*self.bind_create_sequence(stmt)?));
+            },
             Statement::Insert(_) => {
                 return Ok(BoundStatement::Insert(*self.bind_insert(stmt)?));
             },
             Statement::Query(_) => {
                 return Ok(BoundStatement::Select(*self.bind_select(stmt)?));
             },
+            Statement::Delete(_) => {
+                return Ok(BoundStatement::Delete(*self.bind_delete(stmt)?));
+            },
+            Statement::Update { .. } => {
+                return Ok(BoundStatement::Update(*self.bind_update(stmt)?));
+            },
             _ => {
                 panic!("Not supprot yet.");
             }
@@ -54,7 +63,7 @@ impl Binder {
 
 
     // parse a create statements
-    pub fn bind_create(&self, stmt: &Statement) -> Result<Box<CreateStmt>, String> {
+    pub fn bind_create(&mut self, stmt: &Statement) -> Result<Box<CreateStmt>, String> {
         assert!(matches!(stmt, Statement::CreateTable(_)));
         let Statement::CreateTable(create_stmt) = stmt else {
             panic!("Impossible Branch");
@@ -67,15 +76,389 @@ impl Binder {
         
         let mut cols: Vec<Column> = Vec::new();
         for col in &create_stmt.columns {
-            // check other things
-            // col.options.
             cols.push(Binder::get_column(&col)?);
         }
 
-        let stmt = CreateStmt::new(create_stmt.name.0[0].value.clone(), cols);
+        let table_name = create_stmt.name.0[0].value.clone();
+        let mut stmt = CreateStmt::new(table_name.clone(), cols);
+
+        // the check expressions refer to the columns of the created table
+        let schema = Schema::new(&stmt.columns);
+        self.scope = Some(BoundTableRef::new(BoundTable::BaseTable(BoundBaseTableRef::new(table_name, 0, None, schema))));
+
+        // collect the constraints declared inline with a column
+        for (col_idx, col) in create_stmt.columns.iter().enumerate() {
+            if Self::is_serial_type(&col.data_type) {
+                let seq = CreateSequenceStmt::new(format!("{}_{}_seq", stmt.table_name, col.name.value));
+                stmt.defaults[col_idx] = Some(Self::make_nextval(&seq.name));
+                stmt.sequences.push(seq);
+            }
+
+            for opt in &col.options {
+                let constraint_name = opt.name.as_ref().map(|name| name.value.clone());
+                match &opt.option {
+                    ColumnOption::Unique { is_primary, .. } => {
+                        stmt.unique_keys.push(UniqueDef { name: constraint_name, columns: vec![col_idx], is_primary: *is_primary });
+                    },
+                    ColumnOption::ForeignKey { foreign_table, referred_columns, on_delete, on_update, .. } => {
+                        let fk = self.bind_foreign_key(&stmt, constraint_name, vec![col_idx], 
+                            foreign_table, referred_columns, on_delete, on_update)?;
+                        stmt.foreign_keys.push(fk);
+                    },
+                    ColumnOption::Check(expr) => {
+                        let expr = self.bind_expr(expr)?;
+                        stmt.checks.push(CheckDef { name: constraint_name, column: Some(col_idx), expr });
+                    },
+                    ColumnOption::Default(expr) => {
+                        stmt.defaults[col_idx] = Some(self.bind_default(&stmt.columns[col_idx], expr)?);
+                    },
+                    // GENERATED ALWAYS is taken as BY DEFAULT, an explicit value is accepted
+                    ColumnOption::Generated { sequence_options, generation_expr: None, .. } => {
+                        if stmt.columns[col_idx].get_type() != TypeId::INTEGER {
+                            return Err(format!("identity column type must be integer"));
+                        }
+                        let mut seq = CreateSequenceStmt::new(format!("{}_{}_seq", stmt.table_name, col.name.value));
+                        if let Some(options) = sequence_options {
+                            Self::bind_sequence_options(&mut seq, options)?;
+                        }
+                        stmt.defaults[col_idx] = Some(Self::make_nextval(&seq.name));
+                        stmt.sequences.push(seq);
+                    },
+                    ColumnOption::Generated { .. } => {
+                        return Err(format!("Not support generated column"));
+                    },
+                    _ => {}
+                }
+            }
+        }
+
+        // then the table constraints
+        for constraint in &create_stmt.constraints {
+            match constraint {
+                TableConstraint::PrimaryKey { name, columns, .. } => {
+                    let columns = Self::bind_column_indices(&stmt.columns, columns)?;
+                    stmt.unique_keys.push(UniqueDef { name: name.as_ref().map(|n| n.value.clone()), columns, is_primary: true });
+                },
+                TableConstraint::Unique { name, columns, .. } => {
+                    let columns = Self::bind_column_indices(&stmt.columns, columns)?;
+                    stmt.unique_keys.push(UniqueDef { name: name.as_ref().map(|n| n.value.clone()), columns, is_primary: false });
+                },
+                TableConstraint::ForeignKey { name, columns, foreign_table, referred_columns, on_delete, on_update, .. } => {
+                    let columns = Self::bind_column_indices(&stmt.columns, columns)?;
+                    let fk = self.bind_foreign_key(&stmt, name.as_ref().map(|n| n.value.clone()), columns, 
+                        foreign_table, referred_columns, on_delete, on_update)?;
+                    stmt.foreign_keys.push(fk);
+                },
+                TableConstraint::Check { name, expr } => {
+                    let expr = self.bind_expr(expr)?;
+                    stmt.checks.push(CheckDef { name: name.as_ref().map(|n| n.value.clone()), column: None, expr });
+                },
+                _ => {
+                    return Err(format!("Not support table constraint {}", constraint));
+                }
+            }
+        }
+
+        if stmt.unique_keys.iter().filter(|key| key.is_primary).count() > 1 {
+            return Err(format!("multiple primary keys for table {} are not allowed", stmt.table_name));
+        }
+
+        // WITH (tablespace = 'name') places the table in the file of the tablespace,
+        // WITH (compression = off) keeps the large values of it as they are in the overflow pages
+        for option in &create_stmt.with_options {
+            match option {
+                SqlOption::KeyValue { key, value } if key.value.eq_ignore_ascii_case("tablespace") => {
+                    stmt.tablespace = Some(match value {
+                        Expr::Value(Value::SingleQuotedString(name)) => name.clone(),
+                        Expr::Identifier(ident) => ident.value.clone(),
+                        _ => return Err(format!("invalid tablespace name {}", value)),
+                    });
+                },
+                SqlOption::KeyValue { key, value } if key.value.eq_ignore_ascii_case("compression") => {
+                    let setting = match value {
+                        Expr::Value(Value::SingleQuotedString(setting)) => setting.to_lowercase(),
+                        Expr::Value(Value::Boolean(on)) => on.to_string(),
+                        Expr::Identifier(ident) => ident.value.to_lowercase(),
+                        _ => return Err(format!("invalid compression setting {}", value)),
+                    };
+                    stmt.compression = Some(match setting.as_str() {
+                        "on" | "true" | "lz" => true,
+                        "off" | "false" | "none" => false,
+                        _ => return Err(format!("invalid compression setting {}", value)),
+                    });
+                },
+                _ => return Err(format!("Not support table option {}", option)),
+            }
+        }
+
         Ok(Box::new(stmt))
     }
 
+    // resolve the referenced table and columns of a foreign key,
+    // the referenced columns must be covered by a primary key or unique constraint
+    fn bind_foreign_key(&self, stmt: &CreateStmt, name: Option<String>, columns: Vec<usize>, foreign_table: &ObjectName, 
+        referred_columns: &Vec<ast::Ident>, on_delete: &Option<ReferentialAction>, on_update: &Option<ReferentialAction>) -> Result<ForeignKeyDef, String> {
+        let ref_table = foreign_table.0[0].value.clone();
+
+        // a table may refer to itself
+        let catalog = self.catalog.borrow();
+        let (ref_cols, ref_unique_keys) = if ref_table == stmt.table_name {
+            (stmt.columns.clone(), stmt.unique_keys.iter().map(|key| (key.columns.clone(), key.is_primary)).collect::<Vec<(Vec<usize>, bool)>>())
+        } else {
+            let Some(table_info) = catalog.get_table(&ref_table) else {
+                return Err(format!("referenced table {} not found", ref_table));
+            };
+            (table_info.schema.get_columns().clone(), 
+                catalog.get_table_indexes(&ref_table).iter()
+                    .filter(|index| index.is_unique)
+                    .map(|index| (index.key_attrs.clone(), index.is_primary_key)).collect())
+        };
+
+        let ref_columns = if referred_columns.is_empty() {
+            // refer to the primary key by default
+            match ref_unique_keys.iter().find(|(_, is_primary)| *is_primary) {
+                None => {
+                    return Err(format!("there is no primary key for referenced table {}", ref_table));
+                },
+                Some((key, _)) => key.clone(),
+            }
+        } else {
+            Self::bind_column_indices(&ref_cols, referred_columns)?
+        };
+
+        if ref_columns.len() != columns.len() {
+            return Err(format!("number of referencing and referenced columns for foreign key disagree"));
+        }
+        for (col, ref_col) in columns.iter().zip(ref_columns.iter()) {
+            if stmt.columns[*col].get_type() != ref_cols[*ref_col].get_type() {
+                return Err(format!("foreign key column {} can not be implemented, incompatible types", stmt.columns[*col].get_name()));
+            }
+        }
+
+        let mut sorted_ref_columns = ref_columns.clone();
+        sorted_ref_columns.sort();
+        let has_unique = ref_unique_keys.iter().any(|(key, _)| {
+            let mut key = key.clone();
+            key.sort();
+            key == sorted_ref_columns
+        });
+        if !has_unique {
+            return Err(format!("there is no unique constraint matching given keys for referenced table {}", ref_table));
+        }
+
+        Ok(ForeignKeyDef {
+            name,
+            columns,
+            ref_table,
+            ref_columns,
+            on_delete: Self::bind_referential_action(on_delete)?,
+            on_update: Self::bind_referential_action(on_update)?,
+        })
+    }
+
+    pub fn bind_create_sequence(&self, stmt: &Statement) -> Result<Box<CreateSequenceStmt>, String> {
+        let Statement::CreateSequence { name, if_not_exists, data_type, sequence_options, .. } = stmt else {
+            panic!("Impossible Branch");
+        };
+        if let Some(data_type) = data_type {
+            if !matches!(data_type, DataType::Int(_) | DataType::Integer(_)) {
+                return Err(format!("sequence type must be integer"));
+            }
+        }
+
+        let mut seq = CreateSequenceStmt::new(name.0[0].value.clone());
+        seq.if_not_exists = *if_not_exists;
+        Self::bind_sequence_options(&mut seq, sequence_options)?;
+        Ok(Box::new(seq))
+    }
+
+    fn bind_sequence_options(seq: &mut CreateSequenceStmt, options: &Vec<SequenceOptions>) -> Result<(), String> {
+        let (mut start_value, mut min_value, mut max_value) = (None, None, None);
+        for option in options {
+            match option {
+                SequenceOptions::IncrementBy(expr, _) => {
+                    seq.increment = Self::bind_integer_literal(expr)?;
+                },
+                SequenceOptions::StartWith(expr, _) => {
+                    start_value = Some(Self::bind_integer_literal(expr)?);
+                },
+                SequenceOptions::MinValue(Some(expr)) => {
+                    min_value = Some(Self::bind_integer_literal(expr)?);
+                },
+                SequenceOptions::MaxValue(Some(expr)) => {
+                    max_value = Some(Self::bind_integer_literal(expr)?);
+                },
+                SequenceOptions::MinValue(None) | SequenceOptions::MaxValue(None) | SequenceOptions::Cache(_) => {},
+                SequenceOptions::Cycle(true) => {
+                    return Err(format!("Not support CYCLE sequence"));
+                },
+                SequenceOptions::Cycle(false) => {},
+            }
+        }
+
+        // a descending sequence counts down from -1
+        if seq.increment < 0 {
+            seq.min_value = min_value.unwrap_or(i32::MIN);
+            seq.max_value = max_value.unwrap_or(-1);
+            seq.start_value = start_value.unwrap_or(seq.max_value);
+        } else {
+            seq.min_value = min_value.unwrap_or(1);
+            seq.max_value = max_value.unwrap_or(i32::MAX);
+            seq.start_value = start_value.unwrap_or(seq.min_value);
+        }
+        Ok(())
+    }
+
+    fn bind_integer_literal(expr: &Expr) -> Result<i32, String> {
+        match expr {
+            Expr::Value(ast::Value::Number(literal, _)) => {
+                literal.parse().map_err(|err| format!("{}", err))
+            },
+            Expr::UnaryOp { op: UnaryOperator::Minus, expr } => {
+                Self::bind_integer_literal(expr).map(|val| -val)
+            },
+            _ => Err(format!("an integer literal is expected, found {}", expr)),
+        }
+    }
+
+    fn is_serial_type(data_type: &DataType) -> bool {
+        let DataType::Custom(name, _) = data_type else { return false; };
+        name.0.len() == 1 && name.0[0].value.eq_ignore_ascii_case("serial")
+    }
+
+    fn make_nextval(seq_name: &str) -> Box<BoundExpression> {
+        let arg = BoundExpression::Constant(Box::new(BoundConstant::new(ValueFactory::get_varchar_value(seq_name))));
+        Box::new(BoundExpression::FuncCall(Box::new(BoundFuncCall::new("nextval".to_owned(), vec![arg]))))
+    }
+
+    // a default can not refer to any column, and a constant must fit the column type
+    fn bind_default(&self, col: &Column, expr: &Expr) -> Result<Box<BoundExpression>, String> {
+        let bound_expr = self.bind_expr(expr)?;
+        if Self::has_column_ref(&bound_expr) {
+            return Err(format!("cannot use column reference in DEFAULT expression"));
+        }
+        if let BoundExpression::Constant(constant) = bound_expr.as_ref() {
+            if !constant.val.is_null() && !constant.val.get_type().is_coercible_to(col.get_type()) {
+                return Err(format!("column {} is of type {} but default expression is of type {}", 
+                    col.get_name(), col.get_type().to_string(), constant.val.get_type().to_string()));
+            }
+        }
+        Ok(bound_expr)
+    }
+
+    fn has_column_ref(expr: &BoundExpression) -> bool {
+        match expr {
+            BoundExpression::ColumnRef(_) | BoundExpression::Star(_) => true,
+            BoundExpression::BinaryOp(binary_op) => {
+                Self::has_column_ref(&binary_op.left_arg) || Self::has_column_ref(&binary_op.right_arg)
+            },
+            BoundExpression::UnaryOp(unary_op) => Self::has_column_ref(&unary_op.arg),
+            BoundExpression::Alias(alias) => Self::has_column_ref(&alias.expr),
+            BoundExpression::FuncCall(func_call) => func_call.args.iter().any(Self::has_column_ref),
+            BoundExpression::AggCall(agg_call) => agg_call.arg.iter().any(Self::has_column_ref),
+            BoundExpression::Invalid | BoundExpression::Constant(_) | BoundExpression::TypeCast | BoundExpression::Function => false,
+        }
+    }
+
+    fn bind_referential_action(action: &Option<ReferentialAction>) -> Result<ForeignKeyAction, String> {
+        match action {
+            None | Some(ReferentialAction::NoAction) | Some(ReferentialAction::Restrict) => Ok(ForeignKeyAction::Restrict),
+            Some(ReferentialAction::Cascade) => Ok(ForeignKeyAction::Cascade),
+            Some(ReferentialAction::SetNull) => Ok(ForeignKeyAction::SetNull),
+            Some(action) => Err(format!("Not support referential action {}", action)),
+        }
+    }
+
+    fn bind_column_indices(columns: &Vec<Column>, names: &Vec<ast::Ident>) -> Result<Vec<usize>, String> {
+        let mut indices = Vec::new();
+        for name in names {
+            match columns.iter().position(|col| col.get_name() == name.value) {
+                None => {
+                    return Err(format!("column {} named in key does not exist", name.value));
+                },
+                Some(idx) => {
+                    if indices.contains(&idx) {
+                        return Err(format!("column {} appears twice in key", name.value));
+                    }
+                    indices.push(idx);
+                }
+            }
+        }
+        Ok(indices)
+    }
+
+    // bind the target table of delete/update, and set it as the scope of the where clause
+    fn bind_modify_table(&mut self, table_name: &ObjectName) -> Result<Box<BoundBaseTableRef>, String> {
+        let table_name = table_name.0[0].value.clone();
+        let bound_base_table;
+        {
+            let catalog = self.catalog.borrow();
+            let Some(table_info) = catalog.get_table(&table_name) else {
+                return Err(format!("Non exist table"));
+            };
+            bound_base_table = BoundBaseTableRef::new(table_name, table_info.table_oid, None, table_info.schema.clone());
+        }
+
+        self.scope = Some(BoundTableRef::new(BoundTable::BaseTable(bound_base_table.clone())));
+        Ok(Box::new(bound_base_table))
+    }
+
+    // parse a delete statement
+    pub fn bind_delete(&mut self, stmt: &Statement) -> Result<Box<DeleteStmt>, String> {
+        let Statement::Delete(Delete { from, selection, .. }) = stmt else { panic!("Impossible Branch"); };
+
+        let (FromTable::WithFromKeyword(tables) | FromTable::WithoutKeyword(tables)) = from;
+        if tables.len() != 1 || !tables[0].joins.is_empty() {
+            return Err(format!("delete only supports a single table"));
+        }
+        let TableFactor::Table { name, .. } = &tables[0].relation else {
+            return Err(format!("delete only supports a base table"));
+        };
+
+        let table_ref = self.bind_modify_table(name)?;
+        let mut where_cond = None;
+        if let Some(selection) = selection {
+            where_cond = Some(self.bind_where(selection)?);
+        }
+
+        Ok(Box::new(DeleteStmt::new(table_ref, where_cond)))
+    }
+
+    // parse an update statement
+    pub fn bind_update(&mut self, stmt: &Statement) -> Result<Box<UpdateStmt>, String> {
+        let Statement::Update { table, assignments, selection, .. } = stmt else { panic!("Impossible Branch"); };
+
+        if !table.joins.is_empty() {
+            return Err(format!("update only supports a single table"));
+        }
+        let TableFactor::Table { name, .. } = &table.relation else {
+            return Err(format!("update only supports a base table"));
+        };
+
+        let table_ref = self.bind_modify_table(name)?;
+        let mut target_exprs = Vec::new();
+        for assignment in assignments {
+            let AssignmentTarget::ColumnName(col_name) = &assignment.target else {
+                return Err(format!("Not support tuple assignment"));
+            };
+            let col_name = &col_name.0[col_name.0.len() - 1].value;
+            let col_idx = table_ref.schema.get_column_idx(col_name)
+                .map_err(|_| format!("column {} of table {} not found", col_name, table_ref.table_name))?;
+            if target_exprs.iter().any(|(idx, _)| *idx == col_idx) {
+                return Err(format!("multiple assignments to same column {}", col_name));
+            }
+
+            target_exprs.push((col_idx, self.bind_expr(&assignment.value)?));
+        }
+
+        let mut where_cond = None;
+        if let Some(selection) = selection {
+            where_cond = Some(self.bind_where(selection)?);
+        }
+
+        Ok(Box::new(UpdateStmt::new(table_ref, target_exprs, where_cond)))
+    }
+
     // parse a insert statement
     pub fn bind_insert(&mut self, stmt: &Statement) -> Result<Box<InsertStmt>, String> {
         assert!(matches!(stmt, Statement::Insert(_)));
@@ -96,11 +479,27 @@ impl Binder {
             table_oid = table_info.table_oid;
             table_schema = table_info.schema.clone();
         }
+
+        // the target column of each value, all columns in order if no column list is given
+        let mut columns = Vec::new();
+        if insert_stmt.columns.is_empty() {
+            columns.extend(0..table_schema.get_column_count());
+        } else {
+            for ident in &insert_stmt.columns {
+                let Ok(col_idx) = table_schema.get_column_idx(&ident.value) else {
+                    return Err(format!("column {} of relation {} does not exist", ident.value, table_name));
+                };
+                if columns.contains(&col_idx) {
+                    return Err(format!("column {} specified more than once", ident.value));
+                }
+                columns.push(col_idx);
+            }
+        }
         let bound_base_table = BoundBaseTableRef::new(table_name, table_oid, None, table_schema);
         
         Ok(
             Box::new(
-                InsertStmt::new(Box::new(bound_base_table), self.bind_select(stmt)?)
+                InsertStmt::new(Box::new(bound_base_table), columns, self.bind_select(stmt)?)
             )
         )
     }
@@ -250,6 +649,10 @@ impl Binder {
             DataType::Int(_) => {
                 Ok(Column::new(&col_name, TypeId::INTEGER))
             },
+            // serial is an integer, whose default is taken from a sequence
+            DataType::Custom(_, _) if Self::is_serial_type(&col.data_type) => {
+                Ok(Column::new(&col_name, TypeId::INTEGER))
+            },
             DataType::Varchar(len) => {
                 let Some(len) = len else {
                     return Err(format!("The varchar type needs a length"));
@@ -261,6 +664,12 @@ impl Binder {
 
                 Ok(Column::new_varchar(&col_name, TypeId::VARCHAR, length as u32))
             },
+            DataType::Text => {
+                Ok(Column::new(&col_name, TypeId::TEXT))
+            },
+            DataType::Blob(_) | DataType::Bytea => {
+                Ok(Column::new(&col_name, TypeId::BLOB))
+            },
             _ => {
                 Err(format!("Not support data type"))
             }
@@ -391,6 +800,9 @@ impl Binder {
                     )
                 )
             } 
+            Expr::Function(func) => {
+                return self.bind_func_call(func);
+            },
             // TODO: other types handles
             _ => {
                return Err(String::from("Not support expr"));
@@ -398,6 +810,31 @@ impl Binder {
         }
     }
 
+    // only the sequence functions are supported, nextval('seq') and currval('seq')
+    pub fn bind_func_call(&self, func: &ast::Function) -> Result<Box<BoundExpression>, String> {
+        let func_name = func.name.to_string().to_lowercase();
+        if func_name != "nextval" && func_name != "currval" {
+            return Err(format!("function {} does not exist", func_name));
+        }
+
+        let FunctionArguments::List(arg_list) = &func.args else {
+            return Err(format!("function {} needs one argument", func_name));
+        };
+        let mut args = Vec::new();
+        for arg in &arg_list.args {
+            let FunctionArg::Unnamed(FunctionArgExpr::Expr(expr)) = arg else {
+                return Err(format!("Not support function argument {}", arg));
+            };
+            args.push(*self.bind_expr(expr)?);
+        }
+
+        let is_seq_name = matches!(args.as_slice(), [BoundExpression::Constant(constant)] if constant.val.get_type() == TypeId::VARCHAR);
+        if !is_seq_name {
+            return Err(format!("function {} needs a sequence name", func_name));
+        }
+        Ok(Box::new(BoundExpression::FuncCall(Box::new(BoundFuncCall::new(func_name, args)))))
+    }
+
     pub fn bind_column(&self, scope: &BoundTableRef, idents: &Vec<ast::Ident>) -> Result<Box<BoundColumn>, String> {
         // 1.table.col
         // 2.col
@@ -471,7 +908,7 @@ impl Binder {
                 ))
             }
         }
-        Ok(col_ref.unwrap())
+        col_ref.ok_or(format!("column {} does not exist", col_name[0].value))
     }
 
     // TODO: bind a literal value
@@ -495,6 +932,23 @@ impl Binder {
                 let const_expr = Box::new(BoundConstant::new(val));
                 return Ok(Box::new(BoundExpression::Constant(const_expr)));
             },
+            // X'0a1b', the bytes of a blob
+            ast::Value::HexStringLiteral(hex) => {
+                if hex.len() % 2 != 0 || !hex.chars().all(|c| c.is_ascii_hexdigit()) {
+                    return Err(format!("invalid hexadecimal string X'{}'", hex));
+                }
+                let bytes: Vec<u8> = (0..hex.len()).step_by(2)
+                    .map(|i| u8::from_str_radix(&hex[i..i+2], 16).unwrap())
+                    .collect();
+                let const_expr = Box::new(BoundConstant::new(ValueFactory::get_blob_value(&bytes)));
+                return Ok(Box::new(BoundExpression::Constant(const_expr)));
+            },
+            ast::Value::Null => {
+                // the type is unknown until it meets a column
+                let val = ValueFactory::get_null_value(TypeId::INVALID);
+                let const_expr = Box::new(BoundConstant::new(val));
+                return Ok(Box::new(BoundExpression::Constant(const_expr)));
+            },
             _ => {
 
             }
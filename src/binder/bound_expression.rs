@@ -2,7 +2,7 @@ use std::fmt::Display;
 
 use super::expression::{bound_constant::BoundConstant, bound_agg_call::BoundAggCall, bound_star::BoundStar, bound_unary_op::BoundUnaryOp, bound_func_call::BoundFuncCall, bound_binary_op::BoundBinaryOp, bound_alias::BoundAlias, bound_column_ref::BoundColumn};
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub enum BoundExpression {
     Invalid,
     Constant(Box<BoundConstant>),
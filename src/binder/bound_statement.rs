@@ -1,15 +1,16 @@
 use std::fmt::Display;
 
-use super::statement::{select_stmt::SelectStmt, insert_stmt::InsertStmt, create_stmt::CreateStmt};
+use super::statement::{create_sequence_stmt::CreateSequenceStmt, create_stmt::CreateStmt, delete_stmt::DeleteStmt, insert_stmt::InsertStmt, select_stmt::SelectStmt, update_stmt::UpdateStmt};
 
 
 pub enum BoundStatement {
     Invalid,
     Select(SelectStmt),
     Insert(InsertStmt),
-    Update,
+    Update(UpdateStmt),
     Create(CreateStmt),
-    Delete,
+    CreateSequence(CreateSequenceStmt),
+    Delete(DeleteStmt),
     Explain,
     Drop,
     Index,
@@ -25,8 +26,11 @@ impl Display for BoundStatement {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
             Self::Create(create) => { f.write_str(&create.to_string()) }
+            Self::CreateSequence(create) => { f.write_str(&create.to_string()) }
             Self::Select(sel) => { f.write_str(&sel.to_string()) }
             Self::Insert(insert) => { f.write_str(&insert.to_string()) }
+            Self::Update(update) => { f.write_str(&update.to_string()) }
+            Self::Delete(delete) => { f.write_str(&delete.to_string()) }
             _ => { f.write_str("Unkown") }
         }
     }
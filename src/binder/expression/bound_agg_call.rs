@@ -1,10 +1,10 @@
 use crate::binder::bound_expression::{BoundExpressionFeat, BoundExpression};
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct BoundAggCall {
     is_distinct: bool,
     func_name: String,
-    arg: Vec<BoundExpression>,
+    pub arg: Vec<BoundExpression>,
 }
 
 impl BoundAggCall {
@@ -1,5 +1,5 @@
 use crate::binder::bound_expression::{BoundExpression, BoundExpressionFeat};
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct BoundAlias {
     pub alias: String,
     pub expr: BoundExpression,
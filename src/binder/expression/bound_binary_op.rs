@@ -4,7 +4,7 @@ use sqlparser::ast::BinaryOperator;
 
 use crate::binder::bound_expression::{BoundExpression, BoundExpressionFeat};
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct BoundBinaryOp {
     pub op: BinaryOpType,
     pub left_arg: BoundExpression,
@@ -12,7 +12,7 @@ pub struct BoundBinaryOp {
 }
 
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub enum BinaryOpType {
     Plus,
     Sub,
@@ -5,7 +5,7 @@ use crate::binder::bound_expression::BoundExpressionFeat;
 
 
 /// this structure is responisble for building a column which style is `y.x` or 'x'
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct BoundColumn {
     pub col_name: Vec<String>
 }
@@ -1,6 +1,6 @@
 use crate::{typedef::value::Value, binder::bound_expression::BoundExpressionFeat};
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct BoundConstant {
     pub val: Value
 }
@@ -1,12 +1,15 @@
 use crate::binder::bound_expression::{BoundExpression, BoundExpressionFeat};
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct BoundFuncCall {
-    func_name: String,
-    args: Vec<BoundExpression>
+    pub func_name: String,
+    pub args: Vec<BoundExpression>
 }
 
 impl BoundFuncCall {
+    pub fn new(func_name: String, args: Vec<BoundExpression>) -> Self {
+        Self { func_name, args }
+    }
 }
 
 impl BoundExpressionFeat for BoundFuncCall {
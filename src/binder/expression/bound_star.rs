@@ -1,7 +1,7 @@
 use crate::binder::bound_expression::BoundExpressionFeat;
 
 
-#[derive(Debug)] 
+#[derive(Debug, Clone)]
 pub struct BoundStar {
     pub table_or_alias: Option<String>,
 }
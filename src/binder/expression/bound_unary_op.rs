@@ -1,10 +1,10 @@
 use crate::binder::bound_expression::{BoundExpression, BoundExpressionFeat};
 
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct BoundUnaryOp {
     op_name: String,
-    arg: BoundExpression,
+    pub arg: BoundExpression,
 }
 
 impl BoundExpressionFeat for BoundUnaryOp {
@@ -0,0 +1,33 @@
+use crate::binder::bound_statement::BoundStatementFeat;
+
+#[derive(Debug, Clone)]
+pub struct CreateSequenceStmt {
+    pub name: String,
+    pub if_not_exists: bool,
+
+    pub start_value: i32,
+    pub increment: i32,
+    pub min_value: i32,
+    pub max_value: i32,
+}
+
+impl CreateSequenceStmt {
+    // an ascending sequence starting at 1 by default
+    pub fn new(name: String) -> Self {
+        Self {
+            name,
+            if_not_exists: false,
+            start_value: 1,
+            increment: 1,
+            min_value: 1,
+            max_value: i32::MAX,
+        }
+    }
+}
+
+impl BoundStatementFeat for CreateSequenceStmt {
+    fn to_string(&self) -> String {
+        format!("{{{{ sequence={}, start={}, increment={}, min={}, max={} }}}}", 
+            self.name, self.start_value, self.increment, self.min_value, self.max_value)
+    }
+}
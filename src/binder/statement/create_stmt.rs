@@ -1,15 +1,65 @@
-use crate::{catalog::column::Column, binder::bound_statement::BoundStatementFeat};
+use crate::{catalog::{column::Column, constraint::ForeignKeyAction}, binder::{bound_expression::BoundExpression, bound_statement::BoundStatementFeat}};
+
+use super::create_sequence_stmt::CreateSequenceStmt;
+
+/// a PRIMARY KEY or UNIQUE constraint, `columns` are the indices in the table columns
+#[derive(Debug, Clone)]
+pub struct UniqueDef {
+    pub name: Option<String>,
+    pub columns: Vec<usize>,
+    pub is_primary: bool,
+}
+
+/// a REFERENCES/FOREIGN KEY constraint, the referenced table may be the created table itself
+#[derive(Debug, Clone)]
+pub struct ForeignKeyDef {
+    pub name: Option<String>,
+    pub columns: Vec<usize>,
+    pub ref_table: String,
+    pub ref_columns: Vec<usize>,
+    pub on_delete: ForeignKeyAction,
+    pub on_update: ForeignKeyAction,
+}
+
+/// a CHECK constraint, the columns in `expr` are bound to the created table
+#[derive(Debug, Clone)]
+pub struct CheckDef {
+    pub name: Option<String>,
+    // the column, if it is declared with a column
+    pub column: Option<usize>,
+    pub expr: Box<BoundExpression>,
+}
 
 pub struct CreateStmt {
-    table_name: String,
-    columns: Vec<Column>,
+    pub table_name: String,
+    pub columns: Vec<Column>,
+
+    pub unique_keys: Vec<UniqueDef>,
+    pub foreign_keys: Vec<ForeignKeyDef>,
+    pub checks: Vec<CheckDef>,
+    // the DEFAULT expression of each column
+    pub defaults: Vec<Option<Box<BoundExpression>>>,
+    // the sequences owned by SERIAL/IDENTITY columns, created before the table
+    pub sequences: Vec<CreateSequenceStmt>,
+    // WITH (tablespace = 'name'), the table is in the database file without it
+    pub tablespace: Option<String>,
+    // WITH (compression = on/off), TOAST_COMPRESSION without it
+    pub compression: Option<bool>,
 }
 
 impl CreateStmt {
     pub fn new(table_name: String, cols: Vec<Column>) -> Self {
+        let defaults = vec![None; cols.len()];
         Self {
             table_name,
             columns: cols,
+            unique_keys: Vec::new(),
+            foreign_keys: Vec::new(),
+            checks: Vec::new(),
+            defaults,
+            sequences: Vec::new(),
+            tablespace: None,
+            compression: None,
         }
     }
 }
@@ -19,6 +69,8 @@ impl BoundStatementFeat for CreateStmt {
         let str: Vec<String> = self.columns.iter().map(|c| c.to_string()).collect();
         let str = str.join(", ");
         let str = "[".to_owned() + &str + "]";
-        format!("{{{{\n    table={}\n    columns={}\n}}}}", self.table_name, str)
+        let checks: Vec<String> = self.checks.iter().map(|c| c.expr.to_string()).collect();
+        format!("{{{{\n    table={}\n    columns={}\n    unique_keys={:?}\n    foreign_keys={:?}\n    checks=[{}]\n}}}}", 
+            self.table_name, str, self.unique_keys, self.foreign_keys, checks.join(", "))
     }
-}
\ No newline at end of file
+}
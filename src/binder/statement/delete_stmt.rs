@@ -0,0 +1,20 @@
+use crate::binder::{bound_expression::BoundExpression, bound_statement::BoundStatementFeat, bound_table_ref::BoundTableRefFeat, table_ref::bound_base_table::BoundBaseTableRef};
+
+#[derive(Debug)]
+pub struct DeleteStmt {
+    pub table_ref: Box<BoundBaseTableRef>,
+    pub where_by: Option<Box<BoundExpression>>,
+}
+
+impl DeleteStmt {
+    pub fn new(table_ref: Box<BoundBaseTableRef>, where_by: Option<Box<BoundExpression>>) -> Self {
+        Self { table_ref, where_by }
+    }
+}
+
+impl BoundStatementFeat for DeleteStmt {
+    fn to_string(&self) -> String {
+        format!("{{{{ table={}, where={:#?} }}}}", 
+            self.table_ref.to_string(), self.where_by)
+    }
+}
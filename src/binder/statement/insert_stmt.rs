@@ -5,13 +5,16 @@ use super::select_stmt::SelectStmt;
 #[derive(Debug)]
 pub struct InsertStmt {
     pub table_ref: Box<BoundBaseTableRef>,
+    // the column of the table that each selected value goes to
+    pub columns: Vec<usize>,
     pub select: Box<SelectStmt>
 }
 
 impl InsertStmt {
-    pub fn new(table_ref: Box<BoundBaseTableRef>, sel: Box<SelectStmt>) -> Self {
+    pub fn new(table_ref: Box<BoundBaseTableRef>, columns: Vec<usize>, sel: Box<SelectStmt>) -> Self {
         Self {
             table_ref,
+            columns,
             select: sel,
         }
     }
@@ -19,7 +22,7 @@ impl InsertStmt {
 
 impl BoundStatementFeat for InsertStmt {
     fn to_string(&self) -> String {
-        format!("{{{{ table={}, sel={} }}}}", 
-            self.table_ref.to_string(), self.select.to_string())
+        format!("{{{{ table={}, columns={:?}, sel={} }}}}", 
+            self.table_ref.to_string(), self.columns, self.select.to_string())
     }
 }
\ No newline at end of file
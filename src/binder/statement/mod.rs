@@ -1,3 +1,6 @@
 pub mod create_stmt;
 pub mod insert_stmt;
-pub mod select_stmt;
\ No newline at end of file
+pub mod select_stmt;
+pub mod delete_stmt;
+pub mod update_stmt;
+pub mod create_sequence_stmt;
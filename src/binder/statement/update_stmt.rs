@@ -0,0 +1,26 @@
+use crate::binder::{bound_expression::BoundExpression, bound_statement::BoundStatementFeat, bound_table_ref::BoundTableRefFeat, table_ref::bound_base_table::BoundBaseTableRef};
+
+#[derive(Debug)]
+pub struct UpdateStmt {
+    pub table_ref: Box<BoundBaseTableRef>,
+    // (column index in the table schema, the new value)
+    pub target_exprs: Vec<(usize, Box<BoundExpression>)>,
+    pub where_by: Option<Box<BoundExpression>>,
+}
+
+impl UpdateStmt {
+    pub fn new(table_ref: Box<BoundBaseTableRef>, target_exprs: Vec<(usize, Box<BoundExpression>)>, 
+        where_by: Option<Box<BoundExpression>>) -> Self {
+        Self { table_ref, target_exprs, where_by }
+    }
+}
+
+impl BoundStatementFeat for UpdateStmt {
+    fn to_string(&self) -> String {
+        let targets: Vec<String> = self.target_exprs.iter()
+            .map(|(idx, expr)| format!("#{}={}", idx, expr.to_string()))
+            .collect();
+        format!("{{{{ table={}, target_exprs=[{}], where={:#?} }}}}", 
+            self.table_ref.to_string(), targets.join(", "), self.where_by)
+    }
+}
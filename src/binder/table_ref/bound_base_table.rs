@@ -1,6 +1,6 @@
 use crate::{common::config::table_id_t, catalog::schema::Schema, binder::bound_table_ref::BoundTableRefFeat};
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct BoundBaseTableRef {
     pub table_name: String,
     pub table_id: table_id_t,
@@ -0,0 +1,73 @@
+#![allow(warnings)]
+
+use std::{collections::{HashMap, VecDeque}, sync::Mutex};
+
+use crate::common::config::{frame_id_t, page_id_t, BULK_WRITE_RING_SIZE, SCAN_RING_SIZE};
+
+
+/// what the pages are fetched for
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AccessIntent {
+    Normal,
+    // a pass over a whole table, each page is read once
+    SequentialScan,
+    // many new tuples appended to a table
+    BulkWrite,
+}
+
+/// BufferAccessStrategy, like the ones of PostgreSQL. A sequential scan or a bulk write reuses
+/// a small ring of frames instead of pushing the pages of the others out of the whole pool.
+/// A page it reads in goes to the ring, and when the ring is full the oldest frame of it is taken again,
+/// unless it is pinned or touched by a normal access in the meantime, then it is left to the replacer.
+/// It belongs to one scan or insert, and is dropped with it
+#[derive(Debug)]
+pub struct BufferAccessStrategy {
+    intent: AccessIntent,
+    ring_size: usize,
+    // the (frame, page) the strategy read in of each instance of the pool, the oldest at the front
+    rings: Mutex<HashMap<usize, VecDeque<(frame_id_t, page_id_t)>>>,
+}
+
+impl BufferAccessStrategy {
+    pub fn new(intent: AccessIntent) -> Self {
+        let ring_size = match intent {
+            AccessIntent::Normal => 0,
+            AccessIntent::SequentialScan => SCAN_RING_SIZE,
+            AccessIntent::BulkWrite => BULK_WRITE_RING_SIZE,
+        };
+        Self::with_ring_size(intent, ring_size)
+    }
+
+    pub fn with_ring_size(intent: AccessIntent, ring_size: usize) -> Self {
+        Self {
+            intent,
+            ring_size,
+            rings: Mutex::new(HashMap::new()),
+        }
+    }
+
+    pub fn get_intent(&self) -> AccessIntent {
+        self.intent
+    }
+
+    // a normal access goes through the replacer only
+    pub fn uses_ring(&self) -> bool {
+        self.intent != AccessIntent::Normal && self.ring_size > 0
+    }
+
+    // the oldest frame of the ring in the instance if the ring is full there,
+    // the ring is spread over the instances evenly
+    pub(crate) fn pop_full(&self, instance: usize, num_instances: usize) -> Option<(frame_id_t, page_id_t)> {
+        let capacity = ((self.ring_size + num_instances - 1) / num_instances).max(1);
+        let mut rings = self.rings.lock().unwrap();
+        let ring = rings.entry(instance).or_default();
+        if ring.len() < capacity {
+            return None;
+        }
+        ring.pop_front()
+    }
+
+    pub(crate) fn push(&self, instance: usize, frame_id: frame_id_t, page_id: page_id_t) {
+        self.rings.lock().unwrap().entry(instance).or_default().push_back((frame_id, page_id));
+    }
+}
@@ -0,0 +1,228 @@
+#![allow(warnings)]
+
+use std::collections::{HashMap, VecDeque};
+
+use crate::common::config::{frame_id_t, page_id_t, INVALID_PAGE_ID};
+
+use super::replacer::Replacer;
+
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum List {
+    // resident, seen once recently
+    T1,
+    // resident, seen at least twice recently
+    T2,
+}
+
+#[derive(Debug)]
+struct Entry {
+    pub page_id: page_id_t,
+    pub list: List,
+    pub is_evictable: bool,
+}
+
+/// ARC (Megiddo & Modha), the resident pages are split into T1 (recency) and T2 (frequency),
+/// and the pages evicted from them are remembered in the ghost lists B1 and B2.
+/// A hit in B1 means T1 was too small, so the target size p of T1 grows, a hit in B2 shrinks it
+#[derive(Debug)]
+pub struct ARCReplacer {
+    entries: HashMap<frame_id_t, Entry>,
+    // the front is the lru one
+    t1: VecDeque<frame_id_t>,
+    t2: VecDeque<frame_id_t>,
+    b1: VecDeque<page_id_t>,
+    b2: VecDeque<page_id_t>,
+    // the target size of T1
+    p: usize,
+    // the pages coming into the frames, taken by the first record_access
+    incoming: HashMap<frame_id_t, page_id_t>,
+    num_frames: usize,
+    replacer_size: usize,
+}
+
+impl ARCReplacer {
+    pub fn new(num_frames: usize) -> Self {
+        Self {
+            entries: HashMap::new(),
+            t1: VecDeque::new(),
+            t2: VecDeque::new(),
+            b1: VecDeque::new(),
+            b2: VecDeque::new(),
+            p: 0,
+            incoming: HashMap::new(),
+            num_frames,
+            replacer_size: 0,
+        }
+    }
+
+    fn list_mut(&mut self, list: List) -> &mut VecDeque<frame_id_t> {
+        match list {
+            List::T1 => &mut self.t1,
+            List::T2 => &mut self.t2,
+        }
+    }
+
+    // the lru evictable frame of the list
+    fn victim_of(&self, list: &VecDeque<frame_id_t>) -> Option<frame_id_t> {
+        list.iter().find(|fid| self.entries[fid].is_evictable).cloned()
+    }
+
+    fn detach(&mut self, frame_id: frame_id_t) -> Option<Entry> {
+        let entry = self.entries.remove(&frame_id)?;
+        self.list_mut(entry.list).retain(|fid| *fid != frame_id);
+        if entry.is_evictable {
+            self.replacer_size -= 1;
+        }
+        Some(entry)
+    }
+
+    // the directory holds at most c pages in T1 + B1 and 2c pages in all
+    fn trim_ghosts(&mut self) {
+        while self.t1.len() + self.b1.len() > self.num_frames && !self.b1.is_empty() {
+            self.b1.pop_front();
+        }
+        while self.t1.len() + self.t2.len() + self.b1.len() + self.b2.len() > 2 * self.num_frames && !self.b2.is_empty() {
+            self.b2.pop_front();
+        }
+    }
+}
+
+impl Replacer for ARCReplacer {
+    fn evict(&mut self) -> Option<frame_id_t> {
+        if 0 == self.replacer_size {
+            return None;
+        }
+
+        // the incoming page is not known yet, so the tie of the paper (a B2 hit with |T1| == p) goes to T2
+        let victim = if !self.t1.is_empty() && self.t1.len() > self.p {
+            self.victim_of(&self.t1).or_else(|| self.victim_of(&self.t2))
+        } else {
+            self.victim_of(&self.t2).or_else(|| self.victim_of(&self.t1))
+        }?;
+
+        let entry = self.detach(victim)?;
+        if entry.page_id != INVALID_PAGE_ID {
+            match entry.list {
+                List::T1 => self.b1.push_back(entry.page_id),
+                List::T2 => self.b2.push_back(entry.page_id),
+            }
+            self.trim_ghosts();
+        }
+        Some(victim)
+    }
+
+    fn record_access(&mut self, frame_id: frame_id_t) {
+        if frame_id >= self.num_frames {
+            panic!("Error: frame id is greater <{}> than the maxmimum frame size <{}>",
+                frame_id, self.num_frames);
+        }
+
+        // a hit goes to the mru end of T2
+        if let Some(entry) = self.entries.get_mut(&frame_id) {
+            let list = entry.list;
+            entry.list = List::T2;
+            self.list_mut(list).retain(|fid| *fid != frame_id);
+            self.t2.push_back(frame_id);
+            return;
+        }
+
+        let page_id = self.incoming.remove(&frame_id).unwrap_or(INVALID_PAGE_ID);
+        let in_b1 = self.b1.iter().position(|pid| *pid == page_id).filter(|_| page_id != INVALID_PAGE_ID);
+        let in_b2 = self.b2.iter().position(|pid| *pid == page_id).filter(|_| page_id != INVALID_PAGE_ID);
+        let list = if let Some(pos) = in_b1 {
+            let delta = (self.b2.len() / self.b1.len()).max(1);
+            self.p = (self.p + delta).min(self.num_frames);
+            self.b1.remove(pos);
+            List::T2
+        } else if let Some(pos) = in_b2 {
+            let delta = (self.b1.len() / self.b2.len()).max(1);
+            self.p = self.p.saturating_sub(delta);
+            self.b2.remove(pos);
+            List::T2
+        } else {
+            List::T1
+        };
+
+        self.entries.insert(frame_id, Entry { page_id, list, is_evictable: false });
+        self.list_mut(list).push_back(frame_id);
+        self.trim_ghosts();
+    }
+
+    fn set_evictable(&mut self, frame_id: frame_id_t, is_evictable: bool) {
+        let Some(entry) = self.entries.get_mut(&frame_id) else {
+            return;
+        };
+        if entry.is_evictable == is_evictable {
+            return;
+        }
+
+        entry.is_evictable = is_evictable;
+        if is_evictable {
+            self.replacer_size += 1;
+        } else {
+            self.replacer_size -= 1;
+        }
+    }
+
+    fn remove(&mut self, frame_id: frame_id_t) {
+        self.detach(frame_id);
+        self.incoming.remove(&frame_id);
+    }
+
+    fn get_size(&self) -> usize {
+        self.replacer_size
+    }
+
+    fn map_page(&mut self, frame_id: frame_id_t, page_id: page_id_t) {
+        self.incoming.insert(frame_id, page_id);
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use crate::common::config::page_id_t;
+
+    use super::{ARCReplacer, Replacer};
+
+    fn access(replacer: &mut ARCReplacer, fid: usize, pid: page_id_t) {
+        replacer.map_page(fid, pid);
+        replacer.record_access(fid);
+        replacer.set_evictable(fid, true);
+    }
+
+    #[test]
+    fn sample_test() {
+        let mut replacer = ARCReplacer::new(4);
+        for fid in 0..4 {
+            access(&mut replacer, fid, fid as page_id_t + 100);
+        }
+        // 0 and 1 are seen twice, they move to T2
+        replacer.record_access(0);
+        replacer.record_access(1);
+        assert_eq!(4, replacer.get_size());
+
+        // p = 0, T1 = [2, 3] goes first
+        assert_eq!(Some(2), replacer.evict());
+
+        // page 102 comes back from B1, T1 was too small, p grows to 1
+        access(&mut replacer, 2, 102);
+        assert_eq!(1, replacer.p);
+        // T1 = [3] is not over p, so the lru of T2 = [0, 1, 2] goes
+        assert_eq!(Some(0), replacer.evict());
+
+        // page 100 comes back from B2, p shrinks to 0
+        access(&mut replacer, 0, 100);
+        assert_eq!(0, replacer.p);
+        assert_eq!(Some(3), replacer.evict());
+
+        // a pinned frame is passed over
+        replacer.set_evictable(1, false);
+        assert_eq!(Some(2), replacer.evict());
+        assert_eq!(Some(0), replacer.evict());
+        assert_eq!(None, replacer.evict());
+        replacer.remove(1);
+        assert_eq!(0, replacer.get_size());
+    }
+}
@@ -1,10 +1,10 @@
 #![allow(warnings)]
 
-use std::{borrow::Borrow, collections::{HashMap, LinkedList}, sync::{atomic::{AtomicI32, Ordering}, Arc, Mutex, MutexGuard }, usize};
+use std::{borrow::Borrow, collections::{HashMap, LinkedList}, sync::{atomic::{AtomicBool, AtomicUsize, Ordering}, Arc, Condvar, Mutex, MutexGuard, RwLock }, usize};
 
-use crate::{common::config::{frame_id_t, page_id_t}, storage::page_based::{disk::{disk_manager::DiskManager, disk_scheduler::DiskScheduler, log_manager::LogManager}, page::{page::Page, page_guard::{PageGuard, ReadPageGuard, WritePageGuard}}}};
+use crate::{common::{config::{file_id_t, frame_id_t, lsn_t, page_id_t, INVALID_PAGE_ID, MAIN_FILE_ID}, page_addr::PageAddr}, storage::page_based::{disk::{disk_manager::PageStore, disk_scheduler::{DiskRequest, DiskScheduler}, log_manager::LogManager}, page::{page::{Page, PAGE_CORRUPTED}, page_guard::{PageGuard, ReadPageGuard, WritePageGuard}}}};
 
-use super::lruk_replacer::{LRUKReplacer, Replacer};
+use super::{access_strategy::{AccessIntent, BufferAccessStrategy}, free_space_map::FreeSpaceMap, replacer::{Replacer, ReplacerPolicy}};
 
 
 
@@ -14,156 +14,135 @@ pub type PageRef<'a> = &'a mut Page;
 
 #[derive(Debug, )]
 struct BufferPoolManager_ {
-    pub disk_scheduler: Box<DiskScheduler>,
-    pub log_manager: Arc<LogManager>,
     pub pages: Vec<Page>,
     pub page_table: HashMap<page_id_t, frame_id_t>,
     pub free_list: LinkedList<usize>,
     pub pool_size: usize,
-    // page id generator
-    pub page_id_generator: AtomicI32,
-    pub lru_replacer: Box<LRUKReplacer>,
+    // the next page id of this instance, the ids go up by the number of the instances
+    pub next_page_id: page_id_t,
+    pub replacer: Box<dyn Replacer>,
+    // the frames whose data are being read or written out of the latch, they are pinned meanwhile
+    pub io_in_progress: Vec<bool>,
+    // the evicted dirty pages on their way to the disk, with their recLSNs
+    pub writing_pages: HashMap<page_id_t, lsn_t>,
+    // the frames read in by a ring of an access strategy and not accessed normally since, the ring may take them again
+    pub in_ring: Vec<bool>,
+    // the frames being read ahead, a prefetch holds a pin until it is done and taken by a fetch or the replacer
+    pub prefetching: HashMap<frame_id_t, Arc<DiskRequest>>,
 }
 
+/// one partition of the buffer pool, it holds the pages whose id % num_instances == index.
+/// the latch only guards the metadata, the disk i/o is done after releasing it
 #[derive(Debug)]
-pub struct BufferPoolManager {
-    // to protect state(BufferPoolManager_)
+struct BufferPoolInstance {
     state: Mutex<BufferPoolManager_>,
+    // notified when an i/o out of the latch is done
+    io_done: Condvar,
+    disk_scheduler: DiskScheduler,
+    log_manager: Arc<LogManager>,
+    index: usize,
+    num_instances: usize,
 }
 
-const INVALID_FRAME_ID: usize = usize::MAX;
+#[derive(Debug)]
+pub struct BufferPoolManager {
+    // the pages are spread over the instances by page id, each one has its own latch
+    instances: Vec<BufferPoolInstance>,
+    // the instance new_page tries first, it goes round
+    next_instance: AtomicUsize,
+    log_manager: Arc<LogManager>,
+    // the page accesses are recorded while it is set, for replaying them against the replacers
+    tracing: AtomicBool,
+    trace: Mutex<Vec<page_id_t>>,
+    // the page allocators on disk by file, without one the page ids of the database file go up from the end of it
+    // and are never reused, and no page is allocated in a tablespace file
+    free_space_maps: RwLock<HashMap<file_id_t, Arc<FreeSpaceMap>>>,
+    // the page size of the store, the frames are of it
+    page_size: usize,
+}
 
 impl BufferPoolManager {
     // Create a new buffer pool manager with n size and k related to replacer
-    pub fn new(pool_size: usize, disk_mgr: Arc<DiskManager>, k_replacer: usize, lgr: Arc<LogManager>) -> Self {
-        let mut bpm = BufferPoolManager_ {
-            disk_scheduler: Box::new(DiskScheduler::new(disk_mgr)),
-            log_manager: lgr,
-            pool_size: pool_size,
-            page_id_generator: AtomicI32::new(0),
-            pages: Vec::new(),
-            lru_replacer: Box::new(LRUKReplacer::new(pool_size, k_replacer)),
-            page_table: HashMap::new(),
-            free_list: LinkedList::new(),
-        };
+    pub fn new(pool_size: usize, disk_mgr: Arc<dyn PageStore>, k_replacer: usize, lgr: Arc<LogManager>) -> Self {
+        Self::new_sharded(1, pool_size, disk_mgr, k_replacer, lgr)
+    }
 
-        bpm.pages.reserve(pool_size);
-        for _ in 0..pool_size {
-            bpm.pages.push(Page::new());
-        }
+    // Create a buffer pool of pool_size frames partitioned into num_instances instances
+    pub fn new_sharded(num_instances: usize, pool_size: usize, disk_mgr: Arc<dyn PageStore>, k_replacer: usize, lgr: Arc<LogManager>) -> Self {
+        Self::new_with_replacer(num_instances, pool_size, disk_mgr, ReplacerPolicy::LruK(k_replacer), lgr)
+    }
 
-        for i in 0..pool_size {
-            bpm.free_list.push_back(i);
-        }
+    // Create a sharded buffer pool whose instances replace the pages by the policy
+    pub fn new_with_replacer(num_instances: usize, pool_size: usize, disk_mgr: Arc<dyn PageStore>, policy: ReplacerPolicy, lgr: Arc<LogManager>) -> Self {
+        assert!(num_instances > 0, "a buffer pool needs at least one instance");
+        let instance_size = (pool_size + num_instances - 1) / num_instances;
+        let page_size = disk_mgr.get_page_size();
+        let instances = (0..num_instances)
+            .map(|index| BufferPoolInstance::new(index, num_instances, instance_size, disk_mgr.clone(), policy, lgr.clone()))
+            .collect();
 
         Self {
-            state: Mutex::new(bpm),
+            instances,
+            next_instance: AtomicUsize::new(0),
+            log_manager: lgr,
+            tracing: AtomicBool::new(false),
+            trace: Mutex::new(Vec::new()),
+            free_space_maps: RwLock::new(HashMap::new()),
+            page_size,
         }
     }
 
+    pub fn get_page_size(&self) -> usize {
+        self.page_size
+    }
+
+    fn instance_of(&self, page_id: page_id_t) -> &BufferPoolInstance {
+        &self.instances[page_id.rem_euclid(self.instances.len() as page_id_t) as usize]
+    }
+
     // return a new page if it creates successfully,
     // otherwise, return None
     pub fn new_page(&self) -> Option<PageRef> {
-        let mut _lock = self.state.lock().unwrap();
-
-        let mut fid = usize::MAX;
-        {
-            let state = &mut _lock;
-            if !state.free_list.is_empty() {
-                fid = state.free_list.pop_front().unwrap();
-            }
-
-            if INVALID_FRAME_ID == fid { // if still invalid
-                if let Some(frame_id) = state.lru_replacer.evict() {
-                    fid = frame_id;
-                }
-            }
-
-            if INVALID_FRAME_ID == fid {
-                return None;
-            }
-        }
-
-        // then flush the dirty page
-        let fid = fid as usize;
-        let (pid, is_dirty) = {
-            let page = &_lock.pages[fid];
-            (page.page_id, page.is_dirty)
-        };
-
-        // let a = &mut _lock.pages[fid];
-        let page = unsafe { &mut *(&mut _lock.pages[fid] as *mut Page) };
-        if is_dirty {
-            Self::write_page_data(&_lock.disk_scheduler, pid, page);
-        }
-
-        // set meta for new page in the frame
-        let pid = self.allocate_page(&mut _lock);
-        Self::reset_meta(&mut _lock.pages[fid], pid);
-        _lock.pages[fid].pin_count = 1;
-        _lock.lru_replacer.record_access(fid);
-        _lock.page_table.insert(pid, fid as frame_id_t);
-
-        Some(page)     
+        self.new_page_by(MAIN_FILE_ID, None)
     }
 
-    // fetch an existing page from bpm or disk
-    pub fn fetch_page(&self, page_id: page_id_t) -> Option<PageRef> {
-        let mut _lock = self.state.lock().unwrap();
 
+    // fetch an existing page from bpm or disk, the page is pinned.
+    // returns None if every frame of its instance is pinned, or the page on disk is corrupted
+    pub fn fetch_page(&self, page_id: page_id_t) -> Option<PageRef> {
+        self.try_fetch_page(page_id).ok()
+    }
 
-        // check if in page_table
-        if !_lock.page_table.contains_key(&page_id) {
-            return None;
-        }
+    // like fetch_page, with the reason when it fails, a page failing its checksum gives an error
+    // starting with PAGE_CORRUPTED and is not kept in the pool
+    pub fn try_fetch_page(&self, page_id: page_id_t) -> Result<PageRef, String> {
+        self.fetch_page_by(page_id, None)
+    }
 
-        let mut find_idx = _lock.pages.len();
-        for idx in 0.._lock.pages.len() {
-            let p = &_lock.pages[idx];
-            if p.page_id == page_id {
-                find_idx = idx;
-                break;
-            }
+    fn new_page_by(&self, file_id: file_id_t, strategy: Option<&BufferAccessStrategy>) -> Option<PageRef> {
+        if let Some(free_space_map) = self.free_space_map_of(file_id) {
+            let page = free_space_map.allocate(self, self.instances.len(),
+                |page_id| self.instance_of(page_id).new_page_at(page_id, strategy))?;
+            self.record_trace(page.page_id);
+            return Some(page);
         }
-
-        if find_idx < _lock.pages.len() {
-            // let p = unsafe { &mut *(&mut _lock.pages[find_idx] as *mut Page) };
-            let p = &mut _lock.pages[find_idx];
-            p.pin_count += 1;
-            if p.pin_count > 0 {
-                _lock.lru_replacer.set_evictable(find_idx, false);
-            }
-            let rtn = unsafe { &mut *(&mut _lock.pages[find_idx] as *mut Page) };
-            return Some(rtn);
-            // return None;
-        }
-
-        // check available frame in free_list
-        let mut fid = usize::MAX;
-        if !_lock.free_list.is_empty() {
-            fid = _lock.free_list.pop_front().unwrap();
-        } else if let Some(f) = _lock.lru_replacer.evict() {
-            // do nothing, just receive a fid
-            fid = f
-        } else {
+        if file_id != MAIN_FILE_ID {
             return None;
         }
 
-        let mut page = unsafe { &mut *(&mut _lock.pages[fid] as *mut Page) };
-        if page.is_dirty {
-            Self::write_page_data(&_lock.disk_scheduler, page.page_id, page);
-        }
-
-        // then set meta, read data
-        Self::reset_meta(&mut page, page_id);
-        Self::read_page_data(&_lock.disk_scheduler, page_id, &mut page);
-        page.pin_count = 1;
-        _lock.lru_replacer.record_access(fid);
-        // update page table
-        _lock.page_table.insert(page_id, fid);
+        // starts from a different instance each time, and goes on to the others when it is full
+        let n = self.instances.len();
+        let start = self.next_instance.fetch_add(1, Ordering::Relaxed);
+        let page = (0..n).find_map(|i| self.instances[(start + i) % n].new_page(strategy))?;
+        self.record_trace(page.page_id);
         Some(page)
     }
 
+    fn fetch_page_by(&self, page_id: page_id_t, strategy: Option<&BufferAccessStrategy>) -> Result<PageRef, String> {
+        self.record_trace(page_id);
+        self.instance_of(page_id).fetch_page(page_id, strategy)
+    }
+
     // new a page wrapped by a page guard
     pub fn new_page_guarded(&self) -> Option<PageGuard> {
         let page = self.new_page();
@@ -175,6 +154,20 @@ impl BufferPoolManager {
         }
     }
 
+    // new a page for a bulk write, it comes into the ring of the strategy
+    pub fn new_page_guarded_with(&self, strategy: &BufferAccessStrategy) -> Option<PageGuard> {
+        self.new_page_by(MAIN_FILE_ID, Some(strategy)).map(|page| PageGuard::new(self, page))
+    }
+
+    // a new page in the file of a tablespace, it fails if the file has no free space map
+    pub fn new_page_guarded_in(&self, file_id: file_id_t) -> Option<PageGuard> {
+        self.new_page_by(file_id, None).map(|page| PageGuard::new(self, page))
+    }
+
+    pub fn new_page_guarded_in_with(&self, file_id: file_id_t, strategy: &BufferAccessStrategy) -> Option<PageGuard> {
+        self.new_page_by(file_id, Some(strategy)).map(|page| PageGuard::new(self, page))
+    }
+
     pub fn fetch_page_basic(&self, page_id: page_id_t) -> Option<PageGuard> {
         let page = self.fetch_page(page_id);
         match page {
@@ -186,62 +179,354 @@ impl BufferPoolManager {
     }
 
     pub fn fetch_page_read(&self, page_id: page_id_t) -> Option<ReadPageGuard> {
-        let page = self.fetch_page(page_id);
-        match page {
-            None => None,
-            Some(page) => {
-                let lock = page.get_read_lock();
+        self.fetch_page(page_id).map(|page| self.read_guard(page))
+    }
 
-                // cross the compiler checking
-                let mut_page = unsafe {
-                    &mut *(page as *const Page as *mut Page)
-                };
+    // fetch a page for reading with the intent of the strategy, e.g. a sequential scan reads it into its ring
+    pub fn fetch_page_read_with(&self, page_id: page_id_t, strategy: &BufferAccessStrategy) -> Option<ReadPageGuard> {
+        self.try_fetch_page_read_with(page_id, strategy).ok()
+    }
 
-                let guard = PageGuard::new(self, mut_page);
-                Some(ReadPageGuard::new(guard, lock))
-            }
-        }
+    pub fn try_fetch_page_read(&self, page_id: page_id_t) -> Result<ReadPageGuard, String> {
+        self.try_fetch_page(page_id).map(|page| self.read_guard(page))
+    }
+
+    pub fn try_fetch_page_read_with(&self, page_id: page_id_t, strategy: &BufferAccessStrategy) -> Result<ReadPageGuard, String> {
+        self.fetch_page_by(page_id, Some(strategy)).map(|page| self.read_guard(page))
     }
 
     pub fn fetch_page_write(&self, page_id: page_id_t) -> Option<WritePageGuard> {
-        let page = self.fetch_page(page_id);
-        match page {
-            None => None,
-            Some(page) => {
-                let lock = page.get_write_lock();
+        self.fetch_page(page_id).map(|page| self.write_guard(page))
+    }
 
-                // cross the compiler checking
-                let mut_page = unsafe {
-                    &mut *(page as *const Page as *mut Page)
-                };
+    pub fn fetch_page_write_with(&self, page_id: page_id_t, strategy: &BufferAccessStrategy) -> Option<WritePageGuard> {
+        self.fetch_page_by(page_id, Some(strategy)).ok().map(|page| self.write_guard(page))
+    }
 
-                let guard = PageGuard::new(self, mut_page);
-                Some(WritePageGuard::new(guard, lock))
-            }
-        }
+    // read the page into the pool in the background, a later fetch of it waits for the read only if it is not done yet
+    pub fn prefetch_page(&self, page_id: page_id_t, strategy: &BufferAccessStrategy) -> bool {
+        self.instance_of(page_id).prefetch_page(page_id, Some(strategy))
+    }
+
+    fn read_guard<'a>(&'a self, page: PageRef<'a>) -> ReadPageGuard<'a> {
+        let lock = page.get_read_lock();
+
+        // cross the compiler checking
+        let mut_page = unsafe {
+            &mut *(page as *const Page as *mut Page)
+        };
+
+        let guard = PageGuard::new(self, mut_page);
+        ReadPageGuard::new(guard, lock)
+    }
+
+    fn write_guard<'a>(&'a self, page: PageRef<'a>) -> WritePageGuard<'a> {
+        let lock = page.get_write_lock();
+
+        // cross the compiler checking
+        let mut_page = unsafe {
+            &mut *(page as *const Page as *mut Page)
+        };
+
+        let guard = PageGuard::new(self, mut_page);
+        WritePageGuard::new(guard, lock)
     }
 
     /// unpin a page
     /// we need to decrease the pin_count, and when it becomes 0, then make it evictable
     /// then, set the dirty flag if need
     pub fn unpin_page(&self, page_id: page_id_t, is_dirty: bool) -> bool {
+        self.instance_of(page_id).unpin_page(page_id, is_dirty)
+    }
+
+    pub fn flush_page(&self, page_id: page_id_t) -> bool {
+        self.instance_of(page_id).flush_page(page_id)
+    }
+
+    // the pages before `page_id` are taken, e.g. the ones in the database file when it is opened again
+    pub fn set_next_page_id(&self, page_id: page_id_t) {
+        for instance in self.instances.iter() {
+            instance.set_next_page_id(page_id);
+        }
+    }
+
+    pub fn get_log_manager(&self) -> Arc<LogManager> {
+        self.log_manager.clone()
+    }
+
+    // write out all the dirty pages in the pool
+    pub fn flush_all_pages(&self) {
+        for instance in self.instances.iter() {
+            instance.flush_all_pages();
+        }
+    }
+
+    // the pages which may be newer than the ones on disk and their recLSNs, for the checkpoints.
+    // a pinned page may be changed without being marked dirty yet, so it is taken too
+    pub fn get_dirty_pages(&self) -> Vec<(page_id_t, lsn_t)> {
+        self.instances.iter().flat_map(|instance| instance.get_dirty_pages()).collect()
+    }
+
+    /// delete page in bufferpool, if not exists, return true
+    /// if pin_count > 0, then return false
+    /// else remove lru record, and bufferpool , then move it to free_list.
+    /// the page is given back to the free space map of its file then, an error is returned when it can not be
+    pub fn delete_page(&self, page_id: page_id_t) -> Result<bool, String> {
+        if !self.instance_of(page_id).delete_page(page_id) {
+            return Ok(false);
+        }
+        self.deallocate_page(page_id)?;
+        Ok(true)
+    }
+
+    // read the free space map of the database file of num_pages pages, the pages are allocated by it from then on
+    pub fn open_free_space_map(&self, num_pages: page_id_t) -> Result<(), String> {
+        self.open_free_space_map_of(MAIN_FILE_ID, num_pages)
+    }
+
+    // the same for the file of a tablespace, the ids of its pages are far from the ones of the database file,
+    // so they do not move the next page id
+    pub fn open_free_space_map_of(&self, file_id: file_id_t, num_pages: page_id_t) -> Result<(), String> {
+        if self.free_space_map_of(file_id).is_some() {
+            return Err(format!("the free space map of file {} is open already", file_id));
+        }
+        let free_space_map = FreeSpaceMap::open(self, file_id, num_pages)?;
+        if file_id == MAIN_FILE_ID {
+            self.set_next_page_id(num_pages.max(free_space_map.get_num_pages()));
+        }
+        self.free_space_maps.write().unwrap().insert(file_id, Arc::new(free_space_map));
+        Ok(())
+    }
+
+    fn free_space_map_of(&self, file_id: file_id_t) -> Option<Arc<FreeSpaceMap>> {
+        self.free_space_maps.read().unwrap().get(&file_id).cloned()
+    }
+
+    // the free bytes of the page as recorded in the free space map, None if it is not allocated or there is no map
+    pub fn get_free_space(&self, page_id: page_id_t) -> Option<usize> {
+        self.free_space_map_of(PageAddr::file_of(page_id))?.get_free_space(page_id)
+    }
+
+    pub fn update_free_space(&self, page_id: page_id_t, free_bytes: usize) -> Result<(), String> {
+        match self.free_space_map_of(PageAddr::file_of(page_id)) {
+            None => Ok(()),
+            Some(free_space_map) => free_space_map.set_free_space(self, page_id, free_bytes)
+                .map_err(|err| format!("can not record the free space of page {}, {}", page_id, err)),
+        }
+    }
+
+    fn deallocate_page(&self, page_id: page_id_t) -> Result<(), String> {
+        match self.free_space_map_of(PageAddr::file_of(page_id)) {
+            None => Ok(()),
+            Some(free_space_map) => free_space_map.free_page(self, page_id)
+                .map_err(|err| format!("can not free page {}, {}", page_id, err)),
+        }
+    }
+
+    // start recording the page ids of new_page and fetch_page
+    pub fn start_trace(&self) {
+        self.trace.lock().unwrap().clear();
+        self.tracing.store(true, Ordering::SeqCst);
+    }
+
+    // stop recording and return the page accesses since start_trace
+    pub fn take_trace(&self) -> Vec<page_id_t> {
+        self.tracing.store(false, Ordering::SeqCst);
+        std::mem::take(&mut *self.trace.lock().unwrap())
+    }
+
+    fn record_trace(&self, page_id: page_id_t) {
+        if self.tracing.load(Ordering::Relaxed) {
+            self.trace.lock().unwrap().push(page_id);
+        }
+    }
+}
+
+
+impl BufferPoolInstance {
+    fn new(index: usize, num_instances: usize, pool_size: usize, disk_mgr: Arc<dyn PageStore>, policy: ReplacerPolicy, lgr: Arc<LogManager>) -> Self {
+        let mut bpm = BufferPoolManager_ {
+            pool_size: pool_size,
+            next_page_id: index as page_id_t,
+            pages: Vec::new(),
+            replacer: policy.create(pool_size),
+            page_table: HashMap::new(),
+            free_list: LinkedList::new(),
+            io_in_progress: vec![false; pool_size],
+            writing_pages: HashMap::new(),
+            in_ring: vec![false; pool_size],
+            prefetching: HashMap::new(),
+        };
+
+        bpm.pages.reserve(pool_size);
+        for _ in 0..pool_size {
+            bpm.pages.push(Page::new_with_size(disk_mgr.get_page_size()));
+        }
+
+        for i in 0..pool_size {
+            bpm.free_list.push_back(i);
+        }
+
+        Self {
+            state: Mutex::new(bpm),
+            io_done: Condvar::new(),
+            disk_scheduler: DiskScheduler::new(disk_mgr),
+            log_manager: lgr,
+            index,
+            num_instances,
+        }
+    }
+
+    fn new_page(&self, strategy: Option<&BufferAccessStrategy>) -> Option<PageRef> {
         let mut _lock = self.state.lock().unwrap();
 
-        let mut find_idx = usize::MAX;
-        let frames = &mut _lock.pages;
+        // the id is taken only when there is a frame for it
+        let pid = _lock.next_page_id;
+        let (fid, victim) = self.take_frame_for(&mut _lock, strategy, pid)?;
+        _lock.next_page_id += self.num_instances as page_id_t;
+        self.pin_frame(&mut _lock, fid, pid, strategy);
+        self.load_frame(_lock, fid, victim, false).ok()
+    }
 
-        for idx in 0..frames.len() {
-            let page = &frames[idx];
-            if page.page_id == page_id {
-                find_idx = idx;
-                break;
+    // a new page with the id given by the free space map, a page read ahead before it was freed is dropped
+    fn new_page_at(&self, page_id: page_id_t, strategy: Option<&BufferAccessStrategy>) -> Option<PageRef> {
+        let mut _lock = self.state.lock().unwrap();
+        loop {
+            match _lock.page_table.get(&page_id).cloned() {
+                Some(fid) if _lock.io_in_progress[fid] => {
+                    _lock = self.wait_io(_lock, fid);
+                    continue;
+                },
+                Some(fid) if _lock.pages[fid].pin_count > 0 => return None,
+                Some(fid) => Self::discard_frame(&mut _lock, fid),
+                None if _lock.writing_pages.contains_key(&page_id) => {},
+                None => break,
             }
+            _lock = self.io_done.wait(_lock).unwrap();
+        }
+
+        let (fid, victim) = self.take_frame_for(&mut _lock, strategy, page_id)?;
+        // the ids of the other files are not the ones new_page gives
+        if PageAddr::file_of(page_id) == MAIN_FILE_ID && page_id >= _lock.next_page_id {
+            _lock.next_page_id = page_id + self.num_instances as page_id_t;
         }
+        self.pin_frame(&mut _lock, fid, page_id, strategy);
+        self.load_frame(_lock, fid, victim, false).ok()
+    }
+
+    fn fetch_page(&self, page_id: page_id_t, strategy: Option<&BufferAccessStrategy>) -> Result<PageRef, String> {
+        let mut _lock = self.state.lock().unwrap();
+
+        // check if in page_table, a page on its way in or out is waited for
+        loop {
+            match _lock.page_table.get(&page_id).cloned() {
+                Some(fid) if _lock.io_in_progress[fid] => {
+                    _lock = self.wait_io(_lock, fid);
+                    continue;
+                },
+                Some(fid) => {
+                    let page = unsafe { &mut *(&mut _lock.pages[fid] as *mut Page) };
+                    page.pin_count += 1;
+                    _lock.replacer.record_access(fid);
+                    _lock.replacer.set_evictable(fid, false);
+                    // somebody else wants it, the ring leaves it to the replacer
+                    if !strategy.map_or(false, |strategy| strategy.uses_ring()) {
+                        _lock.in_ring[fid] = false;
+                    }
+                    return Ok(page);
+                },
+                None if _lock.writing_pages.contains_key(&page_id) => {},
+                None => break,
+            }
+            _lock = self.io_done.wait(_lock).unwrap();
+        }
+
+        // then take a frame, read data
+        let (fid, victim) = self.take_frame_for(&mut _lock, strategy, page_id)
+            .ok_or_else(|| format!("no free frame for page {}", page_id))?;
+        self.pin_frame(&mut _lock, fid, page_id, strategy);
+        self.load_frame(_lock, fid, victim, true)
+    }
 
-        if usize::MAX == find_idx {
+    // start reading the page into a frame without waiting for it, false if it is in the pool already,
+    // not allocated, or there is no frame for it
+    fn prefetch_page(&self, page_id: page_id_t, strategy: Option<&BufferAccessStrategy>) -> bool {
+        let mut _lock = self.state.lock().unwrap();
+        if page_id < 0 || page_id >= _lock.next_page_id
+            || _lock.page_table.contains_key(&page_id) || _lock.writing_pages.contains_key(&page_id) {
+            return false;
+        }
+        let Some((fid, victim)) = self.take_frame_for(&mut _lock, strategy, page_id) else {
             return false;
-        } 
-        let page = &mut frames[find_idx];
+        };
+        self.pin_frame(&mut _lock, fid, page_id, strategy);
+        let page = unsafe { &mut *(&mut _lock.pages[fid] as *mut Page) };
+        drop(_lock);
+
+        // only the read is left to the scheduler, a dirty victim is written out first,
+        // a victim which can not be written keeps its frame and nothing is read ahead
+        if let Some(victim) = victim {
+            if Self::write_page_data(&self.disk_scheduler, &self.log_manager, victim, page).is_err() {
+                let mut _lock = self.state.lock().unwrap();
+                self.restore_victim(&mut _lock, fid, victim);
+                return false;
+            }
+        }
+        page.data.fill(0);
+        let request = DiskScheduler::create_request(false, page.get_mut_data(), page_id);
+        self.disk_scheduler.schedule(Some(request.clone()));
+
+        let mut _lock = self.state.lock().unwrap();
+        if let Some(victim) = victim {
+            _lock.writing_pages.remove(&victim);
+        }
+        _lock.prefetching.insert(fid, request);
+        self.io_done.notify_all();
+        true
+    }
+
+    // wait for the i/o on the frame, a prefetch is waited for on its request and then finished
+    fn wait_io<'a>(&'a self, lock_guard: MutexGuard<'a, BufferPoolManager_>, fid: frame_id_t) -> MutexGuard<'a, BufferPoolManager_> {
+        let Some(request) = lock_guard.prefetching.get(&fid).cloned() else {
+            return self.io_done.wait(lock_guard).unwrap();
+        };
+        drop(lock_guard);
+        request.callback.wait();
+
+        let mut _lock = self.state.lock().unwrap();
+        self.finish_prefetch(&mut _lock, fid);
+        _lock
+    }
+
+    // a done prefetch gives its pin back, then the page is like any other one in the pool.
+    // a corrupted one is dropped, the fetch of it reads it again and gets the error
+    fn finish_prefetch(&self, lock_guard: &mut MutexGuard<BufferPoolManager_>, fid: frame_id_t) {
+        let result = match lock_guard.prefetching.get(&fid) {
+            Some(request) if request.callback.is_completed() => request.callback.wait_result(),
+            _ => return,
+        };
+        lock_guard.prefetching.remove(&fid);
+        lock_guard.io_in_progress[fid] = false;
+        if result.map_or_else(|err| err.starts_with(PAGE_CORRUPTED), |_| false) {
+            Self::discard_frame(lock_guard, fid);
+            self.io_done.notify_all();
+            return;
+        }
+        let page = &mut lock_guard.pages[fid];
+        page.pin_count -= 1;
+        if 0 == page.pin_count {
+            lock_guard.replacer.set_evictable(fid, true);
+        }
+        self.io_done.notify_all();
+    }
+
+    fn unpin_page(&self, page_id: page_id_t, is_dirty: bool) -> bool {
+        let mut _lock = self.state.lock().unwrap();
+
+        let Some(fid) = _lock.page_table.get(&page_id).cloned() else {
+            return false;
+        };
+        let page = &mut _lock.pages[fid];
         if 0 == page.pin_count {
             return false;
         }
@@ -254,71 +539,103 @@ impl BufferPoolManager {
         // then decrease pin_count
         page.pin_count -= 1;
         if 0 == page.pin_count {
-            _lock.lru_replacer.set_evictable(find_idx, true);
+            _lock.replacer.set_evictable(fid, true);
         }
         return true;
     }
 
-    pub fn flush_page(&self, page_id: page_id_t) -> bool {
+    fn flush_page(&self, page_id: page_id_t) -> bool {
         let mut _lock = self.state.lock().unwrap();
 
-        let val = _lock.page_table.get(&page_id);
+        let fid = loop {
+            match _lock.page_table.get(&page_id).cloned() {
+                None => return false,
+                Some(fid) if _lock.io_in_progress[fid] => {
+                    _lock = self.wait_io(_lock, fid);
+                },
+                Some(fid) => break fid,
+            }
+        };
 
-        match val {
-            None => {
-                return false;
-            },
-            Some(frame_id) => {
-                let frame_id = *frame_id;
-                
-                let frame = unsafe { &mut *(&mut _lock.pages[frame_id] as *mut Page)};
-                if frame.page_id != page_id {
-                    return false;
-                }
+        // pinned for the write so that it is not evicted under it
+        let frame = unsafe { &mut *(&mut _lock.pages[fid] as *mut Page) };
+        frame.pin_count += 1;
+        _lock.replacer.set_evictable(fid, false);
+        _lock.io_in_progress[fid] = true;
+        // cleared before the write, a change in the meantime makes it dirty again
+        frame.is_dirty = false;
+        drop(_lock);
+
+        // the page may be pinned and changed by a write guard, the read latch keeps the copy on disk whole.
+        // the callers drop their guards of the page before flushing it
+        let latch = unsafe { &*(frame as *const Page) }.get_read_lock();
+        let res = Self::write_page_data(&self.disk_scheduler, &self.log_manager, page_id, frame);
+        drop(latch);
 
-                Self::write_page_data(&_lock.disk_scheduler, page_id, frame);
-                frame.is_dirty = false;
-                return true;
-            }
+        let mut _lock = self.state.lock().unwrap();
+        if res.is_err() {
+            frame.is_dirty = true;
+        }
+        _lock.io_in_progress[fid] = false;
+        frame.pin_count -= 1;
+        if 0 == frame.pin_count {
+            _lock.replacer.set_evictable(fid, true);
         }
+        self.io_done.notify_all();
+        return res.is_ok();
     }
 
-    pub fn flush_all_pages(&mut self) {
-
+    // the first id of this instance from page_id on
+    fn set_next_page_id(&self, page_id: page_id_t) {
+        let n = self.num_instances as page_id_t;
+        let offset = (self.index as page_id_t - page_id).rem_euclid(n);
+        self.state.lock().unwrap().next_page_id = page_id + offset;
     }
 
-    /// delete page in bufferpool, if not exists, return false
-    /// if pin_count > 0, then return false
-    /// else remove lru record, and bufferpool , then move it to free_list
-    pub fn delete_page(&self, page_id: page_id_t) -> bool {
-        let mut _lock = self.state.lock().unwrap();
+    fn flush_all_pages(&self) {
+        let page_ids: Vec<page_id_t> = {
+            let _lock = self.state.lock().unwrap();
+            _lock.pages.iter()
+                .filter(|page| page.page_id != INVALID_PAGE_ID && page.is_dirty)
+                .map(|page| page.page_id)
+                .collect()
+        };
+        for page_id in page_ids {
+            self.flush_page(page_id);
+        }
 
-        let mut find_idx: usize = INVALID_FRAME_ID;
-        for idx in 0.._lock.pages.len() {
-            if _lock.pages[idx].get_pid() == page_id {
-                find_idx = idx;
-                break;
-            }
+        // the evicted ones are on disk only after their writes are done
+        let mut _lock = self.state.lock().unwrap();
+        while !_lock.writing_pages.is_empty() {
+            _lock = self.io_done.wait(_lock).unwrap();
         }
+    }
+
+    fn get_dirty_pages(&self) -> Vec<(page_id_t, lsn_t)> {
+        let _lock = self.state.lock().unwrap();
+        _lock.pages.iter()
+            .filter(|page| page.page_id != INVALID_PAGE_ID && (page.is_dirty || page.pin_count > 0))
+            .map(|page| (page.page_id, page.rec_lsn))
+            .chain(_lock.writing_pages.iter().map(|(pid, rec_lsn)| (*pid, *rec_lsn)))
+            .collect()
+    }
+
+    fn delete_page(&self, page_id: page_id_t) -> bool {
+        let mut _lock = self.state.lock().unwrap();
 
-        if INVALID_FRAME_ID == find_idx {
+        let Some(fid) = _lock.page_table.get(&page_id).cloned() else {
             return true;
-        }
+        };
 
-        // check pinnable
-        let p = &_lock.pages[find_idx];
-        if p.get_pincount() > 0 {
+        // check pinnable, a frame with i/o in progress is pinned too
+        if _lock.pages[fid].get_pincount() > 0 {
             return false;
         }
 
         _lock.page_table.remove(&page_id);
-        _lock.lru_replacer.remove(find_idx);
-
-        let p = &mut _lock.pages[find_idx];
-        Self::reset_meta(p, page_id);
-        _lock.free_list.push_back(find_idx);
-        
-        self.deallocate_page(page_id);
+        _lock.replacer.remove(fid);
+        Self::reset_meta(&mut _lock.pages[fid], INVALID_PAGE_ID);
+        _lock.free_list.push_back(fid);
         return true;
     }
 
@@ -326,35 +643,190 @@ impl BufferPoolManager {
     // 
     // 
 
-    /// this is not a safe guard function, better surroundded with lock
-    fn read_page_data(disk_scheduler: &DiskScheduler, page_id: page_id_t, page: &mut Page) {
+    // a frame for a page to come in, the free list first, then the victim of the replacer.
+    // the victim leaves the page table, a dirty one is returned to be written out of the latch
+    fn take_frame(lock_guard: &mut MutexGuard<BufferPoolManager_>) -> Option<(frame_id_t, Option<page_id_t>)> {
+        if let Some(fid) = lock_guard.free_list.pop_front() {
+            return Some((fid, None));
+        }
+        let fid = lock_guard.replacer.evict()?;
+        Some((fid, Self::release_frame(lock_guard, fid)))
+    }
+
+    // the frame for page_id with an access strategy.
+    // the pages read ahead and never fetched are left to the replacer once they are in,
+    // and when every frame is pinned and some of them by the reads ahead, those reads are waited for
+    fn take_frame_for(&self, lock_guard: &mut MutexGuard<BufferPoolManager_>, strategy: Option<&BufferAccessStrategy>, page_id: page_id_t) -> Option<(frame_id_t, Option<page_id_t>)> {
+        if let Some(taken) = self.take_frame_by(lock_guard, strategy, page_id) {
+            return Some(taken);
+        }
+        // the pages read ahead keep their pins until they are fetched, they are given up only when
+        // there is no other frame, a replacer would evict them first as they are accessed once
+        if lock_guard.prefetching.is_empty() {
+            return None;
+        }
+        self.finish_prefetches(lock_guard, false);
+        if let Some(taken) = self.take_frame_by(lock_guard, strategy, page_id) {
+            return Some(taken);
+        }
+        self.finish_prefetches(lock_guard, true);
+        self.take_frame_by(lock_guard, strategy, page_id)
+    }
+
+    fn finish_prefetches(&self, lock_guard: &mut MutexGuard<BufferPoolManager_>, wait: bool) {
+        let prefetched: Vec<(frame_id_t, Arc<DiskRequest>)> = lock_guard.prefetching.iter()
+            .map(|(fid, request)| (*fid, request.clone()))
+            .collect();
+        for (fid, request) in prefetched {
+            if wait {
+                request.callback.wait();
+            }
+            self.finish_prefetch(lock_guard, fid);
+        }
+    }
+
+    // a ring takes its oldest frame again if nobody else uses it, otherwise the frame comes from take_frame and joins the ring
+    fn take_frame_by(&self, lock_guard: &mut MutexGuard<BufferPoolManager_>, strategy: Option<&BufferAccessStrategy>, page_id: page_id_t) -> Option<(frame_id_t, Option<page_id_t>)> {
+        let Some(strategy) = strategy.filter(|strategy| strategy.uses_ring()) else {
+            return Self::take_frame(lock_guard);
+        };
+
+        let reusable = strategy.pop_full(self.index, self.num_instances)
+            .filter(|(fid, old_pid)| lock_guard.in_ring[*fid]
+                && lock_guard.page_table.get(old_pid) == Some(fid)
+                && lock_guard.pages[*fid].pin_count == 0);
+        let taken = match reusable {
+            Some((fid, _)) => {
+                lock_guard.replacer.remove(fid);
+                Some((fid, Self::release_frame(lock_guard, fid)))
+            },
+            None => Self::take_frame(lock_guard),
+        }?;
+        strategy.push(self.index, taken.0, page_id);
+        Some(taken)
+    }
+
+    // the page in the frame leaves the page table, a dirty one is returned to be written out of the latch
+    fn release_frame(lock_guard: &mut MutexGuard<BufferPoolManager_>, fid: frame_id_t) -> Option<page_id_t> {
+        let (victim, is_dirty, rec_lsn) = {
+            let page = &lock_guard.pages[fid];
+            (page.page_id, page.is_dirty, page.rec_lsn)
+        };
+        lock_guard.page_table.remove(&victim);
+        if !is_dirty {
+            return None;
+        }
+        lock_guard.writing_pages.insert(victim, rec_lsn);
+        Some(victim)
+    }
+
+    // give the frame to page_id, pinned and marked with i/o in progress.
+    // the data are left for the write-back of the victim
+    fn pin_frame(&self, lock_guard: &mut MutexGuard<BufferPoolManager_>, fid: frame_id_t, page_id: page_id_t, strategy: Option<&BufferAccessStrategy>) {
+        let rec_lsn = self.log_manager.get_next_lsn();
+        let page = &mut lock_guard.pages[fid];
+        page.page_id = page_id;
+        page.is_dirty = false;
+        page.pin_count = 1;
+        page.rec_lsn = rec_lsn;
+        lock_guard.io_in_progress[fid] = true;
+        lock_guard.in_ring[fid] = strategy.map_or(false, |strategy| strategy.uses_ring());
+        lock_guard.replacer.map_page(fid, page_id);
+        lock_guard.replacer.record_access(fid);
+        lock_guard.replacer.set_evictable(fid, false);
+        lock_guard.page_table.insert(page_id, fid);
+    }
+
+    // write out the victim and read in the page with the latch released, then wake up the waiters
+    // a page failing its checksum gives its frame back, the error is returned
+    fn load_frame(&self, mut lock_guard: MutexGuard<BufferPoolManager_>, fid: frame_id_t, victim: Option<page_id_t>, read: bool) -> Result<PageRef, String> {
+        let page = unsafe { &mut *(&mut lock_guard.pages[fid] as *mut Page) };
+        drop(lock_guard);
+
+        // a victim which can not be written keeps its frame, the page is not loaded and the error is returned
+        if let Some(victim) = victim {
+            if let Err(err) = Self::write_page_data(&self.disk_scheduler, &self.log_manager, victim, page) {
+                let mut _lock = self.state.lock().unwrap();
+                self.restore_victim(&mut _lock, fid, victim);
+                return Err(format!("can not write out page {} for page {}: {}", victim, page.page_id, err));
+            }
+        }
+        page.data.fill(0);
+        // a page beyond the end of the file is not written yet, it stays zeroed
+        let read_result = match read {
+            true => Self::read_page_data(&self.disk_scheduler, page.page_id, page),
+            false => Ok(()),
+        };
+
+        let mut _lock = self.state.lock().unwrap();
+        if let Some(victim) = victim {
+            _lock.writing_pages.remove(&victim);
+        }
+        _lock.io_in_progress[fid] = false;
+        self.io_done.notify_all();
+        match read_result {
+            Err(err) if err.starts_with(PAGE_CORRUPTED) => {
+                Self::discard_frame(&mut _lock, fid);
+                Err(err)
+            },
+            _ => Ok(page),
+        }
+    }
+
+    // the write-back of the victim failed, its data are still in the frame, so the frame goes back to it,
+    // dirty and evictable. the page which was to come in leaves the page table and its waiters read it again
+    fn restore_victim(&self, lock_guard: &mut MutexGuard<BufferPoolManager_>, fid: frame_id_t, victim: page_id_t) {
+        let page_id = lock_guard.pages[fid].page_id;
+        lock_guard.page_table.remove(&page_id);
+        let rec_lsn = lock_guard.writing_pages.remove(&victim).unwrap_or(lock_guard.pages[fid].rec_lsn);
+        let page = &mut lock_guard.pages[fid];
+        page.page_id = victim;
+        page.is_dirty = true;
+        page.pin_count = 0;
+        page.rec_lsn = rec_lsn;
+        lock_guard.io_in_progress[fid] = false;
+        lock_guard.in_ring[fid] = false;
+        lock_guard.page_table.insert(victim, fid);
+        lock_guard.replacer.map_page(fid, victim);
+        lock_guard.replacer.set_evictable(fid, true);
+        self.io_done.notify_all();
+    }
+
+    // the page in the frame is dropped without being written, and the frame goes back to the free list
+    fn discard_frame(lock_guard: &mut MutexGuard<BufferPoolManager_>, fid: frame_id_t) {
+        let page_id = lock_guard.pages[fid].page_id;
+        lock_guard.page_table.remove(&page_id);
+        lock_guard.replacer.set_evictable(fid, true);
+        lock_guard.replacer.remove(fid);
+        lock_guard.in_ring[fid] = false;
+        Self::reset_meta(&mut lock_guard.pages[fid], INVALID_PAGE_ID);
+        lock_guard.free_list.push_back(fid);
+    }
+
+    /// this is not a safe guard function, the frame is pinned and marked with i/o in progress
+    fn read_page_data(disk_scheduler: &DiskScheduler, page_id: page_id_t, page: &mut Page) -> Result<(), String> {
         let request = DiskScheduler::create_request(
             false, 
             page.get_mut_data(), 
             page_id);
         disk_scheduler.schedule(Some(request.clone()));
-        request.callback.wait();
+        request.callback.wait_result()
     }
 
-    /// this is not a safe guard function, better surroundded with lock
-    fn write_page_data(disk_scheduler: &DiskScheduler, page_id: page_id_t, page: &mut Page) {
+    /// this is not a safe guard function, the frame is pinned and marked with i/o in progress.
+    /// the WAL rule: the log records of the changes on the page go to disk before the page,
+    /// the page is not written when they can not be, the redo brings back the committed changes from the log on disk
+    fn write_page_data(disk_scheduler: &DiskScheduler, log_manager: &LogManager, page_id: page_id_t, page: &mut Page) -> Result<(), String> {
+        log_manager.flush(page.get_lsn())?;
+        // taken before the write, a change logged in the middle may miss the disk
+        page.rec_lsn = log_manager.get_next_lsn();
         // let page = unsafe { &mut *page };
         let request = DiskScheduler::create_request(
             true, 
             page.get_mut_data(), 
             page_id);
         disk_scheduler.schedule(Some(request.clone()));
-        request.callback.wait();
-    }
-
-    fn allocate_page(&self, lock_guard: &mut MutexGuard<BufferPoolManager_>) -> page_id_t {
-        let pid = lock_guard.page_id_generator.fetch_add(1, Ordering::SeqCst);
-        return pid; 
-    }
-
-    fn deallocate_page(&self, page_id: page_id_t) {
-        // rightnow, it is a no-op 
-
+        request.callback.wait_result()
     }
 
     fn reset_meta(page: &mut Page, page_id: page_id_t) {
@@ -366,33 +838,43 @@ impl BufferPoolManager {
 }
 
 
+// the scheduler may still read into the frames
+impl Drop for BufferPoolInstance {
+    fn drop(&mut self) {
+        let _lock = self.state.lock().unwrap();
+        for request in _lock.prefetching.values() {
+            request.callback.wait();
+        }
+    }
+}
+
 
 #[cfg(test)]
 mod tests {
     
-    use std::{borrow::BorrowMut, fs, sync::Arc, thread};
+    use std::{borrow::BorrowMut, fs, os::unix::fs::FileExt, sync::{atomic::{AtomicBool, Ordering}, Arc}, thread};
 
     use rand::{seq::SliceRandom, Rng};
 
-    use crate::storage::page_based::{disk::{disk_manager::DiskManager, log_manager::LogManager}, page::page::Page};
+    use crate::{common::config::{page_id_t, PAGE_SIZE}, storage::page_based::{disk::{disk_manager::{DiskManager, PageStore}, log_manager::LogManager, memory_page_store::MemoryPageStore}, page::page::{Page, COMMON_PAGE_HEADER_SIZE, PAGE_CORRUPTED}}};
 
-    use super::{BufferPoolManager};
+    use super::{AccessIntent, BufferAccessStrategy, BufferPoolManager, ReplacerPolicy};
 
     fn compare_value(expected_str: &str, page: &Page) -> bool {
         let len = expected_str.len();
         let page_data = page.get_data();
-        page_data[..len] == expected_str.as_bytes().to_vec()
+        page_data[COMMON_PAGE_HEADER_SIZE..COMMON_PAGE_HEADER_SIZE + len] == expected_str.as_bytes().to_vec()
     }
 
     fn fill_value(page: &mut Page, fill_val: &str) {
         let len = fill_val.len();
         let page_data = page.get_mut_data();
-        page_data[..len].copy_from_slice(fill_val.as_bytes());
+        page_data[COMMON_PAGE_HEADER_SIZE..COMMON_PAGE_HEADER_SIZE + len].copy_from_slice(fill_val.as_bytes());
     }
 
     fn create_bpm(pool_size: usize, k: usize) -> BufferPoolManager {
         // setup
-        let disk_manager = DiskManager::new("test.db").unwrap();
+        let disk_manager = MemoryPageStore::new();
         let bpm = BufferPoolManager::new(pool_size, Arc::new(disk_manager), 
             k, Arc::new(LogManager::new()));
         bpm
@@ -400,12 +882,11 @@ mod tests {
 
     #[test]
     fn sample_test() {
-        let db_name = "test.db";
         let buffer_pool_size = 10;
         let k = 5;
 
         // setup
-        let disk_manager = DiskManager::new(&db_name).unwrap();
+        let disk_manager = MemoryPageStore::new();
         let bpm = BufferPoolManager::new(buffer_pool_size, Arc::new(disk_manager), 
             k, Arc::new(LogManager::new()));
 
@@ -415,7 +896,7 @@ mod tests {
 
         let buf: &mut Vec<u8> = &mut page.data;
         let s = "hello";
-        buf[0..s.len()].copy_from_slice(s.as_bytes());
+        buf[COMMON_PAGE_HEADER_SIZE..COMMON_PAGE_HEADER_SIZE + s.len()].copy_from_slice(s.as_bytes());
 
         // fill up the buffer pool
         for i in 1..buffer_pool_size {
@@ -439,7 +920,7 @@ mod tests {
         // then fetch page 0. cmp data wrote before
         let page = bpm.fetch_page(0).unwrap();
         let buf = &page.borrow_mut().data;
-        println!("{:#?}", String::from_utf8(buf[..s.len()].to_vec()).unwrap_or_default());
+        println!("{:#?}", String::from_utf8(buf[COMMON_PAGE_HEADER_SIZE..COMMON_PAGE_HEADER_SIZE + s.len()].to_vec()).unwrap_or_default());
         assert_eq!(true, bpm.unpin_page(0, true));
 
         // then new page, and could not fetch page 0
@@ -451,12 +932,11 @@ mod tests {
 
     #[test]
     fn binary_data_test() {
-        let db_name = "test.db";
         let buffer_pool_size = 10;
         let k = 5;
 
         // setup
-        let disk_manager = DiskManager::new(&db_name).unwrap();
+        let disk_manager = MemoryPageStore::new();
         let bpm = BufferPoolManager::new(buffer_pool_size, Arc::new(disk_manager), 
             k, Arc::new(LogManager::new()));
 
@@ -502,20 +982,20 @@ mod tests {
             assert_ne!(None, bpm.new_page());
         }
 
+        // the header has the checksum now
         let page = bpm.fetch_page(0).unwrap();
         let recv_buf = &page.borrow_mut().data;
 
-        assert_eq!(true, buf == *recv_buf);
+        assert_eq!(true, buf[COMMON_PAGE_HEADER_SIZE..] == recv_buf[COMMON_PAGE_HEADER_SIZE..]);
     }
 
     #[test]
     fn new_paee_test() {
-        let db_name = "test.db";
         let buffer_pool_size = 10;
         let k = 5;
 
         // setup
-        let disk_manager = DiskManager::new(&db_name).unwrap();
+        let disk_manager = MemoryPageStore::new();
         let bpm = BufferPoolManager::new(buffer_pool_size, Arc::new(disk_manager), 
             k, Arc::new(LogManager::new()));
 
@@ -530,7 +1010,7 @@ mod tests {
             let data = page.get_mut_data();
             // copy data
             let fake_data = format!("{}", i);
-            data[..fake_data.len()].copy_from_slice(fake_data.as_bytes());
+            data[COMMON_PAGE_HEADER_SIZE..COMMON_PAGE_HEADER_SIZE + fake_data.len()].copy_from_slice(fake_data.as_bytes());
             page_ids.push(page.get_pid());
         }
 
@@ -570,20 +1050,11 @@ mod tests {
         for i in 0..100 {
             assert_eq!(None, bpm.new_page());
         }
-        
-        match fs::remove_file("test.db") {
-            Ok(_) => {
-                println!("")
-            },
-            Err(er) => {
-                println!("remove file failed, {}", er);
-            }
-        }
     }
 
     #[test]
     fn unpin_page_test() {
-        let disk_mgr = DiskManager::new("test.db").unwrap();
+        let disk_mgr = MemoryPageStore::new();
         let log_mgr = LogManager::new();
         let bpm = BufferPoolManager::new(2, Arc::new(disk_mgr), 5, Arc::new(log_mgr));
         let mut pid0 = 0;
@@ -593,7 +1064,7 @@ mod tests {
             let page0 = bpm.new_page();
             assert_ne!(None, page0);
             let page0 = page0.unwrap();
-            page0.data[.."page0".len()].copy_from_slice("page0".as_bytes());
+            page0.data[COMMON_PAGE_HEADER_SIZE..COMMON_PAGE_HEADER_SIZE + "page0".len()].copy_from_slice("page0".as_bytes());
 
             pid0 = page0.get_pid();
         }
@@ -603,7 +1074,7 @@ mod tests {
             let page1 = bpm.new_page();
             assert_ne!(None, page1);
             let page1 = page1.unwrap();
-            page1.data[.."page1".len()].copy_from_slice("page1".as_bytes());
+            page1.data[COMMON_PAGE_HEADER_SIZE..COMMON_PAGE_HEADER_SIZE + "page1".len()].copy_from_slice("page1".as_bytes());
             pid1 = page1.get_pid();
         }
 
@@ -624,11 +1095,11 @@ mod tests {
         let page0_dt = "page0";
         let page0 = bpm.fetch_page(pid0);
         let page0 = page0.unwrap();
-        assert_eq!(true, page0.data[..page0_dt.len()] == "page0".as_bytes().to_vec());
+        assert_eq!(true, page0.data[COMMON_PAGE_HEADER_SIZE..COMMON_PAGE_HEADER_SIZE + page0_dt.len()] == "page0".as_bytes().to_vec());
 
         let page1 = bpm.fetch_page(pid1);
         let page1 = page1.unwrap();
-        assert_eq!(true, page1.data[..page0_dt.len()] == "page1".as_bytes().to_vec());
+        assert_eq!(true, page1.data[COMMON_PAGE_HEADER_SIZE..COMMON_PAGE_HEADER_SIZE + page0_dt.len()] == "page1".as_bytes().to_vec());
 
         assert_eq!(true, bpm.unpin_page(pid0, true));
         assert_eq!(true, bpm.unpin_page(pid1, true));
@@ -644,12 +1115,12 @@ mod tests {
         let page0 = bpm.fetch_page(pid0).unwrap();
         let dt1 = "page0updated";
         let page0_data = page0.get_mut_data();
-        page0_data[..dt1.len()].copy_from_slice(dt1.as_bytes());
+        page0_data[COMMON_PAGE_HEADER_SIZE..COMMON_PAGE_HEADER_SIZE + dt1.len()].copy_from_slice(dt1.as_bytes());
 
         let page1 = bpm.fetch_page(pid1).unwrap();
         let dt2 = "page1updated";
         let page1_data = page1.get_mut_data();
-        page1_data[..dt2.len()].copy_from_slice(dt2.as_bytes());
+        page1_data[COMMON_PAGE_HEADER_SIZE..COMMON_PAGE_HEADER_SIZE + dt2.len()].copy_from_slice(dt2.as_bytes());
 
         assert_eq!(true, bpm.unpin_page(pid0, false));
         assert_eq!(true, bpm.unpin_page(pid1, true));
@@ -657,13 +1128,11 @@ mod tests {
         // refetch p0,p1, check
         let page0 = bpm.fetch_page(pid0).unwrap();
         let p0_data = page0.get_data();
-        assert_eq!(true, p0_data[.."page0".len()] == "page0".as_bytes().to_vec());
+        assert_eq!(true, p0_data[COMMON_PAGE_HEADER_SIZE..COMMON_PAGE_HEADER_SIZE + "page0".len()] == "page0".as_bytes().to_vec());
 
         let page1 = bpm.fetch_page(pid1).unwrap();
         let p1_data = page1.get_data();
-        assert_eq!(true, p1_data[..dt2.len()] == dt2.as_bytes().to_vec());
-
-        let _ = fs::remove_file("test.db");
+        assert_eq!(true, p1_data[COMMON_PAGE_HEADER_SIZE..COMMON_PAGE_HEADER_SIZE + dt2.len()] == dt2.as_bytes().to_vec());
     }
 
 
@@ -852,9 +1321,9 @@ mod tests {
         assert_eq!(None, p);
 
         // delete 4 will fail, except unpinning it
-        assert_eq!(false, bpm.delete_page(4));
+        assert_eq!(Ok(false), bpm.delete_page(4));
         bpm.unpin_page(4, false);
-        assert_eq!(true, bpm.delete_page(4));
+        assert_eq!(Ok(true), bpm.delete_page(4));
 
         // new page will success
         let p = bpm.new_page();
@@ -882,7 +1351,7 @@ mod tests {
         bpm.unpin_page(7, false);
 
         // then delete p7
-        assert_eq!(true, bpm.delete_page(7));
+        assert_eq!(Ok(true), bpm.delete_page(7));
 
         // create new page will occupy page7 then get 5, 6
         bpm.new_page();
@@ -931,7 +1400,7 @@ mod tests {
         let p1_ref = &p1.is_dirty;
         assert_eq!(true, bpm.unpin_page(p1id, true));
         // assert_eq!(true, *p1_ref);
-        assert_eq!(true, bpm.delete_page(p1id));
+        assert_eq!(Ok(true), bpm.delete_page(p1id));
 
         // re-fetch p0
         let p0 = bpm.fetch_page(pid);
@@ -941,6 +1410,455 @@ mod tests {
 
     }
 
+    #[test]
+    fn flush_all_pages_test() {
+        let _ = fs::remove_file("test_flush_all.db");
+        let disk_mgr = Arc::new(DiskManager::new("test_flush_all.db").unwrap());
+        let bpm = BufferPoolManager::new(4, disk_mgr.clone(), 2, Arc::new(LogManager::new()));
+
+        let mut pids = Vec::new();
+        for i in 0..4 {
+            let page = bpm.new_page().unwrap();
+            fill_value(page, &format!("page{}", i));
+            pids.push(page.get_pid());
+        }
+        // the pinned pages may be changed, so they are taken as dirty
+        assert_eq!(4, bpm.get_dirty_pages().len());
+        for pid in &pids[..3] {
+            assert_eq!(true, bpm.unpin_page(*pid, true));
+        }
+
+        // the one still pinned is not marked dirty yet, it is left in the pool
+        bpm.flush_all_pages();
+        for (i, pid) in pids[..3].iter().enumerate() {
+            let mut page = Page::new();
+            disk_mgr.read_page(*pid, page.get_mut_data()).unwrap();
+            assert_eq!(true, compare_value(&format!("page{}", i), &page));
+        }
+        assert_eq!(vec![pids[3]], bpm.get_dirty_pages().iter().map(|(pid, _)| *pid).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn fetch_evicted_test() {
+        let _ = fs::remove_file("test_fetch_evicted.db");
+        let disk_mgr = Arc::new(DiskManager::new("test_fetch_evicted.db").unwrap());
+        let bpm = BufferPoolManager::new(8, disk_mgr, 2, Arc::new(LogManager::new()));
+
+        // ten times more pages than frames, the dirty ones are written back when evicted
+        let mut pids = Vec::new();
+        for _ in 0..80 {
+            let page = bpm.new_page().unwrap();
+            fill_value(page, &format!("page{}", page.get_pid()));
+            pids.push(page.get_pid());
+            assert_eq!(true, bpm.unpin_page(page.get_pid(), true));
+        }
+
+        pids.shuffle(&mut rand::thread_rng());
+        for pid in &pids {
+            let page = bpm.fetch_page(*pid).unwrap();
+            assert_eq!(*pid, page.get_pid());
+            assert_eq!(true, compare_value(&format!("page{}", pid), page));
+            assert_eq!(true, bpm.unpin_page(*pid, false));
+        }
+
+        // no frame left when all are pinned
+        let pinned: Vec<_> = pids[..8].iter().map(|pid| bpm.fetch_page(*pid).unwrap().get_pid()).collect();
+        assert_eq!(None, bpm.fetch_page(pids[8]));
+        assert_eq!(None, bpm.new_page());
+        // a pinned page can not be deleted, an unpinned one goes back to the free list
+        assert_eq!(Ok(false), bpm.delete_page(pinned[0]));
+        assert_eq!(true, bpm.unpin_page(pinned[0], false));
+        assert_eq!(Ok(true), bpm.delete_page(pinned[0]));
+        assert_eq!(false, bpm.unpin_page(pinned[0], false));
+        let page = bpm.fetch_page(pids[8]).unwrap();
+        assert_eq!(true, compare_value(&format!("page{}", pids[8]), page));
+    }
+
+    #[test]
+    fn replacer_policy_test() {
+        for policy in [ReplacerPolicy::LruK(2), ReplacerPolicy::Clock, ReplacerPolicy::TwoQueue, ReplacerPolicy::Arc] {
+            let filename = format!("test_policy_{:?}.db", policy).replace(['(', ')'], "");
+            let _ = fs::remove_file(&filename);
+            let disk_mgr = Arc::new(DiskManager::new(&filename).unwrap());
+            let bpm = BufferPoolManager::new_with_replacer(2, 8, disk_mgr, policy, Arc::new(LogManager::new()));
+
+            let mut pids = Vec::new();
+            for _ in 0..80 {
+                let page = bpm.new_page().unwrap();
+                fill_value(page, &format!("page{}", page.get_pid()));
+                pids.push(page.get_pid());
+                assert_eq!(true, bpm.unpin_page(page.get_pid(), true));
+            }
+
+            let mut rand_eng = rand::thread_rng();
+            for _ in 0..400 {
+                let pid = *pids.choose(&mut rand_eng).unwrap();
+                let page = bpm.fetch_page(pid).unwrap();
+                assert_eq!(true, compare_value(&format!("page{}", pid), page));
+                assert_eq!(true, bpm.unpin_page(pid, rand_eng.gen_bool(0.5)));
+            }
+
+            // the pinned frames are never the victims
+            let pinned: Vec<_> = pids.iter().filter(|pid| *pid % 2 == 0).take(4)
+                .map(|pid| bpm.fetch_page(*pid).unwrap().get_pid()).collect();
+            assert_eq!(None, bpm.fetch_page(pids.iter().cloned().find(|pid| pid % 2 == 0 && !pinned.contains(pid)).unwrap()));
+            let _ = fs::remove_file(&filename);
+        }
+    }
+
+    fn starts_with(data: &[u8; 24], expected_str: &str) -> bool {
+        data[COMMON_PAGE_HEADER_SIZE..].starts_with(expected_str.as_bytes())
+    }
+
+    fn is_resident(bpm: &BufferPoolManager, page_id: page_id_t) -> bool {
+        bpm.instance_of(page_id).state.lock().unwrap().page_table.contains_key(&page_id)
+    }
+
+    #[test]
+    fn access_strategy_test() {
+        let _ = fs::remove_file("test_access_strategy.db");
+        let disk_mgr = Arc::new(DiskManager::new("test_access_strategy.db").unwrap());
+        // plain lru, a normal scan pushes everything out
+        let bpm = BufferPoolManager::new_with_replacer(1, 32, disk_mgr, ReplacerPolicy::LruK(1), Arc::new(LogManager::new()));
+
+        let table: Vec<_> = (0..200).map(|_| {
+            let page = bpm.new_page().unwrap();
+            fill_value(page, &format!("page{}", page.get_pid()));
+            bpm.unpin_page(page.get_pid(), true);
+            page.get_pid()
+        }).collect();
+        let hot: Vec<_> = (0..8).map(|_| {
+            let page = bpm.new_page().unwrap();
+            bpm.unpin_page(page.get_pid(), true);
+            page.get_pid()
+        }).collect();
+        let touch = |pids: &[page_id_t]| for pid in pids {
+            bpm.fetch_page(*pid).unwrap();
+            bpm.unpin_page(*pid, false);
+        };
+        touch(&hot);
+
+        // the scan reads through a ring of 4 frames, the point lookup in the middle keeps its page
+        let strategy = BufferAccessStrategy::with_ring_size(AccessIntent::SequentialScan, 4);
+        for pid in table.iter() {
+            let guard = bpm.fetch_page_read_with(*pid, &strategy).unwrap();
+            assert_eq!(true, starts_with(guard.get_as::<[u8; 24]>(), &format!("page{}", pid)));
+            drop(guard);
+            if *pid == table[100] {
+                touch(&[*pid]);
+            }
+        }
+        assert_eq!(true, hot.iter().all(|pid| is_resident(&bpm, *pid)));
+        assert_eq!(true, is_resident(&bpm, table[100]));
+        // the first 176 pages were not in the pool before the scan
+        assert_eq!(true, table[..176].iter().filter(|pid| is_resident(&bpm, **pid)).count() <= 4 + 1);
+
+        // a bulk write does the same with the new pages, the ones it leaves are written out
+        touch(&hot);
+        let strategy = BufferAccessStrategy::with_ring_size(AccessIntent::BulkWrite, 4);
+        let written: Vec<_> = (0..100).map(|_| {
+            let mut guard = bpm.new_page_guarded_with(&strategy).unwrap();
+            let pid = guard.get_pid().unwrap();
+            let data = format!("bulk{}", pid);
+            guard.get_mut_as::<[u8; 24]>()[COMMON_PAGE_HEADER_SIZE..COMMON_PAGE_HEADER_SIZE + data.len()].copy_from_slice(data.as_bytes());
+            pid
+        }).collect();
+        assert_eq!(true, hot.iter().all(|pid| is_resident(&bpm, *pid)));
+        assert_eq!(true, written.iter().filter(|pid| is_resident(&bpm, **pid)).count() <= 4);
+        for pid in written.iter() {
+            let guard = bpm.fetch_page_read(*pid).unwrap();
+            assert_eq!(true, starts_with(guard.get_as::<[u8; 24]>(), &format!("bulk{}", pid)));
+        }
+
+        // without the ring the same scan takes the whole pool
+        touch(&hot);
+        let strategy = BufferAccessStrategy::new(AccessIntent::Normal);
+        for pid in table.iter() {
+            bpm.fetch_page_read_with(*pid, &strategy).unwrap();
+        }
+        assert_eq!(true, hot.iter().all(|pid| !is_resident(&bpm, *pid)));
+        let _ = fs::remove_file("test_access_strategy.db");
+    }
+
+    #[test]
+    fn prefetch_test() {
+        let _ = fs::remove_file("test_prefetch.db");
+        let disk_mgr = Arc::new(DiskManager::new("test_prefetch.db").unwrap());
+        let bpm = BufferPoolManager::new(16, disk_mgr, 2, Arc::new(LogManager::new()));
+        let strategy = BufferAccessStrategy::new(AccessIntent::Normal);
+
+        let pids: Vec<_> = (0..64).map(|_| {
+            let page = bpm.new_page().unwrap();
+            fill_value(page, &format!("page{}", page.get_pid()));
+            bpm.unpin_page(page.get_pid(), true);
+            page.get_pid()
+        }).collect();
+        bpm.flush_all_pages();
+
+        // the reads go on in the background, the fetches wait for them
+        for pid in pids[..8].iter() {
+            assert_eq!(true, bpm.prefetch_page(*pid, &strategy));
+            assert_eq!(true, is_resident(&bpm, *pid));
+            assert_eq!(false, bpm.prefetch_page(*pid, &strategy));
+        }
+        for pid in pids[..8].iter() {
+            let page = bpm.fetch_page(*pid).unwrap();
+            assert_eq!(true, compare_value(&format!("page{}", pid), page));
+            assert_eq!(true, bpm.unpin_page(*pid, false));
+        }
+        // not allocated
+        assert_eq!(false, bpm.prefetch_page(64, &strategy));
+
+        // the pages read ahead and never fetched do not keep their frames
+        let prefetched = pids[8..].iter().filter(|pid| bpm.prefetch_page(**pid, &strategy)).count();
+        assert_eq!(true, prefetched >= 8);
+        let pinned: Vec<_> = pids[32..48].iter().map(|pid| {
+            let page = bpm.fetch_page(*pid).unwrap();
+            assert_eq!(true, compare_value(&format!("page{}", pid), page));
+            page.get_pid()
+        }).collect();
+        assert_eq!(None, bpm.new_page());
+        assert_eq!(false, bpm.prefetch_page(pids[0], &strategy));
+        for pid in pinned {
+            bpm.unpin_page(pid, false);
+        }
+        let _ = fs::remove_file("test_prefetch.db");
+    }
+
+    #[test]
+    fn corrupted_page_test() {
+        let _ = fs::remove_file("test_corrupted_page.db");
+        let disk_mgr = Arc::new(DiskManager::new("test_corrupted_page.db").unwrap());
+        let bpm = BufferPoolManager::new(4, disk_mgr.clone(), 2, Arc::new(LogManager::new()));
+        let strategy = BufferAccessStrategy::new(AccessIntent::Normal);
+        for _ in 0..3 {
+            let page = bpm.new_page().unwrap();
+            fill_value(page, &format!("page{}", page.get_pid()));
+            bpm.unpin_page(page.get_pid(), true);
+        }
+        bpm.flush_all_pages();
+
+        // page 2 is written to the place of page 1, the checksum of it is for page 2
+        let mut data = vec![0; PAGE_SIZE as usize];
+        disk_mgr.read_page(2, &mut data).unwrap();
+        let file = fs::OpenOptions::new().write(true).open("test_corrupted_page.db").unwrap();
+        file.write_all_at(&data, PAGE_SIZE as u64).unwrap();
+
+        let bpm = BufferPoolManager::new(4, disk_mgr, 2, Arc::new(LogManager::new()));
+        bpm.set_next_page_id(3);
+        assert_eq!(true, bpm.try_fetch_page(1).unwrap_err().starts_with(PAGE_CORRUPTED));
+        assert_eq!(None, bpm.fetch_page(1));
+        assert_eq!(false, is_resident(&bpm, 1));
+        // a corrupted page read ahead is dropped, the fetch reads it again
+        assert_eq!(true, bpm.prefetch_page(1, &strategy));
+        assert_eq!(true, bpm.try_fetch_page_read(1).is_err());
+        assert_eq!(false, is_resident(&bpm, 1));
+
+        // the pool goes on, and the frames are given back
+        let page = bpm.fetch_page(2).unwrap();
+        assert_eq!(true, compare_value("page2", page));
+        let pages: Vec<_> = (0..3).map(|_| bpm.new_page().unwrap().get_pid()).collect();
+        assert_eq!(vec![3, 4, 5], pages);
+        assert_eq!(true, bpm.try_fetch_page(1).unwrap_err().starts_with("no free frame"));
+        let _ = fs::remove_file("test_corrupted_page.db");
+    }
+
+    // a store whose writes fail while fail_writes is set
+    #[derive(Debug)]
+    struct FailingStore {
+        store: MemoryPageStore,
+        fail_writes: AtomicBool,
+    }
+
+    impl PageStore for FailingStore {
+        fn read_page(&self, page_id: page_id_t, buf: &mut Vec<u8>) -> Result<(), String> {
+            self.store.read_page(page_id, buf)
+        }
+
+        fn write_page(&self, page_id: page_id_t, page_data: &Vec<u8>) -> Result<(), String> {
+            self.write_pages(page_id, &[page_data])
+        }
+
+        fn read_pages(&self, page_id: page_id_t, bufs: &mut [&mut Vec<u8>]) -> Result<(), String> {
+            self.store.read_pages(page_id, bufs)
+        }
+
+        fn write_pages(&self, page_id: page_id_t, pages_data: &[&Vec<u8>]) -> Result<(), String> {
+            match self.fail_writes.load(Ordering::SeqCst) {
+                true => Err(format!("Error: write error")),
+                false => self.store.write_pages(page_id, pages_data),
+            }
+        }
+
+        fn get_filesize(&self) -> u64 {
+            self.store.get_filesize()
+        }
+
+        fn get_page_size(&self) -> usize {
+            self.store.get_page_size()
+        }
+    }
+
+    #[test]
+    fn failed_write_back_test() {
+        let store = Arc::new(FailingStore { store: MemoryPageStore::new(), fail_writes: AtomicBool::new(false) });
+        let bpm = BufferPoolManager::new(2, store.clone(), 2, Arc::new(LogManager::new()));
+        let strategy = BufferAccessStrategy::new(AccessIntent::Normal);
+        for _ in 0..2 {
+            let page = bpm.new_page().unwrap();
+            fill_value(page, &format!("page{}", page.get_pid()));
+            bpm.unpin_page(page.get_pid(), true);
+        }
+
+        // no dirty page can be written, so no frame is given up
+        store.fail_writes.store(true, Ordering::SeqCst);
+        assert_eq!(None, bpm.new_page());
+        assert_eq!(false, bpm.prefetch_page(2, &strategy));
+        assert_eq!(true, bpm.try_fetch_page(2).unwrap_err().contains("write error"));
+        for pid in 0..2 {
+            assert_eq!(true, is_resident(&bpm, pid));
+            let page = bpm.fetch_page(pid).unwrap();
+            assert_eq!(true, compare_value(&format!("page{}", pid), page));
+            bpm.unpin_page(pid, false);
+        }
+
+        // the victims kept their changes and are written out once the store is back
+        store.fail_writes.store(false, Ordering::SeqCst);
+        let page = bpm.new_page().unwrap();
+        bpm.unpin_page(page.get_pid(), false);
+        let page = bpm.new_page().unwrap();
+        bpm.unpin_page(page.get_pid(), false);
+        for pid in 0..2 {
+            let page = bpm.fetch_page(pid).unwrap();
+            assert_eq!(true, compare_value(&format!("page{}", pid), page));
+            bpm.unpin_page(pid, false);
+        }
+    }
+
+    #[test]
+    fn fetch_stress_test() {
+        let _ = fs::remove_file("test_fetch_stress.db");
+        let disk_mgr = Arc::new(DiskManager::new("test_fetch_stress.db").unwrap());
+        let bpm = Arc::new(BufferPoolManager::new(16, disk_mgr, 2, Arc::new(LogManager::new())));
+        let num_threads = 4;
+        let num_pages = 64;
+
+        let handlers: Vec<_> = (0..num_threads).map(|_| {
+            let bpm = bpm.clone();
+            thread::spawn(move || {
+                let mut pids = Vec::new();
+                for _ in 0..num_pages {
+                    let page = bpm.new_page().unwrap();
+                    fill_value(page, &format!("page{}", page.get_pid()));
+                    pids.push(page.get_pid());
+                    assert_eq!(true, bpm.unpin_page(page.get_pid(), true));
+                }
+
+                let mut rand_eng = rand::thread_rng();
+                for _ in 0..1000 {
+                    let pid = *pids.choose(&mut rand_eng).unwrap();
+                    let page = bpm.fetch_page(pid).unwrap();
+                    assert_eq!(true, compare_value(&format!("page{}", pid), page));
+                    assert_eq!(true, bpm.unpin_page(pid, rand_eng.gen_bool(0.5)));
+                }
+            })
+        }).collect();
+
+        for handler in handlers {
+            handler.join().unwrap();
+        }
+    }
+
+    #[test]
+    fn sharded_test() {
+        let _ = fs::remove_file("test_sharded.db");
+        let disk_mgr = Arc::new(DiskManager::new("test_sharded.db").unwrap());
+        let bpm = Arc::new(BufferPoolManager::new_sharded(4, 16, disk_mgr, 2, Arc::new(LogManager::new())));
+
+        // each instance has 4 frames, the new pages go round the instances
+        let mut pids = Vec::new();
+        for _ in 0..16 {
+            let page = bpm.new_page().unwrap();
+            pids.push(page.get_pid());
+        }
+        pids.sort();
+        assert_eq!((0..16).collect::<Vec<_>>(), pids);
+        assert_eq!(true, bpm.new_page().is_none());
+        // a full instance does not take the pages of the others
+        assert_eq!(true, bpm.unpin_page(5, false));
+        assert_eq!(true, bpm.fetch_page(100).is_none());
+        assert_eq!(17, bpm.new_page().unwrap().get_pid());
+
+        for pid in 0..16 {
+            bpm.unpin_page(pid, true);
+        }
+        bpm.unpin_page(17, true);
+
+        // the same stress as above over the partitions
+        let handlers: Vec<_> = (0..4).map(|_| {
+            let bpm = bpm.clone();
+            thread::spawn(move || {
+                let mut pids = Vec::new();
+                for _ in 0..64 {
+                    let page = bpm.new_page().unwrap();
+                    fill_value(page, &format!("page{}", page.get_pid()));
+                    pids.push(page.get_pid());
+                    assert_eq!(true, bpm.unpin_page(page.get_pid(), true));
+                }
+
+                let mut rand_eng = rand::thread_rng();
+                for _ in 0..1000 {
+                    let pid = *pids.choose(&mut rand_eng).unwrap();
+                    let page = bpm.fetch_page(pid).unwrap();
+                    assert_eq!(true, compare_value(&format!("page{}", pid), page));
+                    assert_eq!(true, bpm.unpin_page(pid, rand_eng.gen_bool(0.5)));
+                }
+            })
+        }).collect();
+
+        for handler in handlers {
+            handler.join().unwrap();
+        }
+    }
+
+    // the scans of all the pages by 8 threads, with one latch and with 8 instances, the pool holds the whole table
+    // in the first round, a quarter of it in the second one. the throughput is measured by `benches/scan_throughput.rs`
+    #[test]
+    fn concurrent_scan_test() {
+        let num_pages = 256;
+        let num_scans = 4;
+        for pool_size in [num_pages, num_pages / 4] {
+            for num_instances in [1, 8] {
+                let filename = format!("test_scan_{}_{}.db", pool_size, num_instances);
+                let _ = fs::remove_file(&filename);
+                let disk_mgr = Arc::new(DiskManager::new(&filename).unwrap());
+                let bpm = Arc::new(BufferPoolManager::new_sharded(num_instances, pool_size, disk_mgr, 2, Arc::new(LogManager::new())));
+                for _ in 0..num_pages {
+                    let mut guard = bpm.new_page_guarded().unwrap();
+                    let pid = guard.get_pid().unwrap();
+                    *guard.get_mut_as::<u8>() = pid as u8;
+                }
+                bpm.flush_all_pages();
+
+                let handlers: Vec<_> = (0..8).map(|_| {
+                    let bpm = bpm.clone();
+                    thread::spawn(move || {
+                        for _ in 0..num_scans {
+                            for pid in 0..num_pages as page_id_t {
+                                let guard = bpm.fetch_page_read(pid).unwrap();
+                                assert_eq!(pid as u8, *guard.get_as::<u8>());
+                            }
+                        }
+                    })
+                }).collect();
+                for handler in handlers {
+                    handler.join().unwrap();
+                }
+                let _ = fs::remove_file(&filename);
+            }
+        }
+    }
+
     /// below is all concurrent test
     #[test]
     fn concurent_test() {
@@ -984,7 +1902,7 @@ mod tests {
 
                     // delete all pages
                     for idx in 0..10 {
-                        assert_eq!(true, bpm_cp.delete_page(pids[idx]));
+                        assert_eq!(Ok(true), bpm_cp.delete_page(pids[idx]));
                     }
                 }));
             }
@@ -1076,7 +1994,7 @@ mod tests {
             let page = bpm.fetch_page(pids[i]);
             assert_ne!(None, page);
             assert_eq!(true, bpm.unpin_page(pids[i], false));
-            assert_eq!(true, bpm.delete_page(pids[i]));
+            assert_eq!(Ok(true), bpm.delete_page(pids[i]));
         }
 
         for i in 10..20 {
@@ -1091,7 +2009,7 @@ mod tests {
                 assert_eq!(true, compare_value(&format!("{}", pids[i]), page));
             }
             assert_eq!(true, bpm.unpin_page(pids[i], false));
-            assert_eq!(true, bpm.delete_page(pids[i]));
+            assert_eq!(Ok(true), bpm.delete_page(pids[i]));
           }
     }
 
@@ -1274,7 +2192,7 @@ mod tests {
 
                             assert_eq!(true, compare_value(&format!("{}", new_pid), page_local));
                             assert_eq!(true, bpm_cp.unpin_page(new_pid, true));
-                            assert_eq!(true, bpm_cp.delete_page(new_pid));
+                            assert_eq!(Ok(true), bpm_cp.delete_page(new_pid));
                         }
 
                         let mut page = bpm_cp.fetch_page(pids_cp[j]);
@@ -1312,10 +2230,8 @@ mod tests {
             }
 
             for i in 0..50 {
-                assert_eq!(true, bpm.delete_page(pids[i]));
+                assert_eq!(Ok(true), bpm.delete_page(pids[i]));
             }
-
-            let _ = fs::remove_file("test.db");
         }    
     }
 
@@ -1400,7 +2316,7 @@ mod tests {
 
                             assert_eq!(true, compare_value(&format!("{}", new_pid), page_local));
                             assert_eq!(true, bpm_cp.unpin_page(new_pid, true));
-                            assert_eq!(true, bpm_cp.delete_page(new_pid));
+                            assert_eq!(Ok(true), bpm_cp.delete_page(new_pid));
                         }
 
                         let mut page = bpm_cp.fetch_page(pids_cp[j]);
@@ -1439,7 +2355,7 @@ mod tests {
                             assert_ne!(None, page);
                             let page = page.unwrap();
                             assert_eq!(true, bpm_cp.unpin_page(page.get_pid(), false));
-                            assert_eq!(true, bpm_cp.delete_page(page.get_pid()));
+                            assert_eq!(Ok(true), bpm_cp.delete_page(page.get_pid()));
                         }
                     }
                 }));
@@ -1450,10 +2366,8 @@ mod tests {
             }
 
             for i in 0..50 {
-                assert_eq!(true, bpm.delete_page(pids[i]));
+                assert_eq!(Ok(true), bpm.delete_page(pids[i]));
             }
-
-            let _ = fs::remove_file("test.db");
         }    
     }
 }
\ No newline at end of file
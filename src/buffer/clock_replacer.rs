@@ -0,0 +1,137 @@
+#![allow(warnings)]
+
+use crate::common::config::frame_id_t;
+
+use super::replacer::Replacer;
+
+
+#[derive(Debug, Clone, Copy)]
+struct ClockEntry {
+    pub referenced: bool,
+    pub is_evictable: bool,
+}
+
+/// Clock (second chance), the hand goes round the frames, a referenced frame has its bit cleared
+/// and is passed over once, the first evictable one without the bit is the victim
+#[derive(Debug)]
+pub struct ClockReplacer {
+    frames: Vec<Option<ClockEntry>>,
+    hand: usize,
+    replacer_size: usize,
+}
+
+impl ClockReplacer {
+    pub fn new(num_frames: usize) -> Self {
+        Self {
+            frames: vec![None; num_frames],
+            hand: 0,
+            replacer_size: 0,
+        }
+    }
+}
+
+impl Replacer for ClockReplacer {
+    fn evict(&mut self) -> Option<frame_id_t> {
+        if 0 == self.replacer_size {
+            return None;
+        }
+
+        // two rounds at most, the first one may only clear the bits
+        for _ in 0..2 * self.frames.len() {
+            let fid = self.hand;
+            self.hand = (self.hand + 1) % self.frames.len();
+
+            let Some(entry) = self.frames[fid].as_mut() else {
+                continue;
+            };
+            if !entry.is_evictable {
+                continue;
+            }
+            if entry.referenced {
+                entry.referenced = false;
+                continue;
+            }
+            self.frames[fid] = None;
+            self.replacer_size -= 1;
+            return Some(fid);
+        }
+        None
+    }
+
+    fn record_access(&mut self, frame_id: frame_id_t) {
+        if frame_id >= self.frames.len() {
+            panic!("Error: frame id is greater <{}> than the maxmimum frame size <{}>",
+                frame_id, self.frames.len());
+        }
+
+        match self.frames[frame_id].as_mut() {
+            Some(entry) => entry.referenced = true,
+            None => self.frames[frame_id] = Some(ClockEntry { referenced: true, is_evictable: false }),
+        }
+    }
+
+    fn set_evictable(&mut self, frame_id: frame_id_t, is_evictable: bool) {
+        let Some(entry) = self.frames.get_mut(frame_id).and_then(|entry| entry.as_mut()) else {
+            return;
+        };
+        if entry.is_evictable == is_evictable {
+            return;
+        }
+
+        entry.is_evictable = is_evictable;
+        if is_evictable {
+            self.replacer_size += 1;
+        } else {
+            self.replacer_size -= 1;
+        }
+    }
+
+    fn remove(&mut self, frame_id: frame_id_t) {
+        if let Some(entry) = self.frames.get_mut(frame_id).and_then(|entry| entry.take()) {
+            if entry.is_evictable {
+                self.replacer_size -= 1;
+            }
+        }
+    }
+
+    fn get_size(&self) -> usize {
+        self.replacer_size
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::{ClockReplacer, Replacer};
+
+    #[test]
+    fn sample_test() {
+        let mut replacer = ClockReplacer::new(7);
+
+        for fid in 1..=6 {
+            replacer.record_access(fid);
+        }
+        for fid in 1..=5 {
+            replacer.set_evictable(fid, true);
+        }
+        replacer.set_evictable(6, false);
+        assert_eq!(5, replacer.get_size());
+
+        // every frame has its bit, the hand clears them all and comes back to 1
+        assert_eq!(Some(1), replacer.evict());
+        // 2 is referenced again, so it gets a second chance
+        replacer.record_access(2);
+        assert_eq!(Some(3), replacer.evict());
+        assert_eq!(Some(4), replacer.evict());
+        assert_eq!(2, replacer.get_size());
+
+        replacer.set_evictable(6, true);
+        replacer.remove(5);
+        assert_eq!(2, replacer.get_size());
+        // 6 still has the bit of its access, 2 lost it on the way to 3
+        assert_eq!(Some(2), replacer.evict());
+        assert_eq!(Some(6), replacer.evict());
+        assert_eq!(None, replacer.evict());
+        assert_eq!(0, replacer.get_size());
+    }
+}
@@ -0,0 +1,215 @@
+#![allow(warnings)]
+
+use std::sync::Mutex;
+
+use crate::{common::{config::{file_id_t, lsn_t, page_id_t, INVALID_LSN, INVALID_TXN_ID}, page_addr::PageAddr}, storage::page_based::{disk::log_record::{LogRecord, LogRecordBody}, page::free_space_map_page::{FreeSpaceMapPage, FSM_FIRST_PAGE_ID, FSM_FREE_PAGE}}};
+
+use super::buffer_pool_manager::BufferPoolManager;
+
+
+#[derive(Debug)]
+struct FreeSpaceMap_ {
+    // the entries of the map pages by page number, up to the last allocated page
+    entries: Vec<u16>,
+    // no page before it is free
+    first_free: usize,
+}
+
+/// FreeSpaceMap, the page allocator of a buffer pool and the free bytes of each page, kept on the map pages
+/// in the file and read through the pool, see `FreeSpaceMapPage`. A copy of the entries is kept in memory.
+/// An allocation takes the first free page and the file grows only when there is none,
+/// it is logged before the map page is changed, so the redo brings back the allocations the map page missed.
+/// The free bytes are not logged, a table heap finds out the real ones on the page.
+/// Each file of a tablespace has a map of its own, the page ids taken and returned are the addresses in it
+#[derive(Debug)]
+pub struct FreeSpaceMap {
+    state: Mutex<FreeSpaceMap_>,
+    file_id: file_id_t,
+    page_size: usize,
+}
+
+impl FreeSpaceMap {
+    // read the map pages among the first num_pages pages of the file
+    pub fn open(bpm: &BufferPoolManager, file_id: file_id_t, num_pages: page_id_t) -> Result<Self, String> {
+        // the pages before the first map page are not managed by it
+        let page_size = bpm.get_page_size();
+        let mut entries = vec![FreeSpaceMapPage::encode(0); FSM_FIRST_PAGE_ID as usize];
+        let mut map_page_id = FSM_FIRST_PAGE_ID;
+        while map_page_id < num_pages {
+            let page_guard = bpm.try_fetch_page_read(PageAddr::new(file_id, map_page_id).to_page_id())?;
+            entries.extend_from_slice(page_guard.get_as::<FreeSpaceMapPage>().get_entries(page_size));
+            map_page_id += FreeSpaceMapPage::entries_per_page(page_size) as page_id_t;
+        }
+        let len = entries.iter().rposition(|entry| *entry != FSM_FREE_PAGE).map_or(0, |pos| pos + 1);
+        entries.truncate(len);
+
+        let first_free = (0..len)
+            .find(|pid| entries[*pid] == FSM_FREE_PAGE && !FreeSpaceMapPage::is_map_page(*pid as page_id_t, page_size))
+            .unwrap_or(len);
+        Ok(Self { state: Mutex::new(FreeSpaceMap_ { entries, first_free }), file_id, page_size })
+    }
+
+    pub fn get_file_id(&self) -> file_id_t {
+        self.file_id
+    }
+
+    // the id of a page of the file
+    fn page_id_of(&self, page_no: page_id_t) -> page_id_t {
+        PageAddr::new(self.file_id, page_no).to_page_id()
+    }
+
+    // the page number of a page of the file
+    fn page_no_of(&self, page_id: page_id_t) -> page_id_t {
+        let addr = PageAddr::of(page_id);
+        assert_eq!(self.file_id, addr.file_id, "page {} is not in file {}", page_id, self.file_id);
+        addr.page_no
+    }
+
+    // one past the last allocated page number
+    pub fn get_num_pages(&self) -> page_id_t {
+        self.state.lock().unwrap().entries.len() as page_id_t
+    }
+
+    // the free bytes of an allocated page
+    pub fn get_free_space(&self, page_id: page_id_t) -> Option<usize> {
+        let state = self.state.lock().unwrap();
+        state.entries.get(self.page_no_of(page_id) as usize).and_then(|entry| FreeSpaceMapPage::decode(*entry))
+    }
+
+    // allocate the first free page for which `take` gets a frame of the pool and return what it returns.
+    // `take` fails when the instance of the page has no frame, then the other pages of that instance are skipped
+    pub fn allocate<'a, T>(&self, bpm: &'a BufferPoolManager, num_instances: usize, mut take: impl FnMut(page_id_t) -> Option<T>) -> Option<T> {
+        let mut state = self.state.lock().unwrap();
+        let mut is_full = vec![false; num_instances];
+        let mut page_no = state.first_free as page_id_t;
+        while is_full.iter().any(|full| !full) && page_no <= PageAddr::MAX_PAGE_NO {
+            let page_id = self.page_id_of(page_no);
+            let entry = state.entries.get(page_no as usize).cloned().unwrap_or(FSM_FREE_PAGE);
+            if entry != FSM_FREE_PAGE || FreeSpaceMapPage::is_map_page(page_no, self.page_size) || is_full[page_id as usize % num_instances] {
+                page_no += 1;
+                continue;
+            }
+
+            // the map page is latched first, the page is not taken if it can not be recorded
+            let mut map_guard = bpm.fetch_page_write(self.page_id_of(FreeSpaceMapPage::map_page_of(page_no, self.page_size)))?;
+            let Some(page) = take(page_id) else {
+                is_full[page_id as usize % num_instances] = true;
+                page_no += 1;
+                continue;
+            };
+            let lsn = Self::append_log(bpm, LogRecordBody::AllocatePage { page_id });
+            let map_page = map_guard.get_mut_as::<FreeSpaceMapPage>();
+            map_page.set_entry(page_no, FreeSpaceMapPage::encode(0), self.page_size);
+            map_page.set_lsn(lsn);
+            Self::set_entry(&mut state, page_no, FreeSpaceMapPage::encode(0));
+            if state.first_free == page_no as usize {
+                state.first_free += 1;
+            }
+            return Some(page);
+        }
+        None
+    }
+
+    // the page may be allocated again
+    pub fn free_page(&self, bpm: &BufferPoolManager, page_id: page_id_t) -> Result<(), String> {
+        let mut state = self.state.lock().unwrap();
+        let page_no = self.page_no_of(page_id);
+        if FreeSpaceMapPage::is_map_page(page_no, self.page_size) || page_no < FSM_FIRST_PAGE_ID
+            || state.entries.get(page_no as usize).map_or(true, |entry| *entry == FSM_FREE_PAGE) {
+            return Ok(());
+        }
+
+        let mut map_guard = bpm.fetch_page_write(self.page_id_of(FreeSpaceMapPage::map_page_of(page_no, self.page_size)))
+            .ok_or(format!("can not fetch the map page of page {}, bpm err", page_id))?;
+        let lsn = Self::append_log(bpm, LogRecordBody::FreePage { page_id });
+        let map_page = map_guard.get_mut_as::<FreeSpaceMapPage>();
+        map_page.set_entry(page_no, FSM_FREE_PAGE, self.page_size);
+        map_page.set_lsn(lsn);
+        Self::set_entry(&mut state, page_no, FSM_FREE_PAGE);
+        state.first_free = state.first_free.min(page_no as usize);
+        Ok(())
+    }
+
+    // record the free bytes of an allocated page, it is not logged
+    pub fn set_free_space(&self, bpm: &BufferPoolManager, page_id: page_id_t, free_bytes: usize) -> Result<(), String> {
+        let mut state = self.state.lock().unwrap();
+        let entry = FreeSpaceMapPage::encode(free_bytes);
+        let page_no = self.page_no_of(page_id);
+        match state.entries.get(page_no as usize) {
+            Some(old_entry) if *old_entry != FSM_FREE_PAGE && *old_entry != entry => {},
+            _ => return Ok(()),
+        }
+
+        let mut map_guard = bpm.fetch_page_write(self.page_id_of(FreeSpaceMapPage::map_page_of(page_no, self.page_size)))
+            .ok_or(format!("can not fetch the map page of page {}, bpm err", page_id))?;
+        map_guard.get_mut_as::<FreeSpaceMapPage>().set_entry(page_no, entry, self.page_size);
+        Self::set_entry(&mut state, page_no, entry);
+        Ok(())
+    }
+
+    fn set_entry(state: &mut FreeSpaceMap_, page_no: page_id_t, entry: u16) {
+        if state.entries.len() <= page_no as usize {
+            state.entries.resize(page_no as usize + 1, FSM_FREE_PAGE);
+        }
+        state.entries[page_no as usize] = entry;
+        let len = state.entries.iter().rposition(|entry| *entry != FSM_FREE_PAGE).map_or(0, |pos| pos + 1);
+        state.entries.truncate(len);
+    }
+
+    // the allocations are out of any transaction, they are never undone
+    fn append_log(bpm: &BufferPoolManager, body: LogRecordBody) -> lsn_t {
+        let mut record = LogRecord::new(INVALID_TXN_ID, INVALID_LSN, body);
+        bpm.get_log_manager().append_log_record(&mut record)
+    }
+}
+
+
+
+#[cfg(test)]
+mod tests {
+    use std::{fs, sync::Arc};
+
+    use crate::{buffer::buffer_pool_manager::BufferPoolManager, common::config::{page_id_t, PAGE_SIZE}, storage::page_based::disk::{disk_manager::{DiskManager, PageStore}, log_manager::LogManager}};
+
+    fn open_bpm(db: &str) -> BufferPoolManager {
+        let disk_mgr = Arc::new(DiskManager::new(db).unwrap());
+        let num_pages = disk_mgr.get_filesize() as page_id_t / PAGE_SIZE as page_id_t;
+        let bpm = BufferPoolManager::new(8, disk_mgr, 2, Arc::new(LogManager::new()));
+        bpm.open_free_space_map(num_pages).unwrap();
+        bpm
+    }
+
+    #[test]
+    fn test_allocate() {
+        let db = "test_fsm.db";
+        let _ = fs::remove_file(db);
+        {
+            let bpm = open_bpm(db);
+            // page 0 is left for the superblock and page 1 is the map page
+            let page_ids: Vec<page_id_t> = (0..3).map(|_| bpm.new_page().unwrap().get_pid()).collect();
+            assert_eq!(vec![2, 3, 4], page_ids);
+            for page_id in page_ids {
+                assert_eq!(true, bpm.unpin_page(page_id, true));
+            }
+
+            // a freed page is taken again before the file grows
+            assert_eq!(Ok(true), bpm.delete_page(3));
+            assert_eq!(None, bpm.get_free_space(3));
+            assert_eq!(3, bpm.new_page().unwrap().get_pid());
+            assert_eq!(true, bpm.unpin_page(3, true));
+            assert_eq!(Some(0), bpm.get_free_space(3));
+
+            bpm.update_free_space(4, 500).unwrap();
+            assert_eq!(Some(500), bpm.get_free_space(4));
+            bpm.flush_all_pages();
+        }
+
+        // the allocations and the free bytes are in the file
+        let bpm = open_bpm(db);
+        assert_eq!(Some(0), bpm.get_free_space(2));
+        assert_eq!(Some(500), bpm.get_free_space(4));
+        assert_eq!(None, bpm.get_free_space(5));
+        assert_eq!(5, bpm.new_page().unwrap().get_pid());
+        let _ = fs::remove_file(db);
+    }
+}
@@ -3,25 +3,10 @@
 use std::{cmp::Reverse, collections::{BinaryHeap, HashMap, LinkedList}, sync::Mutex};
 use crate::common::config::frame_id_t;
 
+use super::replacer::Replacer;
 
 
 
-pub trait Replacer {
-    // evict a frame if exists, otherwise return none
-    fn evict(&mut self) -> Option<frame_id_t>;
-
-    // record access
-    fn record_access(&mut self, frame_id: frame_id_t);
-
-    // set evictable given a frame id
-    fn set_evictable(&mut self, frame_id: frame_id_t, is_evictable: bool);
-
-    // remove a frame no matter what its k-distance is
-    fn remove(&mut self, frame_id: frame_id_t);
-
-    // return the replacer size
-    fn get_size(&self) -> usize;
-}
 
 type timestamp_t = usize;
 const MAX_TIMESTAMP: usize = usize::MAX;
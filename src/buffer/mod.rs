@@ -1,2 +1,9 @@
 pub mod buffer_pool_manager;
-pub mod lruk_replacer;
\ No newline at end of file
+pub mod replacer;
+pub mod lruk_replacer;
+pub mod clock_replacer;
+pub mod two_queue_replacer;
+pub mod arc_replacer;
+pub mod trace_replay;
+pub mod access_strategy;
+pub mod free_space_map;
\ No newline at end of file
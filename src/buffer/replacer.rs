@@ -0,0 +1,52 @@
+#![allow(warnings)]
+
+use std::fmt::Debug;
+
+use crate::common::config::{frame_id_t, page_id_t};
+
+use super::{arc_replacer::ARCReplacer, clock_replacer::ClockReplacer, lruk_replacer::LRUKReplacer, two_queue_replacer::TwoQueueReplacer};
+
+
+/// the page replacement policy of the buffer pool, it is called with the latch of the pool held
+pub trait Replacer: Debug + Send {
+    // evict a frame if exists, otherwise return none
+    fn evict(&mut self) -> Option<frame_id_t>;
+
+    // record access
+    fn record_access(&mut self, frame_id: frame_id_t);
+
+    // set evictable given a frame id
+    fn set_evictable(&mut self, frame_id: frame_id_t, is_evictable: bool);
+
+    // remove a frame no matter what its k-distance is
+    fn remove(&mut self, frame_id: frame_id_t);
+
+    // return the replacer size
+    fn get_size(&self) -> usize;
+
+    // a new page comes into the frame, it is called before the first record_access of it.
+    // the policies which remember the evicted pages (2Q, ARC) need the page id, the others ignore it
+    fn map_page(&mut self, frame_id: frame_id_t, page_id: page_id_t) {}
+}
+
+
+/// the policies to choose from
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ReplacerPolicy {
+    // LRU-K with the k
+    LruK(usize),
+    Clock,
+    TwoQueue,
+    Arc,
+}
+
+impl ReplacerPolicy {
+    pub fn create(&self, num_frames: usize) -> Box<dyn Replacer> {
+        match self {
+            ReplacerPolicy::LruK(k) => Box::new(LRUKReplacer::new(num_frames, *k)),
+            ReplacerPolicy::Clock => Box::new(ClockReplacer::new(num_frames)),
+            ReplacerPolicy::TwoQueue => Box::new(TwoQueueReplacer::new(num_frames)),
+            ReplacerPolicy::Arc => Box::new(ARCReplacer::new(num_frames)),
+        }
+    }
+}
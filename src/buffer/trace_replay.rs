@@ -0,0 +1,173 @@
+#![allow(warnings)]
+
+use std::{collections::HashMap, fs};
+
+use crate::common::config::{frame_id_t, page_id_t};
+
+use super::replacer::ReplacerPolicy;
+
+
+/// the hits and misses of a policy on a trace
+#[derive(Debug, Clone, Copy)]
+pub struct ReplayResult {
+    pub policy: ReplacerPolicy,
+    pub hits: usize,
+    pub misses: usize,
+}
+
+impl ReplayResult {
+    pub fn hit_ratio(&self) -> f64 {
+        if 0 == self.hits + self.misses {
+            return 0.0;
+        }
+        self.hits as f64 / (self.hits + self.misses) as f64
+    }
+}
+
+/// replay the page accesses against a pool of num_frames frames managed by the policy, the way
+/// the buffer pool drives its replacer. every page is unpinned right after its access, and nothing is read or written
+pub fn replay(policy: ReplacerPolicy, num_frames: usize, trace: &[page_id_t]) -> ReplayResult {
+    let mut replacer = policy.create(num_frames);
+    let mut page_table: HashMap<page_id_t, frame_id_t> = HashMap::new();
+    let mut frames: Vec<page_id_t> = Vec::new();
+    let mut result = ReplayResult { policy, hits: 0, misses: 0 };
+
+    for page_id in trace.iter().cloned() {
+        if let Some(fid) = page_table.get(&page_id).cloned() {
+            result.hits += 1;
+            replacer.record_access(fid);
+            continue;
+        }
+
+        result.misses += 1;
+        let fid = if frames.len() < num_frames {
+            frames.push(page_id);
+            frames.len() - 1
+        } else {
+            let fid = replacer.evict().expect("every frame is unpinned");
+            page_table.remove(&frames[fid]);
+            frames[fid] = page_id;
+            fid
+        };
+        page_table.insert(page_id, fid);
+        replacer.map_page(fid, page_id);
+        replacer.record_access(fid);
+        replacer.set_evictable(fid, true);
+    }
+    result
+}
+
+/// replay the trace with each of the policies
+pub fn compare(policies: &[ReplacerPolicy], num_frames: usize, trace: &[page_id_t]) -> Vec<ReplayResult> {
+    policies.iter().map(|policy| replay(*policy, num_frames, trace)).collect()
+}
+
+/// a trace file has a page id on each line
+pub fn load_trace(filename: &str) -> Result<Vec<page_id_t>, String> {
+    let content = fs::read_to_string(filename)
+        .map_err(|err| format!("failed to read the trace {}: {}", filename, err))?;
+    content.split_whitespace()
+        .map(|pid| pid.parse::<page_id_t>().map_err(|err| format!("bad page id {} in the trace {}: {}", pid, filename, err)))
+        .collect()
+}
+
+pub fn save_trace(filename: &str, trace: &[page_id_t]) -> Result<(), String> {
+    let content: String = trace.iter().map(|pid| format!("{}\n", pid)).collect();
+    fs::write(filename, content).map_err(|err| format!("failed to write the trace {}: {}", filename, err))
+}
+
+
+#[cfg(test)]
+mod tests {
+    use std::{fs, sync::Arc};
+
+    use crate::{buffer::{buffer_pool_manager::BufferPoolManager, replacer::ReplacerPolicy}, common::config::page_id_t, storage::page_based::disk::{disk_manager::DiskManager, log_manager::LogManager}};
+
+    use super::{compare, load_trace, replay, save_trace, ReplayResult};
+
+    const POLICIES: [ReplacerPolicy; 5] = [
+        ReplacerPolicy::LruK(1),
+        ReplacerPolicy::LruK(2),
+        ReplacerPolicy::Clock,
+        ReplacerPolicy::TwoQueue,
+        ReplacerPolicy::Arc,
+    ];
+
+    fn print_results(name: &str, results: &[ReplayResult]) {
+        for result in results {
+            println!("{}: {:?} hits {}, misses {}, hit ratio {:.3}",
+                name, result.policy, result.hits, result.misses, result.hit_ratio());
+        }
+    }
+
+    #[test]
+    fn scan_resistance_test() {
+        // a hot set of 8 pages is warmed up, then each access to it is followed by 8 pages of a long scan
+        let num_frames = 64;
+        let mut trace: Vec<page_id_t> = (0..800).map(|i| i % 8).collect();
+        let mut scan_pid: page_id_t = 1000;
+        for i in 0..4000 {
+            trace.push(i % 8);
+            for _ in 0..8 {
+                trace.push(scan_pid);
+                scan_pid += 1;
+            }
+        }
+
+        let results = compare(&POLICIES, num_frames, &trace);
+        print_results("scan", &results);
+
+        // the scan pages never come back, and the lru ones lose the hot pages to them
+        let lru = results[0].hit_ratio();
+        let clock = results[2].hit_ratio();
+        assert_eq!(800, results[0].hits);
+        assert_eq!(true, results[3].hit_ratio() > lru.max(clock));
+        assert_eq!(true, results[4].hit_ratio() > lru.max(clock));
+        for result in results.iter() {
+            assert_eq!(trace.len(), result.hits + result.misses);
+        }
+    }
+
+    #[test]
+    fn working_set_test() {
+        // the working set fits, every policy misses only on the first accesses
+        let trace: Vec<page_id_t> = (0..1000).map(|i| i % 32).collect();
+        for result in compare(&POLICIES, 32, &trace) {
+            assert_eq!(32, result.misses);
+        }
+        assert_eq!(trace.len(), replay(ReplacerPolicy::Clock, 31, &trace).misses);
+    }
+
+    #[test]
+    fn recorded_trace_test() {
+        let _ = fs::remove_file("test_trace.db");
+        let disk_mgr = Arc::new(DiskManager::new("test_trace.db").unwrap());
+        let bpm = BufferPoolManager::new(16, disk_mgr, 2, Arc::new(LogManager::new()));
+
+        bpm.start_trace();
+        let mut pids = Vec::new();
+        for _ in 0..48 {
+            let page = bpm.new_page().unwrap();
+            pids.push(page.get_pid());
+            bpm.unpin_page(page.get_pid(), true);
+        }
+        for i in 0..480 {
+            let pid = pids[(i * 7) % pids.len()];
+            bpm.fetch_page(pid).unwrap();
+            bpm.unpin_page(pid, false);
+        }
+        let trace = bpm.take_trace();
+        assert_eq!(48 + 480, trace.len());
+        // not recorded any more
+        bpm.fetch_page(pids[0]).unwrap();
+        bpm.unpin_page(pids[0], false);
+        assert_eq!(0, bpm.take_trace().len());
+
+        save_trace("test_trace.txt", &trace).unwrap();
+        assert_eq!(trace, load_trace("test_trace.txt").unwrap());
+        print_results("recorded", &compare(&POLICIES, 16, &trace));
+
+        let _ = fs::remove_file("test_trace.txt");
+        let _ = fs::remove_file("test_trace.db");
+    }
+}
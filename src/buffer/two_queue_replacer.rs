@@ -0,0 +1,212 @@
+#![allow(warnings)]
+
+use std::collections::{HashMap, VecDeque};
+
+use crate::common::config::{frame_id_t, page_id_t, INVALID_PAGE_ID};
+
+use super::replacer::Replacer;
+
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Queue {
+    // seen once, fifo
+    A1in,
+    // seen again after leaving A1in, lru
+    Am,
+}
+
+#[derive(Debug)]
+struct Entry {
+    pub page_id: page_id_t,
+    pub queue: Queue,
+    pub is_evictable: bool,
+}
+
+/// 2Q (Johnson & Shasha), a page comes into the fifo A1in, and is remembered in the ghost queue A1out
+/// after its eviction. It goes to the lru Am only when it is accessed again while in A1out,
+/// so a scan touching every page once passes through A1in without pushing out the hot pages in Am
+#[derive(Debug)]
+pub struct TwoQueueReplacer {
+    entries: HashMap<frame_id_t, Entry>,
+    // the front is the oldest one
+    a1in: VecDeque<frame_id_t>,
+    am: VecDeque<frame_id_t>,
+    // the page ids evicted from A1in
+    a1out: VecDeque<page_id_t>,
+    // the pages coming into the frames, taken by the first record_access
+    incoming: HashMap<frame_id_t, page_id_t>,
+    // the size of A1in above which it is evicted from first
+    kin: usize,
+    // the size of A1out
+    kout: usize,
+    num_frames: usize,
+    replacer_size: usize,
+}
+
+impl TwoQueueReplacer {
+    pub fn new(num_frames: usize) -> Self {
+        Self {
+            entries: HashMap::new(),
+            a1in: VecDeque::new(),
+            am: VecDeque::new(),
+            a1out: VecDeque::new(),
+            incoming: HashMap::new(),
+            // the tunings suggested in the paper
+            kin: (num_frames / 4).max(1),
+            kout: (num_frames / 2).max(1),
+            num_frames,
+            replacer_size: 0,
+        }
+    }
+
+    fn queue_mut(&mut self, queue: Queue) -> &mut VecDeque<frame_id_t> {
+        match queue {
+            Queue::A1in => &mut self.a1in,
+            Queue::Am => &mut self.am,
+        }
+    }
+
+    // the oldest evictable frame of the queue
+    fn victim_of(&self, queue: &VecDeque<frame_id_t>) -> Option<frame_id_t> {
+        queue.iter().find(|fid| self.entries[fid].is_evictable).cloned()
+    }
+
+    fn detach(&mut self, frame_id: frame_id_t) -> Option<Entry> {
+        let entry = self.entries.remove(&frame_id)?;
+        self.queue_mut(entry.queue).retain(|fid| *fid != frame_id);
+        if entry.is_evictable {
+            self.replacer_size -= 1;
+        }
+        Some(entry)
+    }
+}
+
+impl Replacer for TwoQueueReplacer {
+    fn evict(&mut self) -> Option<frame_id_t> {
+        if 0 == self.replacer_size {
+            return None;
+        }
+
+        let from_a1in = self.a1in.len() > self.kin;
+        let victim = if from_a1in {
+            self.victim_of(&self.a1in).or_else(|| self.victim_of(&self.am))
+        } else {
+            self.victim_of(&self.am).or_else(|| self.victim_of(&self.a1in))
+        }?;
+
+        let entry = self.detach(victim)?;
+        // only the ones leaving A1in are remembered
+        if entry.queue == Queue::A1in && entry.page_id != INVALID_PAGE_ID {
+            self.a1out.push_back(entry.page_id);
+            if self.a1out.len() > self.kout {
+                self.a1out.pop_front();
+            }
+        }
+        Some(victim)
+    }
+
+    fn record_access(&mut self, frame_id: frame_id_t) {
+        if frame_id >= self.num_frames {
+            panic!("Error: frame id is greater <{}> than the maxmimum frame size <{}>",
+                frame_id, self.num_frames);
+        }
+
+        if let Some(entry) = self.entries.get(&frame_id) {
+            // a hit in A1in does not move it, the accesses close together are counted as one
+            if entry.queue == Queue::Am {
+                self.am.retain(|fid| *fid != frame_id);
+                self.am.push_back(frame_id);
+            }
+            return;
+        }
+
+        let page_id = self.incoming.remove(&frame_id).unwrap_or(INVALID_PAGE_ID);
+        let queue = match self.a1out.iter().position(|pid| *pid == page_id) {
+            Some(pos) if page_id != INVALID_PAGE_ID => {
+                self.a1out.remove(pos);
+                Queue::Am
+            },
+            _ => Queue::A1in,
+        };
+        self.entries.insert(frame_id, Entry { page_id, queue, is_evictable: false });
+        self.queue_mut(queue).push_back(frame_id);
+    }
+
+    fn set_evictable(&mut self, frame_id: frame_id_t, is_evictable: bool) {
+        let Some(entry) = self.entries.get_mut(&frame_id) else {
+            return;
+        };
+        if entry.is_evictable == is_evictable {
+            return;
+        }
+
+        entry.is_evictable = is_evictable;
+        if is_evictable {
+            self.replacer_size += 1;
+        } else {
+            self.replacer_size -= 1;
+        }
+    }
+
+    fn remove(&mut self, frame_id: frame_id_t) {
+        self.detach(frame_id);
+        self.incoming.remove(&frame_id);
+    }
+
+    fn get_size(&self) -> usize {
+        self.replacer_size
+    }
+
+    fn map_page(&mut self, frame_id: frame_id_t, page_id: page_id_t) {
+        self.incoming.insert(frame_id, page_id);
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use crate::common::config::page_id_t;
+
+    use super::{Replacer, TwoQueueReplacer};
+
+    fn access(replacer: &mut TwoQueueReplacer, fid: usize, pid: page_id_t) {
+        replacer.map_page(fid, pid);
+        replacer.record_access(fid);
+        replacer.set_evictable(fid, true);
+    }
+
+    #[test]
+    fn sample_test() {
+        // kin = 1, kout = 2
+        let mut replacer = TwoQueueReplacer::new(4);
+        for fid in 0..4 {
+            access(&mut replacer, fid, fid as page_id_t + 100);
+        }
+        assert_eq!(4, replacer.get_size());
+
+        // A1in is over kin, it is a fifo no matter the hits
+        replacer.record_access(0);
+        assert_eq!(Some(0), replacer.evict());
+        assert_eq!(Some(1), replacer.evict());
+
+        // page 100 comes back while remembered in A1out, it goes to Am
+        access(&mut replacer, 0, 100);
+        access(&mut replacer, 1, 104);
+        // A1in = [2, 3, 1] is still over kin
+        assert_eq!(Some(2), replacer.evict());
+        assert_eq!(Some(3), replacer.evict());
+        // A1in = [1] is within kin, so the victim comes from Am
+        assert_eq!(Some(0), replacer.evict());
+        assert_eq!(Some(1), replacer.evict());
+        assert_eq!(None, replacer.evict());
+
+        // a pinned frame is passed over
+        access(&mut replacer, 2, 200);
+        access(&mut replacer, 3, 201);
+        replacer.set_evictable(2, false);
+        assert_eq!(Some(3), replacer.evict());
+        assert_eq!(None, replacer.evict());
+        replacer.remove(2);
+        assert_eq!(0, replacer.get_size());
+    }
+}
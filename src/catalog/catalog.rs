@@ -2,9 +2,11 @@
 
 use std::{collections::HashMap, sync::{atomic::{AtomicI32, Ordering, AtomicU32}, Arc}, cell::RefCell};
 
-use crate::{buffer::buffer_pool_manager::BufferPoolManager, common::config::table_id_t, storage::page_based::{disk::log_manager::LogManager, table::{self, table_heap::TableHeap}}, transaction::{lock_manager::LockManager, transaction::{Transaction, TransactionRef}}, typedef::type_id::TypeId};
+use crate::{buffer::{access_strategy::{AccessIntent, BufferAccessStrategy}, buffer_pool_manager::BufferPoolManager}, common::{config::{file_id_t, index_id_t, page_id_t, table_id_t, MAIN_FILE_ID, TOAST_COMPRESSION}, rid::RID}, storage::{index::hash_index::HashIndex, page_based::{disk::log_manager::LogManager, table::{self, table_heap::TableHeap, tuple::Tuple}}}, transaction::{lock_manager::LockManager, transaction::{Transaction, TransactionRef}}, typedef::type_id::TypeId};
 
-use super::{schema::Schema, column::Column};
+use crate::binder::bound_expression::BoundExpression;
+
+use super::{column::Column, constraint::{CheckConstraint, ForeignKey}, schema::Schema, sequence::{Sequence, SequenceRef}};
 
 
 pub type TableInfoRef = Arc<TableInfo>;
@@ -18,6 +20,28 @@ pub struct TableInfo {
     pub table_oid: table_id_t,
 }
 
+pub type IndexInfoRef = Arc<IndexInfo>;
+#[derive(Debug)]
+pub struct IndexInfo {
+    pub key_schema: Schema,
+    pub index_name: String,
+    pub index: Arc<HashIndex>,
+    pub index_oid: index_id_t,
+    pub table_name: String,
+
+    // the column indices of the key in the table schema
+    pub key_attrs: Vec<usize>,
+    pub is_unique: bool,
+    pub is_primary_key: bool,
+}
+
+impl IndexInfo {
+    // build the key of this index from a tuple of the indexed table
+    pub fn get_key(&self, tuple: &Tuple, table_schema: &Schema) -> Tuple {
+        tuple.get_key(table_schema, &self.key_attrs, &self.key_schema)
+    }
+}
+
 pub type CataLogRef = Arc<RefCell<CataLog>>;
 
 #[derive(Debug)]
@@ -35,7 +59,23 @@ pub struct CataLog {
     table_id_generator: AtomicU32,
     
     // index meta infos
-    // TODO:
+    index_info: HashMap<index_id_t, IndexInfoRef>,
+    // table_name -> (index_name -> index_oid)
+    index_names: HashMap<String, HashMap<String, index_id_t>>,
+    index_id_generator: AtomicU32,
+
+    // constraint meta infos, the foreign keys declared by a (child) table
+    foreign_keys: HashMap<table_id_t, Vec<ForeignKey>>,
+    check_constraints: HashMap<table_id_t, Vec<CheckConstraint>>,
+    // the DEFAULT expression of each column, None means null
+    column_defaults: HashMap<table_id_t, Vec<Option<Box<BoundExpression>>>>,
+
+    // sequence meta infos
+    sequences: HashMap<String, SequenceRef>,
+    sequence_id_generator: AtomicU32,
+
+    // the file of each tablespace by name, the tables of the database file are in none of them
+    tablespaces: HashMap<String, file_id_t>,
 }
 
 
@@ -49,17 +89,43 @@ impl CataLog {
             table_info: HashMap::new(),
             table_name2id: HashMap::new(),
             table_id_generator: AtomicU32::new(0),
+            index_info: HashMap::new(),
+            index_names: HashMap::new(),
+            index_id_generator: AtomicU32::new(0),
+            foreign_keys: HashMap::new(),
+            check_constraints: HashMap::new(),
+            column_defaults: HashMap::new(),
+            sequences: HashMap::new(),
+            sequence_id_generator: AtomicU32::new(0),
+            tablespaces: HashMap::new(),
         }
     }
 
-    pub fn create_table(&mut self, txn: Option<TransactionRef>, table_name: &str, schema: Schema) -> Option<&TableInfoRef> {
+    pub fn create_table(&mut self, txn: Option<TransactionRef>, table_name: &str, schema: Schema) -> Result<&TableInfoRef, String> {
+        self.create_table_in(txn, table_name, schema, MAIN_FILE_ID, TOAST_COMPRESSION)
+    }
+
+    // create the table with its pages in the file of a tablespace, `compression` tells whether its large values are compressed
+    pub fn create_table_in(&mut self, txn: Option<TransactionRef>, table_name: &str, schema: Schema, file_id: file_id_t, compression: bool) -> Result<&TableInfoRef, String> {
         if self.table_name2id.contains_key(table_name) {
-            return None;
+            return Err(format!("relation \"{}\" already exists", table_name));
         }
 
         // create a table_heap
-        let table_heap = TableHeap::new(self.bpm.clone());
+        let table_heap = TableHeap::new_in(self.bpm.clone(), file_id)?.with_toaster(schema.clone(), compression);
+        Ok(self.add_table(table_name, schema, table_heap))
+    }
 
+    // the table created before a restart, its heap is opened from its first page
+    pub fn open_table_in(&mut self, table_name: &str, schema: Schema, file_id: file_id_t, compression: bool, first_page_id: page_id_t) -> Result<&TableInfoRef, String> {
+        if self.table_name2id.contains_key(table_name) {
+            return Err(format!("relation \"{}\" already exists", table_name));
+        }
+        let table_heap = TableHeap::open_in(self.bpm.clone(), file_id, first_page_id)?.with_toaster(schema.clone(), compression);
+        Ok(self.add_table(table_name, schema, table_heap))
+    }
+
+    fn add_table(&mut self, table_name: &str, schema: Schema, table_heap: TableHeap) -> &TableInfoRef {
         let table_id = self.table_id_generator.fetch_add(1, Ordering::Relaxed);
         let table_info = TableInfo {
             table_name: String::from(table_name),
@@ -71,10 +137,215 @@ impl CataLog {
         // insert to map
         self.table_name2id.insert(String::from(table_name), table_id);
         self.table_info.insert(table_id, Arc::new(table_info));
-        self.table_info.get(&table_id)
+        self.index_names.insert(String::from(table_name), HashMap::new());
+        &self.table_info[&table_id]
+    }
+
+    // create an index on `key_attrs` of the table, the existing tuples are indexed at once
+    pub fn create_index(&mut self, txn: Option<TransactionRef>, index_name: &str, table_name: &str, 
+        key_attrs: Vec<usize>, is_unique: bool, is_primary_key: bool) -> Result<&IndexInfoRef, String> {
+        let Some(table_info) = self.get_table(table_name).cloned() else {
+            return Err(format!("relation \"{}\" does not exist", table_name));
+        };
+        if self.index_names.get(table_name).map_or(false, |indexes| indexes.contains_key(index_name)) {
+            return Err(format!("relation \"{}\" already exists", index_name));
+        }
+
+        let mut key_cols = Vec::new();
+        for attr in &key_attrs {
+            key_cols.push(table_info.schema.get_column(*attr)?.clone());
+        }
+        let key_schema = Schema::new(&key_cols);
+        let index = HashIndex::new(key_schema.clone());
+
+        let index_oid = self.index_id_generator.fetch_add(1, Ordering::Relaxed);
+        let index_info = IndexInfo {
+            key_schema,
+            index_name: String::from(index_name),
+            index: Arc::new(index),
+            index_oid,
+            table_name: String::from(table_name),
+            key_attrs,
+            is_unique,
+            is_primary_key,
+        };
+
+        // populate the index with the live tuples
+        for tuple_pair in table_info.table_heap.clone().make_iterator_with(BufferAccessStrategy::new(AccessIntent::SequentialScan)) {
+            let (meta, tuple) = tuple_pair?;
+            if !meta.is_deleted {
+                let key = index_info.get_key(&tuple, &table_info.schema);
+                index_info.index.insert_entry(&key, tuple.get_rid());
+            }
+        }
+
+        self.index_names.entry(String::from(table_name)).or_insert(HashMap::new())
+            .insert(String::from(index_name), index_oid);
+        self.index_info.insert(index_oid, Arc::new(index_info));
+        Ok(&self.index_info[&index_oid])
     }
 
+    pub fn get_index(&self, index_oid: index_id_t) -> Option<&IndexInfoRef> {
+        self.index_info.get(&index_oid)
+    }
 
+    pub fn get_index_by_name(&self, table_name: &str, index_name: &str) -> Option<&IndexInfoRef> {
+        self.index_names.get(table_name)
+            .and_then(|indexes| indexes.get(index_name))
+            .and_then(|index_oid| self.index_info.get(index_oid))
+    }
+
+    pub fn get_table_indexes(&self, table_name: &str) -> Vec<IndexInfoRef> {
+        match self.index_names.get(table_name) {
+            None => Vec::new(),
+            Some(indexes) => {
+                let mut res: Vec<IndexInfoRef> = indexes.values()
+                    .filter_map(|index_oid| self.index_info.get(index_oid).cloned())
+                    .collect();
+                res.sort_by_key(|index| index.index_oid);
+                res
+            }
+        }
+    }
+
+    pub fn get_primary_key(&self, table_name: &str) -> Option<IndexInfoRef> {
+        self.get_table_indexes(table_name).into_iter()
+            .find(|index| index.is_primary_key)
+    }
+
+    // find a unique index whose key is exactly the given set of columns, in any order
+    pub fn find_unique_index(&self, table_name: &str, key_attrs: &Vec<usize>) -> Option<IndexInfoRef> {
+        let mut attrs = key_attrs.clone();
+        attrs.sort();
+        self.get_table_indexes(table_name).into_iter()
+            .find(|index| {
+                let mut index_attrs = index.key_attrs.clone();
+                index_attrs.sort();
+                index.is_unique && index_attrs == attrs
+            })
+    }
+
+    pub fn insert_index_entries(&self, table_name: &str, tuple: &Tuple, rid: RID) {
+        let Some(table_info) = self.get_table(table_name) else { return; };
+        for index_info in self.get_table_indexes(table_name) {
+            let key = index_info.get_key(tuple, &table_info.schema);
+            index_info.index.insert_entry(&key, rid);
+        }
+    }
+
+    pub fn delete_index_entries(&self, table_name: &str, tuple: &Tuple, rid: RID) {
+        let Some(table_info) = self.get_table(table_name) else { return; };
+        for index_info in self.get_table_indexes(table_name) {
+            let key = index_info.get_key(tuple, &table_info.schema);
+            index_info.index.delete_entry(&key, rid);
+        }
+    }
+
+    pub fn add_foreign_key(&mut self, fk: ForeignKey) -> Result<(), String> {
+        if self.get_table_byid(fk.table_oid).is_none() || self.get_table_byid(fk.ref_table_oid).is_none() {
+            return Err(format!("foreign key {} refers to a non-exist table", fk.name));
+        }
+        if self.get_index(fk.ref_index_oid).is_none() {
+            return Err(format!("foreign key {} refers to a non-exist index", fk.name));
+        }
+
+        self.foreign_keys.entry(fk.table_oid).or_insert(Vec::new()).push(fk);
+        Ok(())
+    }
+
+    // the foreign keys declared by the table
+    pub fn get_foreign_keys(&self, table_oid: table_id_t) -> Vec<ForeignKey> {
+        self.foreign_keys.get(&table_oid).cloned().unwrap_or_default()
+    }
+
+    // the foreign keys of all tables which refer to the table
+    pub fn get_referencing_foreign_keys(&self, ref_table_oid: table_id_t) -> Vec<ForeignKey> {
+        let mut res: Vec<ForeignKey> = self.foreign_keys.values()
+            .flatten()
+            .filter(|fk| fk.ref_table_oid == ref_table_oid)
+            .cloned()
+            .collect();
+        res.sort_by_key(|fk| fk.table_oid);
+        res
+    }
+
+
+
+    pub fn add_check_constraint(&mut self, check: CheckConstraint) -> Result<(), String> {
+        if self.get_table_byid(check.table_oid).is_none() {
+            return Err(format!("check constraint {} refers to a non-exist table", check.name));
+        }
+
+        let checks = self.check_constraints.entry(check.table_oid).or_insert(Vec::new());
+        if checks.iter().any(|c| c.name == check.name) {
+            return Err(format!("constraint \"{}\" already exists", check.name));
+        }
+        checks.push(check);
+        Ok(())
+    }
+
+    pub fn get_check_constraints(&self, table_oid: table_id_t) -> Vec<CheckConstraint> {
+        self.check_constraints.get(&table_oid).cloned().unwrap_or_default()
+    }
+
+    pub fn set_column_defaults(&mut self, table_oid: table_id_t, defaults: Vec<Option<Box<BoundExpression>>>) -> Result<(), String> {
+        let Some(table_info) = self.get_table_byid(table_oid) else {
+            return Err(format!("table {} not found", table_oid));
+        };
+        if table_info.schema.get_column_count() != defaults.len() {
+            return Err(format!("mismatch column size"));
+        }
+
+        self.column_defaults.insert(table_oid, defaults);
+        Ok(())
+    }
+
+    pub fn get_column_default(&self, table_oid: table_id_t, col_idx: usize) -> Option<&Box<BoundExpression>> {
+        self.column_defaults.get(&table_oid)
+            .and_then(|defaults| defaults.get(col_idx))
+            .and_then(|default| default.as_ref())
+    }
+
+    pub fn create_sequence(&mut self, name: &str, start_value: i32, increment: i32, 
+        min_value: i32, max_value: i32) -> Result<SequenceRef, String> {
+        if self.sequences.contains_key(name) {
+            return Err(format!("relation \"{}\" already exists", name));
+        }
+
+        let seq_oid = self.sequence_id_generator.fetch_add(1, Ordering::Relaxed);
+        let seq = Arc::new(Sequence::create(self.bpm.clone(), name, seq_oid, start_value, increment, min_value, max_value)?);
+        self.sequences.insert(String::from(name), seq.clone());
+        Ok(seq)
+    }
+
+    // the sequence created before a restart, it is loaded from its page
+    pub fn open_sequence(&mut self, name: &str, page_id: page_id_t) -> Result<SequenceRef, String> {
+        if self.sequences.contains_key(name) {
+            return Err(format!("relation \"{}\" already exists", name));
+        }
+
+        let seq_oid = self.sequence_id_generator.fetch_add(1, Ordering::Relaxed);
+        let seq = Arc::new(Sequence::open(self.bpm.clone(), name, seq_oid, page_id)?);
+        self.sequences.insert(String::from(name), seq.clone());
+        Ok(seq)
+    }
+
+    // a tablespace whose file is open in the buffer pool
+    pub fn add_tablespace(&mut self, name: &str, file_id: file_id_t) -> Result<(), String> {
+        if self.tablespaces.contains_key(name) {
+            return Err(format!("tablespace \"{}\" already exists", name));
+        }
+        self.tablespaces.insert(name.to_string(), file_id);
+        Ok(())
+    }
+
+    pub fn get_tablespace(&self, name: &str) -> Option<file_id_t> {
+        self.tablespaces.get(name).cloned()
+    }
+
+    pub fn get_sequence(&self, name: &str) -> Option<&SequenceRef> {
+        self.sequences.get(name)
+    }
 
     pub fn get_schema(&self, table_name: &str) -> Option<Schema> {
         match self.table_name2id.get(table_name) {
@@ -94,6 +365,10 @@ impl CataLog {
         }
     }
 
+    pub fn get_tables(&self) -> Vec<&TableInfoRef> {
+        self.table_info.values().collect()
+    }
+
     pub fn get_table_byid(&self, table_id: table_id_t) -> Option<&TableInfoRef> {
         self.table_info.get(&table_id)
     }
@@ -0,0 +1,225 @@
+#![allow(warnings)]
+
+use std::sync::{Arc, Mutex};
+
+use crate::{buffer::buffer_pool_manager::BufferPoolManager, common::config::{file_id_t, page_id_t, HEADER_PAGE_ID, INVALID_PAGE_ID}, storage::page_based::{disk::disk_manager::PageStore, page::{overflow_page::OverflowPage, superblock_page::SuperblockPage}}};
+
+
+// the pages a table was created on, it is opened on them again
+#[derive(Debug, Clone, PartialEq)]
+pub struct TablePages {
+    pub file_id: file_id_t,
+    pub first_page_id: page_id_t,
+    // the pages of the sequences of its serial columns, in the order they are created
+    pub sequence_pages: Vec<page_id_t>,
+}
+
+// a statement which built the catalog, with the pages it was given
+#[derive(Debug, Clone, PartialEq)]
+pub enum CatalogEntry {
+    Table { sql: String, pages: TablePages },
+    Sequence { sql: String, page_id: page_id_t },
+}
+
+#[derive(Debug)]
+struct CatalogStore_ {
+    entries: Vec<CatalogEntry>,
+    // the first page of the chain the entries are on, the one in the superblock
+    root: page_id_t,
+}
+
+/// CatalogStore, keeps the catalog in the database file.
+/// The catalog is kept as the CREATE TABLE / CREATE SEQUENCE statements which built it, each with the pages
+/// it was given, and it is built again at startup by running them on those pages (see `DBInstance::load_catalog`),
+/// the indexes are filled from the tables then.
+/// The entries are written to a new chain of overflow pages on every change, then `catalog_root` of the superblock
+/// is switched to it and the old chain is freed, so a crash leaves either the old catalog or the new one.
+/// Like the superblock the chain is written out instead of being logged, both through the pool
+#[derive(Debug)]
+pub struct CatalogStore {
+    bpm: Arc<BufferPoolManager>,
+    // the superblock is synced with the file, see `DBInstance::create_tablespace`
+    disk_mgr: Arc<dyn PageStore>,
+    state: Mutex<CatalogStore_>,
+}
+
+impl CatalogStore {
+    // read the entries from the chain the superblock points to, a new file has none
+    pub fn open(bpm: Arc<BufferPoolManager>, disk_mgr: Arc<dyn PageStore>) -> Result<Self, String> {
+        let root = bpm.fetch_page_read(HEADER_PAGE_ID)
+            .ok_or(format!("can not fetch the superblock, bpm err"))?
+            .get_as::<SuperblockPage>().get_catalog_root();
+
+        let mut bytes = Vec::new();
+        let mut pid = root;
+        while pid != INVALID_PAGE_ID {
+            let page_guard = bpm.fetch_page_read(pid)
+                .ok_or(format!("can not fetch page {} of the catalog, bpm err", pid))?;
+            let overflow_page = page_guard.get_as::<OverflowPage>();
+            bytes.extend_from_slice(overflow_page.get_data());
+            pid = overflow_page.get_next_page_id();
+        }
+        let entries = match root {
+            INVALID_PAGE_ID => Vec::new(),
+            _ => Self::deserialize(&bytes).map_err(|err| format!("Error: can not read the catalog from page {}, {}", root, err))?,
+        };
+
+        Ok(Self { bpm, disk_mgr, state: Mutex::new(CatalogStore_ { entries, root }) })
+    }
+
+    pub fn get_entries(&self) -> Vec<CatalogEntry> {
+        self.state.lock().unwrap().entries.clone()
+    }
+
+    // add the entry and write the catalog out, it is on disk when this returns
+    pub fn add(&self, entry: CatalogEntry) -> Result<(), String> {
+        let mut state = self.state.lock().unwrap();
+        let mut entries = state.entries.clone();
+        entries.push(entry);
+
+        // the first pages of the new tables are logged, the log goes before the catalog pointing to them
+        self.bpm.get_log_manager().flush_all()?;
+        let root = self.write_chain(&Self::serialize(&entries))?;
+
+        self.bpm.fetch_page_write(HEADER_PAGE_ID)
+            .ok_or(format!("can not fetch the superblock, bpm err"))?
+            .get_mut_as::<SuperblockPage>().set_catalog_root(root);
+        if !self.bpm.flush_page(HEADER_PAGE_ID) {
+            return Err(format!("Error: can not write the superblock"));
+        }
+        self.disk_mgr.sync()?;
+
+        // nothing points to the old chain now
+        let mut pid = state.root;
+        while pid != INVALID_PAGE_ID {
+            let next_page_id = self.bpm.fetch_page_read(pid)
+                .ok_or(format!("can not fetch page {} of the catalog, bpm err", pid))?
+                .get_as::<OverflowPage>().get_next_page_id();
+            self.bpm.delete_page(pid)?;
+            pid = next_page_id;
+        }
+        state.entries = entries;
+        state.root = root;
+        Ok(())
+    }
+
+    // the pages are written from the last one, so each is on disk before the one linking to it
+    fn write_chain(&self, data: &[u8]) -> Result<page_id_t, String> {
+        let page_size = self.bpm.get_page_size();
+        let mut next_page_id = INVALID_PAGE_ID;
+        for chunk in data.chunks(OverflowPage::capacity(page_size)).rev() {
+            let Some(mut page_guard) = self.bpm.new_page_guarded() else {
+                return Err(format!("Error: can not allocate a page for the catalog"));
+            };
+            let pid = page_guard.get_pid().unwrap();
+            let overflow_page = page_guard.get_mut_as::<OverflowPage>();
+            overflow_page.init();
+            overflow_page.set_data(chunk, page_size);
+            overflow_page.set_next_page_id(next_page_id);
+            drop(page_guard);
+            if !self.bpm.flush_page(pid) {
+                return Err(format!("Error: can not write page {} of the catalog", pid));
+            }
+            next_page_id = pid;
+        }
+        Ok(next_page_id)
+    }
+
+    // | num entries | then each | type | sql len | sql | and the pages of it |
+    fn serialize(entries: &Vec<CatalogEntry>) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        bytes.extend((entries.len() as u32).to_le_bytes());
+        for entry in entries {
+            let (type_id, sql) = match entry {
+                CatalogEntry::Table { sql, .. } => (0u8, sql),
+                CatalogEntry::Sequence { sql, .. } => (1u8, sql),
+            };
+            bytes.push(type_id);
+            bytes.extend((sql.len() as u32).to_le_bytes());
+            bytes.extend(sql.as_bytes());
+            match entry {
+                CatalogEntry::Table { pages, .. } => {
+                    bytes.extend((pages.file_id as u32).to_le_bytes());
+                    bytes.extend(pages.first_page_id.to_le_bytes());
+                    bytes.extend((pages.sequence_pages.len() as u32).to_le_bytes());
+                    for page_id in &pages.sequence_pages {
+                        bytes.extend(page_id.to_le_bytes());
+                    }
+                },
+                CatalogEntry::Sequence { page_id, .. } => {
+                    bytes.extend(page_id.to_le_bytes());
+                },
+            }
+        }
+        bytes
+    }
+
+    fn deserialize(bytes: &[u8]) -> Result<Vec<CatalogEntry>, String> {
+        let mut reader = Reader { bytes, offset: 0 };
+        let num_entries = reader.get_u32()?;
+        let mut entries = Vec::new();
+        for _ in 0..num_entries {
+            let type_id = reader.take(1)?[0];
+            let len = reader.get_u32()? as usize;
+            let sql = String::from_utf8(reader.take(len)?.to_vec()).map_err(|err| format!("invalid sql in the catalog, {}", err))?;
+            let entry = match type_id {
+                0 => {
+                    let file_id = reader.get_u32()? as file_id_t;
+                    let first_page_id = reader.get_page_id()?;
+                    let num_sequences = reader.get_u32()?;
+                    let sequence_pages = (0..num_sequences)
+                        .map(|_| reader.get_page_id())
+                        .collect::<Result<Vec<page_id_t>, String>>()?;
+                    CatalogEntry::Table { sql, pages: TablePages { file_id, first_page_id, sequence_pages } }
+                },
+                1 => CatalogEntry::Sequence { sql, page_id: reader.get_page_id()? },
+                _ => return Err(format!("unknown catalog entry type {}", type_id)),
+            };
+            entries.push(entry);
+        }
+        Ok(entries)
+    }
+}
+
+struct Reader<'a> {
+    bytes: &'a [u8],
+    offset: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, len: usize) -> Result<&'a [u8], String> {
+        let res = self.bytes.get(self.offset..self.offset + len).ok_or(format!("the catalog ends at byte {}", self.bytes.len()))?;
+        self.offset += len;
+        Ok(res)
+    }
+
+    fn get_u32(&mut self) -> Result<u32, String> {
+        Ok(u32::from_le_bytes(self.take(4)?.try_into().unwrap()))
+    }
+
+    fn get_page_id(&mut self) -> Result<page_id_t, String> {
+        Ok(page_id_t::from_le_bytes(self.take(size_of::<page_id_t>())?.try_into().unwrap()))
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::{CatalogEntry, CatalogStore, TablePages};
+
+    #[test]
+    fn serialize_test() -> Result<(), String> {
+        let entries = vec![
+            CatalogEntry::Sequence { sql: "CREATE SEQUENCE s".to_string(), page_id: 7 },
+            CatalogEntry::Table {
+                sql: "CREATE TABLE t (a SERIAL, b INT)".to_string(),
+                pages: TablePages { file_id: 1, first_page_id: 9, sequence_pages: vec![8] },
+            },
+        ];
+        let bytes = CatalogStore::serialize(&entries);
+        assert_eq!(entries, CatalogStore::deserialize(&bytes)?);
+        // a torn catalog is not taken
+        assert_eq!(true, CatalogStore::deserialize(&bytes[..bytes.len() - 1]).is_err());
+        Ok(())
+    }
+}
@@ -15,6 +15,7 @@ pub struct Column {
 
 impl Column {
     pub fn new(col_name: &str, t: TypeId) -> Self {
+        // a text or a blob has no length limit
         assert!(t != TypeId::VARCHAR, "Wrong constructor for variable column");
         Self {
             column_name: String::from(col_name),
@@ -47,7 +48,7 @@ impl Column {
     }
 
     pub fn is_inlined(&self) -> bool {
-        self.type_id != TypeId::VARCHAR
+        !self.type_id.is_variable_length()
     } 
 
     pub fn get_name(&self) -> String {
@@ -95,6 +96,10 @@ impl Column {
                 // set a non-zero temporarily
                 4
             },
+            // the offset of the value after the fixed part
+            TypeId::TEXT | TypeId::BLOB => {
+                4
+            },
             _ => {
                 panic!("Not supprted type value")
             }
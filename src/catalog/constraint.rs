@@ -0,0 +1,68 @@
+use std::fmt::Display;
+
+use crate::{binder::bound_expression::BoundExpression, common::config::{index_id_t, table_id_t}};
+
+
+/// the referential action taken on the child rows,
+/// when the referenced row in the parent table is deleted or its key is updated
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ForeignKeyAction {
+    // NO ACTION is treated the same as RESTRICT, since constraints are never deferred
+    Restrict,
+    Cascade,
+    SetNull,
+}
+
+impl Display for ForeignKeyAction {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Restrict => { f.write_str("RESTRICT") },
+            Self::Cascade => { f.write_str("CASCADE") },
+            Self::SetNull => { f.write_str("SET NULL") },
+        }
+    }
+}
+
+
+/// A foreign key from `table_oid(columns)` to `ref_table_oid(ref_columns)`.
+/// The referenced columns are always covered by a unique index of the parent table,
+/// which is used to look up the parent row when a child row is checked.
+#[derive(Debug, Clone)]
+pub struct ForeignKey {
+    pub name: String,
+
+    // the referencing (child) side
+    pub table_oid: table_id_t,
+    pub columns: Vec<usize>,
+
+    // the referenced (parent) side
+    pub ref_table_oid: table_id_t,
+    pub ref_columns: Vec<usize>,
+    pub ref_index_oid: index_id_t,
+
+    pub on_delete: ForeignKeyAction,
+    pub on_update: ForeignKeyAction,
+}
+
+impl ForeignKey {
+    pub fn to_string(&self) -> String {
+        format!("ForeignKey {{{{ name={}, table_oid={}, columns={:?}, ref_table_oid={}, ref_columns={:?}, on_delete={}, on_update={} }}}}",
+            self.name, self.table_oid, self.columns, self.ref_table_oid, self.ref_columns, self.on_delete, self.on_update)
+    }
+}
+
+
+/// A CHECK constraint, the row is rejected when `expr` is evaluated to false on it.
+/// The columns in `expr` are bound to the table, and planned again by each insert/update.
+#[derive(Debug, Clone)]
+pub struct CheckConstraint {
+    pub name: String,
+    pub table_oid: table_id_t,
+    pub expr: Box<BoundExpression>,
+}
+
+impl CheckConstraint {
+    pub fn to_string(&self) -> String {
+        format!("Check {{{{ name={}, table_oid={}, expr={} }}}}", self.name, self.table_oid, self.expr)
+    }
+}
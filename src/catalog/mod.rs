@@ -1,3 +1,6 @@
 pub mod column;
 pub mod schema;
-pub mod catalog;
\ No newline at end of file
+pub mod catalog;
+pub mod constraint;
+pub mod sequence;
+pub mod catalog_store;
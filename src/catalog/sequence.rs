@@ -0,0 +1,167 @@
+#![allow(warnings)]
+
+use std::sync::{atomic::{AtomicI64, Ordering}, Arc, Mutex};
+
+use crate::{buffer::buffer_pool_manager::BufferPoolManager, common::config::{page_id_t, seq_id_t, SEQUENCE_CACHE_SIZE}, storage::page_based::page::sequence_page::SequencePage};
+
+
+#[derive(Debug)]
+struct Sequence_ {
+    // the last value persisted on the page, the values up to it can be handed out without I/O
+    reserved: i64,
+    // the value returned by the last nextval, for currval
+    last_value: Option<i32>,
+}
+
+pub type SequenceRef = Arc<Sequence>;
+
+/// A sequence generates distinct integers.
+/// The values are taken from an atomic counter, like the oid generators of the catalog,
+/// and a batch of `SEQUENCE_CACHE_SIZE` values is reserved on the sequence page before they are handed out.
+/// After a restart the counter starts beyond the reserved batch, so a value never repeats.
+#[derive(Debug)]
+pub struct Sequence {
+    pub name: String,
+    pub seq_oid: seq_id_t,
+    pub page_id: page_id_t,
+
+    pub increment: i32,
+    pub min_value: i32,
+    pub max_value: i32,
+
+    bpm: Arc<BufferPoolManager>,
+    next_value: AtomicI64,
+    state: Mutex<Sequence_>,
+}
+
+impl Sequence {
+    // allocate a sequence page, and persist the options of the sequence
+    pub fn create(bpm: Arc<BufferPoolManager>, name: &str, seq_oid: seq_id_t,
+        start_value: i32, increment: i32, min_value: i32, max_value: i32) -> Result<Self, String> {
+        if increment == 0 {
+            return Err(format!("INCREMENT must not be zero"));
+        }
+        if min_value >= max_value {
+            return Err(format!("MINVALUE ({}) must be less than MAXVALUE ({})", min_value, max_value));
+        }
+        if start_value < min_value || start_value > max_value {
+            return Err(format!("START value ({}) cannot be out of range [{}, {}]", start_value, min_value, max_value));
+        }
+
+        let page_id;
+        {
+            let Some(mut page_guard) = bpm.new_page_guarded() else {
+                return Err(format!("can not allocate a page for sequence {}", name));
+            };
+            page_id = page_guard.get_pid().unwrap();
+            let seq_page = page_guard.get_mut_as::<SequencePage>();
+            seq_page.init(start_value, increment, min_value, max_value);
+        }
+        bpm.flush_page(page_id);
+
+        Self::open(bpm, name, seq_oid, page_id)
+    }
+
+    // load a sequence from its page
+    pub fn open(bpm: Arc<BufferPoolManager>, name: &str, seq_oid: seq_id_t, page_id: page_id_t) -> Result<Self, String> {
+        let Some(page_guard) = bpm.fetch_page_read(page_id) else {
+            return Err(format!("can not fetch page {} of sequence {}", page_id, name));
+        };
+        let seq_page = page_guard.get_as::<SequencePage>();
+        if !seq_page.is_valid() {
+            return Err(format!("page {} is not a sequence page", page_id));
+        }
+
+        let increment = seq_page.get_increment() as i64;
+        let last_value = seq_page.get_last_value() as i64;
+        let (next_value, reserved) = if seq_page.is_called() {
+            (last_value + increment, last_value)
+        } else {
+            (last_value, last_value - increment)
+        };
+
+        Ok(Self {
+            name: name.to_owned(),
+            seq_oid,
+            page_id,
+            increment: seq_page.get_increment(),
+            min_value: seq_page.get_min_value(),
+            max_value: seq_page.get_max_value(),
+            bpm: bpm.clone(),
+            next_value: AtomicI64::new(next_value),
+            state: Mutex::new(Sequence_ { reserved, last_value: None }),
+        })
+    }
+
+    pub fn next_value(&self) -> Result<i32, String> {
+        let increment = self.increment as i64;
+        let val = self.next_value.fetch_add(increment, Ordering::SeqCst);
+        if val > self.max_value as i64 {
+            return Err(format!("nextval: reached maximum value of sequence \"{}\" ({})", self.name, self.max_value));
+        }
+        if val < self.min_value as i64 {
+            return Err(format!("nextval: reached minimum value of sequence \"{}\" ({})", self.name, self.min_value));
+        }
+
+        let mut state = self.state.lock().unwrap();
+        let exceeded = if increment > 0 { val > state.reserved } else { val < state.reserved };
+        if exceeded {
+            // reserve the next batch on the page before the value is handed out
+            let reserved = (val + increment * (SEQUENCE_CACHE_SIZE - 1))
+                .clamp(self.min_value as i64, self.max_value as i64);
+            {
+                let Some(mut page_guard) = self.bpm.fetch_page_write(self.page_id) else {
+                    return Err(format!("can not fetch page {} of sequence {}", self.page_id, self.name));
+                };
+                page_guard.get_mut_as::<SequencePage>().set_last_value(reserved as i32);
+            }
+            self.bpm.flush_page(self.page_id);
+            state.reserved = reserved;
+        }
+
+        state.last_value = Some(val as i32);
+        Ok(val as i32)
+    }
+
+    pub fn current_value(&self) -> Result<i32, String> {
+        self.state.lock().unwrap().last_value
+            .ok_or(format!("currval of sequence \"{}\" is not yet defined", self.name))
+    }
+}
+
+
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use crate::{buffer::buffer_pool_manager::BufferPoolManager, common::config::SEQUENCE_CACHE_SIZE, storage::page_based::disk::{log_manager::LogManager, memory_page_store::MemoryPageStore}};
+
+    use super::Sequence;
+
+    #[test]
+    fn next_value_reopen_test() -> Result<(), String> {
+        let disk_mgr = Arc::new(MemoryPageStore::new());
+        let bpm = Arc::new(BufferPoolManager::new(16, disk_mgr, 2, Arc::new(LogManager::new())));
+
+        let seq = Sequence::create(bpm.clone(), "s", 0, 1, 1, 1, i32::MAX)?;
+        assert!(seq.current_value().is_err());
+        assert_eq!(1, seq.next_value()?);
+        assert_eq!(2, seq.next_value()?);
+        assert_eq!(2, seq.current_value()?);
+
+        // the handed out values are never returned again, the unused reserved ones are skipped
+        let seq = Sequence::open(bpm.clone(), "s", 0, seq.page_id)?;
+        assert_eq!(1 + SEQUENCE_CACHE_SIZE as i32, seq.next_value()?);
+
+        // bounds
+        let seq = Sequence::create(bpm.clone(), "t", 1, 10, -5, 0, 10)?;
+        assert_eq!(10, seq.next_value()?);
+        assert_eq!(5, seq.next_value()?);
+        assert_eq!(0, seq.next_value()?);
+        assert!(seq.next_value().is_err());
+
+        assert!(Sequence::create(bpm.clone(), "u", 2, 1, 0, 1, 10).is_err());
+        Ok(())
+    }
+}
@@ -1,23 +1,69 @@
 #![allow(warnings)]
 
-pub const INVALID_PAGE_ID: i32 = -1;
+pub const INVALID_PAGE_ID: page_id_t = -1;
 pub const INVALID_TXN_ID: i32 = -1;
 pub const INVALID_LSN: i32 = -1;
-pub const HEADER_PAGE_ID: i32 = 0;
+pub const INVALID_TS: i64 = -1;
+// the superblock of the file, see `SuperblockPage`
+pub const HEADER_PAGE_ID: page_id_t = 0;
+// a page id is the address (file_id, page_no) of the page, the page number takes the low bits, see `PageAddr`.
+// a file has up to 2^32 pages, 16 TiB with the default page size
+pub const PAGE_NO_BITS: u32 = 32;
+// the database file, the ids of its pages are the page numbers
+pub const MAIN_FILE_ID: file_id_t = 0;
+// the tablespace files get the ids after it, the file id takes the 16 bits above the page number
+// so a page id stays positive, the superblock lists fewer tablespaces though, see `MAX_TABLESPACES`
+pub const MAX_FILE_ID: file_id_t = file_id_t::MAX;
+// the page size of a database created without one, a file keeps the page size it is created with
 pub const PAGE_SIZE: i32 = 1 << 12;
+// the page sizes a database may be created with, the powers of two between them
+pub const MIN_PAGE_SIZE: usize = 1 << 12;
+pub const MAX_PAGE_SIZE: usize = 1 << 16;
 pub const BUFFER_POOL_SIZE: i32 = 10;
 pub const BUCKET_SIZE: i32 = 50;
 pub const LRUK_REPLACER_K: i32 = 10;
+// the buffer pool of an instance is partitioned by page id, each part has its own latch
+pub const BUFFER_POOL_INSTANCES: usize = 4;
+// the frames of the ring of a sequential scan and of a bulk write, spread over the instances of the pool
+pub const SCAN_RING_SIZE: usize = 16;
+pub const BULK_WRITE_RING_SIZE: usize = 32;
+// the worker threads of the disk scheduler of each buffer pool instance
+pub const DISK_SCHEDULER_WORKERS: usize = 2;
+// how many pages a sequential scan reads ahead
+pub const READ_AHEAD_PAGES: usize = 8;
+// the buffers of O_DIRECT i/o are aligned to the logical block size
+pub const DIRECT_IO_ALIGNMENT: usize = 4096;
+// the submission queue size of an io_uring, a batch of more runs is submitted in parts
+pub const IO_URING_ENTRIES: u32 = 64;
+// the pages are written to a side file first, so that a write torn by a crash can be restored
+pub const DOUBLE_WRITE_BUFFER: bool = true;
 
 pub const VARCHAR_DEFAULT_LENGTH: u32 = 128;
+// the large values of a table are compressed before they are moved to the overflow pages, unless WITH (compression = off)
+pub const TOAST_COMPRESSION: bool = true;
+// how many sequence values are reserved on the sequence page at a time
+pub const SEQUENCE_CACHE_SIZE: i64 = 32;
+// the interval of the deadlock detection
+pub const DEADLOCK_DETECTION_INTERVAL_MS: u64 = 50;
+// the log buffer is flushed before it grows beyond this size
+pub const LOG_BUFFER_SIZE: usize = 32 * PAGE_SIZE as usize;
+// how long the log flush thread waits before writing the buffered records out by itself
+pub const LOG_FLUSH_INTERVAL_MS: u64 = 10;
+// the interval of the fuzzy checkpoints
+pub const CHECKPOINT_INTERVAL_MS: u64 = 30_000;
+// the interval of the background vacuum, it goes through the pages with deleted tuples
+pub const VACUUM_INTERVAL_MS: u64 = 1_000;
 
 
 // =================== define the type alias ================
 pub type frame_id_t = usize;
-pub type page_id_t = i32;
+pub type page_id_t = i64;
+pub type file_id_t = u16;
 pub type txn_id_t = i32;
 pub type lsn_t = i32;
+pub type timestamp_t = i64;
 pub type slot_id_t = u16;
 pub type oid_t = u16;
 pub type table_id_t = u32;
-pub type index_id_t = u32;
\ No newline at end of file
+pub type index_id_t = u32;
+pub type seq_id_t = u32;
\ No newline at end of file
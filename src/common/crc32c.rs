@@ -0,0 +1,49 @@
+#![allow(warnings)]
+
+// CRC-32C (Castagnoli), the reflected polynomial
+const POLY: u32 = 0x82F6_3B78;
+
+const TABLE: [u32; 256] = make_table();
+
+const fn make_table() -> [u32; 256] {
+    let mut table = [0; 256];
+    let mut i = 0;
+    while i < 256 {
+        let mut crc = i as u32;
+        let mut bit = 0;
+        while bit < 8 {
+            crc = if crc & 1 == 1 { (crc >> 1) ^ POLY } else { crc >> 1 };
+            bit += 1;
+        }
+        table[i] = crc;
+        i += 1;
+    }
+    table
+}
+
+pub fn crc32c(data: &[u8]) -> u32 {
+    crc32c_append(0, data)
+}
+
+// go on with the crc of the bytes before, crc32c(a ++ b) == crc32c_append(crc32c(a), b)
+pub fn crc32c_append(crc: u32, data: &[u8]) -> u32 {
+    let mut crc = !crc;
+    for byte in data {
+        crc = TABLE[((crc ^ *byte as u32) & 0xff) as usize] ^ (crc >> 8);
+    }
+    !crc
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::{crc32c, crc32c_append};
+
+    #[test]
+    fn test_crc32c() {
+        assert_eq!(0, crc32c(&[]));
+        assert_eq!(0xE306_9283, crc32c(b"123456789"));
+        assert_eq!(0x8A91_36AA, crc32c(&[0; 32]));
+        assert_eq!(crc32c(b"123456789"), crc32c_append(crc32c(b"1234"), b"56789"));
+    }
+}
@@ -1,60 +1,501 @@
 #![allow(warnings)]
-use std::{sync::{Arc, RwLock}, vec, cell::RefCell};
+use std::{cell::RefCell, fs::File, io::Read, sync::{Arc, RwLock}, time::Duration, vec};
 
-use sqlparser::{dialect::GenericDialect, ast::Statement};
+use sqlparser::{dialect::GenericDialect, ast::{Statement, TransactionIsolationLevel, TransactionMode}, parser::Parser, tokenizer::{Token, TokenWithSpan, Tokenizer}};
 
-use crate::{binder::{binder::Binder, bound_statement::BoundStatement, statement::create_stmt::CreateStmt}, buffer::buffer_pool_manager::BufferPoolManager, catalog::{catalog::{CataLog, CataLogRef}, column::Column, schema::Schema}, execution::{execute_engine::ExecuteEngine, executor_context::{ExecutorContext, ExecutorContextRef}}, planner::planner::Planner, storage::page_based::{disk::{disk_manager::DiskManager, log_manager::LogManager}, table::tuple::Tuple}, transaction::{lock_manager::{LockManager, LockManagerRef}, transaction::{Transaction, TransactionRef}}, typedef::type_id::TypeId};
+use crate::{binder::{binder::Binder, bound_statement::BoundStatement, statement::{create_sequence_stmt::CreateSequenceStmt, create_stmt::CreateStmt}}, buffer::{buffer_pool_manager::BufferPoolManager, replacer::ReplacerPolicy}, catalog::{catalog::{CataLog, CataLogRef}, catalog_store::{CatalogEntry, CatalogStore, TablePages}, column::Column, constraint::{CheckConstraint, ForeignKey}, schema::Schema}, execution::{execute_engine::ExecuteEngine, executor_context::{ExecutorContext, ExecutorContextRef}}, planner::planner::Planner, recovery::{checkpoint_manager::{CheckpointManager, Checkpointer}, log_recovery::LogRecovery}, storage::page_based::{disk::{disk_manager::{DiskBackend, PageStore}, double_write_buffer::DoubleWriteBuffer, log_manager::{LogFlusher, LogManager}, memory_page_store::MemoryPageStore, tablespace::{Tablespace, TablespaceStore}}, page::{page::Page, superblock_page::SuperblockPage}, table::tuple::Tuple}, transaction::{deadlock_detector::DeadlockDetector, lock_manager::{DeadlockPolicy, LockManager, LockManagerRef}, transaction::{IsolationLevel, Transaction, TransactionRef, TransactionState}, transaction_manager::{TransactionManager, TransactionManagerRef}, vacuum_manager::{VacuumManager, VacuumWorker}}, typedef::type_id::TypeId};
 
-use super::{config::LRUK_REPLACER_K, formatwriter::{DefaultFormatWriter, FormatWriter}};
+use super::{page_addr::PageAddr, config::{file_id_t, MAIN_FILE_ID, BUFFER_POOL_INSTANCES, CHECKPOINT_INTERVAL_MS, VACUUM_INTERVAL_MS, DEADLOCK_DETECTION_INTERVAL_MS, DOUBLE_WRITE_BUFFER, HEADER_PAGE_ID, LOG_FLUSH_INTERVAL_MS, MIN_PAGE_SIZE, PAGE_SIZE, LRUK_REPLACER_K, TOAST_COMPRESSION, page_id_t, txn_id_t}, formatwriter::{DefaultFormatWriter, FormatWriter}};
 
 
 
 pub struct DBInstance {
-    disk_mgr: Arc<DiskManager>,
+    disk_mgr: Arc<dyn PageStore>,
+    // the files of the database and its tablespaces, the disk_mgr writes to them
+    tablespace_store: Arc<TablespaceStore>,
+    // how the files are opened, None when the pages are kept in memory
+    backend: Option<DiskBackend>,
     bp_mgr: Arc<BufferPoolManager>,
     lock_mgr: LockManagerRef,
     log_mgr: Arc<LogManager>,
+    // the group commit thread of the log
+    log_flusher: LogFlusher,
+    txn_mgr: TransactionManagerRef,
+    checkpoint_mgr: Arc<CheckpointManager>,
+    // takes the checkpoints in the background
+    checkpointer: Checkpointer,
+    vacuum_mgr: Arc<VacuumManager>,
+    // vacuums the tables in the background
+    vacuum_worker: VacuumWorker,
+    // runs only with the Detection policy
+    deadlock_detector: Option<DeadlockDetector>,
+    // the transaction opened by BEGIN, the statements out of it commit by themselves
+    session_txn: Option<TransactionRef>,
+    // whether the transaction block ran a statement, its isolation level can not be changed then
+    session_txn_used: bool,
+    // the isolation level of the new transactions, set by SET SESSION CHARACTERISTICS
+    default_isolation_level: IsolationLevel,
 
     catalog: RwLock<CataLogRef>,
+    // the catalog on disk
+    catalog_store: CatalogStore,
     
     execute_engine: ExecuteEngine,
 }
 
 impl DBInstance {
     pub fn new(db_filename: &str) -> Result<Self, String> {
+        Self::new_with_policy(db_filename, DeadlockPolicy::Detection)
+    }
+
+    pub fn new_with_policy(db_filename: &str, policy: DeadlockPolicy) -> Result<Self, String> {
+        // seek and read/write behind a lock on the file, the others are asked for with `new_with_backend`
+        Self::new_with_backend(db_filename, policy, DiskBackend::Buffered)
+    }
+
+    // the file is read and written through the backend, e.g. with O_DIRECT or an io_uring
+    pub fn new_with_backend(db_filename: &str, policy: DeadlockPolicy, backend: DiskBackend) -> Result<Self, String> {
+        Self::new_with_page_size(db_filename, policy, backend, PAGE_SIZE as usize)
+    }
+
+    // a new file is created with the page size, an existing one must have been created with it
+    pub fn new_with_page_size(db_filename: &str, policy: DeadlockPolicy, backend: DiskBackend, page_size: usize) -> Result<Self, String> {
         // create the necessary components
-        
-        let disk_mgr = Arc::new(DiskManager::new(db_filename)?);
-        let log_mgr = Arc::new(LogManager::new());
+        SuperblockPage::check_page_size(page_size)?;
+        let tablespaces = Self::check_file_header(db_filename, page_size, MAIN_FILE_ID)?;
+        let tablespace_store = Arc::new(TablespaceStore::new(backend.open_with_page_size(db_filename, page_size)?));
+        // the files of the tablespaces are opened with the database file, the log and the double write buffer cover them too
+        for tablespace in tablespaces.iter() {
+            Self::check_file_header(&tablespace.path, page_size, tablespace.file_id)
+                .map_err(|err| format!("Error: can not open tablespace \"{}\", {}", tablespace.name, err))?;
+            tablespace_store.add_file(tablespace.file_id, backend.open_with_page_size(&tablespace.path, page_size)?)?;
+        }
+        let mut disk_mgr: Arc<dyn PageStore> = tablespace_store.clone();
+        // the torn pages are restored before the log is replayed on them
+        if DOUBLE_WRITE_BUFFER {
+            disk_mgr = Arc::new(DoubleWriteBuffer::open(disk_mgr, &DoubleWriteBuffer::filename_of(db_filename))?);
+        }
+        let log_mgr = Arc::new(LogManager::open(&LogManager::log_filename_of(db_filename))?);
+        // bring the file back to the log before any page is read
+        let mut recovery = LogRecovery::new(disk_mgr.clone(), log_mgr.clone());
+        recovery.recover()?;
+        Self::create(disk_mgr, tablespace_store, Some(backend), log_mgr, policy, recovery.get_next_txn_id())
+    }
+
+    // a scratch database, the pages and the log are kept in memory and gone with the instance
+    pub fn open_in_memory() -> Result<Self, String> {
+        Self::open_in_memory_with_policy(DeadlockPolicy::Detection)
+    }
+
+    pub fn open_in_memory_with_policy(policy: DeadlockPolicy) -> Result<Self, String> {
+        Self::open_in_memory_with_page_size(policy, PAGE_SIZE as usize)
+    }
+
+    pub fn open_in_memory_with_page_size(policy: DeadlockPolicy, page_size: usize) -> Result<Self, String> {
+        SuperblockPage::check_page_size(page_size)?;
+        let tablespace_store = Arc::new(TablespaceStore::new(Arc::new(MemoryPageStore::new_with_page_size(page_size))));
+        Self::create(tablespace_store.clone(), tablespace_store, None, Arc::new(LogManager::new()), policy, 0)
+    }
+
+    pub fn get_page_size(&self) -> usize {
+        self.disk_mgr.get_page_size()
+    }
+
+    // create a tablespace with its file in the directory, the tables may be placed in it by
+    // CREATE TABLE ... WITH (tablespace = 'name'). the file is ready before the database file lists it,
+    // so a crash in between leaves a file which is not part of the database.
+    // the superblock is changed in the pool and written out through it, the double write buffer covers it then
+    pub fn create_tablespace(&self, name: &str, location: &str) -> Result<file_id_t, String> {
+        let write_guard = self.catalog.write().unwrap();
+        let mut catalog = write_guard.borrow_mut();
+        let page_size = self.get_page_size();
+        let mut page_guard = self.bp_mgr.fetch_page_write(HEADER_PAGE_ID)
+            .ok_or(format!("can not fetch the superblock, bpm err"))?;
+        let superblock = page_guard.get_mut_as::<SuperblockPage>();
+        let file_id = superblock.get_tablespaces().iter().map(|tablespace| tablespace.file_id).max().unwrap_or(MAIN_FILE_ID) + 1;
+        let tablespace = Tablespace { file_id, name: name.to_string(), path: Tablespace::path_in(location, name) };
+        superblock.add_tablespace(&tablespace)?;
+
+        let store = match self.backend {
+            Some(backend) => {
+                std::fs::create_dir_all(location)
+                    .map_err(|err| format!("Error: can not create directory {}, err is {}", location, err))?;
+                if std::path::Path::new(&tablespace.path).exists() {
+                    return Err(format!("the file {} of tablespace \"{}\" exists already", tablespace.path, name));
+                }
+                backend.open_with_page_size(&tablespace.path, page_size)?
+            },
+            None => Arc::new(MemoryPageStore::new_with_page_size(page_size)),
+        };
+        let mut file_header = Page::new_with_size(page_size);
+        file_header.cast_as_mut::<SuperblockPage>().init_file(page_size, file_id);
+        store.write_page(HEADER_PAGE_ID, file_header.get_data())?;
+        store.sync()?;
+        self.tablespace_store.add_file(file_id, store)?;
 
-        let bpm = Arc::new(BufferPoolManager::new(
+        drop(page_guard);
+        if !self.bp_mgr.flush_page(HEADER_PAGE_ID) {
+            return Err(format!("Error: can not write the superblock"));
+        }
+        self.disk_mgr.sync()?;
+        self.bp_mgr.open_free_space_map_of(file_id, 1)?;
+        catalog.add_tablespace(name, file_id)?;
+        Ok(file_id)
+    }
+
+    // the superblock at the front of an existing file, checked before the store is opened with the page size,
+    // the tablespaces it lists are returned. the file of a tablespace is there since it is created before it is listed
+    fn check_file_header(filename: &str, page_size: usize, file_id: file_id_t) -> Result<Vec<Tablespace>, String> {
+        let mut header = Vec::with_capacity(MIN_PAGE_SIZE);
+        if let Ok(mut file) = File::open(filename) {
+            file.by_ref().take(MIN_PAGE_SIZE as u64).read_to_end(&mut header)
+                .map_err(|err| format!("Error: can not read the header of {}, err is {}", filename, err))?;
+        }
+        match header.is_empty() {
+            // the store creates it
+            true if file_id == MAIN_FILE_ID => return Ok(Vec::new()),
+            true => return Err(format!("the file {} is missing", filename)),
+            false => header.resize(MIN_PAGE_SIZE, 0),
+        }
+        let superblock = unsafe { &*(header.as_ptr() as *const SuperblockPage) };
+        superblock.validate_file(page_size, file_id).map_err(|err| format!("Error: can not open {}, {}", filename, err))?;
+        Ok(superblock.get_tablespaces())
+    }
+
+    // the superblock of a new file is written before any other page, the one of an existing file is checked again
+    // with its checksum, after the double write buffer restored it if it was torn. the tablespaces are returned
+    fn open_superblock(disk_mgr: &Arc<dyn PageStore>) -> Result<Vec<Tablespace>, String> {
+        let page_size = disk_mgr.get_page_size();
+        let mut page = Page::new_with_size(page_size);
+        if disk_mgr.get_filesize() == 0 {
+            page.cast_as_mut::<SuperblockPage>().init(page_size);
+            disk_mgr.write_page(HEADER_PAGE_ID, page.get_data())?;
+            disk_mgr.sync()?;
+            return Ok(Vec::new());
+        }
+        disk_mgr.read_page(HEADER_PAGE_ID, page.get_mut_data())?;
+        let superblock = page.cast_as::<SuperblockPage>();
+        superblock.validate_file(page_size, MAIN_FILE_ID)?;
+        let tablespaces = superblock.get_tablespaces();
+        for tablespace in tablespaces.iter() {
+            disk_mgr.read_page(PageAddr::new(tablespace.file_id, HEADER_PAGE_ID).to_page_id(), page.get_mut_data())?;
+            page.cast_as::<SuperblockPage>().validate_file(page_size, tablespace.file_id)?;
+        }
+        Ok(tablespaces)
+    }
+
+    // the transactions start from `next_txn_id`, the ones before it are over after the recovery
+    fn create(disk_mgr: Arc<dyn PageStore>, tablespace_store: Arc<TablespaceStore>, backend: Option<DiskBackend>,
+        log_mgr: Arc<LogManager>, policy: DeadlockPolicy, next_txn_id: txn_id_t) -> Result<Self, String> {
+        let tablespaces = Self::open_superblock(&disk_mgr)?;
+        let log_flusher = LogFlusher::new(log_mgr.clone(), Duration::from_millis(LOG_FLUSH_INTERVAL_MS));
+
+        let bpm = Arc::new(BufferPoolManager::new_with_replacer(
+            BUFFER_POOL_INSTANCES,
             128, 
             disk_mgr.clone(), 
-            LRUK_REPLACER_K as usize, 
+            ReplacerPolicy::LruK(LRUK_REPLACER_K as usize), 
             log_mgr.clone())
         );
+        // the pages are allocated by the free space map of the file, the new ones go after the ones in it
+        bpm.open_free_space_map((disk_mgr.get_filesize() as usize / disk_mgr.get_page_size()) as page_id_t)?;
+        for tablespace in tablespaces.iter() {
+            let filesize = tablespace_store.get_filesize_of(tablespace.file_id).unwrap();
+            bpm.open_free_space_map_of(tablespace.file_id, (filesize as usize / disk_mgr.get_page_size()) as page_id_t)?;
+        }
         
-        let lock_mgr = LockManagerRef::new(LockManager::new());
+        let lock_mgr = LockManagerRef::new(LockManager::with_policy(policy));
+        let deadlock_detector = match policy {
+            DeadlockPolicy::Detection => Some(DeadlockDetector::new(lock_mgr.clone(), Duration::from_millis(DEADLOCK_DETECTION_INTERVAL_MS))),
+            _ => None,
+        };
+        let txn_mgr = TransactionManagerRef::new(TransactionManager::new(lock_mgr.clone(), log_mgr.clone()).with_next_txn_id(next_txn_id));
+        let checkpoint_mgr = Arc::new(CheckpointManager::new(txn_mgr.clone(), bpm.clone(), log_mgr.clone()));
+        let checkpointer = Checkpointer::new(checkpoint_mgr.clone(), Duration::from_millis(CHECKPOINT_INTERVAL_MS));
+        let vacuum_mgr = Arc::new(VacuumManager::new(txn_mgr.clone()));
+        let vacuum_worker = VacuumWorker::new(vacuum_mgr.clone(), Duration::from_millis(VACUUM_INTERVAL_MS));
         // create catalog
         let catalog = Arc::new(
             RefCell::new(CataLog::new(bpm.clone(), lock_mgr.clone(), log_mgr.clone()))
         );
+        for tablespace in tablespaces {
+            catalog.borrow_mut().add_tablespace(&tablespace.name, tablespace.file_id)?;
+        }
+        let catalog_store = CatalogStore::open(bpm.clone(), disk_mgr.clone())?;
         
         // create execute engine
         let execute_engine = ExecuteEngine::new();
 
-        Ok(Self {
+        let instance = Self {
             disk_mgr,
+            tablespace_store,
+            backend,
             bp_mgr: bpm,
             lock_mgr,
             log_mgr,
+            log_flusher,
+            txn_mgr,
+            checkpoint_mgr,
+            checkpointer,
+            vacuum_mgr,
+            vacuum_worker,
+            deadlock_detector,
+            session_txn: None,
+            session_txn_used: false,
+            default_isolation_level: IsolationLevel::SnapshotIsolation,
             catalog: RwLock::new(catalog),
+            catalog_store,
             execute_engine,
-        })
+        };
+        instance.load_catalog()?;
+        Ok(instance)
     }
 
-    pub fn execute_sql(sql: &str) {
-        
+    // build the catalog again from the statements in the store, on the pages they were given
+    fn load_catalog(&self) -> Result<(), String> {
+        for entry in self.catalog_store.get_entries() {
+            let (CatalogEntry::Table { sql, .. } | CatalogEntry::Sequence { sql, .. }) = &entry;
+            let (mut binder, stmts) = self.parse_sql(sql)
+                .map_err(|err| format!("Error: can not load the catalog, {} in {}", err, sql))?;
+            match (binder.bind_statement(&stmts[0])?, &entry) {
+                (BoundStatement::Create(create), CatalogEntry::Table { pages, .. }) => {
+                    let write_guard = self.catalog.write().unwrap();
+                    self.create_table(&mut write_guard.borrow_mut(), &create, Some(pages))?;
+                },
+                (BoundStatement::CreateSequence(create_seq), CatalogEntry::Sequence { page_id, .. }) => {
+                    self.catalog.write().unwrap().borrow_mut().open_sequence(&create_seq.name, *page_id)?;
+                },
+                _ => return Err(format!("Error: can not load the catalog, {} does not match its entry", sql)),
+            }
+        }
+        Ok(())
+    }
+
+    // execute a bunch of statements in the session, handles BEGIN/COMMIT/ROLLBACK/SAVEPOINT/SET TRANSACTION.
+    // out of a transaction block each statement runs in its own transaction
+    pub fn execute_sql(&mut self, sql: &str) -> Result<Vec<(Schema, Vec<Tuple>)>, String> {
+        // sqlparser does not take VACUUM, it is a statement of its own
+        if let Some(table_name) = Self::parse_vacuum(sql)? {
+            if self.session_txn.is_some() {
+                return Err(format!("VACUUM cannot run inside a transaction block"));
+            }
+            self.vacuum(table_name.as_deref())?;
+            return Ok(Vec::new());
+        }
+        let (mut binder, stmts) = self.parse_sql(sql)?;
+
+        let mut results = Vec::new();
+        for stmt in &stmts {
+            match stmt {
+                Statement::StartTransaction { modes, .. } => {
+                    if self.session_txn.is_some() {
+                        return Err(format!("there is already a transaction in progress"));
+                    }
+                    let isolation_level = Self::isolation_level_of(modes).unwrap_or(self.default_isolation_level);
+                    self.session_txn = Some(self.begin_transaction_with_level(isolation_level));
+                    self.session_txn_used = false;
+                    continue;
+                },
+                Statement::SetTransaction { modes, session: true, .. } => {
+                    if let Some(isolation_level) = Self::isolation_level_of(modes) {
+                        self.default_isolation_level = isolation_level;
+                    }
+                    continue;
+                },
+                Statement::SetTransaction { modes, .. } => {
+                    let Some(txn) = &self.session_txn else {
+                        return Err(format!("SET TRANSACTION can only be used in transaction blocks"));
+                    };
+                    if let Some(isolation_level) = Self::isolation_level_of(modes) {
+                        if self.session_txn_used {
+                            return Err(format!("SET TRANSACTION ISOLATION LEVEL must be called before any query"));
+                        }
+                        txn.set_isolation_level(isolation_level);
+                    }
+                    continue;
+                },
+                Statement::Commit { .. } => {
+                    let Some(txn) = self.session_txn.take() else {
+                        return Err(format!("there is no transaction in progress"));
+                    };
+                    self.commit_transaction(&txn)?;
+                    continue;
+                },
+                Statement::Rollback { savepoint: None, .. } => {
+                    let Some(txn) = self.session_txn.take() else {
+                        return Err(format!("there is no transaction in progress"));
+                    };
+                    self.abort_transaction(&txn)?;
+                    continue;
+                },
+                Statement::Savepoint { .. } | Statement::ReleaseSavepoint { .. } | Statement::Rollback { .. } => {
+                    if self.session_txn.is_none() {
+                        return Err(format!("savepoints can only be used in transaction blocks"));
+                    }
+                },
+                _ => {}
+            }
+
+            self.session_txn_used = self.session_txn.is_some();
+            let Some(txn) = self.session_txn.clone() else {
+                // autocommit
+                let txn = self.begin_transaction();
+                match self.execute_statement(&mut binder, stmt, &txn) {
+                    Ok(res) => {
+                        self.commit_transaction(&txn)?;
+                        results.extend(res);
+                    },
+                    Err(err) => {
+                        self.abort_transaction(&txn)?;
+                        return Err(err);
+                    }
+                }
+                continue;
+            };
+            match self.execute_statement(&mut binder, stmt, &txn) {
+                Ok(res) => results.extend(res),
+                Err(err) => {
+                    // the lock manager aborted the transaction, e.g. a deadlock victim, the block is over
+                    if txn.get_state() == TransactionState::Aborted {
+                        self.session_txn = None;
+                        self.abort_transaction(&txn)?;
+                    }
+                    return Err(err);
+                }
+            }
+        }
+
+        Ok(results)
+    }
+
+    // sqlparser takes SET TRANSACTION and SET SESSION CHARACTERISTICS only in upper case,
+    // the word after SET [SESSION | LOCAL] is raised when it is one of them
+    fn upper_set_transaction(sql: &str) -> String {
+        let Ok(tokens) = Tokenizer::new(&GenericDialect{}, sql).tokenize_with_location() else {
+            return sql.to_owned();
+        };
+        let tokens: Vec<TokenWithSpan> = tokens.into_iter()
+            .filter(|token| !matches!(token.token, Token::Whitespace(_)))
+            .collect();
+        let is_word = |idx: usize, words: &[&str]| match tokens.get(idx).map(|token| &token.token) {
+            Some(Token::Word(word)) => word.quote_style.is_none() && words.iter().any(|w| word.value.eq_ignore_ascii_case(w)),
+            _ => false,
+        };
+
+        let mut raised = Vec::new();
+        for idx in 0..tokens.len() {
+            let stmt_start = idx == 0 || tokens[idx - 1].token == Token::SemiColon;
+            if !stmt_start || !is_word(idx, &["SET"]) {
+                continue;
+            }
+            let var = if is_word(idx + 1, &["SESSION", "LOCAL"]) { idx + 2 } else { idx + 1 };
+            if is_word(var, &["TRANSACTION", "CHARACTERISTICS"]) {
+                raised.push(tokens[var].span.start);
+            }
+        }
+        if raised.is_empty() {
+            return sql.to_owned();
+        }
+
+        // the locations are lines and columns of chars, counted from 1
+        let mut res = String::with_capacity(sql.len());
+        let mut in_word = false;
+        for (line, text) in sql.split_inclusive('\n').enumerate() {
+            for (column, ch) in text.chars().enumerate() {
+                if raised.iter().any(|loc| loc.line == line as u64 + 1 && loc.column == column as u64 + 1) {
+                    in_word = true;
+                }
+                in_word = in_word && ch.is_ascii_alphabetic();
+                res.push(if in_word { ch.to_ascii_uppercase() } else { ch });
+            }
+        }
+        res
+    }
+
+    // VACUUM [table], None if the sql is not a vacuum.
+    // the sql goes to sqlparser first, only a single VACUUM statement it can not parse is taken here
+    fn parse_vacuum(sql: &str) -> Result<Option<Option<String>>, String> {
+        let sql = Self::upper_set_transaction(sql);
+        if Parser::parse_sql(&GenericDialect{}, &sql).is_ok() {
+            return Ok(None);
+        }
+        let Ok(tokens) = Tokenizer::new(&GenericDialect{}, &sql).tokenize() else {
+            return Ok(None);
+        };
+        let mut tokens: Vec<Token> = tokens.into_iter()
+            .filter(|token| !matches!(token, Token::Whitespace(_)))
+            .collect();
+        while tokens.last() == Some(&Token::SemiColon) {
+            tokens.pop();
+        }
+        match tokens.as_slice() {
+            [Token::Word(word), rest @ ..] if word.quote_style.is_none() && word.value.eq_ignore_ascii_case("vacuum") => match rest {
+                [] => Ok(Some(None)),
+                [Token::Word(table_name)] => Ok(Some(Some(table_name.value.clone()))),
+                _ if rest.contains(&Token::SemiColon) => Err(format!("VACUUM must be a statement of its own")),
+                _ => Err(format!("parse error")),
+            },
+            // the parse error is returned by parse_sql
+            _ => Ok(None),
+        }
+    }
+
+    // remove the dead tuples of the table, or of all the tables, through all of their pages.
+    // returns the tuples removed, the background vacuum goes only through the pages with deleted tuples
+    pub fn vacuum(&self, table_name: Option<&str>) -> Result<usize, String> {
+        let table_heaps = {
+            let read_guard = self.catalog.read().unwrap();
+            let catalog = read_guard.borrow();
+            match table_name {
+                Some(table_name) => {
+                    let table_info = catalog.get_table(table_name).ok_or(format!("relation \"{}\" does not exist", table_name))?;
+                    vec![table_info.table_heap.clone()]
+                },
+                None => catalog.get_tables().iter().map(|table_info| table_info.table_heap.clone()).collect(),
+            }
+        };
+        let mut num_removed = 0;
+        for table_heap in table_heaps {
+            num_removed += self.vacuum_mgr.vacuum(&table_heap, true)?;
+        }
+        Ok(num_removed)
+    }
+
+    // take a checkpoint now, besides the periodic ones
+    pub fn checkpoint(&self) -> Result<(), String> {
+        self.checkpoint_mgr.checkpoint()?;
+        Ok(())
+    }
+
+    // the state of the work in the background, the error of the last periodic checkpoint if it failed
+    pub fn check_background(&self) -> Result<(), String> {
+        if let Some(err) = self.checkpointer.get_error() {
+            return Err(format!("checkpoint failed, {}", err));
+        }
+        if let Some(err) = self.vacuum_worker.get_error() {
+            return Err(format!("vacuum failed, {}", err));
+        }
+        Ok(())
+    }
+
+    pub fn begin_transaction(&self) -> TransactionRef {
+        self.txn_mgr.begin(self.default_isolation_level)
+    }
+
+    pub fn begin_transaction_with_level(&self, isolation_level: IsolationLevel) -> TransactionRef {
+        self.txn_mgr.begin(isolation_level)
+    }
+
+    pub fn commit_transaction(&self, txn: &TransactionRef) -> Result<(), String> {
+        let read_guard = self.catalog.read().unwrap();
+        let catalog = read_guard.borrow();
+        self.txn_mgr.commit(txn, &catalog)
+    }
+
+    pub fn abort_transaction(&self, txn: &TransactionRef) -> Result<(), String> {
+        let read_guard = self.catalog.read().unwrap();
+        let catalog = read_guard.borrow();
+        self.txn_mgr.abort(txn, &catalog)
     }
 
     // execute sql with a transaction
@@ -76,79 +517,257 @@ impl DBInstance {
             todo!()
         }
         
-        // execute a bunch of statements with a txn
-        let has_delete = false;
-        let mut binder;
-        {
-            let read_guard = self.catalog.read().unwrap();
-            binder = Binder::new(read_guard.clone());
+        let results = self.execute_sql_with_result(sql, txn)?;
+        for (res_schema, tuples) in results {
+            // formated rows, and print them
+            let writer = Self::format_res(tuples, &res_schema);
+            writer.print();
+        }
+
+        Ok(())
+    }
+
+    // execute a bunch of statements with a txn, returns the output schema and rows of each statement,
+    // stops at the first statement that fails
+    pub fn execute_sql_with_result(&mut self, sql: &str, txn: TransactionRef) -> Result<Vec<(Schema, Vec<Tuple>)>, String> {
+        let (mut binder, stmts) = self.parse_sql(sql)?;
 
-            if let Err(err) = binder.parse_and_save(sql, &GenericDialect{}) {
-                return Err(format!("parse error"));
+        let mut results = Vec::new();
+        for stmt in &stmts {
+            match stmt {
+                Statement::StartTransaction { .. } | Statement::Commit { .. } | Statement::Rollback { savepoint: None, .. }
+                | Statement::SetTransaction { .. } => {
+                    return Err(format!("the transaction is given, BEGIN/COMMIT/ROLLBACK/SET TRANSACTION are not allowed"));
+                },
+                _ => {}
             }
+            results.extend(self.execute_statement(&mut binder, stmt, &txn)?);
         }
 
-        let Some(stmts) = binder.stmts.take() else { panic!("Error occurred, impossible branch"); };
-        for i in 0..stmts.len() {
-            let mut is_delete = false;
-            let bound_stmt = binder.bind_statement(&stmts[i])?;
-            match &bound_stmt {
-                BoundStatement::Create(create) => {
-                    self.handle_create_stmt(create);
-                },
-                _ => {
+        Ok(results)
+    }
 
-                }
-            }
+    // the isolation level in the modes of BEGIN/SET TRANSACTION, the access modes are ignored
+    fn isolation_level_of(modes: &Vec<TransactionMode>) -> Option<IsolationLevel> {
+        modes.iter().rev().find_map(|mode| match mode {
+            TransactionMode::IsolationLevel(level) => Some(match level {
+                TransactionIsolationLevel::ReadUncommitted => IsolationLevel::ReadUncommitted,
+                TransactionIsolationLevel::ReadCommitted => IsolationLevel::ReadCommitted,
+                TransactionIsolationLevel::RepeatableRead => IsolationLevel::RepeatableRead,
+                TransactionIsolationLevel::Serializable => IsolationLevel::Serializable,
+            }),
+            _ => None,
+        })
+    }
 
+    fn parse_sql(&self, sql: &str) -> Result<(Binder, Vec<Statement>), String> {
+        let read_guard = self.catalog.read().unwrap();
+        let mut binder = Binder::new(read_guard.clone());
+
+        let sql = Self::upper_set_transaction(sql);
+        if let Err(err) = binder.parse_and_save(&sql, &GenericDialect{}) {
+            return Err(format!("parse error"));
+        }
+        let Some(stmts) = binder.stmts.take() else { panic!("Error occurred, impossible branch"); };
+        Ok((binder, stmts))
+    }
 
-            // plan statement
-            let mut planner;
-            let mut plan;
-            let mut execute_context;
-            {
+    // execute a statement with the txn, a failed statement leaves no write behind.
+    // returns the output schema and rows, ddl and transaction control return nothing
+    fn execute_statement(&mut self, binder: &mut Binder, stmt: &Statement, txn: &TransactionRef) -> Result<Option<(Schema, Vec<Tuple>)>, String> {
+        match stmt {
+            Statement::Savepoint { name } => {
+                txn.add_savepoint(&name.value);
+                return Ok(None);
+            },
+            Statement::ReleaseSavepoint { name } => {
+                self.txn_mgr.release_savepoint(txn, &name.value)?;
+                return Ok(None);
+            },
+            Statement::Rollback { savepoint: Some(name), .. } => {
                 let read_guard = self.catalog.read().unwrap();
-                planner = Planner::new(read_guard.clone());
+                self.txn_mgr.rollback_to_savepoint(txn, &name.value, &read_guard.borrow())?;
+                return Ok(None);
+            },
+            _ => {}
+        }
 
-                let res = planner.plan_statement(&bound_stmt);
-                if res.is_err() {
-                    let err = res.unwrap_err();
-                    println!("{}", err);
-                    return Err(err);
-                }
-                plan = res.unwrap();
-                execute_context = self.make_context(read_guard.clone(), txn.clone(), is_delete);
-            }
+        let write_set_len = txn.get_write_set_len();
+        let res = self.execute_bound_statement(binder, stmt, txn);
+        if res.is_err() {
+            let read_guard = self.catalog.read().unwrap();
+            self.txn_mgr.rollback_to(txn, write_set_len, &read_guard.borrow())?;
+        }
+        res
+    }
+
+    fn execute_bound_statement(&mut self, binder: &mut Binder, stmt: &Statement, txn: &TransactionRef) -> Result<Option<(Schema, Vec<Tuple>)>, String> {
+        let mut is_delete = false;
+        let bound_stmt = binder.bind_statement(stmt)?;
+        match &bound_stmt {
+            BoundStatement::Create(create) => {
+                // ddl is done by the catalog directly, no plan is needed
+                self.handle_create_stmt(create, &stmt.to_string())?;
+                return Ok(None);
+            },
+            BoundStatement::CreateSequence(create_seq) => {
+                self.handle_create_sequence_stmt(create_seq, &stmt.to_string())?;
+                return Ok(None);
+            },
+            BoundStatement::Delete(_) | BoundStatement::Update(_) => {
+                is_delete = true;
+            },
+            _ => {
 
-            // execute
-            println!("{}", plan.to_string());
-            let res_schema = plan.get_output_schema().clone();
-            let result_rows = self.execute_engine.execute(plan, txn.clone(), execute_context);
-    
-            // formated rows, and print them
-            match result_rows {
-                Err(err) => {
-                    eprintln!("{}", err);
-                },
-                Ok(tuples) => {
-                    let writer = Self::format_res(tuples, &res_schema);
-                    writer.print();
-                }
             }
         }
 
 
-        Ok(())
+        // plan statement
+        let mut planner;
+        let mut plan;
+        let mut execute_context;
+        {
+            let read_guard = self.catalog.read().unwrap();
+            planner = Planner::new(read_guard.clone());
+
+            plan = planner.plan_statement(&bound_stmt)?;
+            execute_context = self.make_context(read_guard.clone(), txn.clone(), is_delete);
+        }
+
+        // execute
+        let res_schema = plan.get_output_schema().clone();
+        let tuples = self.execute_engine.execute(plan, txn.clone(), execute_context)?;
+        Ok(Some((res_schema, tuples)))
     }
 
 
 
-    pub fn handle_create_stmt(&self, create_stmt: &CreateStmt) {
+    // `sql` is the statement, it is kept in the catalog store
+    pub fn handle_create_sequence_stmt(&self, create_seq: &CreateSequenceStmt, sql: &str) -> Result<(), String> {
+        let write_guard = self.catalog.write().unwrap();
+        let mut catalog = write_guard.borrow_mut();
+        if create_seq.if_not_exists && catalog.get_sequence(&create_seq.name).is_some() {
+            return Ok(());
+        }
+        let seq = catalog.create_sequence(&create_seq.name, create_seq.start_value, create_seq.increment,
+            create_seq.min_value, create_seq.max_value)?;
+        self.catalog_store.add(CatalogEntry::Sequence { sql: sql.to_string(), page_id: seq.page_id })
+    }
 
+    // create the table, `sql` is the statement, it is kept in the catalog store with the pages of the table
+    pub fn handle_create_stmt(&self, create_stmt: &CreateStmt, sql: &str) -> Result<(), String> {
+        let write_guard = self.catalog.write().unwrap();
+        let pages = self.create_table(&mut write_guard.borrow_mut(), create_stmt, None)?;
+        self.catalog_store.add(CatalogEntry::Table { sql: sql.to_string(), pages })
+    }
+
+    // create the table, then the indexes of its unique keys, its checks and defaults, and its foreign keys.
+    // a table of the catalog store is opened on its pages instead, returns the pages of the table
+    fn create_table(&self, catalog: &mut CataLog, create_stmt: &CreateStmt, stored: Option<&TablePages>) -> Result<TablePages, String> {
+        let table_name = &create_stmt.table_name;
+        if catalog.get_table(table_name).is_some() {
+            return Err(format!("relation \"{}\" already exists", table_name));
+        }
+        // the sequences of the serial and identity columns, their defaults call nextval
+        let mut sequence_pages = Vec::new();
+        for (idx, seq) in create_stmt.sequences.iter().enumerate() {
+            let seq = match stored {
+                Some(pages) => catalog.open_sequence(&seq.name, pages.sequence_pages[idx])?,
+                None => catalog.create_sequence(&seq.name, seq.start_value, seq.increment, seq.min_value, seq.max_value)?,
+            };
+            sequence_pages.push(seq.page_id);
+        }
+
+        let file_id = match &create_stmt.tablespace {
+            Some(name) => catalog.get_tablespace(name).ok_or(format!("tablespace \"{}\" does not exist", name))?,
+            None => MAIN_FILE_ID,
+        };
+        let schema = Schema::new(&create_stmt.columns);
+        let compression = create_stmt.compression.unwrap_or(TOAST_COMPRESSION);
+        let table_info = match stored {
+            Some(pages) => catalog.open_table_in(table_name, schema, pages.file_id, compression, pages.first_page_id)?,
+            None => catalog.create_table_in(None, table_name, schema, file_id, compression)?,
+        };
+        let table_oid = table_info.table_oid;
+        let pages = TablePages { file_id: table_info.table_heap.get_file_id(), first_page_id: table_info.table_heap.get_first_page_id(), sequence_pages };
+        self.vacuum_mgr.register(table_info.table_heap.clone());
+
+        let col_names = |cols: &Vec<usize>| cols.iter()
+            .map(|col| create_stmt.columns[*col].get_name())
+            .collect::<Vec<String>>()
+            .join("_");
+
+        for unique_key in &create_stmt.unique_keys {
+            let index_name = match &unique_key.name {
+                Some(name) => name.clone(),
+                None if unique_key.is_primary => format!("{}_pkey", table_name),
+                None => format!("{}_{}_key", table_name, col_names(&unique_key.columns)),
+            };
+            catalog.create_index(None, &index_name, table_name, unique_key.columns.clone(), true, unique_key.is_primary)?;
+        }
+
+        catalog.set_column_defaults(table_oid, create_stmt.defaults.clone())?;
+        for check_def in &create_stmt.checks {
+            let name = match &check_def.name {
+                Some(name) => name.clone(),
+                None => {
+                    let base = match check_def.column {
+                        Some(col) => format!("{}_{}_check", table_name, create_stmt.columns[col].get_name()),
+                        None => format!("{}_check", table_name),
+                    };
+                    // keep the generated names distinct
+                    let checks = catalog.get_check_constraints(table_oid);
+                    let mut name = base.clone();
+                    let mut suffix = 1;
+                    while checks.iter().any(|check| check.name == name) {
+                        name = format!("{}{}", base, suffix);
+                        suffix += 1;
+                    }
+                    name
+                }
+            };
+            catalog.add_check_constraint(CheckConstraint { name, table_oid, expr: check_def.expr.clone() })?;
+        }
+
+        for fk_def in &create_stmt.foreign_keys {
+            let Some(ref_table) = catalog.get_table(&fk_def.ref_table).cloned() else {
+                return Err(format!("referenced table {} not found", fk_def.ref_table));
+            };
+            let Some(ref_index) = catalog.find_unique_index(&fk_def.ref_table, &fk_def.ref_columns) else {
+                return Err(format!("there is no unique constraint matching given keys for referenced table {}", fk_def.ref_table));
+            };
+
+            // keep the columns in the order of the index key, so that a child key can be looked up directly
+            let mut columns = Vec::new();
+            let mut ref_columns = Vec::new();
+            for key_attr in &ref_index.key_attrs {
+                let pos = fk_def.ref_columns.iter().position(|col| col == key_attr).unwrap();
+                columns.push(fk_def.columns[pos]);
+                ref_columns.push(*key_attr);
+            }
+
+            let name = match &fk_def.name {
+                Some(name) => name.clone(),
+                None => format!("{}_{}_fkey", table_name, col_names(&fk_def.columns)),
+            };
+            catalog.add_foreign_key(ForeignKey {
+                name,
+                table_oid,
+                columns,
+                ref_table_oid: ref_table.table_oid,
+                ref_columns,
+                ref_index_oid: ref_index.index_oid,
+                on_delete: fk_def.on_delete,
+                on_update: fk_def.on_update,
+            })?;
+        }
+
+        Ok(pages)
     }
 
     pub fn make_context(&self, catalog: CataLogRef, txn: TransactionRef, is_delete: bool) -> ExecutorContextRef {
-        ExecutorContextRef::new(ExecutorContext::new(catalog, txn))
+        ExecutorContextRef::new(ExecutorContext::new(catalog, txn, self.txn_mgr.clone(), self.lock_mgr.clone(), is_delete))
     }
 
     // transform a Vec<Tuple> and Schema to table 
@@ -187,18 +806,18 @@ impl DBInstance {
 
 
         let mut write_guard = self.catalog.write().unwrap();
-        write_guard.borrow_mut().create_table(None, "t1", t1);
+        write_guard.borrow_mut().create_table(None, "t1", t1).unwrap();
     }
 }
 
 
 #[cfg(test)]
 mod tests {
-    use std::sync::Arc;
+    use std::{fs::{self, OpenOptions}, os::unix::fs::FileExt, sync::Arc};
 
     use tabled::{builder::Builder, grid::records::vec_records::Text, settings::Style};
 
-    use crate::transaction::transaction::Transaction;
+    use crate::{catalog::{column::Column, schema::Schema}, common::{config::{MAIN_FILE_ID, MAX_PAGE_SIZE, PAGE_SIZE}, page_addr::PageAddr, rid::RID}, storage::page_based::{disk::{disk_manager::{DiskBackend, PageStore}, tablespace::Tablespace}, page::{page::Page, superblock_page::FORMAT_VERSION, table_page::TablePage}, table::{toast::Toaster, tuple::Tuple}}, transaction::{lock_manager::{DeadlockPolicy, LockMode, TXN_ABORTED}, transaction::{IsolationLevel, Transaction, TransactionRef}}, typedef::type_id::TypeId};
 
     use super::DBInstance;
 
@@ -224,13 +843,22 @@ mod tests {
     }
 
 
+    // the tests run on in-memory databases, the ones reopening a database have their own file and log,
+    // so the recovery of one never reads the log of another
+    fn open_instance(name: &str, policy: DeadlockPolicy) -> Result<DBInstance, String> {
+        let _ = fs::remove_file(format!("{}.db", name));
+        let _ = fs::remove_file(format!("{}.log", name));
+        let _ = fs::remove_file(format!("{}.dwb", name));
+        DBInstance::new_with_policy(&format!("{}.db", name), policy)
+    }
+
     fn generate_test_table(db: &DBInstance) {
         db.generate_test_tables();
     }
 
     #[test]
     fn test_simple_sql() -> Result<(), String> {
-        let mut instance = DBInstance::new("test.db")?;
+        let mut instance = DBInstance::open_in_memory_with_policy(DeadlockPolicy::Detection)?;
         generate_test_table(&instance); 
 
         let sql = "insert into t1 values 
@@ -238,22 +866,766 @@ mod tests {
                             (2, 'test2', 'female', 2),
                             (3, 'test3', 'female', 3),
                             (4, 'test4', 'female', 4)";
-        let txn = Arc::new(Transaction::new());
+        let txn = instance.begin_transaction();
         
-        let res = instance.execute_sql_txn(sql, txn);
+        let res = instance.execute_sql_txn(sql, txn.clone());
         if res.is_err() {
             println!("{}", res.unwrap_err())
         }
+        instance.commit_transaction(&txn)?;
 
         let sql = "select * from t1 where c='female' and d=2";
-        let txn = Arc::new(Transaction::new());
+        let txn = instance.begin_transaction();
         
-        let res = instance.execute_sql_txn(sql, txn);
+        let res = instance.execute_sql_txn(sql, txn.clone());
         if res.is_err() {
             println!("{}", res.unwrap_err())
         }
+        instance.commit_transaction(&txn)?;
 
         Ok(())
     }
-}
 
+    // run the sql in its own transaction, returns the rows of the last statement, ddl returns nothing
+    fn query(instance: &mut DBInstance, sql: &str) -> Result<Vec<Vec<String>>, String> {
+        let txn = instance.begin_transaction();
+        let res = query_txn(instance, sql, &txn);
+        match res {
+            Ok(_) => instance.commit_transaction(&txn)?,
+            Err(_) => instance.abort_transaction(&txn)?,
+        }
+        res
+    }
+
+    fn query_txn(instance: &mut DBInstance, sql: &str, txn: &TransactionRef) -> Result<Vec<Vec<String>>, String> {
+        let mut results = instance.execute_sql_with_result(sql, txn.clone())?;
+        let Some((schema, tuples)) = results.pop() else { return Ok(Vec::new()); };
+        Ok(tuples.iter()
+            .map(|tuple| (0..schema.get_column_count()).map(|idx| tuple.get_value(&schema, idx).to_string()).collect())
+            .collect())
+    }
+
+    fn create_fk_tables(instance: &mut DBInstance, action: &str) -> Result<(), String> {
+        query(instance, "create table parent (id int primary key, name varchar(16))")?;
+        query(instance, &format!("create table child (id int primary key, pid int references parent(id) on delete {} on update {})", action, action))?;
+        query(instance, "insert into parent values (1, 'a'), (2, 'b')")?;
+        query(instance, "insert into child values (10, 1), (11, 1), (12, 2)")?;
+        Ok(())
+    }
+
+    #[test]
+    fn foreign_key_insert_test() -> Result<(), String> {
+        let mut instance = DBInstance::open_in_memory_with_policy(DeadlockPolicy::Detection)?;
+        create_fk_tables(&mut instance, "restrict")?;
+
+        // no parent row with id 3
+        let res = query(&mut instance, "insert into child values (13, 3)");
+        assert!(res.unwrap_err().contains("violates foreign key constraint \"child_pid_fkey\""));
+
+        // a null key is not checked
+        query(&mut instance, "insert into child values (13, null)")?;
+        // the primary key is unique
+        let res = query(&mut instance, "insert into child values (13, 1)");
+        assert!(res.unwrap_err().contains("duplicate key value violates unique constraint \"child_pkey\""));
+
+        // referenced columns must be unique
+        let res = query(&mut instance, "create table bad (pid int references parent(name))");
+        assert!(res.is_err());
+
+        assert_eq!(4, query(&mut instance, "select * from child")?.len());
+        Ok(())
+    }
+
+    #[test]
+    fn foreign_key_restrict_test() -> Result<(), String> {
+        let mut instance = DBInstance::open_in_memory_with_policy(DeadlockPolicy::Detection)?;
+        create_fk_tables(&mut instance, "restrict")?;
+
+        let res = query(&mut instance, "delete from parent where id = 1");
+        assert!(res.unwrap_err().contains("violates foreign key constraint"));
+        let res = query(&mut instance, "update parent set id = 5 where id = 2");
+        assert!(res.unwrap_err().contains("violates foreign key constraint"));
+
+        // the child rows go first, then the parent can be deleted
+        query(&mut instance, "delete from child where pid = 2")?;
+        query(&mut instance, "delete from parent where id = 2")?;
+        assert_eq!(vec![vec!["1".to_owned(), "a".to_owned()]], query(&mut instance, "select * from parent")?);
+
+        // the key not referenced can be updated
+        query(&mut instance, "update parent set name = 'c' where id = 1")?;
+        assert_eq!(vec![vec!["1".to_owned(), "c".to_owned()]], query(&mut instance, "select * from parent")?);
+        Ok(())
+    }
+
+    #[test]
+    fn foreign_key_cascade_test() -> Result<(), String> {
+        let mut instance = DBInstance::open_in_memory_with_policy(DeadlockPolicy::Detection)?;
+        create_fk_tables(&mut instance, "cascade")?;
+
+        query(&mut instance, "update parent set id = 3 where id = 2")?;
+        assert_eq!(vec![vec!["3".to_owned()]], query(&mut instance, "select pid from child where id = 12")?);
+
+        query(&mut instance, "delete from parent where id = 1")?;
+        assert_eq!(vec![vec!["12".to_owned()]], query(&mut instance, "select id from child")?);
+        Ok(())
+    }
+
+    #[test]
+    fn foreign_key_set_null_test() -> Result<(), String> {
+        let mut instance = DBInstance::open_in_memory_with_policy(DeadlockPolicy::Detection)?;
+        create_fk_tables(&mut instance, "set null")?;
+
+        query(&mut instance, "delete from parent where id = 1")?;
+        let mut rows = query(&mut instance, "select pid from child")?;
+        rows.sort();
+        assert_eq!(vec![vec!["2".to_owned()], vec!["null".to_owned()], vec!["null".to_owned()]], rows);
+
+        query(&mut instance, "update parent set id = 3 where id = 2")?;
+        assert_eq!(vec![vec!["null".to_owned()], vec!["null".to_owned()], vec!["null".to_owned()]], 
+            query(&mut instance, "select pid from child")?);
+        Ok(())
+    }
+
+    #[test]
+    fn foreign_key_action_check_test() -> Result<(), String> {
+        let mut instance = DBInstance::open_in_memory_with_policy(DeadlockPolicy::Detection)?;
+        query(&mut instance, "create table parent (id int primary key)")?;
+        query(&mut instance, "create table child (id int primary key, pid int references parent(id) on update cascade, check (pid < 5))")?;
+        query(&mut instance, "insert into parent values (1), (2)")?;
+        query(&mut instance, "insert into child values (10, 1), (11, 2)")?;
+
+        // the cascaded child row is checked too, and the whole update is undone
+        let res = query(&mut instance, "update parent set id = 7 where id = 2");
+        assert!(res.unwrap_err().contains("new row for relation \"child\" violates check constraint \"child_check\""));
+        assert_eq!(vec![vec!["2".to_owned()]], query(&mut instance, "select id from parent where id = 2")?);
+        assert_eq!(vec![vec!["2".to_owned()]], query(&mut instance, "select pid from child where id = 11")?);
+
+        query(&mut instance, "update parent set id = 3 where id = 2")?;
+        assert_eq!(vec![vec!["3".to_owned()]], query(&mut instance, "select pid from child where id = 11")?);
+
+        // a default can not refer to a column inside a function call either
+        assert!(query(&mut instance, "create table t (a int, b int default nextval(a))").is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn check_and_default_test() -> Result<(), String> {
+        let mut instance = DBInstance::open_in_memory_with_policy(DeadlockPolicy::Detection)?;
+        query(&mut instance, "create table t (a int primary key, b int default 7 check (b < 10), c varchar(8) default 'x', check (a < 100))")?;
+
+        // the omitted columns take their defaults, or null
+        query(&mut instance, "insert into t(a) values (1)")?;
+        query(&mut instance, "insert into t(c, a) values ('y', 2)")?;
+        query(&mut instance, "insert into t(a, b) values (3, null)")?;
+        assert_eq!(vec![
+            vec!["1".to_owned(), "7".to_owned(), "x".to_owned()],
+            vec!["2".to_owned(), "7".to_owned(), "y".to_owned()],
+            vec!["3".to_owned(), "null".to_owned(), "x".to_owned()],
+        ], query(&mut instance, "select * from t")?);
+
+        let res = query(&mut instance, "insert into t values (4, 11, 'z')");
+        assert!(res.unwrap_err().contains("violates check constraint \"t_b_check\""));
+        let res = query(&mut instance, "insert into t(a) values (100)");
+        assert!(res.unwrap_err().contains("violates check constraint \"t_check\""));
+        let res = query(&mut instance, "update t set b = 10 where a = 1");
+        assert!(res.unwrap_err().contains("violates check constraint \"t_b_check\""));
+        assert_eq!(vec![vec!["7".to_owned()]], query(&mut instance, "select b from t where a = 1")?);
+
+        assert!(query(&mut instance, "insert into t(a, d) values (5, 1)").is_err());
+        assert!(query(&mut instance, "insert into t(a, b) values (5)").is_err());
+        assert!(query(&mut instance, "insert into t(a, a) values (5, 5)").is_err());
+        assert!(query(&mut instance, "create table t2 (a int default 'x')").is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn sequence_test() -> Result<(), String> {
+        let mut instance = DBInstance::open_in_memory_with_policy(DeadlockPolicy::Detection)?;
+        query(&mut instance, "create sequence s increment by 5 start with 10")?;
+        // currval before nextval is an error
+        let res = query(&mut instance, "select currval('s')");
+        assert!(res.unwrap_err().contains("currval of sequence \"s\" is not yet defined"));
+        assert_eq!(vec![vec!["10".to_owned()]], query(&mut instance, "select nextval('s')")?);
+        assert_eq!(vec![vec!["15".to_owned()]], query(&mut instance, "select nextval('s')")?);
+        assert_eq!(vec![vec!["15".to_owned()]], query(&mut instance, "select currval('s')")?);
+        assert!(query(&mut instance, "create sequence s").is_err());
+        query(&mut instance, "create sequence if not exists s")?;
+        assert!(query(&mut instance, "select nextval('nope')").is_err());
+
+        // an exhausted sequence fails the insert, no row is left with a null key
+        query(&mut instance, "create sequence m maxvalue 2")?;
+        query(&mut instance, "create table v (id int, w int)")?;
+        let res = query(&mut instance, "insert into v values (nextval('m'), 1), (nextval('m'), 2), (nextval('m'), 3)");
+        assert!(res.unwrap_err().contains("reached maximum value of sequence \"m\""));
+        assert_eq!(0, query(&mut instance, "select * from v")?.len());
+
+        // serial and identity columns take the next value when they are omitted
+        query(&mut instance, "create table t (id serial primary key, v varchar(8))")?;
+        query(&mut instance, "create table u (id int generated by default as identity (increment by 10 start with 100), v int)")?;
+        query(&mut instance, "insert into t(v) values ('a'), ('b')")?;
+        query(&mut instance, "insert into t values (nextval('s'), 'c')")?;
+        query(&mut instance, "insert into u(v) values (1), (2)")?;
+        assert_eq!(vec![
+            vec!["1".to_owned(), "a".to_owned()],
+            vec!["2".to_owned(), "b".to_owned()],
+            vec!["20".to_owned(), "c".to_owned()],
+        ], query(&mut instance, "select * from t")?);
+        assert_eq!(vec![
+            vec!["100".to_owned(), "1".to_owned()],
+            vec!["110".to_owned(), "2".to_owned()],
+        ], query(&mut instance, "select * from u")?);
+        assert_eq!(vec![vec!["2".to_owned()]], query(&mut instance, "select currval('t_id_seq')")?);
+        Ok(())
+    }
+
+    fn rows(rows: &[(i32, i32)]) -> Vec<Vec<String>> {
+        rows.iter().map(|(a, b)| vec![a.to_string(), b.to_string()]).collect()
+    }
+
+    #[test]
+    fn snapshot_isolation_test() -> Result<(), String> {
+        let mut instance = DBInstance::open_in_memory_with_policy(DeadlockPolicy::Detection)?;
+        query(&mut instance, "create table t (a int primary key, b int)")?;
+        query(&mut instance, "insert into t values (1, 10), (2, 20)")?;
+
+        let t1 = instance.begin_transaction();
+        let t2 = instance.begin_transaction();
+        query_txn(&mut instance, "update t set b = 11 where a = 1", &t1)?;
+        query_txn(&mut instance, "insert into t values (3, 30)", &t1)?;
+        query_txn(&mut instance, "delete from t where a = 2", &t1)?;
+
+        // a transaction sees its own writes, the others keep reading the old versions
+        assert_eq!(rows(&[(1, 11), (3, 30)]), query_txn(&mut instance, "select * from t", &t1)?);
+        assert_eq!(rows(&[(1, 10), (2, 20)]), query_txn(&mut instance, "select * from t", &t2)?);
+
+        // the snapshot of t2 is taken before t1 commits
+        instance.commit_transaction(&t1)?;
+        assert_eq!(rows(&[(1, 10), (2, 20)]), query_txn(&mut instance, "select * from t", &t2)?);
+        assert_eq!(rows(&[(1, 11), (3, 30)]), query(&mut instance, "select * from t")?);
+
+        // the first updater wins
+        let res = query_txn(&mut instance, "update t set b = 0 where a = 1", &t2);
+        assert!(res.unwrap_err().contains("could not serialize access"));
+        instance.abort_transaction(&t2)?;
+
+        // the writes of an aborted transaction are invisible
+        let t3 = instance.begin_transaction();
+        query_txn(&mut instance, "update t set b = 12 where a = 1", &t3)?;
+        query_txn(&mut instance, "insert into t values (4, 40)", &t3)?;
+        instance.abort_transaction(&t3)?;
+        assert_eq!(rows(&[(1, 11), (3, 30)]), query(&mut instance, "select * from t")?);
+        query(&mut instance, "update t set b = 13 where a = 1")?;
+        query(&mut instance, "insert into t values (4, 41)")?;
+        assert_eq!(rows(&[(3, 30), (1, 13), (4, 41)]), query(&mut instance, "select * from t")?);
+        Ok(())
+    }
+
+    #[test]
+    fn unique_after_delete_test() -> Result<(), String> {
+        let mut instance = DBInstance::open_in_memory_with_policy(DeadlockPolicy::Detection)?;
+        query(&mut instance, "create table t (a int primary key, b int)")?;
+        query(&mut instance, "insert into t values (1, 10), (2, 20)")?;
+
+        // the key of a row deleted by a running transaction is still taken, the delete may be undone
+        let t1 = instance.begin_transaction();
+        query_txn(&mut instance, "delete from t where a = 2", &t1)?;
+        let res = query(&mut instance, "insert into t values (2, 21)");
+        assert!(res.unwrap_err().contains("duplicate key value violates unique constraint \"t_pkey\""));
+        instance.abort_transaction(&t1)?;
+        assert_eq!(rows(&[(1, 10), (2, 20)]), query(&mut instance, "select * from t")?);
+        assert!(query(&mut instance, "insert into t values (2, 22)").is_err());
+
+        // the own delete frees the key, and a committed one frees it for everyone
+        let t2 = instance.begin_transaction();
+        query_txn(&mut instance, "delete from t where a = 2", &t2)?;
+        query_txn(&mut instance, "insert into t values (2, 23)", &t2)?;
+        instance.commit_transaction(&t2)?;
+        query(&mut instance, "delete from t where a = 1")?;
+        query(&mut instance, "insert into t values (1, 11)")?;
+        assert_eq!(rows(&[(2, 23), (1, 11)]), query(&mut instance, "select * from t")?);
+        assert!(query(&mut instance, "insert into t values (2, 24)").is_err());
+
+        // an update failing after the old version is deleted is undone with its statement, the block goes on
+        session_query(&mut instance, "begin")?;
+        assert!(session_query(&mut instance, "update t set a = 2 where a = 1").is_err());
+        session_query(&mut instance, "update t set b = 12 where a = 1")?;
+        session_query(&mut instance, "commit")?;
+        assert_eq!(rows(&[(2, 23), (1, 12)]), query(&mut instance, "select * from t")?);
+        Ok(())
+    }
+
+    // run the sql in the session of the instance, returns the rows of the last statement
+    fn session_query(instance: &mut DBInstance, sql: &str) -> Result<Vec<Vec<String>>, String> {
+        let mut results = instance.execute_sql(sql)?;
+        let Some((schema, tuples)) = results.pop() else { return Ok(Vec::new()); };
+        Ok(tuples.iter()
+            .map(|tuple| (0..schema.get_column_count()).map(|idx| tuple.get_value(&schema, idx).to_string()).collect())
+            .collect())
+    }
+
+    #[test]
+    fn transaction_block_test() -> Result<(), String> {
+        let mut instance = DBInstance::open_in_memory_with_policy(DeadlockPolicy::Detection)?;
+        session_query(&mut instance, "create table t (a int primary key, b int)")?;
+        session_query(&mut instance, "insert into t values (1, 10)")?;
+
+        // rollback undoes the inserts, updates and deletes, and their index entries
+        session_query(&mut instance, "begin")?;
+        session_query(&mut instance, "insert into t values (2, 20)")?;
+        session_query(&mut instance, "update t set b = 11 where a = 1")?;
+        assert_eq!(rows(&[(2, 20), (1, 11)]), session_query(&mut instance, "select * from t")?);
+        session_query(&mut instance, "delete from t where a = 2")?;
+        session_query(&mut instance, "rollback")?;
+        assert_eq!(rows(&[(1, 10)]), session_query(&mut instance, "select * from t")?);
+        session_query(&mut instance, "insert into t values (2, 21)")?;
+        assert!(session_query(&mut instance, "insert into t values (1, 0)").is_err());
+
+        // savepoints
+        session_query(&mut instance, "begin")?;
+        session_query(&mut instance, "update t set b = 12 where a = 1")?;
+        session_query(&mut instance, "savepoint s1")?;
+        session_query(&mut instance, "delete from t where a = 2")?;
+        session_query(&mut instance, "savepoint s2")?;
+        session_query(&mut instance, "insert into t values (3, 30)")?;
+        session_query(&mut instance, "rollback to savepoint s1")?;
+        assert!(session_query(&mut instance, "rollback to savepoint s2").is_err());
+        session_query(&mut instance, "insert into t values (4, 40)")?;
+        session_query(&mut instance, "release savepoint s1")?;
+        assert!(session_query(&mut instance, "rollback to savepoint s1").is_err());
+
+        // a failed statement is undone alone, the transaction goes on
+        assert!(session_query(&mut instance, "insert into t values (5, 50), (4, 0)").is_err());
+        assert!(session_query(&mut instance, "begin").is_err());
+        session_query(&mut instance, "commit")?;
+        assert_eq!(rows(&[(2, 21), (1, 12), (4, 40)]), session_query(&mut instance, "select * from t")?);
+
+        assert!(session_query(&mut instance, "commit").is_err());
+        assert!(session_query(&mut instance, "savepoint s1").is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn update_in_place_test() -> Result<(), String> {
+        let mut instance = DBInstance::open_in_memory_with_policy(DeadlockPolicy::Detection)?;
+        session_query(&mut instance, "create table t (a int primary key, b varchar(8000))")?;
+        let num_versions = |instance: &DBInstance| instance.catalog.read().unwrap().borrow().get_table("t").unwrap().table_heap.clone().make_iterator().count();
+        let text = |c: &str, len: usize| c.repeat(len);
+
+        // the rows the transaction inserted are updated in place, a longer value moves the row or goes out of line
+        session_query(&mut instance, "begin")?;
+        session_query(&mut instance, "insert into t values (1, 'a'), (2, 'b')")?;
+        session_query(&mut instance, &format!("update t set b = '{}' where a = 1", text("x", 3000)))?;
+        session_query(&mut instance, &format!("update t set b = '{}'", text("y", 6000)))?;
+        assert_eq!(2, num_versions(&instance));
+
+        // the replaced values and the index entries are put back by the rollback of the statement and of the savepoint
+        session_query(&mut instance, "savepoint s1")?;
+        session_query(&mut instance, "update t set a = 3, b = 'z' where a = 1")?;
+        assert!(session_query(&mut instance, "update t set a = 3 where a = 2").is_err());
+        session_query(&mut instance, "rollback to savepoint s1")?;
+        assert!(session_query(&mut instance, "insert into t values (1, 'c')").is_err());
+        session_query(&mut instance, "insert into t values (3, 'c')")?;
+        session_query(&mut instance, "commit")?;
+        assert_eq!(vec![vec!["1".to_string()], vec!["2".to_string()]], session_query(&mut instance, &format!("select a from t where b = '{}'", text("y", 6000)))?);
+        assert_eq!(vec![vec!["3".to_string()]], session_query(&mut instance, "select a from t where b = 'c'")?);
+        assert_eq!(3, num_versions(&instance));
+
+        // a committed row may be seen by other snapshots, its update is a new version
+        let reader = instance.begin_transaction();
+        session_query(&mut instance, "update t set b = 'w' where a = 3")?;
+        assert_eq!(4, num_versions(&instance));
+        assert_eq!(vec![vec!["c".to_string()]], query_txn(&mut instance, "select b from t where a = 3", &reader)?);
+        instance.commit_transaction(&reader)?;
+        Ok(())
+    }
+
+    #[test]
+    fn insert_lock_test() -> Result<(), String> {
+        let mut instance = DBInstance::open_in_memory_with_policy(DeadlockPolicy::Detection)?;
+        query(&mut instance, "create table t (a int, b int)")?;
+        let table_oid = instance.catalog.read().unwrap().borrow().get_table("t").unwrap().table_oid;
+
+        // the table is locked IX and the new rows X by the insert
+        let txn = instance.begin_transaction();
+        query_txn(&mut instance, "insert into t values (1, 10), (2, 20)", &txn)?;
+        assert_eq!(Some(LockMode::IntentionExclusive), txn.get_table_lock(table_oid));
+        assert_eq!(2, txn.get_locked_rows().len());
+        assert!(txn.get_locked_rows().iter().all(|(_, rid)| txn.get_row_lock(rid) == Some(LockMode::Exclusive)));
+        instance.commit_transaction(&txn)?;
+
+        // a row which can not be locked is not inserted
+        let txn = instance.begin_transaction();
+        txn.set_aborted();
+        assert!(query_txn(&mut instance, "insert into t values (3, 30)", &txn).unwrap_err().starts_with(TXN_ABORTED));
+        instance.abort_transaction(&txn)?;
+        let table_heap = instance.catalog.read().unwrap().borrow().get_table("t").unwrap().table_heap.clone();
+        assert_eq!(2, table_heap.make_iterator().count());
+        Ok(())
+    }
+
+    #[test]
+    fn isolation_level_test() -> Result<(), String> {
+        // a younger transaction dies instead of waiting, so the waits show up as errors
+        let mut instance = DBInstance::open_in_memory_with_policy(DeadlockPolicy::WaitDie)?;
+        query(&mut instance, "create table t (a int primary key, b int)")?;
+        query(&mut instance, "insert into t values (1, 10), (2, 20)")?;
+
+        // dirty read: read uncommitted sees the uncommitted update, read committed waits for the writer
+        let writer = instance.begin_transaction_with_level(IsolationLevel::RepeatableRead);
+        query_txn(&mut instance, "update t set b = 11 where a = 1", &writer)?;
+        let ru = instance.begin_transaction_with_level(IsolationLevel::ReadUncommitted);
+        assert_eq!(rows(&[(2, 20), (1, 11)]), query_txn(&mut instance, "select * from t", &ru)?);
+        assert!(instance.lock_mgr.lock_table(&ru, LockMode::IntentionShared, 0).is_err());
+        let rc = instance.begin_transaction_with_level(IsolationLevel::ReadCommitted);
+        let res = query_txn(&mut instance, "select * from t", &rc);
+        assert!(res.unwrap_err().starts_with(TXN_ABORTED));
+        instance.abort_transaction(&rc)?;
+        instance.abort_transaction(&writer)?;
+        assert_eq!(rows(&[(1, 10), (2, 20)]), query_txn(&mut instance, "select * from t", &ru)?);
+        instance.commit_transaction(&ru)?;
+
+        // non-repeatable read: read committed gives the row locks back after reading
+        let rc = instance.begin_transaction_with_level(IsolationLevel::ReadCommitted);
+        assert_eq!(rows(&[(1, 10), (2, 20)]), query_txn(&mut instance, "select * from t", &rc)?);
+        assert!(rc.get_locked_rows().is_empty());
+        query(&mut instance, "update t set b = 12 where a = 1")?;
+        assert_eq!(rows(&[(2, 20), (1, 12)]), query_txn(&mut instance, "select * from t", &rc)?);
+        instance.commit_transaction(&rc)?;
+
+        // repeatable read keeps them, but a new row still shows up (phantom)
+        let rr = instance.begin_transaction_with_level(IsolationLevel::RepeatableRead);
+        assert_eq!(rows(&[(2, 20), (1, 12)]), query_txn(&mut instance, "select * from t", &rr)?);
+        let res = query(&mut instance, "update t set b = 13 where a = 1");
+        assert!(res.unwrap_err().starts_with(TXN_ABORTED));
+        assert_eq!(rows(&[(2, 20), (1, 12)]), query_txn(&mut instance, "select * from t", &rr)?);
+        query(&mut instance, "insert into t values (3, 30)")?;
+        assert_eq!(rows(&[(2, 20), (1, 12), (3, 30)]), query_txn(&mut instance, "select * from t", &rr)?);
+        instance.commit_transaction(&rr)?;
+
+        // serializable locks the table, no phantom
+        let ser = instance.begin_transaction_with_level(IsolationLevel::Serializable);
+        assert_eq!(rows(&[(2, 20), (1, 12), (3, 30)]), query_txn(&mut instance, "select * from t", &ser)?);
+        assert_eq!(Some(LockMode::Shared), ser.get_table_lock(0));
+        let res = query(&mut instance, "insert into t values (4, 40)");
+        assert!(res.unwrap_err().starts_with(TXN_ABORTED));
+        assert_eq!(rows(&[(2, 20), (1, 12), (3, 30)]), query_txn(&mut instance, "select * from t", &ser)?);
+        query_txn(&mut instance, "delete from t where a = 3", &ser)?;
+        assert_eq!(Some(LockMode::SharedIntentionExclusive), ser.get_table_lock(0));
+        instance.commit_transaction(&ser)?;
+        query(&mut instance, "insert into t values (4, 40)")?;
+        assert_eq!(rows(&[(2, 20), (1, 12), (4, 40)]), query(&mut instance, "select * from t")?);
+        Ok(())
+    }
+
+    #[test]
+    fn set_transaction_test() -> Result<(), String> {
+        let mut instance = DBInstance::open_in_memory_with_policy(DeadlockPolicy::Detection)?;
+        session_query(&mut instance, "create table t (a int primary key, b int)")?;
+
+        session_query(&mut instance, "begin isolation level read committed")?;
+        assert_eq!(IsolationLevel::ReadCommitted, instance.session_txn.as_ref().unwrap().get_isolation_level());
+        session_query(&mut instance, "select * from t")?;
+        assert!(session_query(&mut instance, "SET TRANSACTION ISOLATION LEVEL SERIALIZABLE").is_err());
+        session_query(&mut instance, "commit")?;
+
+        session_query(&mut instance, "begin")?;
+        assert_eq!(IsolationLevel::SnapshotIsolation, instance.session_txn.as_ref().unwrap().get_isolation_level());
+        session_query(&mut instance, "SET TRANSACTION ISOLATION LEVEL REPEATABLE READ")?;
+        assert_eq!(IsolationLevel::RepeatableRead, instance.session_txn.as_ref().unwrap().get_isolation_level());
+        session_query(&mut instance, "commit")?;
+
+        // in any case
+        session_query(&mut instance, "begin; set transaction isolation level read committed")?;
+        assert_eq!(IsolationLevel::ReadCommitted, instance.session_txn.as_ref().unwrap().get_isolation_level());
+        session_query(&mut instance, "commit")?;
+        session_query(&mut instance, "Set Session Characteristics As Transaction Isolation Level Repeatable Read")?;
+        session_query(&mut instance, "begin")?;
+        assert_eq!(IsolationLevel::RepeatableRead, instance.session_txn.as_ref().unwrap().get_isolation_level());
+        session_query(&mut instance, "commit")?;
+        assert!(session_query(&mut instance, "SET TRANSACTION ISOLATION LEVEL SERIALIZABLE").is_err());
+
+        // the default of the new transactions
+        session_query(&mut instance, "SET SESSION CHARACTERISTICS AS TRANSACTION ISOLATION LEVEL SERIALIZABLE")?;
+        session_query(&mut instance, "begin")?;
+        assert_eq!(IsolationLevel::Serializable, instance.session_txn.as_ref().unwrap().get_isolation_level());
+        session_query(&mut instance, "insert into t values (1, 10)")?;
+        session_query(&mut instance, "commit")?;
+        assert_eq!(rows(&[(1, 10)]), session_query(&mut instance, "select * from t")?);
+        Ok(())
+    }
+    #[test]
+    fn crash_recovery_test() -> Result<(), String> {
+        let mut instance = open_instance("test_crash_recovery", DeadlockPolicy::Detection)?;
+        session_query(&mut instance, "create table t (a int primary key, b int)")?;
+        session_query(&mut instance, "create sequence s")?;
+        session_query(&mut instance, "create table c (id serial, a int references t (a))")?;
+        session_query(&mut instance, "insert into t values (1, 10), (2, 20), (3, 30)")?;
+        session_query(&mut instance, "insert into c (a) values (1)")?;
+        session_query(&mut instance, "select nextval('s')")?;
+        session_query(&mut instance, "begin")?;
+        session_query(&mut instance, "delete from t where a = 2")?;
+        session_query(&mut instance, "insert into t values (4, 40)")?;
+        session_query(&mut instance, "commit")?;
+
+        // running at the crash
+        session_query(&mut instance, "begin")?;
+        session_query(&mut instance, "insert into t values (5, 50)")?;
+        session_query(&mut instance, "delete from t where a = 3")?;
+        // the crash, no page of the buffer pool is written out
+        drop(instance);
+
+        // the committed changes survive, the ones of the unfinished transaction are rolled back
+        let mut instance = DBInstance::new("test_crash_recovery.db")?;
+        assert_eq!(rows(&[(1, 10), (3, 30), (4, 40)]), session_query(&mut instance, "select * from t")?);
+        // the catalog is back with its indexes, sequences and foreign keys
+        assert_eq!(true, session_query(&mut instance, "insert into t values (1, 11)").is_err());
+        assert_eq!(true, session_query(&mut instance, "insert into c (a) values (2)").is_err());
+        session_query(&mut instance, "insert into c (a) values (3)")?;
+        // the values of the sequences go on beyond the ones handed out before
+        let ids = session_query(&mut instance, "select id from c")?;
+        assert_eq!(true, ids[1][0].parse::<i32>().unwrap() > 1, "{:?}", ids);
+        let next = session_query(&mut instance, "select nextval('s')")?;
+        assert_eq!(true, next[0][0].parse::<i32>().unwrap() > 1, "{:?}", next);
+
+        // the new transactions do not take the ids of the ones before the crash
+        session_query(&mut instance, "insert into t values (5, 50)")?;
+        session_query(&mut instance, "delete from t where a = 4")?;
+        drop(instance);
+        let mut instance = DBInstance::new("test_crash_recovery.db")?;
+        assert_eq!(rows(&[(1, 10), (3, 30), (5, 50)]), session_query(&mut instance, "select * from t")?);
+        Ok(())
+    }
+
+    fn check_backend(name: &str, backend: DiskBackend) -> Result<(), String> {
+        let _ = fs::remove_file(format!("{}.db", name));
+        let _ = fs::remove_file(format!("{}.log", name));
+        let _ = fs::remove_file(format!("{}.dwb", name));
+        let mut instance = DBInstance::new_with_backend(&format!("{}.db", name), DeadlockPolicy::Detection, backend)?;
+        session_query(&mut instance, "create table t (a int, b int)")?;
+        session_query(&mut instance, "insert into t values (1, 10), (2, 20)")?;
+        assert_eq!(rows(&[(1, 10), (2, 20)]), session_query(&mut instance, "select * from t")?);
+        drop(instance);
+
+        // the same file is recovered through the default backend
+        let instance = DBInstance::new(&format!("{}.db", name))?;
+        assert_eq!(true, instance.disk_mgr.get_filesize() > 0);
+        Ok(())
+    }
+
+    #[test]
+    fn disk_backend_test() -> Result<(), String> {
+        check_backend("test_backend_positional", DiskBackend::Positional { direct: false })
+    }
+
+    // io_uring may not be there, run with --ignored where it is
+    #[test]
+    #[ignore]
+    fn io_uring_backend_test() -> Result<(), String> {
+        check_backend("test_backend_io_uring", DiskBackend::IoUring { direct: false })
+    }
+
+    #[test]
+    fn page_size_test() -> Result<(), String> {
+        let (db, other) = ("test_page_size.db", "test_page_size_other.db");
+        for filename in [db, "test_page_size.log", "test_page_size.dwb", other] {
+            let _ = fs::remove_file(filename);
+        }
+        let mut instance = DBInstance::new_with_page_size(db, DeadlockPolicy::Detection, DiskBackend::Buffered, 16384)?;
+        assert_eq!(16384, instance.get_page_size());
+        session_query(&mut instance, "create table t (a int, b int)")?;
+        session_query(&mut instance, "insert into t values (1, 10), (2, 20)")?;
+        assert_eq!(rows(&[(1, 10), (2, 20)]), session_query(&mut instance, "select * from t")?);
+        let first_page_id = instance.catalog.read().unwrap().borrow().get_table("t").unwrap().table_heap.get_first_page_id();
+        drop(instance);
+
+        // the page size is chosen at creation, the file is not opened with another one
+        let err = DBInstance::new(db).err().unwrap();
+        assert_eq!(true, err.contains("page size 16384"), "{}", err);
+        assert_eq!(true, DBInstance::new_with_page_size(db, DeadlockPolicy::Detection, DiskBackend::Buffered, 12288).is_err());
+        let instance = DBInstance::new_with_page_size(db, DeadlockPolicy::Detection, DiskBackend::Buffered, 16384)?;
+        let mut page = Page::new_with_size(16384);
+        instance.disk_mgr.read_page(first_page_id, page.get_mut_data())?;
+        assert_eq!(2, page.cast_as::<TablePage>().get_num_tuples());
+        drop(instance);
+
+        // a file of a newer format
+        let file = OpenOptions::new().write(true).open(db).unwrap();
+        file.write_all_at(&(FORMAT_VERSION + 1).to_ne_bytes(), 16).unwrap();
+        let err = DBInstance::new_with_page_size(db, DeadlockPolicy::Detection, DiskBackend::Buffered, 16384).err().unwrap();
+        assert_eq!(true, err.contains("format version"), "{}", err);
+        fs::write(other, "hello").unwrap();
+        let err = DBInstance::new(other).err().unwrap();
+        assert_eq!(true, err.contains("not a database file"), "{}", err);
+
+        // the largest page
+        let mut instance = DBInstance::open_in_memory_with_page_size(DeadlockPolicy::Detection, MAX_PAGE_SIZE)?;
+        session_query(&mut instance, "create table t (a int, b int)")?;
+        session_query(&mut instance, "insert into t values (1, 10), (2, 20)")?;
+        assert_eq!(rows(&[(1, 10), (2, 20)]), session_query(&mut instance, "select * from t")?);
+
+        for filename in [db, "test_page_size.log", "test_page_size.dwb", other] {
+            let _ = fs::remove_file(filename);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn tablespace_test() -> Result<(), String> {
+        let (db, dir) = ("test_tablespace.db", "test_tablespace_dir");
+        let cleanup = || {
+            for filename in [db, "test_tablespace.log", "test_tablespace.dwb"] {
+                let _ = fs::remove_file(filename);
+            }
+            let _ = fs::remove_dir_all(dir);
+        };
+        cleanup();
+        let first_page_id = |instance: &DBInstance, table: &str| instance.catalog.read().unwrap().borrow().get_table(table).unwrap().table_heap.get_first_page_id();
+
+        let mut instance = DBInstance::new(db)?;
+        let file_id = instance.create_tablespace("archive", dir)?;
+        assert_eq!(1, file_id);
+        assert_eq!(true, instance.create_tablespace("archive", dir).is_err());
+        session_query(&mut instance, "create table t (a int, b int) with (tablespace = 'archive')")?;
+        session_query(&mut instance, "create table u (a int, b int)")?;
+        let err = session_query(&mut instance, "create table v (a int) with (tablespace = 'fast')").err().unwrap();
+        assert_eq!(true, err.contains("does not exist"), "{}", err);
+        session_query(&mut instance, "insert into t values (1, 10), (2, 20), (3, 30)")?;
+        session_query(&mut instance, "insert into u values (4, 40)")?;
+        assert_eq!(rows(&[(1, 10), (2, 20), (3, 30)]), session_query(&mut instance, "select * from t")?);
+
+        // page 0 of the file is its superblock and page 1 its map page
+        let page_id = first_page_id(&instance, "t");
+        assert_eq!(PageAddr::new(file_id, 2), PageAddr::of(page_id));
+        assert_eq!(MAIN_FILE_ID, PageAddr::file_of(first_page_id(&instance, "u")));
+        // the crash, no page of the buffer pool is written out
+        drop(instance);
+
+        // the file of the tablespace is opened with the database and the log brings its pages back
+        let mut instance = DBInstance::new(db)?;
+        let mut page = Page::new();
+        instance.disk_mgr.read_page(page_id, page.get_mut_data())?;
+        assert_eq!(3, page.cast_as::<TablePage>().get_num_tuples());
+        // the catalog keeps the table in the tablespace, on the pages it was created on
+        assert_eq!(page_id, first_page_id(&instance, "t"));
+        assert_eq!(rows(&[(1, 10), (2, 20), (3, 30)]), session_query(&mut instance, "select * from t")?);
+        let path = Tablespace::path_in(dir, "archive");
+        assert_eq!(3 * PAGE_SIZE as u64, fs::metadata(&path).unwrap().len());
+        session_query(&mut instance, "create table v (a int, b int) with (tablespace = archive)")?;
+        assert_eq!(PageAddr::new(file_id, 3), PageAddr::of(first_page_id(&instance, "v")));
+        assert_eq!(2, instance.create_tablespace("fast", dir)?);
+        drop(instance);
+
+        // the database is not opened without the file of a tablespace
+        fs::remove_file(&path).unwrap();
+        let err = DBInstance::new(db).err().unwrap();
+        assert_eq!(true, err.contains("archive"), "{}", err);
+        cleanup();
+        Ok(())
+    }
+
+    #[test]
+    fn toast_test() -> Result<(), String> {
+        let db = "test_toast.db";
+        let cleanup = || {
+            for filename in [db, "test_toast.log", "test_toast.dwb"] {
+                let _ = fs::remove_file(filename);
+            }
+        };
+        cleanup();
+        let text = "all work and no play makes jack a dull boy. ".repeat(300);
+        let hex: String = (0..6000).map(|i| format!("{:02x}", (i * 7919 % 256) as u8)).collect();
+
+        let mut instance = DBInstance::new(db)?;
+        session_query(&mut instance, "create table t (a int, b text, c blob)")?;
+        session_query(&mut instance, "create table u (a int, b text, c bytea) with (compression = off)")?;
+        assert_eq!(true, session_query(&mut instance, "create table v (a int) with (compression = 'gzip')").is_err());
+        for table in ["t", "u"] {
+            session_query(&mut instance, &format!("insert into {} values (1, '{}', X'{}'), (2, 'small', null)", table, text, hex))?;
+            let expected = vec![vec!["1".to_string(), text.clone(), format!("\\x{}", hex)], vec!["2".to_string(), "small".to_string(), "null".to_string()]];
+            let result = session_query(&mut instance, &format!("select * from {}", table))?;
+            assert_eq!(expected[0], result[0]);
+            assert_eq!(expected[1][..2], result[1][..2]);
+        }
+        assert_eq!(true, session_query(&mut instance, "insert into t values (3, 'x', X'abc')").is_err());
+
+        // the tuple on the page points to the overflow pages
+        let first_page_id = instance.catalog.read().unwrap().borrow().get_table("t").unwrap().table_heap.get_first_page_id();
+        let schema = Schema::new(&vec![Column::new("a", TypeId::INTEGER), Column::new("b", TypeId::TEXT), Column::new("c", TypeId::BLOB)]);
+        let read_stored = |instance: &DBInstance| -> Result<Tuple, String> {
+            let mut page = Page::new();
+            instance.disk_mgr.read_page(first_page_id, page.get_mut_data())?;
+            Ok(page.cast_as::<TablePage>().get_tuple(&RID { pid: first_page_id, sid: 0 })?.1)
+        };
+        // the crash, the overflow pages are on disk already and the log brings the tuple back
+        drop(instance);
+
+        let instance = DBInstance::new(db)?;
+        let stored = read_stored(&instance)?;
+        assert_eq!(true, stored.get_length() <= PAGE_SIZE as usize / 4);
+        let toaster = Toaster::new(instance.bp_mgr.clone(), MAIN_FILE_ID, schema.clone(), true);
+        let tuple = toaster.detoast(&stored)?;
+        assert_eq!(text, tuple.get_value(&schema, 1).to_string());
+        assert_eq!(format!("\\x{}", hex), tuple.get_value(&schema, 2).to_string());
+        drop(instance);
+        cleanup();
+        Ok(())
+    }
+
+    #[test]
+    fn vacuum_test() -> Result<(), String> {
+        let db = "test_vacuum.db";
+        let cleanup = || {
+            for filename in [db, "test_vacuum.log", "test_vacuum.dwb"] {
+                let _ = fs::remove_file(filename);
+            }
+        };
+        cleanup();
+        let mut instance = DBInstance::new(db)?;
+        session_query(&mut instance, "create table t (a int, b varchar(64))")?;
+        let values: Vec<String> = (0..20).map(|i| format!("({}, '{}')", i, "v".repeat(50))).collect();
+        session_query(&mut instance, &format!("insert into t values {}", values.join(", ")))?;
+        let first_page_id = instance.catalog.read().unwrap().borrow().get_table("t").unwrap().table_heap.get_first_page_id();
+        let free_space = instance.bp_mgr.get_free_space(first_page_id).unwrap();
+
+        // the snapshot of the reader is older than the delete, the deleted rows stay for it
+        let reader = instance.begin_transaction();
+        session_query(&mut instance, "delete from t where a < 10")?;
+        assert_eq!(0, instance.vacuum(Some("t"))?);
+        assert_eq!(20, query_txn(&mut instance, "select * from t", &reader)?.len());
+        instance.commit_transaction(&reader)?;
+
+        session_query(&mut instance, "VACUUM t;")?;
+        assert_eq!(0, instance.vacuum(None)?);
+        assert_eq!(true, instance.bp_mgr.get_free_space(first_page_id).unwrap() >= free_space + 10 * 50);
+        assert_eq!(10, session_query(&mut instance, "select * from t")?.len());
+        session_query(&mut instance, "begin")?;
+        assert_eq!(true, session_query(&mut instance, "vacuum").is_err());
+        session_query(&mut instance, "commit")?;
+        assert_eq!(true, session_query(&mut instance, "vacuum u").is_err());
+        // a vacuum is taken only as a statement of its own, a string holding the word is not one
+        assert_eq!(true, session_query(&mut instance, "vacuum t; select * from t").is_err());
+        assert_eq!(0, session_query(&mut instance, "select * from t where b = 'vacuum t'")?.len());
+        assert_eq!(Ok(Some(Some("T t".to_string()))), DBInstance::parse_vacuum("VACUUM \"T t\";;"));
+        assert_eq!(None, instance.check_background().err());
+
+        // the new rows take the room on the page and the slots of the old ones
+        session_query(&mut instance, &format!("insert into t values (100, '{}'), (101, 'w')", "w".repeat(60)))?;
+        let schema = Schema::new(&vec![Column::new("a", TypeId::INTEGER), Column::new_varchar("b", TypeId::VARCHAR, 64)]);
+        // the crash, the log compacts the page again before the inserts are redone
+        drop(instance);
+
+        let instance = DBInstance::new(db)?;
+        let mut page = Page::new();
+        instance.disk_mgr.read_page(first_page_id, page.get_mut_data())?;
+        let table_page = page.cast_as::<TablePage>();
+        assert_eq!(20, table_page.get_num_tuples());
+        let rid = |sid: u16| RID { pid: first_page_id, sid };
+        assert_eq!((true, false), (table_page.is_vacuumed(&rid(9)), table_page.is_vacuumed(&rid(10))));
+        assert_eq!("10", table_page.get_tuple(&rid(10))?.1.get_value(&schema, 0).to_string());
+        assert_eq!("100", table_page.get_tuple(&rid(0))?.1.get_value(&schema, 0).to_string());
+        assert_eq!("101", table_page.get_tuple(&rid(1))?.1.get_value(&schema, 0).to_string());
+        drop(instance);
+        cleanup();
+        Ok(())
+    }
+}
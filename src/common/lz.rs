@@ -0,0 +1,132 @@
+#![allow(warnings)]
+
+// a small LZ77 like the pglz of PostgreSQL, for the large values of the tuples.
+// a control byte tells for each of the next 8 items whether it is a literal byte or a match,
+// a match is 2 bytes, the distance back in the 12 high bits and the length - MIN_MATCH in the 4 low ones
+const MIN_MATCH: usize = 3;
+const MAX_MATCH: usize = MIN_MATCH + 0xf;
+const MAX_DISTANCE: usize = 0xfff;
+const HASH_BITS: u32 = 12;
+
+fn hash(bytes: &[u8]) -> usize {
+    let key = (bytes[0] as u32) << 16 | (bytes[1] as u32) << 8 | bytes[2] as u32;
+    (key.wrapping_mul(2654435761) >> (32 - HASH_BITS)) as usize
+}
+
+// None if the data does not get smaller
+pub fn compress(src: &[u8]) -> Option<Vec<u8>> {
+    let mut out = Vec::with_capacity(src.len());
+    // the last position of each hash of 3 bytes
+    let mut heads = vec![usize::MAX; 1 << HASH_BITS];
+    let mut pos = 0;
+    while pos < src.len() {
+        let control = out.len();
+        out.push(0u8);
+        for bit in 0..8 {
+            if pos >= src.len() {
+                break;
+            }
+            let mut match_len = 0;
+            let mut distance = 0;
+            if pos + MIN_MATCH <= src.len() {
+                let head = hash(&src[pos..]);
+                let candidate = heads[head];
+                heads[head] = pos;
+                if candidate != usize::MAX && pos - candidate <= MAX_DISTANCE {
+                    let max_len = (src.len() - pos).min(MAX_MATCH);
+                    while match_len < max_len && src[candidate + match_len] == src[pos + match_len] {
+                        match_len += 1;
+                    }
+                    distance = pos - candidate;
+                }
+            }
+
+            if match_len >= MIN_MATCH {
+                out[control] |= 1 << bit;
+                out.extend_from_slice(&(((distance << 4) | (match_len - MIN_MATCH)) as u16).to_le_bytes());
+                for skipped in pos + 1..pos + match_len {
+                    if skipped + MIN_MATCH <= src.len() {
+                        heads[hash(&src[skipped..])] = skipped;
+                    }
+                }
+                pos += match_len;
+            } else {
+                out.push(src[pos]);
+                pos += 1;
+            }
+        }
+        if out.len() >= src.len() {
+            return None;
+        }
+    }
+    match out.len() < src.len() {
+        true => Some(out),
+        false => None,
+    }
+}
+
+// the data was raw_len bytes before it was compressed
+pub fn decompress(src: &[u8], raw_len: usize) -> Result<Vec<u8>, String> {
+    let mut out = Vec::with_capacity(raw_len);
+    let mut pos = 0;
+    while pos < src.len() {
+        let control = src[pos];
+        pos += 1;
+        for bit in 0..8 {
+            if pos >= src.len() {
+                break;
+            }
+            if control & (1 << bit) == 0 {
+                out.push(src[pos]);
+                pos += 1;
+                continue;
+            }
+            if pos + 2 > src.len() {
+                return Err(format!("the compressed data is cut off at {}", pos));
+            }
+            let token = u16::from_le_bytes([src[pos], src[pos + 1]]) as usize;
+            pos += 2;
+            let (distance, match_len) = (token >> 4, (token & 0xf) + MIN_MATCH);
+            if distance == 0 || distance > out.len() {
+                return Err(format!("invalid match distance {} at {}", distance, pos));
+            }
+            // the match may run into the bytes it produces
+            let start = out.len() - distance;
+            for i in 0..match_len {
+                out.push(out[start + i]);
+            }
+        }
+    }
+    match out.len() == raw_len {
+        true => Ok(out),
+        false => Err(format!("the data is {} bytes after decompression, {} expected", out.len(), raw_len)),
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::{compress, decompress};
+
+    #[test]
+    fn test_round_trip() {
+        let text = "the quick brown fox jumps over the lazy dog, ".repeat(200);
+        let compressed = compress(text.as_bytes()).unwrap();
+        assert_eq!(true, compressed.len() < text.len() / 4);
+        assert_eq!(text.as_bytes(), &decompress(&compressed, text.len()).unwrap()[..]);
+
+        // a run is a match into itself
+        let run = vec![7u8; 5000];
+        let compressed = compress(&run).unwrap();
+        assert_eq!(run, decompress(&compressed, run.len()).unwrap());
+
+        // data which does not compress is left as it is
+        let mut seed = 12345u32;
+        let noise: Vec<u8> = (0..4096).map(|_| { seed = seed.wrapping_mul(1103515245).wrapping_add(12345); (seed >> 16) as u8 }).collect();
+        assert_eq!(None, compress(&noise));
+        assert_eq!(None, compress(b""));
+
+        assert_eq!(true, decompress(&compressed, run.len() + 1).is_err());
+        assert_eq!(true, decompress(&[0x01, 0x10, 0x00], 3).is_err());
+    }
+}
@@ -3,3 +3,6 @@ pub mod rwlatch;
 pub mod rid;
 pub mod instance;
 pub mod formatwriter;
+pub mod crc32c;
+pub mod page_addr;
+pub mod lz;
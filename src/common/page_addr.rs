@@ -0,0 +1,68 @@
+#![allow(warnings)]
+
+use super::config::{file_id_t, page_id_t, INVALID_PAGE_ID, MAX_FILE_ID, PAGE_NO_BITS};
+
+
+/// PageAddr, where a page is, the file of its tablespace and the page number in the file.
+/// It is packed into a page_id_t with the file id in the high bits, so the page table, the RIDs
+/// and the log records keep one number for a page, and the pages of the database file keep their ids.
+/// The page number has `PAGE_NO_BITS` bits and the file id 16 above them, the top bits are left clear
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct PageAddr {
+    pub file_id: file_id_t,
+    pub page_no: page_id_t,
+}
+
+impl PageAddr {
+    pub const MAX_PAGE_NO: page_id_t = (1 << PAGE_NO_BITS) - 1;
+
+    pub fn new(file_id: file_id_t, page_no: page_id_t) -> Self {
+        assert!(file_id <= MAX_FILE_ID, "file id {} is out of range", file_id);
+        assert!(page_no >= 0 && page_no <= Self::MAX_PAGE_NO, "page number {} is out of range", page_no);
+        Self { file_id, page_no }
+    }
+
+    pub fn of(page_id: page_id_t) -> Self {
+        assert!(page_id != INVALID_PAGE_ID, "the invalid page has no address");
+        Self {
+            file_id: (page_id >> PAGE_NO_BITS) as file_id_t,
+            page_no: page_id & Self::MAX_PAGE_NO,
+        }
+    }
+
+    pub fn to_page_id(&self) -> page_id_t {
+        ((self.file_id as page_id_t) << PAGE_NO_BITS) | self.page_no
+    }
+
+    // the file a page id is in
+    pub fn file_of(page_id: page_id_t) -> file_id_t {
+        Self::of(page_id).file_id
+    }
+}
+
+
+
+#[cfg(test)]
+mod tests {
+    use crate::common::config::{MAIN_FILE_ID, MAX_FILE_ID};
+
+    use super::PageAddr;
+
+    #[test]
+    fn test_pack() {
+        // the pages of the database file keep their ids
+        assert_eq!(37, PageAddr::new(MAIN_FILE_ID, 37).to_page_id());
+        assert_eq!(PageAddr::new(MAIN_FILE_ID, 37), PageAddr::of(37));
+
+        // a file has more than 2^24 pages, and there are more than 127 files
+        assert_eq!((1 << 32) - 1, PageAddr::MAX_PAGE_NO);
+        for addr in [PageAddr::new(1, 0), PageAddr::new(3, 12345), PageAddr::new(200, 1 << 31), PageAddr::new(MAX_FILE_ID, PageAddr::MAX_PAGE_NO)] {
+            let page_id = addr.to_page_id();
+            assert_eq!(true, page_id >= 0);
+            assert_eq!(addr, PageAddr::of(page_id));
+            assert_eq!(addr.file_id, PageAddr::file_of(page_id));
+        }
+        // adjacent pages of a file have adjacent ids
+        assert_eq!(PageAddr::new(2, 8).to_page_id() + 1, PageAddr::new(2, 9).to_page_id());
+    }
+}
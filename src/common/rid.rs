@@ -1,8 +1,9 @@
-use super::config::{page_id_t, slot_id_t, INVALID_PAGE_ID};
+use super::{config::{page_id_t, slot_id_t, INVALID_PAGE_ID}, page_addr::PageAddr};
 
 
+// pid is the page id of the tuple, with the file of its tablespace in it, see `PageAddr`
 #[repr(C)]
-#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
 pub struct RID {
     pub pid: page_id_t,
     pub sid: slot_id_t,
@@ -15,4 +16,9 @@ impl RID {
             sid: 0,
         }
     }
+
+    // the file and the page number of the page of the tuple
+    pub fn get_page_addr(&self) -> PageAddr {
+        PageAddr::of(self.pid)
+    }
 }
@@ -0,0 +1,322 @@
+#![allow(warnings)]
+
+use crate::{binder::table_ref::bound_base_table::BoundBaseTableRef, buffer::access_strategy::{AccessIntent, BufferAccessStrategy}, catalog::{catalog::{CataLog, TableInfoRef}, constraint::{ForeignKey, ForeignKeyAction}, schema::Schema}, common::{config::INVALID_TXN_ID, rid::RID}, execution::{executor_context::ExecutorContext, expressions::expr::ExpressionRef}, planner::planner::Planner, storage::page_based::table::tuple::{Tuple, TupleMeta}, transaction::transaction::{WriteRecord, WriteType}, typedef::{type_trait::CmpBool, value::Value}};
+
+
+/// Checks the unique and foreign key constraints of a table when its rows are modified,
+/// and carries out the referential actions on the child rows.
+///
+/// The index entries of a table point to the versions which are not deleted by a committed transaction,
+/// the entries of a deleted version are removed when the delete commits. An index entry means the key is taken,
+/// unless the version is inserted by an aborted transaction, or deleted by the transaction itself.
+/// The rows are read and written in the snapshot of the transaction of `ctx`.
+pub struct ConstraintChecker {
+}
+
+impl ConstraintChecker {
+
+    // check a tuple before it is inserted into the table,
+    // when it is an update, the old tuple should have been marked deleted
+    pub fn check_insert(ctx: &ExecutorContext, catalog: &CataLog, table_info: &TableInfoRef, tuple: &Tuple) -> Result<(), String> {
+        Self::check_unique(ctx, catalog, table_info, tuple)?;
+        Self::check_foreign_keys(ctx, catalog, table_info, tuple)
+    }
+
+    // a CHECK constraint fails only when it is false, null passes
+    pub fn check_expressions(table_info: &TableInfoRef, tuple: &Tuple, checks: &Vec<(String, ExpressionRef)>) -> Result<(), String> {
+        for (name, predicate) in checks {
+            let res = predicate.evalute(tuple, &table_info.schema)?;
+            if !res.is_null() && !*res.as_ptr::<bool>() {
+                return Err(format!("new row for relation \"{}\" violates check constraint \"{}\"", table_info.table_name, name));
+            }
+        }
+        Ok(())
+    }
+
+    fn check_unique(ctx: &ExecutorContext, catalog: &CataLog, table_info: &TableInfoRef, tuple: &Tuple) -> Result<(), String> {
+        for index_info in catalog.get_table_indexes(&table_info.table_name) {
+            if !index_info.is_unique {
+                continue;
+            }
+
+            // nulls are never equal to each other
+            if Self::has_null(tuple, &table_info.schema, &index_info.key_attrs) {
+                continue;
+            }
+            let key = index_info.get_key(tuple, &table_info.schema);
+            if Self::has_live_entry(ctx, table_info, &index_info.index.scan_key(&key))? {
+                return Err(format!("duplicate key value violates unique constraint \"{}\"", index_info.index_name));
+            }
+        }
+        Ok(())
+    }
+
+    // the referenced key of each foreign key must exist in the parent table
+    fn check_foreign_keys(ctx: &ExecutorContext, catalog: &CataLog, table_info: &TableInfoRef, tuple: &Tuple) -> Result<(), String> {
+        for fk in catalog.get_foreign_keys(table_info.table_oid) {
+            // a key with null is not checked
+            if Self::has_null(tuple, &table_info.schema, &fk.columns) {
+                continue;
+            }
+
+            let Some(ref_index) = catalog.get_index(fk.ref_index_oid) else {
+                return Err(format!("index of foreign key {} not found", fk.name));
+            };
+            // the fk columns are kept in the order of the index key
+            let key = tuple.get_key(&table_info.schema, &fk.columns, &ref_index.key_schema);
+            let Some(ref_info) = catalog.get_table_byid(fk.ref_table_oid) else {
+                return Err(format!("table of foreign key {} not found", fk.name));
+            };
+            if !Self::has_live_entry(ctx, ref_info, &ref_index.index.scan_key(&key))? {
+                return Err(format!("insert or update on table \"{}\" violates foreign key constraint \"{}\"",
+                    table_info.table_name, fk.name));
+            }
+        }
+        Ok(())
+    }
+
+    // called after a row of the parent table is deleted
+    pub fn on_delete(ctx: &ExecutorContext, catalog: &CataLog, table_info: &TableInfoRef, old_tuple: &Tuple) -> Result<(), String> {
+        for fk in catalog.get_referencing_foreign_keys(table_info.table_oid) {
+            if Self::has_null(old_tuple, &table_info.schema, &fk.ref_columns) {
+                continue;
+            }
+
+            let children = Self::scan_children(ctx, catalog, table_info, &fk, old_tuple)?;
+            if children.is_empty() {
+                continue;
+            }
+            let child_info = catalog.get_table_byid(fk.table_oid).unwrap().clone();
+            match fk.on_delete {
+                ForeignKeyAction::Restrict => {
+                    return Err(Self::restrict_error(table_info, &child_info, &fk));
+                },
+                ForeignKeyAction::Cascade => {
+                    for (rid, child) in children {
+                        Self::delete_tuple(ctx, catalog, &child_info, rid, &child)?;
+                    }
+                },
+                ForeignKeyAction::SetNull => {
+                    let checks = Self::plan_checks(ctx, &child_info)?;
+                    for (rid, child) in children {
+                        let mut values = Self::get_values(&child, &child_info.schema);
+                        for col in &fk.columns {
+                            values[*col] = Value::new_null(values[*col].get_type());
+                        }
+                        Self::update_child(ctx, catalog, &child_info, rid, &child, &values, &checks)?;
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+
+    // called after a row of the parent table is updated
+    pub fn on_update(ctx: &ExecutorContext, catalog: &CataLog, table_info: &TableInfoRef, old_tuple: &Tuple, new_tuple: &Tuple) -> Result<(), String> {
+        for fk in catalog.get_referencing_foreign_keys(table_info.table_oid) {
+            if Self::has_null(old_tuple, &table_info.schema, &fk.ref_columns)
+                || !Self::key_changed(old_tuple, new_tuple, &table_info.schema, &fk.ref_columns) {
+                continue;
+            }
+
+            let children = Self::scan_children(ctx, catalog, table_info, &fk, old_tuple)?;
+            if children.is_empty() {
+                continue;
+            }
+            let child_info = catalog.get_table_byid(fk.table_oid).unwrap().clone();
+            match fk.on_update {
+                ForeignKeyAction::Restrict => {
+                    return Err(Self::restrict_error(table_info, &child_info, &fk));
+                },
+                ForeignKeyAction::Cascade => {
+                    let checks = Self::plan_checks(ctx, &child_info)?;
+                    for (rid, child) in children {
+                        let mut values = Self::get_values(&child, &child_info.schema);
+                        for (col, ref_col) in fk.columns.iter().zip(fk.ref_columns.iter()) {
+                            values[*col] = new_tuple.get_value(&table_info.schema, *ref_col);
+                        }
+                        Self::update_child(ctx, catalog, &child_info, rid, &child, &values, &checks)?;
+                    }
+                },
+                ForeignKeyAction::SetNull => {
+                    let checks = Self::plan_checks(ctx, &child_info)?;
+                    for (rid, child) in children {
+                        let mut values = Self::get_values(&child, &child_info.schema);
+                        for col in &fk.columns {
+                            values[*col] = Value::new_null(values[*col].get_type());
+                        }
+                        Self::update_child(ctx, catalog, &child_info, rid, &child, &values, &checks)?;
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+
+    // the CHECK constraints of a child table, planned when a referential action rewrites its rows
+    fn plan_checks(ctx: &ExecutorContext, child_info: &TableInfoRef) -> Result<Vec<(String, ExpressionRef)>, String> {
+        let table_ref = BoundBaseTableRef::new(child_info.table_name.clone(), child_info.table_oid, None, child_info.schema.clone());
+        Planner::new(ctx.get_catalog()).plan_checks(&table_ref)
+    }
+
+    // a child row rewritten by a referential action passes the checks of its table as any update does
+    fn update_child(ctx: &ExecutorContext, catalog: &CataLog, child_info: &TableInfoRef, rid: RID, child: &Tuple, values: &Vec<Value>, checks: &Vec<(String, ExpressionRef)>) -> Result<RID, String> {
+        Self::check_expressions(child_info, &Tuple::build(values, &child_info.schema), checks)?;
+        Self::update_tuple(ctx, catalog, child_info, rid, child, values)
+    }
+
+    // the restricted children are checked before the parent row is touched,
+    // `new_tuple` is None when the row is deleted
+    fn check_restrict(ctx: &ExecutorContext, catalog: &CataLog, table_info: &TableInfoRef, old_tuple: &Tuple, new_tuple: Option<&Tuple>) -> Result<(), String> {
+        for fk in catalog.get_referencing_foreign_keys(table_info.table_oid) {
+            let action = match new_tuple {
+                None => fk.on_delete,
+                Some(new_tuple) => {
+                    if !Self::key_changed(old_tuple, new_tuple, &table_info.schema, &fk.ref_columns) {
+                        continue;
+                    }
+                    fk.on_update
+                }
+            };
+            if action != ForeignKeyAction::Restrict || Self::has_null(old_tuple, &table_info.schema, &fk.ref_columns) {
+                continue;
+            }
+
+            if !Self::scan_children(ctx, catalog, table_info, &fk, old_tuple)?.is_empty() {
+                let child_info = catalog.get_table_byid(fk.table_oid).unwrap();
+                return Err(Self::restrict_error(table_info, child_info, &fk));
+            }
+        }
+        Ok(())
+    }
+
+    // delete a row, then apply the actions to its children.
+    // the row is marked first, so a cycle of cascades stops when it comes back
+    pub fn delete_tuple(ctx: &ExecutorContext, catalog: &CataLog, table_info: &TableInfoRef, rid: RID, tuple: &Tuple) -> Result<(), String> {
+        let txn = ctx.get_txn();
+        ctx.lock_row_exclusive(table_info.table_oid, rid)?;
+        let mut meta = table_info.table_heap.get_meta(&rid)?;
+        if meta.delete_txn_id == txn.get_txn_id() {
+            return Ok(());
+        }
+        ctx.get_txn_mgr().check_write(&meta, &txn)?;
+        Self::check_restrict(ctx, catalog, table_info, tuple, None)?;
+
+        meta.is_deleted = true;
+        meta.delete_txn_id = txn.get_txn_id();
+        table_info.table_heap.update_meta(&meta, &rid, Some(&txn))?;
+        txn.append_write_record(WriteRecord::new(table_info.table_oid, rid, WriteType::Delete, txn.get_prev_lsn()));
+
+        Self::on_delete(ctx, catalog, table_info, tuple)
+    }
+
+    // update a row by deleting the old version and inserting the new one, returns the rid of the new one.
+    // the delete is in the write set before anything else may fail, the statement is rolled back as a whole then.
+    // a version inserted by the transaction itself is seen by no other one, it is updated in place and keeps its rid
+    pub fn update_tuple(ctx: &ExecutorContext, catalog: &CataLog, table_info: &TableInfoRef, rid: RID, old_tuple: &Tuple, values: &Vec<Value>) -> Result<RID, String> {
+        let txn = ctx.get_txn();
+        ctx.lock_row_exclusive(table_info.table_oid, rid)?;
+        let mut meta = table_info.table_heap.get_meta(&rid)?;
+        if meta.delete_txn_id == txn.get_txn_id() {
+            return Ok(rid);
+        }
+        ctx.get_txn_mgr().check_write(&meta, &txn)?;
+        let new_tuple = Tuple::build(values, &table_info.schema);
+        Self::check_restrict(ctx, catalog, table_info, old_tuple, Some(&new_tuple))?;
+
+        if meta.insert_txn_id == txn.get_txn_id() {
+            let tuples = table_info.table_heap.update_tuple(&rid, &new_tuple, Some(&txn))?;
+            catalog.delete_index_entries(&table_info.table_name, old_tuple, rid);
+            txn.append_write_record(WriteRecord::new_update(table_info.table_oid, rid, tuples, txn.get_prev_lsn()));
+
+            Self::check_insert(ctx, catalog, table_info, &new_tuple)?;
+            catalog.insert_index_entries(&table_info.table_name, &new_tuple, rid);
+            Self::on_update(ctx, catalog, table_info, old_tuple, &new_tuple)?;
+            return Ok(rid);
+        }
+
+        meta.is_deleted = true;
+        meta.delete_txn_id = txn.get_txn_id();
+        table_info.table_heap.update_meta(&meta, &rid, Some(&txn))?;
+        txn.append_write_record(WriteRecord::new(table_info.table_oid, rid, WriteType::Delete, txn.get_prev_lsn()));
+
+        Self::check_insert(ctx, catalog, table_info, &new_tuple)?;
+        let new_meta = TupleMeta::new(txn.get_txn_id(), INVALID_TXN_ID, false);
+        let new_rid = table_info.table_heap.insert_tuple_with(&new_meta, &new_tuple, Some(&txn), &BufferAccessStrategy::new(AccessIntent::Normal),
+            |new_rid| ctx.lock_new_row(table_info.table_oid, new_rid))
+            .map_err(|err| format!("can not insert tuple into table {}: {}", table_info.table_name, err))?;
+        txn.append_write_record(WriteRecord::new(table_info.table_oid, new_rid, WriteType::Insert, txn.get_prev_lsn()));
+        catalog.insert_index_entries(&table_info.table_name, &new_tuple, new_rid);
+
+        Self::on_update(ctx, catalog, table_info, old_tuple, &new_tuple)?;
+        Ok(new_rid)
+    }
+
+    // find the rows of the child table visible to the transaction which refer to the parent row
+    fn scan_children(ctx: &ExecutorContext, catalog: &CataLog, table_info: &TableInfoRef, fk: &ForeignKey, parent: &Tuple) -> Result<Vec<(RID, Tuple)>, String> {
+        let Some(child_info) = catalog.get_table_byid(fk.table_oid) else {
+            return Err(format!("table of foreign key {} not found", fk.name));
+        };
+        let ref_values = fk.ref_columns.iter()
+            .map(|col| parent.get_value(&table_info.schema, *col))
+            .collect::<Vec<Value>>();
+
+        let txn = ctx.get_txn();
+        let txn_mgr = ctx.get_txn_mgr();
+        let mut children = Vec::new();
+        for tuple_pair in child_info.table_heap.clone().make_iterator() {
+            let (meta, tuple) = tuple_pair?;
+            if !txn_mgr.is_visible(&meta, &txn) {
+                continue;
+            }
+            let matched = fk.columns.iter().zip(ref_values.iter())
+                .all(|(col, ref_val)| {
+                    let CmpBool::CmpTrue = tuple.get_value(&child_info.schema, *col).compare_equal(ref_val) else { return false; };
+                    true
+                });
+            if matched {
+                children.push((tuple.get_rid(), tuple));
+            }
+        }
+        Ok(children)
+    }
+
+    fn restrict_error(table_info: &TableInfoRef, child_info: &TableInfoRef, fk: &ForeignKey) -> String {
+        format!("update or delete on table \"{}\" violates foreign key constraint \"{}\" on table \"{}\"",
+            table_info.table_name, fk.name, child_info.table_name)
+    }
+
+    // the versions inserted by aborted transactions are garbage, and the ones deleted by the transaction itself,
+    // or by one committed as its entries are being removed, are gone. the delete of another running one may be undone yet
+    fn has_live_entry(ctx: &ExecutorContext, table_info: &TableInfoRef, rids: &Vec<RID>) -> Result<bool, String> {
+        let txn_mgr = ctx.get_txn_mgr();
+        let txn_id = ctx.get_txn().get_txn_id();
+        for rid in rids {
+            let meta = table_info.table_heap.get_meta(rid)?;
+            if txn_mgr.is_aborted(meta.insert_txn_id) {
+                continue;
+            }
+            if !meta.is_deleted || (meta.delete_txn_id != txn_id && !txn_mgr.is_committed(meta.delete_txn_id)) {
+                return Ok(true);
+            }
+        }
+        Ok(false)
+    }
+
+    fn has_null(tuple: &Tuple, schema: &Schema, cols: &Vec<usize>) -> bool {
+        cols.iter().any(|col| tuple.get_value(schema, *col).is_null())
+    }
+
+    fn key_changed(old_tuple: &Tuple, new_tuple: &Tuple, schema: &Schema, cols: &Vec<usize>) -> bool {
+        cols.iter().any(|col| {
+            let CmpBool::CmpTrue = old_tuple.get_value(schema, *col).compare_equal(&new_tuple.get_value(schema, *col)) else { return true; };
+            false
+        })
+    }
+
+    pub fn get_values(tuple: &Tuple, schema: &Schema) -> Vec<Value> {
+        (0..schema.get_column_count())
+            .map(|col| tuple.get_value(schema, col))
+            .collect()
+    }
+}
@@ -2,7 +2,7 @@
 
 use std::sync::Arc;
 
-use crate::{catalog::catalog::CataLogRef, transaction::transaction::{Transaction, TransactionRef}};
+use crate::{catalog::catalog::CataLogRef, common::{config::table_id_t, rid::RID}, transaction::{lock_manager::{LockManagerRef, LockMode}, transaction::{Transaction, TransactionRef}, transaction_manager::TransactionManagerRef}};
 
 
 //TOOD: a context associated with an executor
@@ -12,20 +12,48 @@ pub type ExecutorContextRef = Arc<ExecutorContext>;
 pub struct ExecutorContext {
     catalog: CataLogRef,
     txn: TransactionRef,
+    txn_mgr: TransactionManagerRef,
+    lock_mgr: LockManagerRef,
+    // the rows read are going to be deleted or updated, the scan locks them exclusively
+    is_delete: bool,
 }
 
 
 impl ExecutorContext {
-    pub fn new(catalog: CataLogRef, txn: TransactionRef) -> Self {
-        Self { catalog, txn }
+    pub fn new(catalog: CataLogRef, txn: TransactionRef, txn_mgr: TransactionManagerRef, lock_mgr: LockManagerRef, is_delete: bool) -> Self {
+        Self { catalog, txn, txn_mgr, lock_mgr, is_delete }
     }
 
     pub fn get_txn(&self) -> TransactionRef {
         self.txn.clone()
     }
 
+    pub fn get_txn_mgr(&self) -> TransactionManagerRef {
+        self.txn_mgr.clone()
+    }
+
+    pub fn get_lock_mgr(&self) -> LockManagerRef {
+        self.lock_mgr.clone()
+    }
+
+    pub fn is_delete(&self) -> bool {
+        self.is_delete
+    }
+
     pub fn get_catalog(&self) -> CataLogRef {
         self.catalog.clone()
     }
+
+    // a row is written under IX on its table and X on itself, in every isolation level
+    pub fn lock_row_exclusive(&self, table_oid: table_id_t, rid: RID) -> Result<(), String> {
+        self.lock_mgr.lock_table(&self.txn, LockMode::IntentionExclusive, table_oid)?;
+        self.lock_mgr.lock_row(&self.txn, LockMode::Exclusive, table_oid, rid)
+    }
+
+    // a row being inserted is locked under the latch of its page, it does not wait there.
+    // the IX on the table is taken before
+    pub fn lock_new_row(&self, table_oid: table_id_t, rid: RID) -> Result<(), String> {
+        self.lock_mgr.lock_row_nowait(&self.txn, LockMode::Exclusive, table_oid, rid)
+    }
 }
 
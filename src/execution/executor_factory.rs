@@ -1,6 +1,6 @@
 #![allow(warnings)]
 
-use crate::execution::executors::{seqscan_executor::SeqScanExecutor, insert_executor::InsertExecutor};
+use crate::execution::executors::{seqscan_executor::SeqScanExecutor, insert_executor::InsertExecutor, delete_executor::DeleteExecutor, update_executor::UpdateExecutor};
 
 use super::{executor_context::ExecutorContextRef, executors::{executor::Executor, filter_executor::FilterExecutor, proj_executor::ProjectExecutor, values_executor::ValuesExecutor}, plans::plan::{FilterPlan, PlanNode, PlanNodeRef}};
 
@@ -32,6 +32,14 @@ impl ExecutorFactory {
             PlanNode::Filter(filter_plan) => {
                 let child_exec = Self::create_executor(filter_plan.get_child_plan(), ctx.clone());
                 return Box::new(FilterExecutor::new(plan, child_exec, ctx));
+            },
+            PlanNode::Delete(delete_plan) => {
+                let child_exec = Self::create_executor(delete_plan.get_child_plan(), ctx.clone());
+                return Box::new(DeleteExecutor::new(plan, child_exec, ctx));
+            },
+            PlanNode::Update(update_plan) => {
+                let child_exec = Self::create_executor(update_plan.get_child_plan(), ctx.clone());
+                return Box::new(UpdateExecutor::new(plan, child_exec, ctx));
             }
             _ => {
                 panic!("not support type");
@@ -0,0 +1,70 @@
+#![allow(warnings)]
+
+use crate::{catalog::{catalog::TableInfoRef, schema::Schema}, common::rid::RID, execution::{constraint_checker::ConstraintChecker, executor_context::ExecutorContextRef, plans::plan::{PlanNode, PlanNodeRef}}, storage::page_based::table::tuple::Tuple, typedef::value_factory::ValueFactory};
+
+use super::executor::Executor;
+
+
+pub struct DeleteExecutor {
+    delete_plan: PlanNodeRef,
+
+    table_info: TableInfoRef,
+    child_executor: Box<dyn Executor>,
+    ctx: ExecutorContextRef,
+    is_deleted: bool,
+}
+
+impl DeleteExecutor {
+    pub fn new(plan: PlanNodeRef, child_exec: Box<dyn Executor>, ctx: ExecutorContextRef) -> Self {
+        let PlanNode::Delete(delete_plan) = plan.as_ref() else { panic!("Error"); };
+
+        let catalog = ctx.get_catalog();
+        let table_info = catalog.borrow()
+            .get_table_byid(delete_plan.table_id).unwrap().clone();
+        Self {
+            delete_plan: plan,
+            table_info,
+            child_executor: child_exec,
+            ctx,
+            is_deleted: false,
+        }
+    }
+}
+
+impl Executor for DeleteExecutor {
+    fn init(&mut self) {
+        self.child_executor.init();
+    }
+
+    fn next(&mut self) -> Result<Option<(RID, Tuple)>, String> {
+        if self.is_deleted {
+            return Ok(None);
+        }
+
+        let catalog = self.ctx.get_catalog();
+        let txn_id = self.ctx.get_txn().get_txn_id();
+        let mut delete_rows = 0;
+        while let Some((rid, tuple)) = self.child_executor.next()? {
+            // the row may be removed by a cascade already
+            if self.table_info.table_heap.get_meta(&rid)?.delete_txn_id == txn_id {
+                continue;
+            }
+            ConstraintChecker::delete_tuple(&self.ctx, &catalog.borrow(), &self.table_info, rid, &tuple)?;
+            delete_rows += 1;
+        }
+
+        let value = ValueFactory::get_integer_value(delete_rows);
+        let rtn_tuple = Tuple::build(&vec![value], self.delete_plan.get_output_schema());
+
+        self.is_deleted = true;
+        Ok(Some((RID::new(), rtn_tuple)))
+    }
+
+    fn get_output_schema(&self) -> &Schema {
+        self.delete_plan.get_output_schema()
+    }
+
+    fn get_context(&self) -> &ExecutorContextRef {
+        &self.ctx
+    }
+}
@@ -1,4 +1,4 @@
-use crate::{catalog::schema::Schema, common::rid::RID, execution::{executor_context::ExecutorContextRef, expressions::expr::{Expression, ExpressionFeat}, plans::plan::{PlanNode, PlanNodeRef}}, storage::page_based::table::tuple::{self, Tuple}};
+use crate::{catalog::schema::Schema, common::rid::RID, execution::{executor_context::ExecutorContextRef, plans::plan::{PlanNode, PlanNodeRef}}, storage::page_based::table::tuple::{self, Tuple}};
 
 use super::executor::Executor;
 
@@ -26,11 +26,10 @@ impl Executor for FilterExecutor {
 
     fn next(&mut self) -> Result<Option<(RID, Tuple)>, String> {
         let PlanNode::Filter(filter_plan) = self.filter_plan.as_ref() else { panic!("Error"); };
-        let Expression::CmpExpr(cmp_expr) = filter_plan.predicate.as_ref() else { panic!("Error"); };
 
         while let Some(tuple_pair) = self.child_executor.next()? {
             let tuple = tuple_pair.1;
-            let res = cmp_expr.evalute(&tuple, self.child_executor.get_output_schema());
+            let res = filter_plan.predicate.evalute(&tuple, self.child_executor.get_output_schema())?;
             
             if !res.is_null() && *res.as_ptr::<bool>() {
                 return Ok(Some((tuple_pair.0, tuple)))
@@ -2,7 +2,7 @@
 
 use core::panic;
 
-use crate::{catalog::schema::Schema, common::{config::INVALID_TXN_ID, rid::RID}, execution::{executor_context::{ExecutorContext, ExecutorContextRef}, plans::plan::{InsertPlan, PlanNode, PlanNodeRef}}, storage::page_based::table::{table_heap::TableHeapRef, tuple::{Tuple, TupleMeta}}, typedef::value_factory::ValueFactory};
+use crate::{buffer::access_strategy::{AccessIntent, BufferAccessStrategy}, catalog::{catalog::TableInfoRef, schema::Schema}, common::{config::INVALID_TXN_ID, rid::RID}, execution::{constraint_checker::ConstraintChecker, executor_context::{ExecutorContext, ExecutorContextRef}, plans::plan::{InsertPlan, PlanNode, PlanNodeRef}}, storage::page_based::table::{table_heap::TableHeapRef, tuple::{Tuple, TupleMeta}}, transaction::{lock_manager::LockMode, transaction::{WriteRecord, WriteType}}, typedef::value_factory::ValueFactory};
 
 use super::executor::Executor;
 
@@ -10,10 +10,12 @@ use super::executor::Executor;
 pub struct InsertExecutor {
     insert_plan: PlanNodeRef,
 
-    table_heap: TableHeapRef,
+    table_info: TableInfoRef,
     child_executor: Box<dyn Executor>,
     ctx: ExecutorContextRef,
     is_inserted: bool,
+    // the pages filled by the insert go through a ring
+    strategy: BufferAccessStrategy,
 }
 
 impl InsertExecutor {
@@ -22,14 +24,15 @@ impl InsertExecutor {
         
         let table_id = insert_plan.table_id;
         let catalog = ctx.get_catalog();
-        let table_heap = catalog.borrow()
-            .get_table_byid(table_id).unwrap().table_heap.clone();
+        let table_info = catalog.borrow()
+            .get_table_byid(table_id).unwrap().clone();
         Self { 
             insert_plan: plan, 
             child_executor: child_exec, 
             ctx,
-            table_heap,
+            table_info,
             is_inserted: false,
+            strategy: BufferAccessStrategy::new(AccessIntent::BulkWrite),
         }
     }
 }
@@ -47,14 +50,26 @@ impl Executor for InsertExecutor {
         let PlanNode::Insert(insert_plan) = self.insert_plan.as_ref() else { panic!("Error"); };
         let mut insert_rows = 0;
         
+        let catalog = self.ctx.get_catalog();
+        let txn = self.ctx.get_txn();
+        // IX on the table before any row of it, the rows are locked as they are inserted
+        self.ctx.get_lock_mgr().lock_table(&txn, LockMode::IntentionExclusive, self.table_info.table_oid)?;
         while let Some(tuple_pair) = self.child_executor.next()? {
             let (_, tuple) = tuple_pair;
             
+            let catalog = catalog.borrow();
+            ConstraintChecker::check_expressions(&self.table_info, &tuple, &insert_plan.checks)?;
+            ConstraintChecker::check_insert(&self.ctx, &catalog, &self.table_info, &tuple)?;
+
             let meta = TupleMeta::new(
-                INVALID_TXN_ID,
+                self.ctx.get_txn().get_txn_id(),
                 INVALID_TXN_ID, 
                 false);
-            self.table_heap.insert_tuple(&meta, &tuple);
+            let rid = self.table_info.table_heap.insert_tuple_with(&meta, &tuple, Some(&txn), &self.strategy,
+                |rid| self.ctx.lock_new_row(self.table_info.table_oid, rid))
+                .map_err(|err| format!("can not insert tuple into table {}: {}", self.table_info.table_name, err))?;
+            catalog.insert_index_entries(&self.table_info.table_name, &tuple, rid);
+            txn.append_write_record(WriteRecord::new(self.table_info.table_oid, rid, WriteType::Insert, txn.get_prev_lsn()));
             insert_rows += 1;
         }
 
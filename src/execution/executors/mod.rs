@@ -4,3 +4,5 @@ pub mod seqscan_executor;
 pub mod proj_executor;
 pub mod values_executor;
 pub mod filter_executor;
+pub mod delete_executor;
+pub mod update_executor;
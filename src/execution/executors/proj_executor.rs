@@ -33,7 +33,7 @@ impl Executor for ProjectExecutor {
             let PlanNode::Proj(proj_plan) = self.plan.as_ref() else { panic!("Err"); };
             values.reserve(self.get_output_schema().get_column_count());
             for col in &proj_plan.expressions {
-                values.push(col.evalute(&tuple, self.child_exec.get_output_schema()));
+                values.push(col.evalute(&tuple, self.child_exec.get_output_schema())?);
             }   
 
             let out_tuple = Tuple::build(&values, self.get_output_schema());
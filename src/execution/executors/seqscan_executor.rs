@@ -2,7 +2,7 @@
 
 use std::sync::Arc;
 
-use crate::{catalog::{catalog::TableInfoRef, schema::Schema}, common::rid::RID, execution::{executor_context::{ExecutorContext, ExecutorContextRef}, plans::plan::{PlanNode, PlanNodeRef, SeqScanPlan}}, storage::page_based::table::{table_heap::{TableHeap, TableHeapRef}, table_iter::TableIter, tuple::{Tuple, TupleMeta}}};
+use crate::{buffer::access_strategy::{AccessIntent, BufferAccessStrategy}, catalog::{catalog::TableInfoRef, schema::Schema}, common::rid::RID, execution::{executor_context::{ExecutorContext, ExecutorContextRef}, plans::plan::{PlanNode, PlanNodeRef, SeqScanPlan}}, storage::page_based::table::{table_heap::{TableHeap, TableHeapRef}, table_iter::TableIter, tuple::{Tuple, TupleMeta}}, transaction::{lock_manager::LockMode, transaction::IsolationLevel}, common::config::table_id_t};
 
 use super::executor::Executor;
 
@@ -10,8 +10,10 @@ use super::executor::Executor;
 
 pub struct SeqScanExecutor {
     plannode: PlanNodeRef,
+    table_oid: table_id_t,
     table_heap: TableHeapRef,
     table_iter: Option<TableIter>,
+    table_locked: bool,
 
     ctx: ExecutorContextRef,
 }
@@ -27,9 +29,28 @@ impl SeqScanExecutor {
         
         Self { 
             plannode: plan, 
+            table_oid: table_info.table_oid,
             table_heap: table_info.table_heap.clone(),
             ctx: ctx, 
             table_iter: None,
+            table_locked: false,
+        }
+    }
+
+    // (table lock, row lock) of the scan, the snapshot isolation and read uncommitted read without locks.
+    // serializable locks the whole table so no row is inserted into what it reads
+    fn lock_modes(&self) -> Option<(LockMode, Option<LockMode>)> {
+        let isolation_level = self.ctx.get_txn().get_isolation_level();
+        if self.ctx.is_delete() {
+            return match isolation_level {
+                IsolationLevel::Serializable => Some((LockMode::SharedIntentionExclusive, Some(LockMode::Exclusive))),
+                _ => Some((LockMode::IntentionExclusive, Some(LockMode::Exclusive))),
+            };
+        }
+        match isolation_level {
+            IsolationLevel::SnapshotIsolation | IsolationLevel::ReadUncommitted => None,
+            IsolationLevel::ReadCommitted | IsolationLevel::RepeatableRead => Some((LockMode::IntentionShared, Some(LockMode::Shared))),
+            IsolationLevel::Serializable => Some((LockMode::Shared, None)),
         }
     }
 }
@@ -37,14 +58,42 @@ impl SeqScanExecutor {
 impl Executor for SeqScanExecutor {
     fn init(&mut self) {
         let table_heap = self.table_heap.clone();
-        self.table_iter = Some(table_heap.make_iterator());
+        // the scan goes through a ring of frames, the working set of the others stays in the pool
+        self.table_iter = Some(table_heap.make_iterator_with(BufferAccessStrategy::new(AccessIntent::SequentialScan)));
     }
 
     fn next(&mut self) -> Result<Option<(RID, Tuple)>, String> {
-        let mut table_iter = self.table_iter.as_mut().unwrap();
+        let txn = self.ctx.get_txn();
+        let txn_mgr = self.ctx.get_txn_mgr();
+        let lock_mgr = self.ctx.get_lock_mgr();
+        let lock_modes = self.lock_modes();
+        if let (false, Some((table_mode, _))) = (self.table_locked, lock_modes) {
+            lock_mgr.lock_table(&txn, table_mode, self.table_oid)?;
+            self.table_locked = true;
+        }
 
+        let mut table_iter = self.table_iter.as_mut().unwrap();
         while let Some(tuple_pair) = table_iter.next() {
-            let tuple = tuple_pair.1;
+            let (meta, tuple) = tuple_pair?;
+            // skip the versions out of the snapshot of the transaction
+            if !txn_mgr.is_visible(&meta, &txn) {
+                continue;
+            }
+
+            if let Some((_, Some(row_mode))) = lock_modes {
+                let rid = tuple.get_rid();
+                lock_mgr.lock_row(&txn, row_mode, self.table_oid, rid)?;
+                // the version may be removed while waiting for the lock, e.g. by a rollback or a committed update
+                let visible = txn_mgr.is_visible(&self.table_heap.get_meta(&rid)?, &txn);
+                // read committed gives the shared lock back once the row is read, a later read may see another version.
+                // the row may be locked exclusively by the transaction already, it is kept
+                if txn.get_isolation_level() == IsolationLevel::ReadCommitted && txn.get_row_lock(&rid) == Some(LockMode::Shared) {
+                    lock_mgr.unlock_row(&txn, self.table_oid, rid)?;
+                }
+                if !visible {
+                    continue;
+                }
+            }
 
             return Ok(Some((tuple.get_rid(), tuple)));
         }
@@ -0,0 +1,78 @@
+#![allow(warnings)]
+
+use crate::{catalog::{catalog::TableInfoRef, schema::Schema}, common::rid::RID, execution::{constraint_checker::ConstraintChecker, executor_context::ExecutorContextRef, plans::plan::{PlanNode, PlanNodeRef}}, storage::page_based::table::tuple::Tuple, typedef::{value::Value, value_factory::ValueFactory}};
+
+use super::executor::Executor;
+
+
+pub struct UpdateExecutor {
+    update_plan: PlanNodeRef,
+
+    table_info: TableInfoRef,
+    child_executor: Box<dyn Executor>,
+    ctx: ExecutorContextRef,
+    is_updated: bool,
+}
+
+impl UpdateExecutor {
+    pub fn new(plan: PlanNodeRef, child_exec: Box<dyn Executor>, ctx: ExecutorContextRef) -> Self {
+        let PlanNode::Update(update_plan) = plan.as_ref() else { panic!("Error"); };
+
+        let catalog = ctx.get_catalog();
+        let table_info = catalog.borrow()
+            .get_table_byid(update_plan.table_id).unwrap().clone();
+        Self {
+            update_plan: plan,
+            table_info,
+            child_executor: child_exec,
+            ctx,
+            is_updated: false,
+        }
+    }
+}
+
+impl Executor for UpdateExecutor {
+    fn init(&mut self) {
+        self.child_executor.init();
+    }
+
+    fn next(&mut self) -> Result<Option<(RID, Tuple)>, String> {
+        if self.is_updated {
+            return Ok(None);
+        }
+
+        let PlanNode::Update(update_plan) = self.update_plan.as_ref() else { panic!("Error"); };
+        let catalog = self.ctx.get_catalog();
+        let txn_id = self.ctx.get_txn().get_txn_id();
+        let mut update_rows = 0;
+        while let Some((rid, tuple)) = self.child_executor.next()? {
+            // the row may be changed by a cascade already
+            if self.table_info.table_heap.get_meta(&rid)?.delete_txn_id == txn_id {
+                continue;
+            }
+
+            let values = update_plan.target_expressions.iter()
+                .map(|expr| expr.evalute(&tuple, self.child_executor.get_output_schema()))
+                .collect::<Result<Vec<Value>, String>>()?;
+            let new_tuple = Tuple::build(&values, &self.table_info.schema);
+            ConstraintChecker::check_expressions(&self.table_info, &new_tuple, &update_plan.checks)?;
+
+            ConstraintChecker::update_tuple(&self.ctx, &catalog.borrow(), &self.table_info, rid, &tuple, &values)?;
+            update_rows += 1;
+        }
+
+        let value = ValueFactory::get_integer_value(update_rows);
+        let rtn_tuple = Tuple::build(&vec![value], self.update_plan.get_output_schema());
+
+        self.is_updated = true;
+        Ok(Some((RID::new(), rtn_tuple)))
+    }
+
+    fn get_output_schema(&self) -> &Schema {
+        self.update_plan.get_output_schema()
+    }
+
+    fn get_context(&self) -> &ExecutorContextRef {
+        &self.ctx
+    }
+}
@@ -40,7 +40,7 @@ impl Executor for ValuesExecutor {
         let mut values = Vec::new();
         for expr in row {
             // api need a tuple and schema, we give it a dummy value
-            values.push(expr.evalute(&Tuple::new(), &Schema::new(&vec![])));
+            values.push(expr.evalute(&Tuple::new(), &Schema::new(&vec![]))?);
         }
 
         self.cursor += 1;
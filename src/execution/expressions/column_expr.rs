@@ -31,15 +31,15 @@ impl ExpressionFeat for ColumnValueExpr {
         self.rtn_type        
     }
 
-    fn evalute(&self, tuple: &Tuple, schema: &Schema) -> crate::typedef::value::Value {
-        tuple.get_value(schema, self.col_idx)        
+    fn evalute(&self, tuple: &Tuple, schema: &Schema) -> Result<Value, String> {
+        Ok(tuple.get_value(schema, self.col_idx))
     }
 
-    fn evalute_join(&self, tuple_left: &Tuple, schema_left: &Schema, tuple_right: &Tuple, schema_right: &Schema) -> Value {
+    fn evalute_join(&self, tuple_left: &Tuple, schema_left: &Schema, tuple_right: &Tuple, schema_right: &Schema) -> Result<Value, String> {
         if self.tuple_idx == 0 {
-            tuple_left.get_value(schema_left, self.col_idx)
+            Ok(tuple_left.get_value(schema_left, self.col_idx))
         } else {
-            tuple_right.get_value(schema_right, self.col_idx)
+            Ok(tuple_right.get_value(schema_right, self.col_idx))
         }
     }
 }
\ No newline at end of file
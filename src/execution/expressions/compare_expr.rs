@@ -63,17 +63,17 @@ impl CompareExpr {
 }
 
 impl ExpressionFeat for CompareExpr {
-    fn evalute(&self, tuple: &Tuple, schema: &Schema) -> Value {
-        let left_arg = self.get_child_at(0).evalute(tuple, schema);
-        let right_arg = self.get_child_at(1).evalute(tuple, schema);
+    fn evalute(&self, tuple: &Tuple, schema: &Schema) -> Result<Value, String> {
+        let left_arg = self.get_child_at(0).evalute(tuple, schema)?;
+        let right_arg = self.get_child_at(1).evalute(tuple, schema)?;
 
-        ValueFactory::get_boolean_value(Self::performe_compare(self.cmp_type.clone(), &left_arg, &right_arg))
+        Ok(ValueFactory::get_boolean_value(Self::performe_compare(self.cmp_type.clone(), &left_arg, &right_arg)))
     }
 
-    fn evalute_join(&self, tuple_left: &Tuple, schema_left: &Schema, tuple_right: &Tuple, schema_right: &Schema) -> Value {
-        let lhs = self.get_child_at(0).evalute(tuple_left, schema_left);
-        let rhs = self.get_child_at(1).evalute(tuple_right, schema_right);
-        match self.cmp_type  {
+    fn evalute_join(&self, tuple_left: &Tuple, schema_left: &Schema, tuple_right: &Tuple, schema_right: &Schema) -> Result<Value, String> {
+        let lhs = self.get_child_at(0).evalute(tuple_left, schema_left)?;
+        let rhs = self.get_child_at(1).evalute(tuple_right, schema_right)?;
+        Ok(match self.cmp_type  {
             CmpType::Lt => {
                 ValueFactory::get_boolean_value(lhs.compare_less_than(&rhs))
             },
@@ -92,7 +92,7 @@ impl ExpressionFeat for CompareExpr {
             CmpType::Equal => {
                 ValueFactory::get_boolean_value(lhs.compare_equal(&rhs))
             }
-        }        
+        })
     }
 
     fn get_return_type(&self) -> TypeId {
@@ -30,11 +30,11 @@ impl ExpressionFeat for ConstantExpr {
         self.rtn_type
     }
 
-    fn evalute(&self, tuple: &Tuple, schema: &Schema) -> Value {
-        self.val.clone()
+    fn evalute(&self, tuple: &Tuple, schema: &Schema) -> Result<Value, String> {
+        Ok(self.val.clone())
     }
 
-    fn evalute_join(&self, tuple_left: &Tuple, schema_left: &Schema, tuple_right: &Tuple, schema_right: &Schema) -> Value {
-        self.val.clone()
+    fn evalute_join(&self, tuple_left: &Tuple, schema_left: &Schema, tuple_right: &Tuple, schema_right: &Schema) -> Result<Value, String> {
+        Ok(self.val.clone())
     }
 }
\ No newline at end of file
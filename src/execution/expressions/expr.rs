@@ -4,7 +4,7 @@ use std::{fmt::Display, sync::Arc, thread::sleep};
 
 use crate::{typedef::{type_id::TypeId, value::Value}, storage::page_based::table::tuple::Tuple, catalog::schema::Schema};
 
-use super::{column_expr::ColumnValueExpr, compare_expr::CompareExpr, constant_expr::ConstantExpr};
+use super::{column_expr::ColumnValueExpr, compare_expr::CompareExpr, constant_expr::ConstantExpr, sequence_expr::SequenceExpr};
 
 
 pub type ExpressionRef = Arc<Expression>;
@@ -14,30 +14,35 @@ pub enum Expression {
     ColumnExpr(ColumnValueExpr),
     ConstantExpr(ConstantExpr),
     CmpExpr(CompareExpr),
+    SeqExpr(SequenceExpr),
 }
 
 impl Expression {
     pub fn get_return_type(&self) -> TypeId {
         match self {
             Self::ColumnExpr(col) => { col.get_return_type() },
-            Self::ConstantExpr(constant) => { constant.get_return_type() }
+            Self::ConstantExpr(constant) => { constant.get_return_type() },
+            Self::CmpExpr(cmp_expr) => { cmp_expr.get_return_type() },
+            Self::SeqExpr(seq_expr) => { seq_expr.get_return_type() },
             _ => {
                 panic!("Not support return type");
             }
         }
     }
 
-    pub fn evalute(&self, tuple: &Tuple, schema: &Schema) -> Value {
+    pub fn evalute(&self, tuple: &Tuple, schema: &Schema) -> Result<Value, String> {
         match self {
             Self::ColumnExpr(col) => { col.evalute(tuple, schema) },
-            Self::ConstantExpr(constant) => { constant.evalute(tuple, schema) }
+            Self::ConstantExpr(constant) => { constant.evalute(tuple, schema) },
+            Self::CmpExpr(cmp_expr) => { cmp_expr.evalute(tuple, schema) },
+            Self::SeqExpr(seq_expr) => { seq_expr.evalute(tuple, schema) },
             _ => {
                 panic!("Not support return type");
             }
         }
     }
 
-    fn evalute_join(&self, tuple_left: &Tuple, schema_left: &Schema, tuple_right: &Tuple, schema_right: &Schema) -> Value {
+    fn evalute_join(&self, tuple_left: &Tuple, schema_left: &Schema, tuple_right: &Tuple, schema_right: &Schema) -> Result<Value, String> {
         match self {
             Self::ColumnExpr(col) => { col.evalute_join(tuple_left, schema_left, tuple_right, schema_right) },
             Self::ConstantExpr(constant) => { constant.evalute_join(tuple_left, schema_left, tuple_right, schema_right) }
@@ -54,9 +59,10 @@ pub trait ExpressionFeat {
 
     fn get_return_type(&self) -> TypeId;
 
-    fn evalute(&self, tuple: &Tuple, schema: &Schema) -> Value;
+    // an expression fails only when something it calls does, e.g. an exhausted sequence
+    fn evalute(&self, tuple: &Tuple, schema: &Schema) -> Result<Value, String>;
 
-    fn evalute_join(&self, tuple_left: &Tuple, schema_left: &Schema, tuple_right: &Tuple, schema_right: &Schema) -> Value;    
+    fn evalute_join(&self, tuple_left: &Tuple, schema_left: &Schema, tuple_right: &Tuple, schema_right: &Schema) -> Result<Value, String>;    
 }
 
 
@@ -65,7 +71,8 @@ impl Display for Expression {
         match self {
             Self::ColumnExpr(column) => { f.write_str(&column.to_string()) },
             Self::ConstantExpr(constant) => { f.write_str(&constant.to_string()) },
-            Self::CmpExpr(cmp_expr) => { f.write_str(&cmp_expr.to_string()) }
+            Self::CmpExpr(cmp_expr) => { f.write_str(&cmp_expr.to_string()) },
+            Self::SeqExpr(seq_expr) => { f.write_str(&seq_expr.to_string()) }
         }
     }
 }
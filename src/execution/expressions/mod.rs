@@ -2,4 +2,5 @@ pub mod expr;
 pub mod column_expr;
 pub mod constant_expr;
 pub mod compare_expr;
-pub mod logic_expr;
\ No newline at end of file
+pub mod logic_expr;
+pub mod sequence_expr;
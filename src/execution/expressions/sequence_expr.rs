@@ -0,0 +1,61 @@
+#![allow(warnings)]
+
+use std::fmt::Display;
+
+use crate::{catalog::{schema::Schema, sequence::SequenceRef}, storage::page_based::table::tuple::Tuple, typedef::{type_id::TypeId, value::Value, value_factory::ValueFactory}};
+
+use super::expr::{ExpressionFeat, ExpressionRef};
+
+#[derive(Debug, Clone)]
+pub enum SequenceFunc {
+    NextVal,
+    CurrVal,
+}
+
+impl Display for SequenceFunc {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::NextVal => { f.write_str("nextval") },
+            Self::CurrVal => { f.write_str("currval") },
+        }
+    }
+}
+
+/// nextval/currval of a sequence, the sequence is resolved when planning
+#[derive(Debug, Clone)]
+pub struct SequenceExpr {
+    pub func: SequenceFunc,
+    pub seq: SequenceRef,
+
+    pub children: Vec<ExpressionRef>,
+    rtn_type: TypeId,
+}
+
+impl SequenceExpr {
+    pub fn new(func: SequenceFunc, seq: SequenceRef) -> Self {
+        Self { func, seq, children: Vec::new(), rtn_type: TypeId::INTEGER }
+    }
+}
+
+impl ExpressionFeat for SequenceExpr {
+    fn to_string(&self) -> String {
+        format!("{}('{}')", self.func, self.seq.name)
+    }
+
+    fn get_return_type(&self) -> TypeId {
+        self.rtn_type
+    }
+
+    // an exhausted sequence, or currval before nextval, fails the statement
+    fn evalute(&self, tuple: &Tuple, schema: &Schema) -> Result<Value, String> {
+        let val = match self.func {
+            SequenceFunc::NextVal => self.seq.next_value()?,
+            SequenceFunc::CurrVal => self.seq.current_value()?,
+        };
+        Ok(ValueFactory::get_integer_value(val))
+    }
+
+    fn evalute_join(&self, tuple_left: &Tuple, schema_left: &Schema, tuple_right: &Tuple, schema_right: &Schema) -> Result<Value, String> {
+        self.evalute(tuple_left, schema_left)
+    }
+}
@@ -3,4 +3,5 @@ pub mod expressions;
 pub mod executors;
 pub mod execute_engine;
 pub mod executor_context;
-pub mod executor_factory;
\ No newline at end of file
+pub mod executor_factory;
+pub mod constraint_checker;
\ No newline at end of file
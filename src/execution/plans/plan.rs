@@ -16,6 +16,8 @@ pub enum PlanNode {
     Insert(InsertPlan),
     Values(ValuesPlan),
     Filter(FilterPlan),
+    Delete(DeletePlan),
+    Update(UpdatePlan),
 }
 
 impl PlanNode {
@@ -26,6 +28,8 @@ impl PlanNode {
             PlanNode::Insert(insert) => { insert.output_schema() },
             PlanNode::Values(values) => { values.output_schema() },
             PlanNode::Filter(filter) => { filter.output_schema() },
+            PlanNode::Delete(delete) => { delete.output_schema() },
+            PlanNode::Update(update) => { update.output_schema() },
         }
     }
 }
@@ -40,6 +44,8 @@ impl Display for PlanNode {
             Self::Insert(insert) => { f.write_str(&insert.to_string(true)) },
             Self::Values(vals) => { f.write_str(&vals.to_string(true)) },
             Self::Filter(filter) => { f.write_str(&filter.to_string(true)) },
+            Self::Delete(delete) => { f.write_str(&delete.to_string(true)) },
+            Self::Update(update) => { f.write_str(&update.to_string(true)) },
         }
     }
 }
@@ -160,7 +166,7 @@ impl ProjectionPlan {
 
         for expr in exprs {
             let rtn_type = expr.get_return_type();
-            if TypeId::INTEGER == rtn_type {
+            if TypeId::VARCHAR != rtn_type {
                 columns.push(Column::new("<unnamed>", rtn_type));
             } else {
                 columns.push(Column::new_varchar("<unnamed>", rtn_type, VARCHAR_DEFAULT_LENGTH));
@@ -250,6 +256,8 @@ pub struct InsertPlan {
     pub children: Vec<PlanNodeRef>,
 
     pub table_id: table_id_t,
+    // (name, predicate) of the CHECK constraints
+    pub checks: Vec<(String, ExpressionRef)>,
 }
 
 impl InsertPlan {
@@ -276,6 +284,75 @@ impl PlanNodeFeat for InsertPlan {
 }
 
 
+//========================== Delete Plan ==================
+#[derive(Debug)]
+pub struct DeletePlan {
+    pub output_schema: Schema,
+    pub children: Vec<PlanNodeRef>,
+
+    pub table_id: table_id_t,
+}
+
+impl DeletePlan {
+    pub fn get_child_plan(&self) -> PlanNodeRef {
+        assert_eq!(1, self.children.len());
+        self.get_child_at(0)
+    }
+}
+
+impl PlanNodeFeat for DeletePlan {
+
+    fn plannode_tostring(&self) -> String {
+        format!("Delete {{{{ table_id={} }}}}", self.table_id)
+    }
+
+    fn get_children(&self) -> &Vec<PlanNodeRef> {
+        &self.children        
+    }
+
+    fn output_schema(&self) -> &Schema {
+        &self.output_schema        
+    }
+}
+
+
+//========================== Update Plan ==================
+#[derive(Debug)]
+pub struct UpdatePlan {
+    pub output_schema: Schema,
+    pub children: Vec<PlanNodeRef>,
+
+    pub table_id: table_id_t,
+    // one expression for each column of the table, evaluated on the old tuple
+    pub target_expressions: Vec<ExpressionRef>,
+    // (name, predicate) of the CHECK constraints
+    pub checks: Vec<(String, ExpressionRef)>,
+}
+
+impl UpdatePlan {
+    pub fn get_child_plan(&self) -> PlanNodeRef {
+        assert_eq!(1, self.children.len());
+        self.get_child_at(0)
+    }
+}
+
+impl PlanNodeFeat for UpdatePlan {
+
+    fn plannode_tostring(&self) -> String {
+        let exprs_str: Vec<String> = self.target_expressions.iter().map(|expr| expr.to_string()).collect();
+        format!("Update {{{{ table_id={}, target_exprs=[{}] }}}}", self.table_id, exprs_str.join(", "))
+    }
+
+    fn get_children(&self) -> &Vec<PlanNodeRef> {
+        &self.children        
+    }
+
+    fn output_schema(&self) -> &Schema {
+        &self.output_schema        
+    }
+}
+
+
 //============================= Filter Plan =========================//
 // related to where clause
 
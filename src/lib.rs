@@ -16,6 +16,9 @@ pub mod binder;
 
 pub mod transaction;
 
+// crash recovery from the write-ahead log
+pub mod recovery;
+
 // execution engine
 pub mod execution;
 
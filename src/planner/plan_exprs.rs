@@ -1,6 +1,6 @@
 #![allow(warnings)]
 
-use crate::{binder::{bound_expression::{BoundExpression, BoundExpressionFeat}, expression::{bound_binary_op::BoundBinaryOp, bound_column_ref::BoundColumn, bound_constant::BoundConstant}}, execution::{expressions::{column_expr::ColumnValueExpr, constant_expr::ConstantExpr, expr::{Expression, ExpressionRef}}, plans::plan::{PlanNode, PlanNodeRef}}};
+use crate::{binder::{bound_expression::{BoundExpression, BoundExpressionFeat}, expression::{bound_binary_op::BoundBinaryOp, bound_column_ref::BoundColumn, bound_constant::BoundConstant, bound_func_call::BoundFuncCall}}, execution::{expressions::{column_expr::ColumnValueExpr, constant_expr::ConstantExpr, expr::{Expression, ExpressionRef}, sequence_expr::{SequenceExpr, SequenceFunc}}, plans::plan::{PlanNode, PlanNodeRef}}};
 
 use super::planner::Planner;
 
@@ -21,7 +21,7 @@ impl Planner {
 
             },
             BoundExpression::FuncCall(func) => {
-
+                return Ok((Self::UNAMED_COLUMN.to_owned(), self.plan_func_call(func, children)?));
             },
             _ => {
                 panic!("Not support type");
@@ -37,6 +37,26 @@ impl Planner {
         return self.get_binary_op_expr(&binary_op.op, left_expr, right_expr);
     }
 
+    // the sequence functions are bound to the sequence here
+    pub fn plan_func_call(&self, func: &Box<BoundFuncCall>, children: &Vec<PlanNodeRef>) -> Result<ExpressionRef, String> {
+        let seq_func = match func.func_name.as_str() {
+            "nextval" => SequenceFunc::NextVal,
+            "currval" => SequenceFunc::CurrVal,
+            _ => {
+                return Err(format!("function {} does not exist", func.func_name));
+            }
+        };
+        let Some(BoundExpression::Constant(seq_name)) = func.args.first() else {
+            return Err(format!("function {} needs a sequence name", func.func_name));
+        };
+
+        let seq_name = String::from_utf8(seq_name.val.get_data()).unwrap_or_default();
+        let Some(seq) = self.catalog.borrow().get_sequence(&seq_name).cloned() else {
+            return Err(format!("relation \"{}\" does not exist", seq_name));
+        };
+        Ok(ExpressionRef::new(Expression::SeqExpr(SequenceExpr::new(seq_func, seq))))
+    }
+
     pub fn plan_constant(&self, constant: &Box<BoundConstant>, children: &Vec<PlanNodeRef>) -> Result<ExpressionRef, String> {
         let expr = Expression::ConstantExpr(ConstantExpr::new(constant.val.clone(), Vec::new()));
         Ok(ExpressionRef::new(expr))
@@ -14,9 +14,10 @@ impl Planner {
         // plan from 
         let mut plan: PlanNodeRef;
         if let BoundTable::Empty(_) = &select.table_ref.as_ref() {
+            // a select without from produces one empty row, e.g. select nextval('s')
             let values_plan_node = ValuesPlan::new(
                 Arc::new(Schema::new(&Vec::new())), 
-                Vec::new());
+                vec![Vec::new()]);
             plan = Arc::new(PlanNode::Values(values_plan_node));
         } else {
             plan = self.plan_table_ref(&select.table_ref)?;
@@ -1,9 +1,9 @@
 #![allow(warnings)]
 
-use crate::{binder::{bound_expression::{BoundExpression, BoundExpressionFeat}, bound_statement::BoundStatement, bound_table_ref::{BoundTable, BoundTableRef}, statement::{insert_stmt::InsertStmt, select_stmt::SelectStmt}, table_ref::{bound_base_table::BoundBaseTableRef, bound_values_list_table::BoundValuesList}}, catalog::{catalog::{CataLog, CataLogRef}, column::Column, schema::{Schema, SchemaRef}}, common::config::VARCHAR_DEFAULT_LENGTH, execution::{expressions::expr::{Expression, ExpressionRef}, plans::plan::{InsertPlan, PlanNode, PlanNodeRef, SeqScanPlan, ValuesPlan}}, typedef::{integer_type::IntegerType, type_id::TypeId}};
+use crate::{binder::{bound_expression::{BoundExpression, BoundExpressionFeat}, bound_statement::BoundStatement, bound_table_ref::{BoundTable, BoundTableRef}, statement::{delete_stmt::DeleteStmt, insert_stmt::InsertStmt, select_stmt::SelectStmt, update_stmt::UpdateStmt}, table_ref::{bound_base_table::BoundBaseTableRef, bound_values_list_table::BoundValuesList}}, catalog::{catalog::{CataLog, CataLogRef}, column::Column, schema::{Schema, SchemaRef}}, common::config::{table_id_t, VARCHAR_DEFAULT_LENGTH}, execution::{expressions::{column_expr::ColumnValueExpr, constant_expr::ConstantExpr, expr::{Expression, ExpressionRef}}, plans::plan::{DeletePlan, FilterPlan, InsertPlan, PlanNode, PlanNodeRef, ProjectionPlan, SeqScanPlan, UpdatePlan, ValuesPlan}}, typedef::{integer_type::IntegerType, type_id::TypeId, value_factory::ValueFactory}};
 
 pub struct Planner {
-    catalog: CataLogRef,
+    pub(super) catalog: CataLogRef,
     universal_id: u32,
 }
 
@@ -41,6 +41,12 @@ impl Planner {
             BoundStatement::Insert(insert) => {
                 self.plan_insert(insert)
             },
+            BoundStatement::Delete(delete) => {
+                self.plan_delete(delete)
+            },
+            BoundStatement::Update(update) => {
+                self.plan_update(update)
+            },
             _ => {
                 Err(format!("Not support statement type"))
             }
@@ -55,8 +61,35 @@ impl Planner {
         
         let table_schema = &bound_table.schema.get_columns();
         let child_schema = sel_plan.get_output_schema().get_columns();
-        if !table_schema.iter().zip(child_schema.iter()).all(|(a, b)| a.get_type() == b.get_type()) {
-            return Err(format!("child schema mismatched the table schema"));
+        if child_schema.len() > insert.columns.len() {
+            return Err(format!("INSERT has more expressions than target columns"));
+        } else if child_schema.len() < insert.columns.len() {
+            return Err(format!("INSERT has more target columns than expressions"));
+        }
+        for (child_col, col_idx) in child_schema.iter().zip(insert.columns.iter()) {
+            let col = &table_schema[*col_idx];
+            if !child_col.get_type().is_coercible_to(col.get_type()) {
+                return Err(format!("column {} is of type {} but expression is of type {}", 
+                    col.get_name(), col.get_type().to_string(), child_col.get_type().to_string()));
+            }
+        }
+
+        // rearrange the values in the order of table columns, the omitted columns take their defaults
+        let mut child = sel_plan;
+        if !insert.columns.iter().cloned().eq(0..table_schema.len()) {
+            let mut exprs = Vec::new();
+            for col_idx in 0..table_schema.len() {
+                let col_type = table_schema[col_idx].get_type();
+                let expr = match insert.columns.iter().position(|idx| *idx == col_idx) {
+                    Some(pos) => ExpressionRef::new(Expression::ColumnExpr(ColumnValueExpr::new(0, pos, col_type))),
+                    None => self.plan_column_default(bound_table.table_id, col_idx, col_type)?,
+                };
+                exprs.push(expr);
+            }
+
+            child = PlanNodeRef::new(PlanNode::Proj(
+                ProjectionPlan::new(SchemaRef::new(Schema::copy(&bound_table.schema)), exprs, vec![child])
+            ));
         }
         
         let insert_plan_schema = Schema::new(&vec![Column::new("__rows", TypeId::INTEGER)]);
@@ -64,14 +97,117 @@ impl Planner {
             PlanNode::Insert(
                 InsertPlan { 
                     output_schema: insert_plan_schema,
-                    children: vec![sel_plan],
+                    children: vec![child],
                     table_id: bound_table.table_id,
+                    checks: self.plan_checks(bound_table)?,
                 }
             )
         );
         Ok(insert_plan)
     }
 
+    // the DEFAULT of a column, or null if it has none
+    fn plan_column_default(&self, table_id: table_id_t, col_idx: usize, col_type: TypeId) -> Result<ExpressionRef, String> {
+        let default = self.catalog.borrow().get_column_default(table_id, col_idx).cloned();
+        let Some(default) = default else {
+            let null_val = ValueFactory::get_null_value(col_type);
+            return Ok(ExpressionRef::new(Expression::ConstantExpr(ConstantExpr::new(null_val, Vec::new()))));
+        };
+
+        let (_, expr) = self.plan_expression(&default, &Vec::new())?;
+        let expr_type = expr.get_return_type();
+        if expr_type != col_type && expr_type != TypeId::INVALID {
+            return Err(format!("default expression of type {} does not fit the column type {}", 
+                expr_type.to_string(), col_type.to_string()));
+        }
+        Ok(expr)
+    }
+
+    // plan the CHECK constraints of the table, they are evaluated on a row laid out by the table schema
+    pub fn plan_checks(&self, table_ref: &BoundBaseTableRef) -> Result<Vec<(String, ExpressionRef)>, String> {
+        let checks = self.catalog.borrow().get_check_constraints(table_ref.table_id);
+        if checks.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let scan = self.plan_base_table_ref(table_ref)?;
+        let mut res = Vec::new();
+        for check in checks {
+            let (_, expr) = self.plan_expression(&check.expr, &vec![scan.clone()])?;
+            res.push((check.name, expr));
+        }
+        Ok(res)
+    }
+
+    // scan the table and filter by the where clause, the rows left are passed to delete/update
+    fn plan_modify_child(&mut self, table_ref: &BoundBaseTableRef, where_by: &Option<Box<BoundExpression>>) -> Result<PlanNodeRef, String> {
+        let mut plan = self.plan_base_table_ref(table_ref)?;
+        if let Some(where_cond) = where_by {
+            let (_, expr) = self.plan_expression(where_cond.as_ref(), &vec![plan.clone()])?;
+            let out_schema = plan.get_output_schema();
+            plan = PlanNodeRef::new(PlanNode::Filter(
+                FilterPlan::new(SchemaRef::new(Schema::copy(out_schema)), vec![plan], expr)
+            ));
+        }
+        Ok(plan)
+    }
+
+    pub fn plan_delete(&mut self, delete: &DeleteStmt) -> Result<PlanNodeRef, String> {
+        let child = self.plan_modify_child(&delete.table_ref, &delete.where_by)?;
+
+        let delete_plan_schema = Schema::new(&vec![Column::new("__rows", TypeId::INTEGER)]);
+        Ok(PlanNodeRef::new(
+            PlanNode::Delete(
+                DeletePlan {
+                    output_schema: delete_plan_schema,
+                    children: vec![child],
+                    table_id: delete.table_ref.table_id,
+                }
+            )
+        ))
+    }
+
+    pub fn plan_update(&mut self, update: &UpdateStmt) -> Result<PlanNodeRef, String> {
+        let child = self.plan_modify_child(&update.table_ref, &update.where_by)?;
+        let children = vec![child];
+
+        // the columns not assigned keep the old value
+        let table_schema = &update.table_ref.schema;
+        let mut target_expressions = Vec::new();
+        for col_idx in 0..table_schema.get_column_count() {
+            let expr = match update.target_exprs.iter().find(|(idx, _)| *idx == col_idx) {
+                Some((_, bound_expr)) => {
+                    let (_, expr) = self.plan_expression(bound_expr, &children)?;
+                    let col_type = table_schema.get_column(col_idx)?.get_type();
+                    let expr_type = expr.get_return_type();
+                    if expr_type != col_type && expr_type != TypeId::INVALID {
+                        return Err(format!("column {} is of type {} but expression is of type {}", 
+                            table_schema.get_column(col_idx)?.get_name(), col_type.to_string(), expr_type.to_string()));
+                    }
+                    expr
+                },
+                None => {
+                    let column = table_schema.get_column(col_idx)?;
+                    ExpressionRef::new(Expression::ColumnExpr(ColumnValueExpr::new(0, col_idx, column.get_type())))
+                }
+            };
+            target_expressions.push(expr);
+        }
+
+        let update_plan_schema = Schema::new(&vec![Column::new("__rows", TypeId::INTEGER)]);
+        Ok(PlanNodeRef::new(
+            PlanNode::Update(
+                UpdatePlan {
+                    output_schema: update_plan_schema,
+                    children,
+                    table_id: update.table_ref.table_id,
+                    target_expressions,
+                    checks: self.plan_checks(&update.table_ref)?,
+                }
+            )
+        ))
+    }
+
 
     pub fn plan_table_ref(&self, table_ref: &BoundTableRef) -> Result<PlanNodeRef, String> {
         match table_ref.as_ref() {
@@ -101,9 +237,13 @@ impl Planner {
         let mut col_idx = 0;
         let mock_name = &values_list.mock_name;
         let mut cols = Vec::new();
-        for bound_expr in &bound_rows[0] {
-            let BoundExpression::Constant(constant) = bound_expr.as_ref() else { panic!("Error: bound_values_list err"); };
-            let constant_type = constant.val.get_type();
+        for _ in &rows[0] {
+            // the type of a null is unknown, take the first row that has a typed value in this column,
+            // a column of all nulls is treated as integer
+            let constant_type = rows.iter()
+                .map(|row| row[col_idx].get_return_type())
+                .find(|type_id| *type_id != TypeId::INVALID)
+                .unwrap_or(TypeId::INTEGER);
             let col_name = format!("{}.{}", mock_name.clone(), col_idx);
             if constant_type != TypeId::VARCHAR {
                 cols.push(Column::new(&col_name, constant_type))
@@ -0,0 +1,124 @@
+#![allow(warnings)]
+
+use std::{sync::{mpsc::{self, RecvTimeoutError}, Arc, Mutex}, thread::{self, JoinHandle}, time::Duration};
+
+use crate::{buffer::buffer_pool_manager::BufferPoolManager, common::config::{lsn_t, INVALID_LSN, INVALID_TXN_ID}, storage::page_based::disk::{log_manager::LogManager, log_record::{LogRecord, LogRecordBody}}, transaction::transaction_manager::TransactionManagerRef};
+
+
+/// CheckpointManager, takes the fuzzy checkpoints which bound the work of the recovery.
+/// A checkpoint writes a BEGIN_CHECKPOINT record, then an END_CHECKPOINT record with the active transaction table
+/// and the dirty page table taken in between, nothing is stopped or written out for it.
+/// The analysis of the recovery starts from the last complete checkpoint, and the log before
+/// the oldest record the redo or the undo may need is truncated.
+#[derive(Debug)]
+pub struct CheckpointManager {
+    txn_mgr: TransactionManagerRef,
+    bpm: Arc<BufferPoolManager>,
+    log_mgr: Arc<LogManager>,
+}
+
+impl CheckpointManager {
+    pub fn new(txn_mgr: TransactionManagerRef, bpm: Arc<BufferPoolManager>, log_mgr: Arc<LogManager>) -> Self {
+        Self { txn_mgr, bpm, log_mgr }
+    }
+
+    // take a checkpoint, returns the lsn of its begin record
+    pub fn checkpoint(&self) -> Result<lsn_t, String> {
+        if !self.log_mgr.is_enabled() {
+            return Ok(INVALID_LSN);
+        }
+        let begin_lsn = self.append_log(LogRecordBody::BeginCheckpoint);
+        let active_txns = self.txn_mgr.get_active_txns();
+        let dirty_pages = self.bpm.get_dirty_pages();
+        let next_txn_id = self.txn_mgr.get_next_txn_id();
+
+        // the redo starts from the oldest recLSN, the undo goes back to the BEGIN of the oldest running transaction
+        let truncate_lsn = dirty_pages.iter().map(|(_, rec_lsn)| *rec_lsn)
+            .chain(self.txn_mgr.get_oldest_active_lsn())
+            .fold(begin_lsn, lsn_t::min);
+
+        let end_lsn = self.append_log(LogRecordBody::EndCheckpoint { active_txns, dirty_pages, next_txn_id });
+        self.log_mgr.flush(end_lsn)?;
+        self.log_mgr.truncate(truncate_lsn)?;
+        Ok(begin_lsn)
+    }
+
+    fn append_log(&self, body: LogRecordBody) -> lsn_t {
+        self.log_mgr.append_log_record(&mut LogRecord::new(INVALID_TXN_ID, INVALID_LSN, body))
+    }
+}
+
+
+/// A background thread which takes a checkpoint at an interval, it stops when dropped.
+/// The error of a failed checkpoint is kept until one succeeds, see `get_error`
+#[derive(Debug)]
+pub struct Checkpointer {
+    handler: Option<JoinHandle<()>>,
+    // dropped to wake up and stop the thread
+    stop: Option<mpsc::Sender<()>>,
+    error: Arc<Mutex<Option<String>>>,
+}
+
+impl Checkpointer {
+    pub fn new(checkpoint_mgr: Arc<CheckpointManager>, interval: Duration) -> Self {
+        let (stop, receiver) = mpsc::channel::<()>();
+        let error = Arc::new(Mutex::new(None));
+        let handler = {
+            let error = error.clone();
+            thread::spawn(move || {
+                while let Err(RecvTimeoutError::Timeout) = receiver.recv_timeout(interval) {
+                    *error.lock().unwrap() = checkpoint_mgr.checkpoint().err();
+                }
+            })
+        };
+
+        Self { handler: Some(handler), stop: Some(stop), error }
+    }
+
+    // the error of the last checkpoint, None when it is taken
+    pub fn get_error(&self) -> Option<String> {
+        self.error.lock().unwrap().clone()
+    }
+}
+
+impl Drop for Checkpointer {
+    fn drop(&mut self) {
+        self.stop.take();
+        if let Some(handler) = self.handler.take() {
+            let _ = handler.join();
+        }
+    }
+}
+
+
+
+#[cfg(test)]
+mod tests {
+    use std::{fs, sync::Arc, thread, time::Duration};
+
+    use crate::{buffer::buffer_pool_manager::BufferPoolManager, storage::page_based::disk::{log_manager::LogManager, memory_page_store::MemoryPageStore}, transaction::{lock_manager::LockManager, transaction_manager::TransactionManager}};
+
+    use super::{CheckpointManager, Checkpointer};
+
+    #[test]
+    fn checkpointer_error_test() -> Result<(), String> {
+        let dir = "test_checkpointer_error";
+        let _ = fs::remove_dir_all(dir);
+        fs::create_dir(dir).unwrap();
+        let log_mgr = Arc::new(LogManager::open(&format!("{}/test.log", dir))?);
+        let bpm = Arc::new(BufferPoolManager::new(4, Arc::new(MemoryPageStore::new()), 2, log_mgr.clone()));
+        let txn_mgr = Arc::new(TransactionManager::new(Arc::new(LockManager::new()), log_mgr.clone()));
+        let checkpoint_mgr = Arc::new(CheckpointManager::new(txn_mgr, bpm, log_mgr));
+
+        let checkpointer = Checkpointer::new(checkpoint_mgr, Duration::from_millis(10));
+        thread::sleep(Duration::from_millis(100));
+        assert_eq!(None, checkpointer.get_error());
+
+        // the log can not be truncated without its directory, the checkpoints fail
+        fs::remove_dir_all(dir).unwrap();
+        thread::sleep(Duration::from_millis(100));
+        let err = checkpointer.get_error().unwrap();
+        assert_eq!(true, err.contains("can not create log file"), "{}", err);
+        Ok(())
+    }
+}
@@ -0,0 +1,533 @@
+#![allow(warnings)]
+
+use std::{collections::{BinaryHeap, HashMap}, sync::Arc};
+
+use crate::{common::{config::{lsn_t, page_id_t, txn_id_t, INVALID_LSN, INVALID_PAGE_ID, INVALID_TXN_ID}, page_addr::PageAddr, rid::RID}, storage::page_based::{disk::{disk_manager::PageStore, log_manager::LogManager, log_record::{LogRecord, LogRecordBody}}, page::{free_space_map_page::{FreeSpaceMapPage, FSM_FREE_PAGE}, page::Page, table_page::TablePage}, table::tuple::TupleMeta}};
+
+
+/// LogRecovery, brings the database file back to the state of the log after a crash, the way of ARIES:
+/// 1. analysis, finds the transactions not finished (the losers) and the pages which may be stale on disk
+/// 2. redo, repeats the history, a record is replayed when its lsn is beyond the pageLSN of its page
+/// 3. undo, rolls back the losers the newest change first, each undo is logged as a compensation record (CLR),
+///    so a crash during the recovery never undoes a change twice
+///
+/// The analysis starts from the last checkpoint (see `CheckpointManager`) instead of the beginning of the log.
+/// It runs before the buffer pool serves any page, the pages are read from and written back to the file directly.
+#[derive(Debug)]
+pub struct LogRecovery {
+    disk_mgr: Arc<dyn PageStore>,
+    log_mgr: Arc<LogManager>,
+    // the pages touched by the recovery, written back at the end
+    pages: HashMap<page_id_t, Page>,
+    // the active transaction table, the transactions without a COMMIT/ABORT and their last lsn
+    active_txns: HashMap<txn_id_t, lsn_t>,
+    // the dirty page table, the pages changed by the log and the first lsn which may not be on them (recLSN)
+    dirty_pages: HashMap<page_id_t, lsn_t>,
+    // beyond the ids of the transactions in the log
+    next_txn_id: txn_id_t,
+}
+
+impl LogRecovery {
+    pub fn new(disk_mgr: Arc<dyn PageStore>, log_mgr: Arc<LogManager>) -> Self {
+        Self {
+            disk_mgr,
+            log_mgr,
+            pages: HashMap::new(),
+            active_txns: HashMap::new(),
+            dirty_pages: HashMap::new(),
+            next_txn_id: 0,
+        }
+    }
+
+    // run the three phases, returns the ids of the loser transactions rolled back
+    pub fn recover(&mut self) -> Result<Vec<txn_id_t>, String> {
+        let records = self.log_mgr.read_log()?;
+        if records.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        self.analysis(&records);
+        self.redo(&records)?;
+        let mut losers: Vec<txn_id_t> = self.active_txns.keys().cloned().collect();
+        losers.sort();
+        self.undo(&records)?;
+
+        // the log goes first, then the pages, see the WAL rule
+        self.log_mgr.flush_all()?;
+        self.flush_pages()?;
+        Ok(losers)
+    }
+
+    // the id the transactions after the restart start from, the ones before it are over once the recovery is done
+    pub fn get_next_txn_id(&self) -> txn_id_t {
+        self.next_txn_id
+    }
+
+    // the tables start from the ones of the last complete checkpoint, and the log is scanned from its begin record
+    fn analysis(&mut self, records: &Vec<LogRecord>) {
+        let mut start = 0;
+        if let Some(end) = records.iter().rposition(|record| matches!(record.body, LogRecordBody::EndCheckpoint { .. })) {
+            let LogRecordBody::EndCheckpoint { active_txns, dirty_pages, next_txn_id } = &records[end].body else { unreachable!() };
+            self.next_txn_id = *next_txn_id;
+            self.active_txns.extend(active_txns.iter().cloned());
+            self.dirty_pages.extend(dirty_pages.iter().cloned());
+            start = records[..end].iter()
+                .rposition(|record| record.body == LogRecordBody::BeginCheckpoint)
+                .unwrap_or(end);
+        }
+
+        for record in &records[start..] {
+            // the records out of any transaction, e.g. the first page of a table heap
+            if record.txn_id != INVALID_TXN_ID {
+                self.next_txn_id = self.next_txn_id.max(record.txn_id + 1);
+                match record.body {
+                    LogRecordBody::Commit | LogRecordBody::Abort => {
+                        self.active_txns.remove(&record.txn_id);
+                    },
+                    _ => {
+                        self.active_txns.insert(record.txn_id, record.lsn);
+                    }
+                }
+            }
+            for page_id in Self::pages_of(record, self.disk_mgr.get_page_size()) {
+                self.dirty_pages.entry(page_id).or_insert(record.lsn);
+            }
+        }
+    }
+
+    fn redo(&mut self, records: &Vec<LogRecord>) -> Result<(), String> {
+        let Some(start_lsn) = self.dirty_pages.values().min().cloned() else {
+            return Ok(());
+        };
+        for record in records.iter().filter(|record| record.lsn >= start_lsn) {
+            for page_id in Self::pages_of(record, self.disk_mgr.get_page_size()) {
+                // the change is on the page already
+                if self.dirty_pages.get(&page_id).map_or(true, |rec_lsn| record.lsn < *rec_lsn) {
+                    continue;
+                }
+                let page = self.fetch_page(page_id)?;
+                if page.get_lsn() >= record.lsn {
+                    continue;
+                }
+                Self::redo_on(page, record)?;
+            }
+        }
+        Ok(())
+    }
+
+    // replay the record on one of its pages, the page takes the lsn of the record
+    fn redo_on(page: &mut Page, record: &LogRecord) -> Result<(), String> {
+        let (page_id, page_size) = (page.get_pid(), page.get_data().len());
+        if let LogRecordBody::AllocatePage { page_id: allocated } | LogRecordBody::FreePage { page_id: allocated } = &record.body {
+            // the free bytes of a page allocated again are not known, the table heap finds them out
+            let map_page = page.cast_as_mut::<FreeSpaceMapPage>();
+            match &record.body {
+                LogRecordBody::AllocatePage { .. } => map_page.set_entry(PageAddr::of(*allocated).page_no, FreeSpaceMapPage::encode(0), page_size),
+                _ => map_page.set_entry(PageAddr::of(*allocated).page_no, FSM_FREE_PAGE, page_size),
+            }
+            map_page.set_lsn(record.lsn);
+            return Ok(());
+        }
+        let table_page = page.cast_as_mut::<TablePage>();
+        match &record.body {
+            LogRecordBody::Insert { rid, meta, tuple } => {
+                // the slot is the one taken before the crash, it is empty or the next new one again
+                table_page.insert_tuple_at(rid.sid, meta, tuple)
+                    .ok_or(format!("can not redo the insert of lsn {}, slot {} of page {} is taken or the page is full",
+                        record.lsn, rid.sid, rid.pid))?;
+            },
+            LogRecordBody::UpdateTuple { rid, tuple } => {
+                table_page.update_tuple(rid, tuple)?;
+            },
+            LogRecordBody::MoveTuple { rid, from, to, tuple } => {
+                if page_id == from.pid && from != rid {
+                    table_page.vacuum(&[from.sid])?;
+                }
+                if page_id == rid.pid {
+                    table_page.set_forward(rid, to)?;
+                }
+                if page_id == to.pid {
+                    table_page.insert_moved_at(to.sid, rid, tuple)
+                        .ok_or(format!("can not redo the move of lsn {}, slot {} of page {} is taken or the page is full",
+                            record.lsn, to.sid, to.pid))?;
+                }
+            },
+            LogRecordBody::MarkDelete { rid, new_meta, .. } | LogRecordBody::Update { rid, new_meta, .. }
+                | LogRecordBody::Clr { rid, new_meta, .. } => {
+                table_page.update_tuple_meta(new_meta, rid)?;
+            },
+            LogRecordBody::Vacuum { slots, .. } => {
+                table_page.vacuum(slots)?;
+            },
+            LogRecordBody::NewPage { prev_page_id, page_id: new_page_id } => {
+                if page_id == *new_page_id {
+                    table_page.init(page_size);
+                } else {
+                    table_page.set_next_page_id(*new_page_id);
+                }
+            },
+            _ => {},
+        }
+        table_page.set_lsn(record.lsn);
+        Ok(())
+    }
+
+    fn undo(&mut self, records: &Vec<LogRecord>) -> Result<(), String> {
+        let lsn_index: HashMap<lsn_t, usize> = records.iter().enumerate()
+            .map(|(idx, record)| (record.lsn, idx))
+            .collect();
+
+        // the next record to undo of every loser, the largest lsn goes first
+        let mut to_undo: BinaryHeap<(lsn_t, txn_id_t)> = self.active_txns.iter()
+            .map(|(txn_id, lsn)| (*lsn, *txn_id))
+            .collect();
+        while let Some((lsn, txn_id)) = to_undo.pop() {
+            let record = lsn_index.get(&lsn).map(|idx| &records[*idx])
+                .ok_or(format!("log record {} of transaction {} not found", lsn, txn_id))?;
+            let next_lsn = match &record.body {
+                LogRecordBody::Insert { rid, meta, .. } => {
+                    // the same as the rollback of the transaction manager, the version is deleted by the inserter
+                    let mut new_meta = meta.clone();
+                    new_meta.is_deleted = true;
+                    new_meta.delete_txn_id = txn_id;
+                    self.compensate(txn_id, rid, &new_meta, record.prev_lsn)?;
+                    record.prev_lsn
+                },
+                LogRecordBody::MarkDelete { rid, old_meta, .. } | LogRecordBody::Update { rid, old_meta, .. } => {
+                    self.compensate(txn_id, rid, old_meta, record.prev_lsn)?;
+                    record.prev_lsn
+                },
+                // the changes before it are undone already
+                LogRecordBody::Clr { undo_next_lsn, .. } => *undo_next_lsn,
+                // a linked page stays in the table heap, the tuple updated is deleted by the undo of its insert
+                LogRecordBody::NewPage { .. } | LogRecordBody::UpdateTuple { .. } | LogRecordBody::MoveTuple { .. } => record.prev_lsn,
+                _ => INVALID_LSN,
+            };
+
+            if next_lsn == INVALID_LSN {
+                self.append_log(txn_id, LogRecordBody::Abort);
+                self.active_txns.remove(&txn_id);
+            } else {
+                to_undo.push((next_lsn, txn_id));
+            }
+        }
+        Ok(())
+    }
+
+    // put the meta back and log it as a compensation record
+    fn compensate(&mut self, txn_id: txn_id_t, rid: &RID, meta: &TupleMeta, undo_next_lsn: lsn_t) -> Result<(), String> {
+        let old_meta = self.fetch_page(rid.pid)?.cast_as::<TablePage>().get_tuple_meta(rid)?;
+        let lsn = self.append_log(txn_id, LogRecordBody::Clr { rid: *rid, old_meta, new_meta: meta.clone(), undo_next_lsn });
+        let table_page = self.fetch_page(rid.pid)?.cast_as_mut::<TablePage>();
+        table_page.update_tuple_meta(meta, rid)?;
+        table_page.set_lsn(lsn);
+        Ok(())
+    }
+
+    fn append_log(&mut self, txn_id: txn_id_t, body: LogRecordBody) -> lsn_t {
+        let prev_lsn = self.active_txns.get(&txn_id).cloned().unwrap_or(INVALID_LSN);
+        let mut record = LogRecord::new(txn_id, prev_lsn, body);
+        let lsn = self.log_mgr.append_log_record(&mut record);
+        self.active_txns.insert(txn_id, lsn);
+        lsn
+    }
+
+    // the pages changed by the record
+    fn pages_of(record: &LogRecord, page_size: usize) -> Vec<page_id_t> {
+        match &record.body {
+            LogRecordBody::Insert { rid, .. } | LogRecordBody::MarkDelete { rid, .. }
+                | LogRecordBody::Update { rid, .. } | LogRecordBody::Clr { rid, .. } => vec![rid.pid],
+            LogRecordBody::Vacuum { page_id, .. } => vec![*page_id],
+            LogRecordBody::UpdateTuple { rid, .. } => vec![rid.pid],
+            LogRecordBody::MoveTuple { rid, from, to, .. } => {
+                match from == rid {
+                    true => vec![to.pid, rid.pid],
+                    false => vec![to.pid, rid.pid, from.pid],
+                }
+            },
+            LogRecordBody::NewPage { prev_page_id, page_id } => {
+                match *prev_page_id == INVALID_PAGE_ID {
+                    true => vec![*page_id],
+                    false => vec![*prev_page_id, *page_id],
+                }
+            },
+            // the map page is in the file of the page
+            LogRecordBody::AllocatePage { page_id } | LogRecordBody::FreePage { page_id } => {
+                let addr = PageAddr::of(*page_id);
+                vec![PageAddr::new(addr.file_id, FreeSpaceMapPage::map_page_of(addr.page_no, page_size)).to_page_id()]
+            },
+            _ => vec![],
+        }
+    }
+
+    // read the page from the file once, a page never written out is all zeros
+    fn fetch_page(&mut self, page_id: page_id_t) -> Result<&mut Page, String> {
+        if !self.pages.contains_key(&page_id) {
+            let page_size = self.disk_mgr.get_page_size();
+            let mut page = Page::new_with_size(page_size);
+            page.page_id = page_id;
+            if self.disk_mgr.contains_page(page_id) {
+                self.disk_mgr.read_page(page_id, page.get_mut_data())?;
+            }
+            self.pages.insert(page_id, page);
+        }
+        Ok(self.pages.get_mut(&page_id).unwrap())
+    }
+
+    fn flush_pages(&mut self) -> Result<(), String> {
+        let mut page_ids: Vec<page_id_t> = self.pages.keys().cloned().collect();
+        page_ids.sort();
+        for page_id in page_ids {
+            self.disk_mgr.write_page(page_id, self.pages[&page_id].get_data())?;
+        }
+        self.pages.clear();
+        Ok(())
+    }
+}
+
+
+
+#[cfg(test)]
+mod tests {
+    use std::{fs, sync::Arc};
+
+    use crate::{buffer::buffer_pool_manager::BufferPoolManager, catalog::{catalog::CataLog, column::Column, schema::Schema}, common::{config::{page_id_t, INVALID_PAGE_ID, INVALID_TXN_ID, PAGE_SIZE}, rid::RID}, storage::page_based::{disk::{disk_manager::{DiskManager, PageStore}, log_manager::LogManager, log_record::LogRecordBody}, page::{free_space_map_page::{FreeSpaceMapPage, FSM_FIRST_PAGE_ID, FSM_FREE_PAGE}, page::Page, table_page::TablePage}, table::{table_heap::TableHeap, tuple::{Tuple, TupleMeta}}}, transaction::{lock_manager::LockManager, transaction::{IsolationLevel, TransactionRef, WriteRecord, WriteType}, transaction_manager::TransactionManager}, typedef::{type_id::TypeId, value::Value}};
+
+    use super::LogRecovery;
+    use crate::recovery::checkpoint_manager::CheckpointManager;
+
+    fn insert(catalog: &CataLog, txn: &TransactionRef, val: i32) -> RID {
+        let table_info = catalog.get_table("t").unwrap();
+        let tuple = Tuple::build(&vec![Value::new_integer(TypeId::INTEGER, val)], &table_info.schema);
+        let meta = TupleMeta::new(txn.get_txn_id(), INVALID_TXN_ID, false);
+        let rid = table_info.table_heap.insert_tuple(&meta, &tuple, Some(txn)).unwrap();
+        txn.append_write_record(WriteRecord::new(table_info.table_oid, rid, WriteType::Insert, txn.get_prev_lsn()));
+        rid
+    }
+
+    fn delete(catalog: &CataLog, txn: &TransactionRef, rid: RID) -> Result<(), String> {
+        let table_info = catalog.get_table("t").unwrap();
+        let mut meta = table_info.table_heap.get_meta(&rid)?;
+        meta.is_deleted = true;
+        meta.delete_txn_id = txn.get_txn_id();
+        table_info.table_heap.update_meta(&meta, &rid, Some(txn))?;
+        txn.append_write_record(WriteRecord::new(table_info.table_oid, rid, WriteType::Delete, txn.get_prev_lsn()));
+        Ok(())
+    }
+
+    // the tuples of the table heap as they are in the file
+    fn read_table(disk_mgr: &DiskManager, first_page_id: page_id_t, schema: &Schema) -> Result<Vec<(TupleMeta, String)>, String> {
+        let mut rows = Vec::new();
+        let mut page_id = first_page_id;
+        while page_id != INVALID_PAGE_ID {
+            let mut page = Page::new();
+            disk_mgr.read_page(page_id, page.get_mut_data())?;
+            let table_page = page.cast_as::<TablePage>();
+            for sid in 0..table_page.get_num_tuples() {
+                let (meta, tuple) = table_page.get_tuple(&RID { pid: page_id, sid })?;
+                rows.push((meta, tuple.get_value(schema, 0).to_string()));
+            }
+            page_id = table_page.get_next_page_id();
+        }
+        Ok(rows)
+    }
+
+    #[test]
+    fn recovery_test() -> Result<(), String> {
+        let _ = fs::remove_file("test_recovery.db");
+        let _ = fs::remove_file("test_recovery.log");
+        let schema = Schema::new(&vec![Column::new("a", TypeId::INTEGER)]);
+
+        let (first_page_id, loser_id) = {
+            let disk_mgr = Arc::new(DiskManager::new("test_recovery.db")?);
+            let log_mgr = Arc::new(LogManager::open("test_recovery.log")?);
+            // a small pool, some pages are evicted in the middle of the workload
+            let bpm = Arc::new(BufferPoolManager::new(3, disk_mgr, 2, log_mgr.clone()));
+            let mut catalog = CataLog::new(bpm, Arc::new(LockManager::new()), log_mgr.clone());
+            let txn_mgr = TransactionManager::new(Arc::new(LockManager::new()), log_mgr.clone());
+            let first_page_id = catalog.create_table(None, "t", schema.clone()).unwrap().table_heap.get_first_page_id();
+
+            let t1 = txn_mgr.begin(IsolationLevel::SnapshotIsolation);
+            let mut last_rid = RID::new();
+            for val in 0..1000 {
+                last_rid = insert(&catalog, &t1, val);
+            }
+            txn_mgr.commit(&t1, &catalog)?;
+
+            // the loser, it is running at the crash
+            let t2 = txn_mgr.begin(IsolationLevel::SnapshotIsolation);
+            for val in 1000..1010 {
+                insert(&catalog, &t2, val);
+            }
+            delete(&catalog, &t2, last_rid)?;
+
+            // rolled back before the crash
+            let t3 = txn_mgr.begin(IsolationLevel::SnapshotIsolation);
+            for val in 2000..2005 {
+                insert(&catalog, &t3, val);
+            }
+            txn_mgr.abort(&t3, &catalog)?;
+
+            // the crash, the buffer pool is dropped without flushing its pages
+            (first_page_id, t2.get_txn_id())
+        };
+
+        let disk_mgr = Arc::new(DiskManager::new("test_recovery.db")?);
+        let log_mgr = Arc::new(LogManager::open("test_recovery.log")?);
+        assert_eq!(vec![loser_id], LogRecovery::new(disk_mgr.clone(), log_mgr.clone()).recover()?);
+
+        // the committed tuples survive, the ones of the loser and the aborted one are gone
+        let rows = read_table(&disk_mgr, first_page_id, &schema)?;
+        assert_eq!(1015, rows.len());
+        let live: Vec<String> = rows.iter().filter(|(meta, _)| !meta.is_deleted).map(|(_, val)| val.clone()).collect();
+        assert_eq!((0..1000).map(|val| val.to_string()).collect::<Vec<String>>(), live);
+
+        // the undo is logged, the loser ends with an abort record
+        let records = log_mgr.read_log()?;
+        let num_clrs = records.iter()
+            .filter(|record| record.txn_id == loser_id && matches!(record.body, LogRecordBody::Clr { .. }))
+            .count();
+        assert_eq!(11, num_clrs);
+        let last = records.last().unwrap();
+        assert_eq!((loser_id, LogRecordBody::Abort), (last.txn_id, last.body.clone()));
+
+        // a second recovery finds nothing to undo and changes nothing
+        drop(log_mgr);
+        let log_mgr = Arc::new(LogManager::open("test_recovery.log")?);
+        assert!(LogRecovery::new(disk_mgr.clone(), log_mgr).recover()?.is_empty());
+        assert_eq!(rows, read_table(&disk_mgr, first_page_id, &schema)?);
+        Ok(())
+    }
+
+    #[test]
+    fn update_redo_test() -> Result<(), String> {
+        let _ = fs::remove_file("test_update_redo.db");
+        let _ = fs::remove_file("test_update_redo.log");
+        let schema = Schema::new(&vec![Column::new("a", TypeId::INTEGER), Column::new_varchar("b", TypeId::VARCHAR, 3000)]);
+        let build = |a: i32, len: usize| Tuple::build(&vec![Value::new_integer(TypeId::INTEGER, a), Value::new_varchar(TypeId::VARCHAR, &"x".repeat(len))], &schema);
+
+        let rids = {
+            let disk_mgr = Arc::new(DiskManager::new("test_update_redo.db")?);
+            let log_mgr = Arc::new(LogManager::open("test_update_redo.log")?);
+            let bpm = Arc::new(BufferPoolManager::new(4, disk_mgr, 2, log_mgr.clone()));
+            let txn_mgr = TransactionManager::new(Arc::new(LockManager::new()), log_mgr.clone());
+            let catalog = CataLog::new(bpm.clone(), Arc::new(LockManager::new()), log_mgr.clone());
+            // without a toaster, the values stay on the pages
+            let table_heap = TableHeap::new(bpm).unwrap();
+
+            let txn = txn_mgr.begin(IsolationLevel::SnapshotIsolation);
+            let meta = TupleMeta::new(txn.get_txn_id(), INVALID_TXN_ID, false);
+            let rids: Vec<RID> = (0..3).map(|i| table_heap.insert_tuple(&meta, &build(i, 1000), Some(&txn)).unwrap()).collect();
+            // one grows in place, the page has no room for the other one any more
+            table_heap.update_tuple(&rids[0], &build(10, 1500), Some(&txn))?;
+            table_heap.update_tuple(&rids[2], &build(12, 2000), Some(&txn))?;
+            txn_mgr.commit(&txn, &catalog)?;
+            // the crash, the pages are not written out
+            rids
+        };
+
+        let disk_mgr = Arc::new(DiskManager::new("test_update_redo.db")?);
+        let log_mgr = Arc::new(LogManager::open("test_update_redo.log")?);
+        assert!(LogRecovery::new(disk_mgr.clone(), log_mgr).recover()?.is_empty());
+        let mut page = Page::new();
+        disk_mgr.read_page(rids[0].pid, page.get_mut_data())?;
+        let table_page = page.cast_as::<TablePage>();
+        assert_eq!(build(10, 1500).get_data(), table_page.get_tuple(&rids[0])?.1.get_data());
+        assert_eq!(build(1, 1000).get_data(), table_page.get_tuple(&rids[1])?.1.get_data());
+        let to = table_page.get_forward(&rids[2]).unwrap();
+
+        let mut page = Page::new();
+        disk_mgr.read_page(to.pid, page.get_mut_data())?;
+        let table_page = page.cast_as::<TablePage>();
+        assert_eq!(Some(rids[2]), table_page.get_moved_from(&to));
+        assert_eq!(build(12, 2000).get_data(), table_page.get_tuple(&to)?.1.get_data());
+        Ok(())
+    }
+
+    #[test]
+    fn allocation_redo_test() -> Result<(), String> {
+        let _ = fs::remove_file("test_allocation_redo.db");
+        let _ = fs::remove_file("test_allocation_redo.log");
+        {
+            let disk_mgr = Arc::new(DiskManager::new("test_allocation_redo.db")?);
+            let log_mgr = Arc::new(LogManager::open("test_allocation_redo.log")?);
+            let bpm = BufferPoolManager::new(4, disk_mgr, 2, log_mgr.clone());
+            bpm.open_free_space_map(0)?;
+            for page_id in 2..5 {
+                assert_eq!(page_id, bpm.new_page().unwrap().get_pid());
+                bpm.unpin_page(page_id, true);
+            }
+            assert_eq!(Ok(true), bpm.delete_page(3));
+            // the crash, the log is on disk but the map page is not
+            log_mgr.flush_all()?;
+        }
+
+        let disk_mgr = Arc::new(DiskManager::new("test_allocation_redo.db")?);
+        let log_mgr = Arc::new(LogManager::open("test_allocation_redo.log")?);
+        LogRecovery::new(disk_mgr.clone(), log_mgr).recover()?;
+        let mut page = Page::new();
+        disk_mgr.read_page(FSM_FIRST_PAGE_ID, page.get_mut_data())?;
+        let map_page = page.cast_as::<FreeSpaceMapPage>();
+        let entries: Vec<u16> = (2..5).map(|page_id| map_page.get_entry(page_id, PAGE_SIZE as usize)).collect();
+        assert_eq!(vec![FreeSpaceMapPage::encode(0), FSM_FREE_PAGE, FreeSpaceMapPage::encode(0)], entries);
+        Ok(())
+    }
+
+    #[test]
+    fn checkpoint_test() -> Result<(), String> {
+        let _ = fs::remove_file("test_checkpoint.db");
+        let _ = fs::remove_file("test_checkpoint.log");
+        let schema = Schema::new(&vec![Column::new("a", TypeId::INTEGER)]);
+
+        let (first_page_id, loser_id, checkpoint_lsn) = {
+            let disk_mgr = Arc::new(DiskManager::new("test_checkpoint.db")?);
+            let log_mgr = Arc::new(LogManager::open("test_checkpoint.log")?);
+            let bpm = Arc::new(BufferPoolManager::new(3, disk_mgr, 2, log_mgr.clone()));
+            let mut catalog = CataLog::new(bpm.clone(), Arc::new(LockManager::new()), log_mgr.clone());
+            let txn_mgr = Arc::new(TransactionManager::new(Arc::new(LockManager::new()), log_mgr.clone()));
+            let checkpoint_mgr = CheckpointManager::new(txn_mgr.clone(), bpm.clone(), log_mgr.clone());
+            let first_page_id = catalog.create_table(None, "t", schema.clone()).unwrap().table_heap.get_first_page_id();
+
+            let t1 = txn_mgr.begin(IsolationLevel::SnapshotIsolation);
+            for val in 0..1000 {
+                insert(&catalog, &t1, val);
+            }
+            txn_mgr.commit(&t1, &catalog)?;
+            let t2 = txn_mgr.begin(IsolationLevel::SnapshotIsolation);
+            let rid = insert(&catalog, &t2, 1000);
+
+            // t2 is in the active transaction table, its BEGIN is kept
+            let checkpoint_lsn = checkpoint_mgr.checkpoint()?;
+            let records = log_mgr.read_log()?;
+            assert!(records[0].lsn > 1);
+            assert!(records.iter().any(|record| record.txn_id == t2.get_txn_id() && record.body == LogRecordBody::Begin));
+
+            delete(&catalog, &t2, rid)?;
+            insert(&catalog, &t2, 1001);
+            let t3 = txn_mgr.begin(IsolationLevel::SnapshotIsolation);
+            insert(&catalog, &t3, 1002);
+            txn_mgr.commit(&t3, &catalog)?;
+
+            // the crash
+            (first_page_id, t2.get_txn_id(), checkpoint_lsn)
+        };
+
+        let disk_mgr = Arc::new(DiskManager::new("test_checkpoint.db")?);
+        let log_mgr = Arc::new(LogManager::open("test_checkpoint.log")?);
+        assert_eq!(vec![loser_id], LogRecovery::new(disk_mgr.clone(), log_mgr.clone()).recover()?);
+        let live: Vec<String> = read_table(&disk_mgr, first_page_id, &schema)?.into_iter()
+            .filter(|(meta, _)| !meta.is_deleted)
+            .map(|(_, val)| val)
+            .collect();
+        assert_eq!((0..1000).chain(1002..1003).map(|val| val.to_string()).collect::<Vec<String>>(), live);
+
+        // the undo goes back beyond the checkpoint, to the BEGIN of the loser
+        let records = log_mgr.read_log()?;
+        let num_clrs = records.iter()
+            .filter(|record| record.txn_id == loser_id && matches!(record.body, LogRecordBody::Clr { .. }))
+            .count();
+        assert_eq!(3, num_clrs);
+        assert!(records.iter().any(|record| record.lsn == checkpoint_lsn && record.body == LogRecordBody::BeginCheckpoint));
+        Ok(())
+    }
+}
@@ -0,0 +1,2 @@
+pub mod log_recovery;
+pub mod checkpoint_manager;
\ No newline at end of file
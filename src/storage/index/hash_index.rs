@@ -0,0 +1,93 @@
+#![allow(warnings)]
+
+use std::{collections::HashMap, sync::{Arc, Mutex}};
+
+use crate::{catalog::schema::Schema, common::rid::RID, storage::page_based::table::tuple::Tuple};
+
+
+pub type HashIndexRef = Arc<HashIndex>;
+
+/// An in-memory hash index, maps the key bytes (built by `Tuple::get_key`) to the rids holding it.
+/// A key may map to several rids, uniqueness is checked by the caller against the live tuples.
+#[derive(Debug)]
+pub struct HashIndex {
+    key_schema: Schema,
+    table: Mutex<HashMap<Vec<u8>, Vec<RID>>>,
+}
+
+impl HashIndex {
+    pub fn new(key_schema: Schema) -> Self {
+        Self {
+            key_schema,
+            table: Mutex::new(HashMap::new()),
+        }
+    }
+
+    pub fn get_key_schema(&self) -> &Schema {
+        &self.key_schema
+    }
+
+    pub fn insert_entry(&self, key: &Tuple, rid: RID) {
+        let mut table = self.table.lock().unwrap();
+        let rids = table.entry(key.get_data().clone()).or_insert(Vec::new());
+        if !rids.contains(&rid) {
+            rids.push(rid);
+        }
+    }
+
+    pub fn delete_entry(&self, key: &Tuple, rid: RID) {
+        let mut table = self.table.lock().unwrap();
+        if let Some(rids) = table.get_mut(key.get_data()) {
+            rids.retain(|r| *r != rid);
+            if rids.is_empty() {
+                table.remove(key.get_data());
+            }
+        }
+    }
+
+    // returns all rids stored under the key
+    pub fn scan_key(&self, key: &Tuple) -> Vec<RID> {
+        let table = self.table.lock().unwrap();
+        match table.get(key.get_data()) {
+            None => Vec::new(),
+            Some(rids) => rids.clone(),
+        }
+    }
+}
+
+
+
+#[cfg(test)]
+mod tests {
+    use crate::{catalog::{column::Column, schema::Schema}, common::rid::RID, storage::page_based::table::tuple::Tuple, typedef::{type_id::TypeId, value::Value}};
+
+    use super::HashIndex;
+
+    #[test]
+    fn insert_scan_delete_test() {
+        let schema = Schema::new(&vec![
+            Column::new("a", TypeId::INTEGER),
+            Column::new_varchar("b", TypeId::VARCHAR, 16),
+        ]);
+        let key_schema = Schema::new(&vec![Column::new("a", TypeId::INTEGER)]);
+        let index = HashIndex::new(key_schema.clone());
+
+        let t1 = Tuple::build(&vec![Value::new_integer(TypeId::INTEGER, 1), Value::new_varchar(TypeId::VARCHAR, "x")], &schema);
+        let t2 = Tuple::build(&vec![Value::new_integer(TypeId::INTEGER, 2), Value::new_varchar(TypeId::VARCHAR, "y")], &schema);
+        let k1 = t1.get_key(&schema, &vec![0], &key_schema);
+        let k2 = t2.get_key(&schema, &vec![0], &key_schema);
+
+        let rid1 = RID { pid: 0, sid: 0 };
+        let rid2 = RID { pid: 0, sid: 1 };
+        index.insert_entry(&k1, rid1);
+        index.insert_entry(&k2, rid2);
+        index.insert_entry(&k2, rid2);
+
+        assert_eq!(vec![rid1], index.scan_key(&k1));
+        assert_eq!(vec![rid2], index.scan_key(&k2));
+
+        index.delete_entry(&k1, rid1);
+        assert!(index.scan_key(&k1).is_empty());
+        assert_eq!(vec![rid2], index.scan_key(&k2));
+    }
+}
@@ -0,0 +1 @@
+pub mod hash_index;
\ No newline at end of file
@@ -1 +1,2 @@
-pub mod page_based;
\ No newline at end of file
+pub mod page_based;
+pub mod index;
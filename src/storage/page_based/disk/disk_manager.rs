@@ -1,14 +1,126 @@
 #![allow(warnings)]
 
-use std::{fs::{File, OpenOptions}, io::{Read, Seek, SeekFrom, Write}, sync};
+use std::{fmt::Debug, fs::{File, OpenOptions}, io::{Read, Seek, SeekFrom, Write}, sync::{self, Arc}};
 
 
-use crate::common::config::{page_id_t, PAGE_SIZE};
+use crate::{common::config::{page_id_t, PAGE_SIZE}, storage::page_based::page::page::{Page, PAGE_CORRUPTED}};
 
+use super::positional_disk_manager::PositionalDiskManager;
 
-pub trait PageStore {
+
+/// a run of adjacent pages read or written by one i/o
+#[derive(Debug)]
+pub enum PageIo<'a> {
+    Read(page_id_t, Vec<&'a mut Vec<u8>>),
+    Write(page_id_t, Vec<&'a Vec<u8>>),
+}
+
+impl<'a> PageIo<'a> {
+    pub fn page_id(&self) -> page_id_t {
+        match self {
+            PageIo::Read(page_id, _) | PageIo::Write(page_id, _) => *page_id,
+        }
+    }
+
+    pub fn num_pages(&self) -> usize {
+        match self {
+            PageIo::Read(_, bufs) => bufs.len(),
+            PageIo::Write(_, pages_data) => pages_data.len(),
+        }
+    }
+}
+
+/// how the pages of the file are read and written
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DiskBackend {
+    // seek and read/write behind a lock on the file, DiskManager
+    Buffered,
+    // pread/pwrite, with direct the file is opened with O_DIRECT
+    Positional { direct: bool },
+    // pread/pwrite, and the batches of the disk scheduler go through an io_uring, linux only
+    IoUring { direct: bool },
+}
+
+impl DiskBackend {
+    pub fn open(&self, filename: &str) -> Result<Arc<dyn PageStore>, String> {
+        self.open_with_page_size(filename, PAGE_SIZE as usize)
+    }
+
+    pub fn open_with_page_size(&self, filename: &str, page_size: usize) -> Result<Arc<dyn PageStore>, String> {
+        Ok(match *self {
+            DiskBackend::Buffered => Arc::new(DiskManager::new_with_page_size(filename, page_size)?),
+            DiskBackend::Positional { direct } => Arc::new(PositionalDiskManager::new(filename, direct, page_size)?),
+            DiskBackend::IoUring { direct } => Arc::new(PositionalDiskManager::with_io_uring(filename, direct, page_size)?),
+        })
+    }
+}
+
+pub trait PageStore: Debug + Send + Sync {
     fn read_page(&self, page_id: page_id_t, buf: &mut Vec<u8>) -> Result<(), String>;
     fn write_page(&self, page_id: page_id_t, page_data: &Vec<u8>) -> Result<(), String>;
+
+    // the pages from page_id on, one after another, a store may serve them with one i/o
+    fn read_pages(&self, page_id: page_id_t, bufs: &mut [&mut Vec<u8>]) -> Result<(), String> {
+        for (i, buf) in bufs.iter_mut().enumerate() {
+            self.read_page(page_id + i as page_id_t, buf)?;
+        }
+        Ok(())
+    }
+
+    fn write_pages(&self, page_id: page_id_t, pages_data: &[&Vec<u8>]) -> Result<(), String> {
+        for (i, page_data) in pages_data.iter().enumerate() {
+            self.write_page(page_id + i as page_id_t, page_data)?;
+        }
+        Ok(())
+    }
+
+    // the runs of a batch of the disk scheduler, in the order of their pages, with the result of each
+    fn submit(&self, ios: &mut [PageIo]) -> Vec<Result<(), String>> {
+        ios.iter_mut().map(|io| match io {
+            PageIo::Read(page_id, bufs) => self.read_pages(*page_id, bufs),
+            PageIo::Write(page_id, pages_data) => self.write_pages(*page_id, pages_data),
+        }).collect()
+    }
+
+    // the pages written so far are on disk when it returns
+    fn sync(&self) -> Result<(), String> {
+        Ok(())
+    }
+
+    fn get_filesize(&self) -> u64;
+
+    // whether the page is within the store, a page beyond the end was never written
+    fn contains_page(&self, page_id: page_id_t) -> bool {
+        (page_id as u64 + 1) * self.get_page_size() as u64 <= self.get_filesize() as u64
+    }
+
+    // the size of the pages read and written, fixed for the life of the store
+    fn get_page_size(&self) -> usize;
+}
+
+// the checksums of the pages from page_id on, set in the copy to be written
+pub(crate) fn set_checksums(page_id: page_id_t, data: &mut [u8], page_size: usize) {
+    for (i, chunk) in data.chunks_mut(page_size).enumerate() {
+        Page::set_checksum(page_id + i as page_id_t, chunk);
+    }
+}
+
+// the pages read from page_id on are checked and copied out, the ones beyond the data are left as they are.
+// a page cut off by the end of the data is short
+pub(crate) fn copy_checked(page_id: page_id_t, data: &[u8], bufs: &mut [&mut Vec<u8>], page_size: usize) -> Result<(), String> {
+    let mut result = Ok(());
+    for (i, (buf, chunk)) in bufs.iter_mut().zip(data.chunks(page_size)).enumerate() {
+        let checked = match chunk.len() == page_size {
+            true => Page::verify_checksum(page_id + i as page_id_t, chunk),
+            false => Err(format!("{}: short read of page {}, {} bytes", PAGE_CORRUPTED, page_id + i as page_id_t, chunk.len())),
+        };
+        if checked.is_ok() {
+            buf.copy_from_slice(chunk);
+        } else if result.is_ok() {
+            result = checked;
+        }
+    }
+    result
 }
 
 /// DiskManager, responsible for actually read-write operations based on the page_id
@@ -17,12 +129,17 @@ pub trait PageStore {
 pub struct DiskManager {
     filename: String,
     file_fd: sync::RwLock<File>,
+    page_size: usize,
     num_writes: u32,
     num_flushed: u32,
 }
 
 impl DiskManager {
     pub fn new(filename: &str) -> Result<Self, String> {
+        Self::new_with_page_size(filename, PAGE_SIZE as usize)
+    }
+
+    pub fn new_with_page_size(filename: &str, page_size: usize) -> Result<Self, String> {
         let find_res = filename.rfind(".");
         match find_res {
             None => Err(format!("Error: invalid find name, must surround with a suffix")),
@@ -43,6 +160,7 @@ impl DiskManager {
                     Ok(Self {
                         filename: filename.to_owned(),
                         file_fd: sync::RwLock::new(res.expect(&format!("Error: file {filename} opened failed."))),
+                        page_size,
                         num_writes: 0,
                         num_flushed: 0,
                     })
@@ -51,49 +169,44 @@ impl DiskManager {
         }
     }    
 
-    pub fn get_filesize(&self) -> u32 {
-        let read_guard = self.file_fd.read().unwrap();
-        let meta = read_guard.metadata();
-        // println!("{}", meta.unwrap().)
-        meta.expect("Erorr: get filesize error").len() as u32
-    }
-
 } 
 
 impl PageStore for DiskManager {
     fn read_page(&self, page_id: page_id_t, buf: &mut Vec<u8>) -> Result<(), String> {
         // println!("{}", buf.len());
-        assert!(buf.len() == PAGE_SIZE as usize);
+        assert!(buf.len() == self.page_size);
         
-        let offset: u32 = (page_id * PAGE_SIZE) as u32;
-        if offset >= self.get_filesize() {
+        let offset = page_id as u64 * self.page_size as u64;
+        if offset >= self.get_filesize() as u64 {
             println!("Error: invalid page size {}",  offset);
             return Err(format!("Error: invalid page size"));
         }
 
         // get shared_lock 
         let mut write_guard = self.file_fd.write().unwrap();
-        write_guard.seek(SeekFrom::Start(offset as u64)).unwrap();
-        let read_res = write_guard.read(buf.as_mut_slice());
+        write_guard.seek(SeekFrom::Start(offset)).unwrap();
+        let read_res = write_guard.read_exact(buf.as_mut_slice());
         match read_res {
+            Err(err) if err.kind() == std::io::ErrorKind::UnexpectedEof => {
+                Err(format!("{}: short read of page {}", PAGE_CORRUPTED, page_id))
+            },
             Err(err) => {
                 println!("Error: read_file error, error is {}", err);
                 Err(format!("Error: invalid page size"))
             },
-            Ok(read_size) => {
-                assert!(read_size == PAGE_SIZE as usize);
-                Ok(())
-            }
+            Ok(()) => Page::verify_checksum(page_id, buf),
         }
     }
 
     fn write_page(&self, page_id: page_id_t, page_data: &Vec<u8>) -> Result<(), String> {
-        assert!(page_data.len() == PAGE_SIZE as usize);
+        assert!(page_data.len() == self.page_size);
         
-        let offset: u32 = (page_id * PAGE_SIZE) as u32;
+        let offset = page_id as u64 * self.page_size as u64;
+        let mut page_data = page_data.clone();
+        Page::set_checksum(page_id, &mut page_data);
 
         let mut write_guard = self.file_fd.write().unwrap();
-        write_guard.seek(SeekFrom::Start(offset as u64)).expect("Seek Error");
+        write_guard.seek(SeekFrom::Start(offset)).expect("Seek Error");
         let write_res = write_guard.write(&page_data);
         match write_res {
             Err(err) => {
@@ -101,57 +214,160 @@ impl PageStore for DiskManager {
                 Err(format!("Error: write error"))
             },
             Ok(write_size) => {
-                assert!(write_size == PAGE_SIZE as usize);
+                assert!(write_size == self.page_size);
                 write_guard.flush();
                 Ok(())
             }
         }
     }
+
+    // one read for all the pages, the ones beyond the end of the file are left as they are
+    fn read_pages(&self, page_id: page_id_t, bufs: &mut [&mut Vec<u8>]) -> Result<(), String> {
+        let offset = page_id as u64 * self.page_size as u64;
+        let filesize = self.get_filesize() as u64;
+        if offset >= filesize {
+            return Err(format!("Error: invalid page size"));
+        }
+
+        let len = (bufs.len() as u64 * self.page_size as u64).min(filesize - offset) as usize;
+        let mut data = vec![0; len];
+        {
+            let mut write_guard = self.file_fd.write().unwrap();
+            write_guard.seek(SeekFrom::Start(offset)).map_err(|err| format!("Error: seek error {}", err))?;
+            write_guard.read_exact(&mut data).map_err(|err| format!("Error: read_file error, error is {}", err))?;
+        }
+        copy_checked(page_id, &data, bufs, self.page_size)
+    }
+
+    fn write_pages(&self, page_id: page_id_t, pages_data: &[&Vec<u8>]) -> Result<(), String> {
+        let offset = page_id as u64 * self.page_size as u64;
+        let mut data: Vec<u8> = pages_data.iter().flat_map(|page_data| page_data.iter().cloned()).collect();
+        set_checksums(page_id, &mut data, self.page_size);
+
+        let mut write_guard = self.file_fd.write().unwrap();
+        write_guard.seek(SeekFrom::Start(offset)).map_err(|err| format!("Error: seek error {}", err))?;
+        write_guard.write_all(&data).map_err(|err| format!("Error: write error {}", err))?;
+        write_guard.flush();
+        Ok(())
+    }
+
+    fn sync(&self) -> Result<(), String> {
+        self.file_fd.read().unwrap().sync_data().map_err(|err| format!("Error: sync error {}", err))
+    }
+
+    fn get_filesize(&self) -> u64 {
+        let read_guard = self.file_fd.read().unwrap();
+        let meta = read_guard.metadata();
+        // println!("{}", meta.unwrap().)
+        meta.expect("Erorr: get filesize error").len()
+    }
+
+    fn get_page_size(&self) -> usize {
+        self.page_size
+    }
 }
 
 
 
 #[cfg(test)]
 mod tests {
-    use crate::common::config::PAGE_SIZE;
+    use std::{fs::OpenOptions, os::unix::fs::FileExt};
+
+    use crate::{common::config::{page_id_t, PAGE_SIZE}, storage::page_based::page::page::{Page, COMMON_PAGE_HEADER_SIZE, PAGE_CORRUPTED}};
     use super::{DiskManager, PageStore};
 
+    // the pages as they are read back, with their checksums
+    fn with_checksums(page_id: page_id_t, pages: &[Vec<u8>]) -> Vec<Vec<u8>> {
+        pages.iter().enumerate().map(|(i, page)| {
+            let mut page = page.clone();
+            Page::set_checksum(page_id + i as page_id_t, &mut page);
+            page
+        }).collect()
+    }
+
     #[test]
     fn test_simple_read_write() {
-        let disk = DiskManager::new("test.db").unwrap();
+        // a test.db of an older format fails the checksums
+        let _ = std::fs::remove_file("test_simple_read_write.db");
+        let disk = DiskManager::new("test_simple_read_write.db").unwrap();
 
         let mut buf = Vec::new();
         buf.resize(PAGE_SIZE as usize, 0);
         
         let s = "world";
-        buf[0..s.len()].copy_from_slice(s.as_bytes());
+        buf[COMMON_PAGE_HEADER_SIZE..COMMON_PAGE_HEADER_SIZE + s.len()].copy_from_slice(s.as_bytes());
 
         disk.write_page(0, &buf.to_vec()).unwrap();
 
         let mut page = Vec::new();
         page.resize(PAGE_SIZE as usize, 0);
         let res = disk.read_page(0, &mut page).unwrap();
-        println!("{:#?}", String::from_utf8(page[..s.len()].to_vec()));
+        println!("{:#?}", String::from_utf8(page[COMMON_PAGE_HEADER_SIZE..COMMON_PAGE_HEADER_SIZE + s.len()].to_vec()));
     }
 
     #[test]
     fn test_read_write() {
-        let disk = DiskManager::new("test.db").unwrap();
+        let _ = std::fs::remove_file("test_read_write.db");
+        let disk = DiskManager::new("test_read_write.db").unwrap();
 
         let mut buf = Vec::new();
         buf.resize(PAGE_SIZE as usize, 0);
 
         let s = "hello";
 
-        buf[0..s.len()].copy_from_slice(s.as_bytes());
+        buf[COMMON_PAGE_HEADER_SIZE..COMMON_PAGE_HEADER_SIZE + s.len()].copy_from_slice(s.as_bytes());
         disk.write_page(1, &buf.to_vec()).unwrap();
 
         let mut page = Vec::new();
         page.resize(PAGE_SIZE as usize, 0);
         let _ = disk.read_page(0, &mut page).unwrap();
-        println!("{:#?}", String::from_utf8(page[..s.len()].to_vec()));
+        println!("{:#?}", String::from_utf8(page[COMMON_PAGE_HEADER_SIZE..COMMON_PAGE_HEADER_SIZE + s.len()].to_vec()));
         
         let _ = disk.read_page(1, &mut page).unwrap();
-        println!("{:#?}", String::from_utf8(page[..s.len()].to_vec()));
+        println!("{:#?}", String::from_utf8(page[COMMON_PAGE_HEADER_SIZE..COMMON_PAGE_HEADER_SIZE + s.len()].to_vec()));
+    }
+
+    #[test]
+    fn test_read_write_pages() {
+        let _ = std::fs::remove_file("test_pages.db");
+        let disk = DiskManager::new("test_pages.db").unwrap();
+
+        let pages: Vec<Vec<u8>> = (0..4).map(|i| vec![i as u8 + 1; PAGE_SIZE as usize]).collect();
+        disk.write_pages(2, &pages.iter().collect::<Vec<_>>()).unwrap();
+        assert_eq!(6 * PAGE_SIZE as u64, disk.get_filesize());
+
+        // 5 and 6 are beyond the end of the file, they are left as they are
+        let mut bufs: Vec<Vec<u8>> = (0..5).map(|_| vec![0; PAGE_SIZE as usize]).collect();
+        disk.read_pages(2, &mut bufs.iter_mut().collect::<Vec<_>>()).unwrap();
+        assert_eq!(with_checksums(2, &pages)[..], bufs[..4]);
+        assert_eq!(vec![0; PAGE_SIZE as usize], bufs[4]);
+        assert_eq!(true, disk.read_pages(6, &mut bufs.iter_mut().collect::<Vec<_>>()).is_err());
+        let _ = std::fs::remove_file("test_pages.db");
+    }
+
+    #[test]
+    fn test_checksum_mismatch() {
+        let _ = std::fs::remove_file("test_checksum.db");
+        let disk = DiskManager::new("test_checksum.db").unwrap();
+        let pages: Vec<Vec<u8>> = (0..3).map(|i| vec![i as u8 + 1; PAGE_SIZE as usize]).collect();
+        disk.write_pages(0, &pages.iter().collect::<Vec<_>>()).unwrap();
+
+        // a bit flipped on disk
+        let file = OpenOptions::new().read(true).write(true).open("test_checksum.db").unwrap();
+        file.write_all_at(&[0xff], PAGE_SIZE as u64 + 100).unwrap();
+        let mut buf = vec![0; PAGE_SIZE as usize];
+        assert_eq!(true, disk.read_page(1, &mut buf).unwrap_err().starts_with(PAGE_CORRUPTED));
+        disk.read_page(0, &mut buf).unwrap();
+        assert_eq!(with_checksums(0, &pages[..1])[0], buf);
+
+        // the other pages of the run are still read
+        let mut bufs: Vec<Vec<u8>> = (0..3).map(|_| vec![0; PAGE_SIZE as usize]).collect();
+        assert_eq!(true, disk.read_pages(0, &mut bufs.iter_mut().collect::<Vec<_>>()).unwrap_err().starts_with(PAGE_CORRUPTED));
+        assert_eq!(with_checksums(2, &pages[2..])[0], bufs[2]);
+
+        // the last page is cut off
+        file.set_len(2 * PAGE_SIZE as u64 + 100).unwrap();
+        assert_eq!(true, disk.read_page(2, &mut buf).unwrap_err().starts_with(PAGE_CORRUPTED));
+        let _ = std::fs::remove_file("test_checksum.db");
     }
-}
\ No newline at end of file
+}
@@ -1,50 +1,58 @@
-use std::{sync::{Arc, mpsc, atomic::*}, thread::{JoinHandle, self}};
+use std::{sync::{Arc, Condvar, Mutex, mpsc}, thread::{JoinHandle, self}};
 
-use crate::common::config::page_id_t;
+use crate::common::config::{page_id_t, DISK_SCHEDULER_WORKERS};
 
-use super::disk_manager::{DiskManager, PageStore};
+use super::disk_manager::{PageIo, PageStore};
 
 
 
 /// we use tokio async framework to shcedule i/o read/write
 
-/// like a promise<bool> in c++
+/// like a promise<Result> in c++, it may be waited for by more than one thread
+#[derive(Debug)]
 pub struct Promise {
-    sender: mpsc::Sender<bool>,
-    receiver: mpsc::Receiver<bool>,
+    result: Mutex<Option<Result<(), String>>>,
+    cv: Condvar,
 }
 
 impl Promise {
     pub fn new() -> Self {
-        let (tx, rx) = mpsc::channel();
         Self {
-            sender: tx,
-            receiver: rx,
+            result: Mutex::new(None),
+            cv: Condvar::new(),
         }
     }
 
     pub fn wait(&self) {
-        match self.receiver.recv() {
-            Err(err) => {
-                println!("Error: {}", err);
-            },
-            Ok(_) => {
+        let _ = self.wait_result();
+    }
 
-            }
+    // the result of the i/o, e.g. a page failing its checksum
+    pub fn wait_result(&self) -> Result<(), String> {
+        let mut result = self.result.lock().unwrap();
+        while result.is_none() {
+            result = self.cv.wait(result).unwrap();
         }
+        result.clone().unwrap()
+    }
+
+    // without waiting
+    pub fn is_completed(&self) -> bool {
+        self.result.lock().unwrap().is_some()
     }
 
     pub fn completed(&self) {
-        match self.sender.send(true) {
-            Err(err) => {
-                println!("Error: {}", err);
-            },
-            Ok(_) => {}
-        }
+        self.completed_with(Ok(()));
+    }
+
+    pub fn completed_with(&self, result: Result<(), String>) {
+        *self.result.lock().unwrap() = Some(result);
+        self.cv.notify_all();
     }
 }
 
 
+#[derive(Debug)]
 pub struct DiskRequest {
     pub is_write: bool,
     pub data: *mut Vec<u8>,
@@ -59,17 +67,19 @@ unsafe impl Sync for DiskRequest {}
 
 pub type Msg = Option<Arc<DiskRequest>>;
 
-/// we need a msg channel to send and recv msg, so we set it a field in DiskScheudler
+// at most so many queued requests are taken by a worker at a time
+const MAX_BATCH_SIZE: usize = 32;
+
+/// we need a msg channel to send and recv msg, so we set it a field in DiskScheudler.
+/// the workers share the channel, a worker takes the requests queued up at the time,
+/// and the ones of adjacent pages in the same direction are served by one i/o
 #[derive(Debug)]
 pub struct DiskScheduler {
     // execute async task necessarily
-    disk_manager: Arc<DiskManager>,
-
-    // thread handle
-    handler: Option<JoinHandle<()>>,
+    disk_manager: Arc<dyn PageStore>,
 
-    // stop service
-    stop: Arc<AtomicBool>,
+    // worker thread handles
+    handlers: Vec<JoinHandle<()>>,
 
     // writer, this should be in the outside of the thread
     writer: Arc<mpsc::Sender<Msg>>,
@@ -77,18 +87,24 @@ pub struct DiskScheduler {
 
 
 impl DiskScheduler {
-    pub fn new(disk_manager: Arc<DiskManager>) -> Self {
-        // reader, we need move it into thread itself
+    pub fn new(disk_manager: Arc<dyn PageStore>) -> Self {
+        Self::with_workers(disk_manager, DISK_SCHEDULER_WORKERS)
+    }
+
+    pub fn with_workers(disk_manager: Arc<dyn PageStore>, num_workers: usize) -> Self {
+        // reader, shared by the workers
         // reader: mpsc::Receiver<Msg>,
         let (wr, rd) = mpsc::channel();
+        let rd = Arc::new(Mutex::new(rd));
 
         let mut scheduler = Self {
             disk_manager,
-            stop: Arc::new(AtomicBool::new(false)),
             writer: Arc::new(wr),
-            handler: None,
+            handlers: Vec::new(),
         };
-        scheduler.start_thread(rd);
+        for _ in 0..num_workers.max(1) {
+            scheduler.start_thread(rd.clone());
+        }
         scheduler
     }
 
@@ -96,46 +112,93 @@ impl DiskScheduler {
         Arc::new(DiskRequest { is_write, data, page_id: pid, callback: Promise::new() })
     }
 
-    fn start_thread(&mut self, recv: mpsc::Receiver<Msg>) {
+    fn start_thread(&mut self, recv: Arc<Mutex<mpsc::Receiver<Msg>>>) {
         let rd = recv;
         let disk_mgr = self.disk_manager.clone();
-        let stop = self.stop.clone();
 
         let handler = thread::spawn(move || {
-            Self::start(rd, disk_mgr, stop);
+            Self::start(rd, disk_mgr);
         });
 
-        self.handler = Some(handler);
-    }
-
-    fn start(reader: mpsc::Receiver<Msg>, disk_manager: Arc<DiskManager>, stop: Arc<AtomicBool>) {
-        while !stop.load(Ordering::Relaxed) {
-
-            match reader.recv() {
-                Err(err) => {
-                    println!("Err: {}", err);
-                    break;
-                },
-                Ok(msg) => {
-                    if let Some(msg) = msg {
-
-                        if msg.is_write {
-                            let page_data = unsafe { &(*msg.data) };
-                            let _ = disk_manager.write_page(msg.page_id, page_data.as_ref());
-                        } else {
-                            let page_data = unsafe { &mut (*msg.data)};
-                            let _ = disk_manager.read_page(msg.page_id, page_data);
-                        }
-                        msg.callback.completed();
-                    } else {
+        self.handlers.push(handler);
+    }
+
+    fn start(reader: Arc<Mutex<mpsc::Receiver<Msg>>>, disk_manager: Arc<dyn PageStore>) {
+        loop {
+            // a none msg stops the worker which takes it, after the requests taken with it
+            let mut stop = false;
+            let mut batch = Vec::new();
+            {
+                let reader = reader.lock().unwrap();
+                match reader.recv() {
+                    Err(err) => {
+                        println!("Err: {}", err);
                         break;
+                    },
+                    Ok(None) => break,
+                    Ok(Some(msg)) => batch.push(msg),
+                }
+                while batch.len() < MAX_BATCH_SIZE {
+                    match reader.try_recv() {
+                        Ok(Some(msg)) => batch.push(msg),
+                        Ok(None) => {
+                            stop = true;
+                            break;
+                        },
+                        Err(_) => break,
                     }
-
                 }
             }
 
+            Self::serve(batch, disk_manager.as_ref());
+            if stop {
+                break;
+            }
+        }
+    }
+
+    // the requests are ordered by page id, a run of adjacent pages in the same direction is one i/o,
+    // and the runs of the batch are submitted to the store together.
+    // the order of the requests of the same page is kept
+    fn serve(mut batch: Vec<Arc<DiskRequest>>, disk_manager: &dyn PageStore) {
+        batch.sort_by_key(|msg| msg.page_id);
+
+        let mut runs: Vec<std::ops::Range<usize>> = Vec::new();
+        let mut start = 0;
+        while start < batch.len() {
+            let mut end = start + 1;
+            while end < batch.len()
+                && batch[end].is_write == batch[start].is_write
+                && batch[end].page_id == batch[end - 1].page_id + 1 {
+                end += 1;
+            }
+            runs.push(start..end);
+            start = end;
+        }
+
+        let mut ios: Vec<PageIo> = runs.iter().map(|run| {
+            let run = &batch[run.clone()];
+            if run[0].is_write {
+                PageIo::Write(run[0].page_id, run.iter().map(|msg| unsafe { &(*msg.data) }).collect())
+            } else {
+                PageIo::Read(run[0].page_id, run.iter().map(|msg| unsafe { &mut (*msg.data) }).collect())
+            }
+        }).collect();
+        let results = disk_manager.submit(&mut ios);
+        drop(ios);
+
+        for (run, result) in runs.into_iter().zip(results.into_iter()) {
+            // a failed read of several pages is read again page by page, so that only the bad ones fail
+            if result.is_err() && run.len() > 1 && !batch[run.start].is_write {
+                for msg in batch[run].iter() {
+                    msg.callback.completed_with(disk_manager.read_page(msg.page_id, unsafe { &mut (*msg.data) }));
+                }
+                continue;
+            }
+            for msg in batch[run].iter() {
+                msg.callback.completed_with(result.clone());
+            }
         }
-        println!("Background thread exit");
     }
 
     pub fn schedule(&self, req_msg: Msg) {
@@ -144,15 +207,17 @@ impl DiskScheduler {
 
 }
 
-// we need close the thread when drop
+// we need close the threads when drop
 impl Drop for DiskScheduler {
     fn drop(&mut self) {
-        if let Some(handler) = self.handler.take() {
-            // send none msg
+        // send a none msg to each worker
+        for _ in 0..self.handlers.len() {
             match self.writer.send(None) {
                 Err(_) => { panic!("Error occurred") },
                 Ok(_) => {},
             }
+        }
+        for handler in self.handlers.drain(..) {
             let _ = handler.join();
         }
     }
@@ -162,15 +227,19 @@ impl Drop for DiskScheduler {
 
 #[cfg(test)]
 mod tests {
-    use std::sync::{Arc, RwLock};
+    use std::{fs::OpenOptions, os::unix::fs::FileExt, sync::{Arc, RwLock}};
 
-    use crate::storage::page_based::{disk::{disk_manager::DiskManager}, page::page::Page};
+    use crate::{common::config::{page_id_t, INVALID_LSN, PAGE_SIZE}, storage::page_based::{disk::{disk_manager::{DiskManager, PageStore}, memory_page_store::MemoryPageStore}, page::page::{Page, COMMON_PAGE_HEADER_SIZE, PAGE_CORRUPTED}}};
     use super::{DiskScheduler, DiskRequest, Promise};
 
 
     #[test]
     fn test_schedule() {
-        let disk_mgr = DiskManager::new("test.db").unwrap();
+        let disk_mgr = MemoryPageStore::new();
+        let mut hello = vec![0; PAGE_SIZE as usize];
+        hello[COMMON_PAGE_HEADER_SIZE..COMMON_PAGE_HEADER_SIZE + 5].copy_from_slice("hello".as_bytes());
+        disk_mgr.write_page(1, &hello).unwrap();
+        Page::set_checksum(1, &mut hello);
         let disk_mgr_wrap = Arc::new(disk_mgr);
 
         let disk_scheduler = DiskScheduler::new(disk_mgr_wrap);
@@ -184,6 +253,7 @@ mod tests {
             page_id: 1,
             pin_count: 0,
             is_dirty: false,
+            rec_lsn: INVALID_LSN,
             data: buf,
             rwlatch: RwLock::new(()),
         };
@@ -206,7 +276,66 @@ mod tests {
 
         // check buf
         println!("{:#?}", &page.get_data()[..20]);
-        println!("{:#?}", String::from_utf8(page.get_data()[..5].to_vec()))
+        println!("{:#?}", String::from_utf8(page.get_data()[COMMON_PAGE_HEADER_SIZE..COMMON_PAGE_HEADER_SIZE + 5].to_vec()));
+        assert_eq!(hello, *page.get_data());
+
+    }
+
+    #[test]
+    fn test_schedule_batch() {
+        let _ = std::fs::remove_file("test_schedule_batch.db");
+        let disk_mgr = Arc::new(DiskManager::new("test_schedule_batch.db").unwrap());
+        let disk_scheduler = DiskScheduler::with_workers(disk_mgr, 3);
+
+        // the writes queued together are served out of order, in runs of adjacent pages
+        let mut pages: Vec<Vec<u8>> = (0..32).map(|i| vec![i as u8; PAGE_SIZE as usize]).collect();
+        let requests: Vec<_> = pages.iter_mut().enumerate().rev()
+            .map(|(i, data)| DiskScheduler::create_request(true, data, i as page_id_t))
+            .collect();
+        for request in requests.iter() {
+            disk_scheduler.schedule(Some(request.clone()));
+        }
+        for request in requests.iter() {
+            request.callback.wait();
+            assert_eq!(true, request.callback.is_completed());
+        }
 
+        let mut bufs: Vec<Vec<u8>> = (0..32).map(|_| vec![0; PAGE_SIZE as usize]).collect();
+        let requests: Vec<_> = bufs.iter_mut().enumerate()
+            .map(|(i, data)| DiskScheduler::create_request(false, data, i as page_id_t))
+            .collect();
+        for request in requests.iter() {
+            disk_scheduler.schedule(Some(request.clone()));
+        }
+        // more than one thread may wait for a request
+        let waiter = {
+            let request = requests[31].clone();
+            std::thread::spawn(move || request.callback.wait())
+        };
+        for request in requests.iter() {
+            request.callback.wait();
+        }
+        waiter.join().unwrap();
+        for (i, page) in pages.iter_mut().enumerate() {
+            Page::set_checksum(i as page_id_t, page);
+        }
+        assert_eq!(pages, bufs);
+
+        // a corrupted page in a run fails its own request only
+        let file = OpenOptions::new().write(true).open("test_schedule_batch.db").unwrap();
+        file.write_all_at(&[0xff; 16], 7 * PAGE_SIZE as u64 + 100).unwrap();
+        let requests: Vec<_> = bufs.iter_mut().enumerate()
+            .map(|(i, data)| DiskScheduler::create_request(false, data, i as page_id_t))
+            .collect();
+        for request in requests.iter() {
+            disk_scheduler.schedule(Some(request.clone()));
+        }
+        for (i, request) in requests.iter().enumerate() {
+            match request.callback.wait_result() {
+                Err(err) => assert_eq!((7, true), (i, err.starts_with(PAGE_CORRUPTED))),
+                Ok(()) => assert_ne!(7, i),
+            }
+        }
+        let _ = std::fs::remove_file("test_schedule_batch.db");
     }
-}
\ No newline at end of file
+}
@@ -0,0 +1,227 @@
+#![allow(warnings)]
+
+use std::{fs::{File, OpenOptions}, io::{Read, Seek, SeekFrom, Write}, sync::{Arc, Mutex}};
+
+use crate::{common::config::page_id_t, storage::page_based::page::page::{Page, PAGE_CORRUPTED}};
+
+use super::disk_manager::{PageIo, PageStore};
+
+
+/// DoubleWriteBuffer, like the one of InnoDB. The pages of a write go to a side file first, which is synced
+/// before they are written to their places in the file of the store, so a page torn by a crash in the middle
+/// of its write has a good copy in the side file, and the one in the side file is torn only if the other is not written yet.
+/// When it is opened, the pages of the store failing their checksums are restored from the side file
+#[derive(Debug)]
+pub struct DoubleWriteBuffer {
+    store: Arc<dyn PageStore>,
+    // the pages of the last write, one write goes through it at a time
+    file: Mutex<File>,
+}
+
+impl DoubleWriteBuffer {
+    pub fn new(store: Arc<dyn PageStore>, filename: &str) -> Result<Self, String> {
+        let file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .open(filename)
+            .map_err(|err| format!("Error: can not open double write file {}, err is {}", filename, err))?;
+        Ok(Self { store, file: Mutex::new(file) })
+    }
+
+    // open it and restore the torn pages of the store
+    pub fn open(store: Arc<dyn PageStore>, filename: &str) -> Result<Self, String> {
+        let dwb = Self::new(store, filename)?;
+        dwb.recover()?;
+        Ok(dwb)
+    }
+
+    // the side file of a database file, e.g. test.db -> test.dwb
+    pub fn filename_of(db_filename: &str) -> String {
+        match db_filename.rfind(".") {
+            Some(pos) => format!("{}.dwb", &db_filename[..pos]),
+            None => format!("{}.dwb", db_filename),
+        }
+    }
+
+    // the pages of the store which are corrupted and have a good copy in the side file are written back,
+    // the ids of them are returned. the side file is emptied afterwards
+    pub fn recover(&self) -> Result<Vec<page_id_t>, String> {
+        let mut file = self.file.lock().unwrap();
+        let mut data = Vec::new();
+        file.seek(SeekFrom::Start(0)).map_err(|err| format!("Error: seek error {}", err))?;
+        file.read_to_end(&mut data).map_err(|err| format!("Error: can not read double write file, err is {}", err))?;
+
+        let mut restored = Vec::new();
+        let mut buf = vec![0; self.store.get_page_size()];
+        // an entry cut off by the crash is torn, its page is not written yet
+        for entry in data.chunks_exact(self.entry_size()) {
+            let page_id = page_id_t::from_le_bytes(entry[..size_of::<page_id_t>()].try_into().unwrap());
+            let page_data = entry[size_of::<page_id_t>()..].to_vec();
+            if Page::verify_checksum(page_id, &page_data).is_err() {
+                continue;
+            }
+            // a page beyond the end of the file is not there yet, the log brings it back
+            match self.store.read_page(page_id, &mut buf) {
+                Err(err) if err.starts_with(PAGE_CORRUPTED) => {
+                    self.store.write_page(page_id, &page_data)?;
+                    restored.push(page_id);
+                },
+                _ => {},
+            }
+        }
+        self.store.sync()?;
+        file.set_len(0).map_err(|err| format!("Error: can not truncate double write file, err is {}", err))?;
+        Ok(restored)
+    }
+
+    // an entry of the side file, the page id and the page with its checksum
+    fn entry_size(&self) -> usize {
+        size_of::<page_id_t>() + self.store.get_page_size()
+    }
+
+    // the pages to be written are put in the side file and synced, with the file latch held till they are in the store
+    fn write_ahead(&self, file: &mut File, writes: &[(page_id_t, Vec<&Vec<u8>>)]) -> Result<(), String> {
+        let mut data = Vec::with_capacity(writes.iter().map(|(_, pages)| pages.len()).sum::<usize>() * self.entry_size());
+        for (page_id, pages_data) in writes.iter() {
+            for (i, page_data) in pages_data.iter().enumerate() {
+                let page_id = page_id + i as page_id_t;
+                let mut page_data = (*page_data).clone();
+                Page::set_checksum(page_id, &mut page_data);
+                data.extend_from_slice(&page_id.to_le_bytes());
+                data.extend_from_slice(&page_data);
+            }
+        }
+
+        file.set_len(0).map_err(|err| format!("Error: can not truncate double write file, err is {}", err))?;
+        file.seek(SeekFrom::Start(0)).map_err(|err| format!("Error: seek error {}", err))?;
+        file.write_all(&data).map_err(|err| format!("Error: can not write double write file, err is {}", err))?;
+        file.sync_data().map_err(|err| format!("Error: can not sync double write file, err is {}", err))
+    }
+}
+
+impl PageStore for DoubleWriteBuffer {
+    fn read_page(&self, page_id: page_id_t, buf: &mut Vec<u8>) -> Result<(), String> {
+        self.store.read_page(page_id, buf)
+    }
+
+    fn write_page(&self, page_id: page_id_t, page_data: &Vec<u8>) -> Result<(), String> {
+        self.write_pages(page_id, &[page_data])
+    }
+
+    fn read_pages(&self, page_id: page_id_t, bufs: &mut [&mut Vec<u8>]) -> Result<(), String> {
+        self.store.read_pages(page_id, bufs)
+    }
+
+    fn write_pages(&self, page_id: page_id_t, pages_data: &[&Vec<u8>]) -> Result<(), String> {
+        let mut file = self.file.lock().unwrap();
+        self.write_ahead(&mut file, &[(page_id, pages_data.to_vec())])?;
+        self.store.write_pages(page_id, pages_data)?;
+        self.store.sync()
+    }
+
+    // a batch only of reads does not touch the side file
+    fn submit(&self, ios: &mut [PageIo]) -> Vec<Result<(), String>> {
+        let writes: Vec<(page_id_t, Vec<&Vec<u8>>)> = ios.iter()
+            .filter_map(|io| match io {
+                PageIo::Write(page_id, pages_data) => Some((*page_id, pages_data.clone())),
+                PageIo::Read(..) => None,
+            })
+            .collect();
+        if writes.is_empty() {
+            return self.store.submit(ios);
+        }
+
+        let mut file = self.file.lock().unwrap();
+        if let Err(err) = self.write_ahead(&mut file, &writes) {
+            return ios.iter().map(|_| Err(err.clone())).collect();
+        }
+        let mut results = self.store.submit(ios);
+        if let Err(err) = self.store.sync() {
+            for (io, result) in ios.iter().zip(results.iter_mut()) {
+                if matches!(io, PageIo::Write(..)) && result.is_ok() {
+                    *result = Err(err.clone());
+                }
+            }
+        }
+        results
+    }
+
+    fn sync(&self) -> Result<(), String> {
+        self.store.sync()
+    }
+
+    fn get_filesize(&self) -> u64 {
+        self.store.get_filesize()
+    }
+
+    fn contains_page(&self, page_id: page_id_t) -> bool {
+        self.store.contains_page(page_id)
+    }
+
+    fn get_page_size(&self) -> usize {
+        self.store.get_page_size()
+    }
+}
+
+
+
+#[cfg(test)]
+mod tests {
+    use std::{fs::{self, OpenOptions}, os::unix::fs::FileExt, sync::Arc};
+
+    use crate::{common::config::{page_id_t, PAGE_SIZE}, storage::page_based::{disk::disk_manager::{DiskManager, PageStore}, page::page::{COMMON_PAGE_HEADER_SIZE, PAGE_CORRUPTED}}};
+
+    use super::DoubleWriteBuffer;
+
+    #[test]
+    fn test_torn_write() {
+        let (db, dwb_file) = ("test_dwb.db", "test_dwb.dwb");
+        let _ = fs::remove_file(db);
+        let _ = fs::remove_file(dwb_file);
+        assert_eq!(dwb_file, DoubleWriteBuffer::filename_of(db));
+
+        let pages: Vec<Vec<u8>> = (0..4).map(|i| {
+            let mut page = vec![0; PAGE_SIZE as usize];
+            page[COMMON_PAGE_HEADER_SIZE..].fill(i as u8 + 1);
+            page
+        }).collect();
+        let mut expected = vec![vec![0; PAGE_SIZE as usize]; 4];
+        {
+            let dwb = DoubleWriteBuffer::open(Arc::new(DiskManager::new(db).unwrap()), dwb_file).unwrap();
+            dwb.write_pages(0, &pages.iter().collect::<Vec<_>>()).unwrap();
+            for (i, page) in expected.iter_mut().enumerate() {
+                dwb.read_page(i as page_id_t, page).unwrap();
+            }
+        }
+
+        // the crash tore the write of page 2 after the first 2KiB, the side file still has the batch
+        let file = OpenOptions::new().write(true).open(db).unwrap();
+        file.write_all_at(&vec![0xab; PAGE_SIZE as usize / 2], 2 * PAGE_SIZE as u64 + PAGE_SIZE as u64 / 2).unwrap();
+        let disk = Arc::new(DiskManager::new(db).unwrap());
+        let mut buf = vec![0; PAGE_SIZE as usize];
+        assert_eq!(true, disk.read_page(2, &mut buf).unwrap_err().starts_with(PAGE_CORRUPTED));
+
+        let dwb = DoubleWriteBuffer::new(disk.clone(), dwb_file).unwrap();
+        assert_eq!(vec![2], dwb.recover().unwrap());
+        for (i, page) in expected.iter().enumerate() {
+            disk.read_page(i as page_id_t, &mut buf).unwrap();
+            assert_eq!(*page, buf);
+        }
+        // nothing is left to restore
+        assert_eq!(0, fs::metadata(dwb_file).unwrap().len());
+        assert_eq!(Vec::<page_id_t>::new(), dwb.recover().unwrap());
+
+        // a torn entry of the side file is skipped, its page was not written
+        dwb.write_page(1, &pages[3]).unwrap();
+        drop(dwb);
+        let file = OpenOptions::new().write(true).open(dwb_file).unwrap();
+        file.set_len(100).unwrap();
+        let dwb = DoubleWriteBuffer::open(disk.clone(), dwb_file).unwrap();
+        dwb.read_page(1, &mut buf).unwrap();
+        assert_eq!(pages[3][COMMON_PAGE_HEADER_SIZE..], buf[COMMON_PAGE_HEADER_SIZE..]);
+
+        let _ = fs::remove_file(db);
+        let _ = fs::remove_file(dwb_file);
+    }
+}
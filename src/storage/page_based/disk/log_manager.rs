@@ -1,12 +1,444 @@
+#![allow(warnings)]
+
+use std::{fs::{self, File, OpenOptions}, io::{Read, Seek, SeekFrom, Write}, path::Path, sync::{atomic::{AtomicBool, Ordering}, Arc, Condvar, Mutex, MutexGuard}, thread::{self, JoinHandle}, time::Duration};
+
+use crate::common::config::{lsn_t, INVALID_LSN, LOG_BUFFER_SIZE};
+
+use super::log_record::LogRecord;
+
+
 #[derive(Debug)]
-pub struct LogManager {
+struct LogManager_ {
+    // the records appended after the last flush
+    log_buffer: Vec<u8>,
+    next_lsn: lsn_t,
+    // the records up to it are on disk
+    persistent_lsn: lsn_t,
+    // someone waits for the buffered records to be on disk
+    flush_requested: bool,
+    // the buffer is taken by a flush which is writing without the latch
+    flushing: bool,
+    num_flushes: u64,
+    // a write of the log failed, the records after `persistent_lsn` may or may not be on disk,
+    // none is written from then on and the flushes waiting for them fail
+    error: Option<String>,
+}
 
+/// LogManager, the write-ahead log of the database kept in a companion `.log` file.
+/// The records are appended to the log buffer and given increasing lsns (starting from 1, a zeroed page has seen no change),
+/// the buffer is written out by the flush thread (see `LogFlusher`), so the transactions committing at the same time
+/// share one write and one fsync. Without the flush thread the one waiting writes the buffer itself.
+/// A log manager without a file logs nothing, the lsns are all invalid.
+/// A failed write is not retried, the flushes beyond the records on disk return its error, e.g. a commit fails then.
+#[derive(Debug)]
+pub struct LogManager {
+    log_filename: String,
+    log_file: Option<Mutex<File>>,
+    state: Mutex<LogManager_>,
+    // wakes up the flush thread
+    flush_cv: Condvar,
+    // wakes up the ones waiting for a flush
+    persist_cv: Condvar,
+    flusher_running: AtomicBool,
 }
 
 impl LogManager {
     pub fn new() -> Self {
+        Self::create(String::new(), None, 1)
+    }
+
+    // open the log, the records after a torn one at the end are cut off
+    pub fn open(log_filename: &str) -> Result<Self, String> {
+        let mut file = OpenOptions::new().read(true).write(true).create(true).open(log_filename)
+            .map_err(|err| format!("Error: can not open log file {}, err is {}", log_filename, err))?;
+
+        let (records, valid_len) = Self::read_records(&mut file)?;
+        file.set_len(valid_len as u64).map_err(|err| format!("Error: can not truncate log file, err is {}", err))?;
+        file.seek(SeekFrom::End(0)).map_err(|err| format!("Error: can not seek log file, err is {}", err))?;
+
+        let next_lsn = records.last().map(|record| record.lsn + 1).unwrap_or(1);
+        Ok(Self::create(log_filename.to_owned(), Some(file), next_lsn))
+    }
+
+    // the log file of a database file, e.g. test.db -> test.log
+    pub fn log_filename_of(db_filename: &str) -> String {
+        match db_filename.rfind(".") {
+            Some(pos) => format!("{}.log", &db_filename[..pos]),
+            None => format!("{}.log", db_filename),
+        }
+    }
+
+    fn create(log_filename: String, log_file: Option<File>, next_lsn: lsn_t) -> Self {
         Self {
-            
+            log_filename,
+            log_file: log_file.map(Mutex::new),
+            state: Mutex::new(LogManager_ {
+                log_buffer: Vec::new(),
+                next_lsn,
+                persistent_lsn: next_lsn - 1,
+                flush_requested: false,
+                flushing: false,
+                num_flushes: 0,
+                error: None,
+            }),
+            flush_cv: Condvar::new(),
+            persist_cv: Condvar::new(),
+            flusher_running: AtomicBool::new(false),
+        }
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.log_file.is_some()
+    }
+
+    // give the record an lsn and put it into the log buffer, returns the lsn.
+    // a full buffer is flushed first
+    pub fn append_log_record(&self, record: &mut LogRecord) -> lsn_t {
+        if !self.is_enabled() {
+            return INVALID_LSN;
+        }
+
+        let mut state = self.state.lock().unwrap();
+        record.lsn = state.next_lsn;
+        let bytes = record.serialize();
+        // after a failed write the records stay in the buffer, the flush of them fails
+        while !state.log_buffer.is_empty() && state.log_buffer.len() + bytes.len() > LOG_BUFFER_SIZE && state.error.is_none() {
+            let last_lsn = state.next_lsn - 1;
+            state = self.wait_flushed(state, last_lsn);
+        }
+
+        // the lsn is taken again, others may append while waiting
+        record.lsn = state.next_lsn;
+        state.next_lsn += 1;
+        state.log_buffer.extend(record.serialize());
+        record.lsn
+    }
+
+    // make sure the records up to `lsn` are on disk.
+    // a page may carry an lsn beyond the log, e.g. it is written without the log manager, all the records are flushed then
+    pub fn flush(&self, lsn: lsn_t) -> Result<(), String> {
+        if !self.is_enabled() || lsn == INVALID_LSN {
+            return Ok(());
+        }
+        let state = self.state.lock().unwrap();
+        let lsn = lsn.min(state.next_lsn - 1);
+        let state = self.wait_flushed(state, lsn);
+        match state.persistent_lsn < lsn {
+            true => Err(state.error.clone().unwrap()),
+            false => Ok(()),
+        }
+    }
+
+    pub fn flush_all(&self) -> Result<(), String> {
+        let lsn = self.get_next_lsn() - 1;
+        self.flush(lsn)
+    }
+
+    pub fn get_next_lsn(&self) -> lsn_t {
+        self.state.lock().unwrap().next_lsn
+    }
+
+    pub fn get_persistent_lsn(&self) -> lsn_t {
+        self.state.lock().unwrap().persistent_lsn
+    }
+
+    pub fn get_num_flushes(&self) -> u64 {
+        self.state.lock().unwrap().num_flushes
+    }
+
+    // all the records on disk, in the order of lsn
+    pub fn read_log(&self) -> Result<Vec<LogRecord>, String> {
+        let Some(log_file) = &self.log_file else {
+            return Ok(Vec::new());
+        };
+        let mut file = log_file.lock().unwrap();
+        let (records, _) = Self::read_records(&mut file)?;
+        file.seek(SeekFrom::End(0)).map_err(|err| format!("Error: can not seek log file, err is {}", err))?;
+        Ok(records)
+    }
+
+    // drop the records before `lsn` from the log, the recovery does not need them any more (see `CheckpointManager`).
+    // the rest is copied to a new file which then replaces the log, a crash in the middle leaves one of the two
+    pub fn truncate(&self, lsn: lsn_t) -> Result<(), String> {
+        let Some(log_file) = &self.log_file else {
+            return Ok(());
+        };
+        let mut file = log_file.lock().unwrap();
+        let mut bytes = Vec::new();
+        file.seek(SeekFrom::Start(0)).map_err(|err| format!("Error: can not seek log file, err is {}", err))?;
+        file.read_to_end(&mut bytes).map_err(|err| format!("Error: can not read log file, err is {}", err))?;
+
+        let mut offset = 0;
+        while offset < bytes.len() {
+            let Ok((record, size)) = LogRecord::deserialize(&bytes[offset..]) else {
+                break;
+            };
+            if record.lsn >= lsn {
+                break;
+            }
+            offset += size;
+        }
+
+        let tmp_filename = format!("{}.tmp", self.log_filename);
+        let mut tmp_file = File::create(&tmp_filename)
+            .map_err(|err| format!("Error: can not create log file {}, err is {}", tmp_filename, err))?;
+        tmp_file.write_all(&bytes[offset..]).and_then(|_| tmp_file.sync_data())
+            .map_err(|err| format!("Error: can not write log file {}, err is {}", tmp_filename, err))?;
+        fs::rename(&tmp_filename, &self.log_filename)
+            .map_err(|err| format!("Error: can not replace log file {}, err is {}", self.log_filename, err))?;
+        // the rename is on disk with the directory, until then a crash may bring the old file back
+        let dir = Path::new(&self.log_filename).parent().filter(|dir| !dir.as_os_str().is_empty()).unwrap_or(Path::new("."));
+        File::open(dir).and_then(|dir| dir.sync_all())
+            .map_err(|err| format!("Error: can not sync directory {}, err is {}", dir.display(), err))?;
+
+        *file = OpenOptions::new().read(true).write(true).open(&self.log_filename)
+            .map_err(|err| format!("Error: can not open log file {}, err is {}", self.log_filename, err))?;
+        file.seek(SeekFrom::End(0)).map_err(|err| format!("Error: can not seek log file, err is {}", err))?;
+        Ok(())
+    }
+
+    // returns the records and the length of the log they take, a torn record ends the log
+    fn read_records(file: &mut File) -> Result<(Vec<LogRecord>, usize), String> {
+        let mut bytes = Vec::new();
+        file.seek(SeekFrom::Start(0)).map_err(|err| format!("Error: can not seek log file, err is {}", err))?;
+        file.read_to_end(&mut bytes).map_err(|err| format!("Error: can not read log file, err is {}", err))?;
+
+        let mut records = Vec::new();
+        let mut offset = 0;
+        while offset < bytes.len() {
+            let Ok((record, size)) = LogRecord::deserialize(&bytes[offset..]) else {
+                break;
+            };
+            records.push(record);
+            offset += size;
+        }
+        Ok((records, offset))
+    }
+
+    // wait until the records up to `lsn` are on disk, the flush thread writes them if it is running.
+    // it gives up once a write fails
+    fn wait_flushed<'a>(&'a self, mut state: MutexGuard<'a, LogManager_>, lsn: lsn_t) -> MutexGuard<'a, LogManager_> {
+        while state.persistent_lsn < lsn && state.error.is_none() {
+            if state.flushing {
+                state = self.persist_cv.wait(state).unwrap();
+            } else if self.flusher_running.load(Ordering::SeqCst) {
+                state.flush_requested = true;
+                self.flush_cv.notify_one();
+                state = self.persist_cv.wait(state).unwrap();
+            } else {
+                state = self.write_buffer(state);
+            }
+        }
+        state
+    }
+
+    // write out the buffered records, the latch is released while writing so others can go on appending
+    fn write_buffer<'a>(&'a self, mut state: MutexGuard<'a, LogManager_>) -> MutexGuard<'a, LogManager_> {
+        if state.flushing || state.log_buffer.is_empty() || state.error.is_some() {
+            return state;
+        }
+        let buffer = std::mem::take(&mut state.log_buffer);
+        let last_lsn = state.next_lsn - 1;
+        state.flushing = true;
+        state.flush_requested = false;
+        drop(state);
+
+        let res = {
+            let mut file = self.log_file.as_ref().unwrap().lock().unwrap();
+            file.write_all(&buffer).and_then(|_| file.sync_data())
+        };
+
+        let mut state = self.state.lock().unwrap();
+        state.flushing = false;
+        match res {
+            Ok(_) => {
+                state.persistent_lsn = last_lsn;
+                state.num_flushes += 1;
+            },
+            // the log can not be lost silently, the records are kept for no one to write them
+            Err(err) => {
+                state.error = Some(format!("Error: can not write log file {}, err is {}", self.log_filename, err));
+                let mut rest = std::mem::replace(&mut state.log_buffer, buffer);
+                state.log_buffer.append(&mut rest);
+            },
+        }
+        self.persist_cv.notify_all();
+        state
+    }
+}
+
+impl Drop for LogManager {
+    fn drop(&mut self) {
+        if self.is_enabled() {
+            let state = self.state.lock().unwrap();
+            self.write_buffer(state);
+        }
+    }
+}
+
+
+/// The flush thread of the log manager, it writes out the log buffer once asked to, or every `interval`.
+/// Stops when dropped.
+#[derive(Debug)]
+pub struct LogFlusher {
+    log_mgr: Arc<LogManager>,
+    handler: Option<JoinHandle<()>>,
+    stop: Arc<AtomicBool>,
+}
+
+impl LogFlusher {
+    pub fn new(log_mgr: Arc<LogManager>, interval: Duration) -> Self {
+        let stop = Arc::new(AtomicBool::new(false));
+        log_mgr.flusher_running.store(true, Ordering::SeqCst);
+
+        let handler = {
+            let (log_mgr, stop) = (log_mgr.clone(), stop.clone());
+            thread::spawn(move || {
+                let mut state = log_mgr.state.lock().unwrap();
+                while !stop.load(Ordering::SeqCst) {
+                    if !state.flush_requested {
+                        state = log_mgr.flush_cv.wait_timeout(state, interval).unwrap().0;
+                    }
+                    state = log_mgr.write_buffer(state);
+                }
+                log_mgr.write_buffer(state);
+            })
+        };
+
+        Self { log_mgr, handler: Some(handler), stop }
+    }
+}
+
+impl Drop for LogFlusher {
+    fn drop(&mut self) {
+        {
+            // the flag is set under the latch, so the thread does not miss the notification
+            let _state = self.log_mgr.state.lock().unwrap();
+            self.stop.store(true, Ordering::SeqCst);
+            self.log_mgr.flusher_running.store(false, Ordering::SeqCst);
+            self.log_mgr.flush_cv.notify_all();
+        }
+        if let Some(handler) = self.handler.take() {
+            handler.join().unwrap();
+        }
+    }
+}
+
+
+
+#[cfg(test)]
+mod tests {
+    use std::{fs::{self, File, OpenOptions}, io::Write, sync::{Arc, Barrier}, thread, time::Duration};
+
+    use crate::{common::config::INVALID_LSN, storage::page_based::disk::log_record::{LogRecord, LogRecordBody}};
+
+    use super::{LogFlusher, LogManager};
+
+    fn append(log_mgr: &LogManager, txn_id: i32, body: LogRecordBody) -> i32 {
+        log_mgr.append_log_record(&mut LogRecord::new(txn_id, INVALID_LSN, body))
+    }
+
+    #[test]
+    fn append_flush_test() -> Result<(), String> {
+        let log_filename = "test_append_flush.log";
+        let _ = fs::remove_file(log_filename);
+        {
+            let log_mgr = LogManager::open(log_filename)?;
+            assert_eq!(1, append(&log_mgr, 0, LogRecordBody::Begin));
+            assert_eq!(2, append(&log_mgr, 0, LogRecordBody::NewPage { prev_page_id: 0, page_id: 1 }));
+            assert_eq!(0, log_mgr.get_persistent_lsn());
+            assert!(log_mgr.read_log()?.is_empty());
+
+            log_mgr.flush(2)?;
+            assert_eq!(2, log_mgr.get_persistent_lsn());
+            assert_eq!(3, append(&log_mgr, 0, LogRecordBody::Commit));
+            // the buffered records are flushed when it is dropped
+        }
+
+        // the lsn goes on after a reopen, the torn record at the end is cut off
+        OpenOptions::new().append(true).open(log_filename).unwrap().write_all(&[40, 0, 0, 0, 1]).unwrap();
+        let log_mgr = LogManager::open(log_filename)?;
+        let records = log_mgr.read_log()?;
+        assert_eq!(vec![1, 2, 3], records.iter().map(|record| record.lsn).collect::<Vec<_>>());
+        assert_eq!(LogRecordBody::Commit, records[2].body);
+        assert_eq!(4, append(&log_mgr, 1, LogRecordBody::Begin));
+        log_mgr.flush_all()?;
+        assert_eq!(4, log_mgr.read_log()?.len());
+
+        // no log without a file
+        let log_mgr = LogManager::new();
+        assert_eq!(INVALID_LSN, append(&log_mgr, 0, LogRecordBody::Begin));
+        log_mgr.flush(10)?;
+        Ok(())
+    }
+
+    #[test]
+    fn write_error_test() -> Result<(), String> {
+        let log_filename = "test_write_error.log";
+        fs::write(log_filename, []).unwrap();
+        // the writes to a file opened read only fail
+        let log_mgr = LogManager::create(log_filename.to_string(), Some(File::open(log_filename).unwrap()), 1);
+        let lsn = append(&log_mgr, 0, LogRecordBody::Commit);
+        assert_eq!(true, log_mgr.flush(lsn).is_err());
+        assert_eq!(0, log_mgr.get_persistent_lsn());
+
+        // no write is tried again, the flushes of the records not on disk fail
+        append(&log_mgr, 1, LogRecordBody::Begin);
+        assert_eq!(true, log_mgr.flush_all().is_err());
+        assert_eq!(true, log_mgr.flush(INVALID_LSN).is_ok());
+        Ok(())
+    }
+
+    #[test]
+    fn truncate_test() -> Result<(), String> {
+        let log_filename = "test_truncate.log";
+        let _ = fs::remove_file(log_filename);
+        let log_mgr = LogManager::open(log_filename)?;
+        for txn_id in 0..10 {
+            append(&log_mgr, txn_id, LogRecordBody::Begin);
         }
+        log_mgr.flush_all()?;
+
+        log_mgr.truncate(4)?;
+        assert_eq!((4..=10).collect::<Vec<_>>(), log_mgr.read_log()?.iter().map(|record| record.lsn).collect::<Vec<_>>());
+        // the records go on to the new file, the lsn goes on after a reopen
+        assert_eq!(11, append(&log_mgr, 10, LogRecordBody::Begin));
+        drop(log_mgr);
+        let log_mgr = LogManager::open(log_filename)?;
+        assert_eq!(8, log_mgr.read_log()?.len());
+        assert_eq!(12, log_mgr.get_next_lsn());
+        Ok(())
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn group_commit_test() -> Result<(), String> {
+        let log_filename = "test_group_commit.log";
+        let _ = fs::remove_file(log_filename);
+        let log_mgr = Arc::new(LogManager::open(log_filename)?);
+        let flusher = LogFlusher::new(log_mgr.clone(), Duration::from_secs(10));
+
+        // the commits waiting together are written by one flush
+        let num_threads = 8;
+        let barrier = Arc::new(Barrier::new(num_threads));
+        let handles = (0..num_threads).map(|txn_id| {
+            let (log_mgr, barrier) = (log_mgr.clone(), barrier.clone());
+            thread::spawn(move || {
+                let lsn = append(&log_mgr, txn_id as i32, LogRecordBody::Commit);
+                barrier.wait();
+                log_mgr.flush(lsn).unwrap();
+                assert!(log_mgr.get_persistent_lsn() >= lsn);
+            })
+        }).collect::<Vec<_>>();
+        for handle in handles {
+            handle.join().unwrap();
+        }
+        assert_eq!(1, log_mgr.get_num_flushes());
+        assert_eq!(num_threads, log_mgr.read_log()?.len());
+
+        // the flush thread writes out the rest when it stops
+        append(&log_mgr, 0, LogRecordBody::Abort);
+        drop(flusher);
+        assert_eq!(num_threads + 1, log_mgr.read_log()?.len());
+        Ok(())
+    }
+}
@@ -0,0 +1,335 @@
+#![allow(warnings)]
+
+use crate::{common::{config::{lsn_t, page_id_t, txn_id_t, INVALID_LSN}, rid::RID}, storage::page_based::table::tuple::{Tuple, TupleMeta}};
+
+
+// | size | lsn | txn_id | prev_lsn | type |, the body follows
+const LOG_HEADER_SIZE: usize = 4 * size_of::<i32>() + size_of::<u8>();
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum LogRecordBody {
+    Begin,
+    Commit,
+    Abort,
+    // a tuple is inserted at the rid
+    Insert { rid: RID, meta: TupleMeta, tuple: Tuple },
+    // the version at the rid is marked deleted
+    MarkDelete { rid: RID, old_meta: TupleMeta, new_meta: TupleMeta },
+    // the meta at the rid is changed otherwise, e.g. a deleted version is put back
+    Update { rid: RID, old_meta: TupleMeta, new_meta: TupleMeta },
+    // a table page is linked after `prev_page_id`, which is invalid for the first page of a table heap
+    NewPage { prev_page_id: page_id_t, page_id: page_id_t },
+    // the compensation of an undone change, it is redone but never undone,
+    // the undo goes on from `undo_next_lsn`
+    Clr { rid: RID, old_meta: TupleMeta, new_meta: TupleMeta, undo_next_lsn: lsn_t },
+    // a fuzzy checkpoint, the tables are taken after the begin record is written,
+    // the active transactions with their last lsns and the dirty pages with their recLSNs,
+    // and the next txn id, the records of the transactions before it may be truncated
+    BeginCheckpoint,
+    EndCheckpoint { active_txns: Vec<(txn_id_t, lsn_t)>, dirty_pages: Vec<(page_id_t, lsn_t)>, next_txn_id: txn_id_t },
+    // the page is taken or given back in the free space map, it changes the map page of the page
+    AllocatePage { page_id: page_id_t },
+    FreePage { page_id: page_id_t },
+    // the dead tuples of the slots are removed from the table page and the page is compacted, see `TablePage::vacuum`
+    Vacuum { page_id: page_id_t, slots: Vec<u16> },
+    // the tuple at the rid is replaced and keeps its meta, see `TablePage::update_tuple`.
+    // the two are redone but never undone, the version is undone by the undo of its insert
+    UpdateTuple { rid: RID, tuple: Tuple },
+    // the tuple of the slot `rid` is moved from `from` to `to`, the slot forwards to the new place
+    // and the copy at `from` is removed, unless it is the slot itself
+    MoveTuple { rid: RID, from: RID, to: RID, tuple: Tuple },
+}
+
+/// A record of the write-ahead log.
+/// The records of a transaction are chained by `prev_lsn`, the first one (BEGIN) has an invalid one.
+#[derive(Debug, Clone, PartialEq)]
+pub struct LogRecord {
+    pub lsn: lsn_t,
+    pub txn_id: txn_id_t,
+    pub prev_lsn: lsn_t,
+    pub body: LogRecordBody,
+}
+
+impl LogRecord {
+    // the lsn is given by the log manager when the record is appended
+    pub fn new(txn_id: txn_id_t, prev_lsn: lsn_t, body: LogRecordBody) -> Self {
+        Self { lsn: INVALID_LSN, txn_id, prev_lsn, body }
+    }
+
+    fn get_type_id(&self) -> u8 {
+        match &self.body {
+            LogRecordBody::Begin => 0,
+            LogRecordBody::Commit => 1,
+            LogRecordBody::Abort => 2,
+            LogRecordBody::Insert { .. } => 3,
+            LogRecordBody::MarkDelete { .. } => 4,
+            LogRecordBody::Update { .. } => 5,
+            LogRecordBody::NewPage { .. } => 6,
+            LogRecordBody::Clr { .. } => 7,
+            LogRecordBody::BeginCheckpoint => 8,
+            LogRecordBody::EndCheckpoint { .. } => 9,
+            LogRecordBody::AllocatePage { .. } => 10,
+            LogRecordBody::FreePage { .. } => 11,
+            LogRecordBody::Vacuum { .. } => 12,
+            LogRecordBody::UpdateTuple { .. } => 13,
+            LogRecordBody::MoveTuple { .. } => 14,
+        }
+    }
+
+    pub fn serialize(&self) -> Vec<u8> {
+        let mut body = Vec::new();
+        match &self.body {
+            LogRecordBody::Begin | LogRecordBody::Commit | LogRecordBody::Abort | LogRecordBody::BeginCheckpoint => {},
+            LogRecordBody::Insert { rid, meta, tuple } => {
+                put_rid(&mut body, rid);
+                put_meta(&mut body, meta);
+                put_tuple(&mut body, tuple);
+            },
+            LogRecordBody::MarkDelete { rid, old_meta, new_meta } | LogRecordBody::Update { rid, old_meta, new_meta } => {
+                put_rid(&mut body, rid);
+                put_meta(&mut body, old_meta);
+                put_meta(&mut body, new_meta);
+            },
+            LogRecordBody::NewPage { prev_page_id, page_id } => {
+                body.extend(prev_page_id.to_ne_bytes());
+                body.extend(page_id.to_ne_bytes());
+            },
+            LogRecordBody::Clr { rid, old_meta, new_meta, undo_next_lsn } => {
+                put_rid(&mut body, rid);
+                put_meta(&mut body, old_meta);
+                put_meta(&mut body, new_meta);
+                body.extend(undo_next_lsn.to_ne_bytes());
+            },
+            LogRecordBody::EndCheckpoint { active_txns, dirty_pages, next_txn_id } => {
+                put_pairs(&mut body, active_txns);
+                put_dirty_pages(&mut body, dirty_pages);
+                body.extend(next_txn_id.to_ne_bytes());
+            },
+            LogRecordBody::AllocatePage { page_id } | LogRecordBody::FreePage { page_id } => {
+                body.extend(page_id.to_ne_bytes());
+            },
+            LogRecordBody::Vacuum { page_id, slots } => {
+                body.extend(page_id.to_ne_bytes());
+                body.extend((slots.len() as u32).to_ne_bytes());
+                for sid in slots {
+                    body.extend(sid.to_ne_bytes());
+                }
+            },
+            LogRecordBody::UpdateTuple { rid, tuple } => {
+                put_rid(&mut body, rid);
+                put_tuple(&mut body, tuple);
+            },
+            LogRecordBody::MoveTuple { rid, from, to, tuple } => {
+                put_rid(&mut body, rid);
+                put_rid(&mut body, from);
+                put_rid(&mut body, to);
+                put_tuple(&mut body, tuple);
+            },
+        }
+
+        let size = LOG_HEADER_SIZE + body.len();
+        let mut buf = Vec::with_capacity(size);
+        buf.extend((size as u32).to_ne_bytes());
+        buf.extend(self.lsn.to_ne_bytes());
+        buf.extend(self.txn_id.to_ne_bytes());
+        buf.extend(self.prev_lsn.to_ne_bytes());
+        buf.push(self.get_type_id());
+        buf.extend(body);
+        buf
+    }
+
+    // read the record at the start of `bytes`, returns it and its size.
+    // a record cut off at the end of the log is an error
+    pub fn deserialize(bytes: &[u8]) -> Result<(Self, usize), String> {
+        let mut reader = Reader { bytes, pos: 0 };
+        let size = reader.get_u32()? as usize;
+        if size < LOG_HEADER_SIZE || size > bytes.len() {
+            return Err(format!("log record of size {} is incomplete", size));
+        }
+        let mut reader = Reader { bytes: &bytes[..size], pos: reader.pos };
+        let lsn = reader.get_i32()?;
+        let txn_id = reader.get_i32()?;
+        let prev_lsn = reader.get_i32()?;
+        let body = match reader.get_u8()? {
+            0 => LogRecordBody::Begin,
+            1 => LogRecordBody::Commit,
+            2 => LogRecordBody::Abort,
+            3 => LogRecordBody::Insert { rid: reader.get_rid()?, meta: reader.get_meta()?, tuple: reader.get_tuple()? },
+            4 => LogRecordBody::MarkDelete { rid: reader.get_rid()?, old_meta: reader.get_meta()?, new_meta: reader.get_meta()? },
+            5 => LogRecordBody::Update { rid: reader.get_rid()?, old_meta: reader.get_meta()?, new_meta: reader.get_meta()? },
+            6 => LogRecordBody::NewPage { prev_page_id: reader.get_page_id()?, page_id: reader.get_page_id()? },
+            7 => LogRecordBody::Clr {
+                rid: reader.get_rid()?,
+                old_meta: reader.get_meta()?,
+                new_meta: reader.get_meta()?,
+                undo_next_lsn: reader.get_i32()?,
+            },
+            8 => LogRecordBody::BeginCheckpoint,
+            9 => LogRecordBody::EndCheckpoint { active_txns: reader.get_pairs()?, dirty_pages: reader.get_dirty_pages()?, next_txn_id: reader.get_i32()? },
+            10 => LogRecordBody::AllocatePage { page_id: reader.get_page_id()? },
+            11 => LogRecordBody::FreePage { page_id: reader.get_page_id()? },
+            12 => {
+                let page_id = reader.get_page_id()?;
+                let len = reader.get_u32()?;
+                let slots = (0..len).map(|_| reader.get_u16()).collect::<Result<Vec<u16>, String>>()?;
+                LogRecordBody::Vacuum { page_id, slots }
+            },
+            13 => LogRecordBody::UpdateTuple { rid: reader.get_rid()?, tuple: reader.get_tuple()? },
+            14 => LogRecordBody::MoveTuple {
+                rid: reader.get_rid()?,
+                from: reader.get_rid()?,
+                to: reader.get_rid()?,
+                tuple: reader.get_tuple()?,
+            },
+            type_id => {
+                return Err(format!("unknown log record type {}", type_id));
+            }
+        };
+        Ok((Self { lsn, txn_id, prev_lsn, body }, size))
+    }
+}
+
+fn put_rid(buf: &mut Vec<u8>, rid: &RID) {
+    buf.extend(rid.pid.to_ne_bytes());
+    buf.extend(rid.sid.to_ne_bytes());
+}
+
+fn put_tuple(buf: &mut Vec<u8>, tuple: &Tuple) {
+    buf.extend((tuple.get_length() as u32).to_ne_bytes());
+    buf.extend(tuple.get_data());
+}
+
+fn put_pairs(buf: &mut Vec<u8>, pairs: &Vec<(i32, i32)>) {
+    buf.extend((pairs.len() as u32).to_ne_bytes());
+    for (first, second) in pairs {
+        buf.extend(first.to_ne_bytes());
+        buf.extend(second.to_ne_bytes());
+    }
+}
+
+fn put_dirty_pages(buf: &mut Vec<u8>, pages: &Vec<(page_id_t, lsn_t)>) {
+    buf.extend((pages.len() as u32).to_ne_bytes());
+    for (page_id, rec_lsn) in pages {
+        buf.extend(page_id.to_ne_bytes());
+        buf.extend(rec_lsn.to_ne_bytes());
+    }
+}
+
+fn put_meta(buf: &mut Vec<u8>, meta: &TupleMeta) {
+    buf.extend(meta.insert_txn_id.to_ne_bytes());
+    buf.extend(meta.delete_txn_id.to_ne_bytes());
+    buf.push(meta.is_deleted as u8);
+}
+
+struct Reader<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn get_bytes(&mut self, len: usize) -> Result<&'a [u8], String> {
+        if self.pos + len > self.bytes.len() {
+            return Err(format!("log record is incomplete"));
+        }
+        let res = &self.bytes[self.pos..self.pos + len];
+        self.pos += len;
+        Ok(res)
+    }
+
+    fn get_u8(&mut self) -> Result<u8, String> {
+        Ok(self.get_bytes(1)?[0])
+    }
+
+    fn get_u16(&mut self) -> Result<u16, String> {
+        Ok(u16::from_ne_bytes(self.get_bytes(2)?.try_into().unwrap()))
+    }
+
+    fn get_u32(&mut self) -> Result<u32, String> {
+        Ok(u32::from_ne_bytes(self.get_bytes(4)?.try_into().unwrap()))
+    }
+
+    fn get_i32(&mut self) -> Result<i32, String> {
+        Ok(i32::from_ne_bytes(self.get_bytes(4)?.try_into().unwrap()))
+    }
+
+    fn get_page_id(&mut self) -> Result<page_id_t, String> {
+        Ok(page_id_t::from_ne_bytes(self.get_bytes(size_of::<page_id_t>())?.try_into().unwrap()))
+    }
+
+    fn get_rid(&mut self) -> Result<RID, String> {
+        Ok(RID { pid: self.get_page_id()?, sid: self.get_u16()? })
+    }
+
+    fn get_meta(&mut self) -> Result<TupleMeta, String> {
+        Ok(TupleMeta::new(self.get_i32()?, self.get_i32()?, self.get_u8()? != 0))
+    }
+
+    fn get_tuple(&mut self) -> Result<Tuple, String> {
+        let len = self.get_u32()? as usize;
+        Tuple::deserialize(&self.get_bytes(len)?.to_vec())
+    }
+
+    fn get_pairs(&mut self) -> Result<Vec<(i32, i32)>, String> {
+        let len = self.get_u32()?;
+        (0..len).map(|_| Ok((self.get_i32()?, self.get_i32()?))).collect()
+    }
+
+    fn get_dirty_pages(&mut self) -> Result<Vec<(page_id_t, lsn_t)>, String> {
+        let len = self.get_u32()?;
+        (0..len).map(|_| Ok((self.get_page_id()?, self.get_i32()?))).collect()
+    }
+}
+
+
+
+#[cfg(test)]
+mod tests {
+    use crate::{common::{config::INVALID_LSN, rid::RID}, storage::page_based::table::tuple::{Tuple, TupleMeta}};
+
+    use super::{LogRecord, LogRecordBody};
+
+    #[test]
+    fn serialize_test() -> Result<(), String> {
+        let rid = RID { pid: 3, sid: 7 };
+        let (old_meta, new_meta) = (TupleMeta::new(1, -1, false), TupleMeta::new(1, 2, true));
+        let bodies = vec![
+            LogRecordBody::Begin,
+            LogRecordBody::Commit,
+            LogRecordBody::Abort,
+            LogRecordBody::Insert { rid, meta: old_meta.clone(), tuple: Tuple::deserialize(&vec![1, 2, 3, 4, 5])? },
+            LogRecordBody::MarkDelete { rid, old_meta: old_meta.clone(), new_meta: new_meta.clone() },
+            LogRecordBody::Update { rid, old_meta: new_meta.clone(), new_meta: old_meta.clone() },
+            LogRecordBody::NewPage { prev_page_id: 3, page_id: 4 },
+            LogRecordBody::Clr { rid, old_meta, new_meta, undo_next_lsn: 5 },
+            LogRecordBody::BeginCheckpoint,
+            LogRecordBody::EndCheckpoint { active_txns: vec![(2, 9)], dirty_pages: vec![(3, 1), (4, 7)], next_txn_id: 3 },
+            LogRecordBody::AllocatePage { page_id: 5 },
+            LogRecordBody::FreePage { page_id: 5 },
+            LogRecordBody::Vacuum { page_id: 3, slots: vec![0, 7] },
+            LogRecordBody::UpdateTuple { rid, tuple: Tuple::deserialize(&vec![6, 7])? },
+            LogRecordBody::MoveTuple { rid, from: RID { pid: 4, sid: 1 }, to: RID { pid: 5, sid: 0 }, tuple: Tuple::deserialize(&vec![8; 20])? },
+        ];
+
+        let mut bytes = Vec::new();
+        let mut records = Vec::new();
+        for (lsn, body) in bodies.into_iter().enumerate() {
+            let mut record = LogRecord::new(2, INVALID_LSN, body);
+            record.lsn = lsn as i32 + 1;
+            bytes.extend(record.serialize());
+            records.push(record);
+        }
+
+        let mut offset = 0;
+        for record in &records {
+            let (read, size) = LogRecord::deserialize(&bytes[offset..])?;
+            assert_eq!(*record, read);
+            offset += size;
+        }
+        assert_eq!(bytes.len(), offset);
+
+        // a torn record
+        let last_size = records.last().unwrap().serialize().len();
+        assert!(LogRecord::deserialize(&bytes[bytes.len() - last_size..bytes.len() - 1]).is_err());
+        Ok(())
+    }
+}
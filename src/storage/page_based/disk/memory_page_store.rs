@@ -0,0 +1,118 @@
+#![allow(warnings)]
+
+use std::sync::RwLock;
+
+use crate::{common::config::{page_id_t, PAGE_SIZE}, storage::page_based::page::page::Page};
+
+use super::disk_manager::{copy_checked, PageStore};
+
+
+/// MemoryPageStore, the pages are kept in memory and gone with it.
+/// It behaves like a file, the pages written beyond the end grow it, and the ones in between are zeroed,
+/// the pages are kept with their checksums as on disk.
+/// For the tests and the scratch databases, nothing is shared between two of them
+#[derive(Debug)]
+pub struct MemoryPageStore {
+    pages: RwLock<Vec<Vec<u8>>>,
+    page_size: usize,
+}
+
+impl MemoryPageStore {
+    pub fn new() -> Self {
+        Self::new_with_page_size(PAGE_SIZE as usize)
+    }
+
+    pub fn new_with_page_size(page_size: usize) -> Self {
+        Self { pages: RwLock::new(Vec::new()), page_size }
+    }
+
+    pub fn num_pages(&self) -> usize {
+        self.pages.read().unwrap().len()
+    }
+}
+
+impl PageStore for MemoryPageStore {
+    fn read_page(&self, page_id: page_id_t, buf: &mut Vec<u8>) -> Result<(), String> {
+        assert!(buf.len() == self.page_size);
+        let pages = self.pages.read().unwrap();
+        match pages.get(page_id as usize) {
+            Some(page_data) if page_id >= 0 => {
+                Page::verify_checksum(page_id, page_data)?;
+                buf.copy_from_slice(page_data);
+                Ok(())
+            },
+            _ => Err(format!("Error: invalid page size")),
+        }
+    }
+
+    fn write_page(&self, page_id: page_id_t, page_data: &Vec<u8>) -> Result<(), String> {
+        self.write_pages(page_id, &[page_data])
+    }
+
+    // the pages beyond the end are left as they are
+    fn read_pages(&self, page_id: page_id_t, bufs: &mut [&mut Vec<u8>]) -> Result<(), String> {
+        let pages = self.pages.read().unwrap();
+        if page_id < 0 || page_id as usize >= pages.len() {
+            return Err(format!("Error: invalid page size"));
+        }
+        let end = (page_id as usize + bufs.len()).min(pages.len());
+        copy_checked(page_id, &pages[page_id as usize..end].concat(), bufs, self.page_size)
+    }
+
+    fn write_pages(&self, page_id: page_id_t, pages_data: &[&Vec<u8>]) -> Result<(), String> {
+        if page_id < 0 {
+            return Err(format!("Error: invalid page id {}", page_id));
+        }
+        let mut pages = self.pages.write().unwrap();
+        let end = page_id as usize + pages_data.len();
+        if pages.len() < end {
+            pages.resize(end, vec![0; self.page_size]);
+        }
+        for (i, page_data) in pages_data.iter().enumerate() {
+            assert!(page_data.len() == self.page_size);
+            pages[page_id as usize + i].copy_from_slice(page_data);
+            Page::set_checksum(page_id + i as page_id_t, &mut pages[page_id as usize + i]);
+        }
+        Ok(())
+    }
+
+    fn get_filesize(&self) -> u64 {
+        (self.num_pages() * self.page_size) as u64
+    }
+
+    fn get_page_size(&self) -> usize {
+        self.page_size
+    }
+}
+
+
+
+#[cfg(test)]
+mod tests {
+    use crate::{common::config::PAGE_SIZE, storage::page_based::{disk::disk_manager::PageStore, page::page::Page}};
+    use super::MemoryPageStore;
+
+    #[test]
+    fn test_memory_read_write() {
+        let store = MemoryPageStore::new();
+        let mut buf = vec![0; PAGE_SIZE as usize];
+        assert_eq!(true, store.read_page(0, &mut buf).is_err());
+
+        // page 1 and 2 are zeroed, like the hole of a file
+        let mut page = vec![7; PAGE_SIZE as usize];
+        store.write_page(3, &page).unwrap();
+        Page::set_checksum(3, &mut page);
+        assert_eq!(4 * PAGE_SIZE as u64, store.get_filesize());
+        store.read_page(3, &mut buf).unwrap();
+        assert_eq!(page, buf);
+        store.read_page(1, &mut buf).unwrap();
+        assert_eq!(vec![0; PAGE_SIZE as usize], buf);
+
+        let mut bufs: Vec<Vec<u8>> = (0..3).map(|_| vec![1; PAGE_SIZE as usize]).collect();
+        store.read_pages(2, &mut bufs.iter_mut().collect::<Vec<_>>()).unwrap();
+        assert_eq!(vec![0; PAGE_SIZE as usize], bufs[0]);
+        assert_eq!(page, bufs[1]);
+        assert_eq!(vec![1; PAGE_SIZE as usize], bufs[2]);
+        assert_eq!(true, store.read_pages(4, &mut bufs.iter_mut().collect::<Vec<_>>()).is_err());
+    }
+}
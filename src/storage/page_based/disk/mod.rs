@@ -1,3 +1,8 @@
 pub mod disk_manager;
+pub mod positional_disk_manager;
+pub mod memory_page_store;
+pub mod double_write_buffer;
 pub mod log_manager;
-pub mod disk_scheduler;
\ No newline at end of file
+pub mod log_record;
+pub mod disk_scheduler;
+pub mod tablespace;
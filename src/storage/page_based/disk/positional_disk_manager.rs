@@ -0,0 +1,404 @@
+#![allow(warnings)]
+
+use std::{alloc::{self, Layout}, fmt, fs::{File, OpenOptions}, os::unix::fs::{FileExt, OpenOptionsExt}, sync::{atomic::{AtomicBool, Ordering}, Mutex}};
+
+#[cfg(target_os = "linux")]
+use std::os::unix::io::AsRawFd;
+
+#[cfg(target_os = "linux")]
+use io_uring::{opcode, squeue, types, IoUring};
+
+use crate::{common::config::{page_id_t, DIRECT_IO_ALIGNMENT, IO_URING_ENTRIES}, storage::page_based::page::page::{Page, PAGE_CORRUPTED}};
+
+use super::disk_manager::{copy_checked, set_checksums, PageIo, PageStore};
+
+
+/// a zeroed buffer aligned for O_DIRECT, the Vec<u8> of a page is not
+struct AlignedBuf {
+    ptr: *mut u8,
+    layout: Layout,
+}
+
+impl AlignedBuf {
+    fn new(len: usize) -> Self {
+        let layout = Layout::from_size_align(len.max(DIRECT_IO_ALIGNMENT), DIRECT_IO_ALIGNMENT).unwrap();
+        let ptr = unsafe { alloc::alloc_zeroed(layout) };
+        if ptr.is_null() {
+            alloc::handle_alloc_error(layout);
+        }
+        Self { ptr, layout }
+    }
+
+    fn as_slice(&self) -> &[u8] {
+        unsafe { std::slice::from_raw_parts(self.ptr, self.layout.size()) }
+    }
+
+    fn as_mut_slice(&mut self) -> &mut [u8] {
+        unsafe { std::slice::from_raw_parts_mut(self.ptr, self.layout.size()) }
+    }
+}
+
+impl Drop for AlignedBuf {
+    fn drop(&mut self) {
+        unsafe { alloc::dealloc(self.ptr, self.layout) };
+    }
+}
+
+/// PositionalDiskManager, reads and writes the pages with pread/pwrite at their offsets,
+/// so the i/o of different pages does not wait for a lock on the file as with DiskManager.
+/// With direct the file is opened with O_DIRECT and the pages go through aligned buffers.
+/// With a ring the batches of the scheduler are submitted to an io_uring at once (linux only)
+pub struct PositionalDiskManager {
+    filename: String,
+    file: File,
+    direct: bool,
+    page_size: usize,
+    #[cfg(target_os = "linux")]
+    ring: Option<Mutex<IoUring>>,
+    // set when the ring failed with i/o in flight, the ios go through pread/pwrite from then on
+    ring_failed: AtomicBool,
+}
+
+impl PositionalDiskManager {
+    pub fn new(filename: &str, direct: bool, page_size: usize) -> Result<Self, String> {
+        let file = Self::open_file(filename, direct)?;
+        Ok(Self {
+            filename: filename.to_owned(),
+            file,
+            direct,
+            page_size,
+            #[cfg(target_os = "linux")]
+            ring: None,
+            ring_failed: AtomicBool::new(false),
+        })
+    }
+
+    // fails where io_uring is not supported or not allowed
+    pub fn with_io_uring(filename: &str, direct: bool, page_size: usize) -> Result<Self, String> {
+        #[cfg(target_os = "linux")]
+        {
+            let mut store = Self::new(filename, direct, page_size)?;
+            let ring = IoUring::new(IO_URING_ENTRIES)
+                .map_err(|err| format!("Error: can not set up an io_uring, err is {}", err))?;
+            store.ring = Some(Mutex::new(ring));
+            return Ok(store);
+        }
+        #[cfg(not(target_os = "linux"))]
+        Err(format!("Error: io_uring is only supported on linux"))
+    }
+
+    fn open_file(filename: &str, direct: bool) -> Result<File, String> {
+        if filename.rfind(".").is_none() {
+            return Err(format!("Error: invalid find name, must surround with a suffix"));
+        }
+
+        let mut options = OpenOptions::new();
+        options.read(true).write(true).create(true);
+        if direct {
+            #[cfg(target_os = "linux")]
+            options.custom_flags(libc::O_DIRECT);
+            #[cfg(not(target_os = "linux"))]
+            return Err(format!("Error: O_DIRECT is only supported on linux"));
+        }
+        options.open(filename)
+            .map_err(|err| format!("Error: can not open a file {}, err is {}", filename, err))
+    }
+
+    pub fn is_direct(&self) -> bool {
+        self.direct
+    }
+
+    pub fn uses_io_uring(&self) -> bool {
+        #[cfg(target_os = "linux")]
+        return self.ring.is_some() && !self.ring_failed.load(Ordering::SeqCst);
+        #[cfg(not(target_os = "linux"))]
+        false
+    }
+
+    fn offset_of(&self, page_id: page_id_t) -> u64 {
+        page_id as u64 * self.page_size as u64
+    }
+
+    // reads as much as there is up to the end of the file, the bytes read are returned
+    fn read_at(&self, offset: u64, buf: &mut [u8]) -> Result<usize, String> {
+        let mut read_size = 0;
+        while read_size < buf.len() {
+            match self.file.read_at(&mut buf[read_size..], offset + read_size as u64) {
+                Ok(0) => break,
+                Ok(size) => read_size += size,
+                Err(err) if err.kind() == std::io::ErrorKind::Interrupted => continue,
+                Err(err) => return Err(format!("Error: read_file error, error is {}", err)),
+            }
+        }
+        Ok(read_size)
+    }
+
+    fn write_at(&self, offset: u64, data: &[u8]) -> Result<(), String> {
+        self.file.write_all_at(data, offset).map_err(|err| format!("Error: write error {}", err))
+    }
+
+    // the pages from page_id on, into one buffer, aligned when the file is opened with O_DIRECT
+    fn read_run(&self, page_id: page_id_t, bufs: &mut [&mut Vec<u8>]) -> Result<(), String> {
+        let offset = self.offset_of(page_id);
+        if offset >= self.get_filesize() as u64 {
+            return Err(format!("Error: invalid page size"));
+        }
+
+        let len = bufs.len() * self.page_size;
+        let mut data = AlignedBuf::new(len);
+        let read_size = self.read_at(offset, &mut data.as_mut_slice()[..len])?;
+        copy_checked(page_id, &data.as_slice()[..read_size], bufs, self.page_size)
+    }
+
+    fn write_run(&self, page_id: page_id_t, pages_data: &[&Vec<u8>]) -> Result<(), String> {
+        let data = self.copy_in(page_id, pages_data);
+        self.write_at(self.offset_of(page_id), &data.as_slice()[..pages_data.len() * self.page_size])
+    }
+
+    // the pages to be written, with their checksums
+    fn copy_in(&self, page_id: page_id_t, pages_data: &[&Vec<u8>]) -> AlignedBuf {
+        let len = pages_data.len() * self.page_size;
+        let mut data = AlignedBuf::new(len);
+        for (chunk, page_data) in data.as_mut_slice().chunks_mut(self.page_size).zip(pages_data.iter()) {
+            assert!(page_data.len() == self.page_size);
+            chunk.copy_from_slice(page_data);
+        }
+        set_checksums(page_id, &mut data.as_mut_slice()[..len], self.page_size);
+        data
+    }
+
+    // one sqe for each run, the runs are pushed and waited for with one call at a time.
+    // a run of a page of the run before it is drained, so the order of the i/o of a page is kept
+    #[cfg(target_os = "linux")]
+    fn submit_to_ring(&self, ring: &Mutex<IoUring>, ios: &mut [PageIo]) -> Vec<Result<(), String>> {
+        let filesize = self.get_filesize() as u64;
+        let mut ring = ring.lock().unwrap();
+        let mut results = Vec::with_capacity(ios.len());
+
+        for chunk in ios.chunks_mut(IO_URING_ENTRIES as usize) {
+            // the ring was given up, nothing more goes through it
+            if self.ring_failed.load(Ordering::SeqCst) {
+                results.extend(chunk.iter().map(|_| Err(format!("Error: the io_uring has failed"))));
+                continue;
+            }
+
+            let mut datas: Vec<AlignedBuf> = Vec::with_capacity(chunk.len());
+            let mut last_page_id = None;
+            for (i, io) in chunk.iter().enumerate() {
+                let (page_id, num_pages) = (io.page_id(), io.num_pages());
+                let len = num_pages * self.page_size;
+                let mut data = match io {
+                    PageIo::Read(..) => AlignedBuf::new(len),
+                    PageIo::Write(page_id, pages_data) => self.copy_in(*page_id, pages_data),
+                };
+                let fd = types::Fd(self.file.as_raw_fd());
+                let offset = self.offset_of(page_id);
+                let mut entry = match io {
+                    PageIo::Read(..) => opcode::Read::new(fd, data.as_mut_slice().as_mut_ptr(), len as u32).offset(offset).build(),
+                    PageIo::Write(..) => opcode::Write::new(fd, data.as_slice().as_ptr(), len as u32).offset(offset).build(),
+                }.user_data(i as u64);
+                if last_page_id.map_or(false, |last| last >= page_id) {
+                    entry = entry.flags(squeue::Flags::IO_DRAIN);
+                }
+                last_page_id = Some(page_id + num_pages as page_id_t - 1);
+
+                // the queue holds a whole chunk
+                unsafe { ring.submission().push(&entry).unwrap() };
+                datas.push(data);
+            }
+
+            // the buffers are not touched before every sqe of the chunk is completed.
+            // when the ring can not tell it any more the kernel may still use them, they are leaked
+            // with the ring, and the ios are not done again as a write in flight may land after it
+            let mut res_of = vec![-1; chunk.len()];
+            if let Err(err) = Self::wait_all(&mut ring, &mut res_of) {
+                self.ring_failed.store(true, Ordering::SeqCst);
+                std::mem::forget(datas);
+                results.extend(chunk.iter().map(|_| Err(err.clone())));
+                continue;
+            }
+
+            // a read may stop at the end of the file, any other short or failed one is done again with pread/pwrite
+            for ((io, data), res) in chunk.iter_mut().zip(datas.iter()).zip(res_of.into_iter()) {
+                let len = io.num_pages() * self.page_size;
+                results.push(match io {
+                    PageIo::Read(page_id, bufs) if res > 0 && (res as usize == len || self.offset_of(*page_id) + res as u64 >= filesize) => {
+                        copy_checked(*page_id, &data.as_slice()[..res as usize], bufs, self.page_size)
+                    },
+                    PageIo::Read(page_id, bufs) => self.read_run(*page_id, bufs),
+                    PageIo::Write(..) if res as usize == len => Ok(()),
+                    PageIo::Write(page_id, pages_data) => self.write_run(*page_id, pages_data),
+                });
+            }
+        }
+        results
+    }
+
+    // submits what is pushed and waits until there is a cqe for each of res_of
+    #[cfg(target_os = "linux")]
+    fn wait_all(ring: &mut IoUring, res_of: &mut [i32]) -> Result<(), String> {
+        let mut num_done = 0;
+        while num_done < res_of.len() {
+            match ring.submit_and_wait(res_of.len() - num_done) {
+                Ok(_) => {},
+                // interrupted, or the completion queue is to be reaped first
+                Err(err) if err.kind() == std::io::ErrorKind::Interrupted || err.raw_os_error() == Some(libc::EBUSY) => {},
+                Err(err) => return Err(format!("Error: io_uring submit error {}", err)),
+            }
+            for cqe in ring.completion() {
+                res_of[cqe.user_data() as usize] = cqe.result();
+                num_done += 1;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Debug for PositionalDiskManager {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("PositionalDiskManager")
+            .field("filename", &self.filename)
+            .field("direct", &self.direct)
+            .field("page_size", &self.page_size)
+            .field("io_uring", &self.uses_io_uring())
+            .finish()
+    }
+}
+
+impl PageStore for PositionalDiskManager {
+    fn read_page(&self, page_id: page_id_t, buf: &mut Vec<u8>) -> Result<(), String> {
+        assert!(buf.len() == self.page_size);
+        let offset = self.offset_of(page_id);
+        if offset >= self.get_filesize() as u64 {
+            return Err(format!("Error: invalid page size"));
+        }
+
+        let read_size = if self.direct {
+            let mut data = AlignedBuf::new(self.page_size);
+            let read_size = self.read_at(offset, data.as_mut_slice())?;
+            buf[..read_size].copy_from_slice(&data.as_slice()[..read_size]);
+            read_size
+        } else {
+            self.read_at(offset, buf)?
+        };
+        if read_size != self.page_size {
+            return Err(format!("{}: short read of page {}, {} bytes", PAGE_CORRUPTED, page_id, read_size));
+        }
+        Page::verify_checksum(page_id, buf)
+    }
+
+    fn write_page(&self, page_id: page_id_t, page_data: &Vec<u8>) -> Result<(), String> {
+        self.write_run(page_id, &[page_data])
+    }
+
+    fn read_pages(&self, page_id: page_id_t, bufs: &mut [&mut Vec<u8>]) -> Result<(), String> {
+        self.read_run(page_id, bufs)
+    }
+
+    fn write_pages(&self, page_id: page_id_t, pages_data: &[&Vec<u8>]) -> Result<(), String> {
+        self.write_run(page_id, pages_data)
+    }
+
+    fn submit(&self, ios: &mut [PageIo]) -> Vec<Result<(), String>> {
+        #[cfg(target_os = "linux")]
+        if let Some(ring) = self.ring.as_ref().filter(|_| !self.ring_failed.load(Ordering::SeqCst)) {
+            return self.submit_to_ring(ring, ios);
+        }
+
+        ios.iter_mut().map(|io| match io {
+            PageIo::Read(page_id, bufs) => self.read_run(*page_id, bufs),
+            PageIo::Write(page_id, pages_data) => self.write_run(*page_id, pages_data),
+        }).collect()
+    }
+
+    fn sync(&self) -> Result<(), String> {
+        self.file.sync_data().map_err(|err| format!("Error: sync error {}", err))
+    }
+
+    fn get_filesize(&self) -> u64 {
+        self.file.metadata().expect("Erorr: get filesize error").len()
+    }
+
+    fn get_page_size(&self) -> usize {
+        self.page_size
+    }
+}
+
+
+
+#[cfg(test)]
+mod tests {
+    use crate::{common::config::PAGE_SIZE, storage::page_based::{disk::disk_manager::{PageIo, PageStore}, page::page::{Page, PAGE_CORRUPTED}}};
+    use super::{AlignedBuf, PositionalDiskManager};
+
+    fn check_store(store: &PositionalDiskManager) {
+        // as they are read back, with their checksums
+        let pages: Vec<Vec<u8>> = (0..6).map(|i| {
+            let mut page = vec![i as u8 + 1; PAGE_SIZE as usize];
+            Page::set_checksum(i, &mut page);
+            page
+        }).collect();
+        store.write_page(0, &pages[0]).unwrap();
+        store.write_pages(1, &pages[1..3].iter().collect::<Vec<_>>()).unwrap();
+        assert_eq!(3 * PAGE_SIZE as u64, store.get_filesize());
+
+        let mut buf = vec![0; PAGE_SIZE as usize];
+        store.read_page(1, &mut buf).unwrap();
+        assert_eq!(pages[1], buf);
+        assert_eq!(true, store.read_page(3, &mut buf).is_err());
+
+        // two runs of a batch, the write of page 4 goes before the read of it
+        let mut bufs: Vec<Vec<u8>> = (0..3).map(|_| vec![0; PAGE_SIZE as usize]).collect();
+        let (head, tail) = bufs.split_at_mut(2);
+        let mut ios = vec![
+            PageIo::Write(3, vec![&pages[3], &pages[4]]),
+            PageIo::Read(0, head.iter_mut().collect()),
+            PageIo::Read(4, tail.iter_mut().collect()),
+        ];
+        assert_eq!(true, store.submit(&mut ios).iter().all(|result| result.is_ok()));
+        drop(ios);
+        assert_eq!(pages[..2], bufs[..2]);
+        assert_eq!(pages[4], bufs[2]);
+
+        // the pages beyond the end of the file are left as they are
+        let mut bufs: Vec<Vec<u8>> = (0..3).map(|_| vec![0; PAGE_SIZE as usize]).collect();
+        store.read_pages(4, &mut bufs.iter_mut().collect::<Vec<_>>()).unwrap();
+        assert_eq!(pages[4], bufs[0]);
+        assert_eq!(vec![0; PAGE_SIZE as usize], bufs[1]);
+
+        // a page written to the place of another one does not pass
+        store.write_page(5, &pages[5]).unwrap();
+        store.read_page(5, &mut buf).unwrap();
+        let mut data = AlignedBuf::new(PAGE_SIZE as usize);
+        data.as_mut_slice().copy_from_slice(&buf);
+        store.write_at(4 * PAGE_SIZE as u64, data.as_slice()).unwrap();
+        assert_eq!(true, store.read_page(4, &mut buf).unwrap_err().starts_with(PAGE_CORRUPTED));
+    }
+
+    #[test]
+    fn test_positional_read_write() {
+        let _ = std::fs::remove_file("test_positional.db");
+        check_store(&PositionalDiskManager::new("test_positional.db", false, PAGE_SIZE as usize).unwrap());
+        let _ = std::fs::remove_file("test_positional.db");
+    }
+
+    // not every file system takes O_DIRECT, tmpfs does not, run with --ignored where it does
+    #[test]
+    #[ignore]
+    fn test_direct_read_write() {
+        let _ = std::fs::remove_file("test_positional_direct.db");
+        check_store(&PositionalDiskManager::new("test_positional_direct.db", true, PAGE_SIZE as usize).unwrap());
+        let _ = std::fs::remove_file("test_positional_direct.db");
+    }
+
+    // the kernel may not have io_uring or forbid it in a sandbox, run with --ignored where it does
+    #[test]
+    #[ignore]
+    fn test_io_uring_read_write() {
+        let _ = std::fs::remove_file("test_io_uring.db");
+        let store = PositionalDiskManager::with_io_uring("test_io_uring.db", false, PAGE_SIZE as usize).unwrap();
+        assert_eq!(true, store.uses_io_uring());
+        check_store(&store);
+        let _ = std::fs::remove_file("test_io_uring.db");
+    }
+}
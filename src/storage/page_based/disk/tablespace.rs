@@ -0,0 +1,207 @@
+#![allow(warnings)]
+
+use std::{collections::{BTreeMap, HashMap}, sync::{Arc, RwLock}};
+
+use crate::common::{config::{file_id_t, page_id_t, MAIN_FILE_ID}, page_addr::PageAddr};
+
+use super::disk_manager::{PageIo, PageStore};
+
+
+/// a tablespace, a file the pages of the tables placed in it are kept in, e.g. on another disk.
+/// The database file is the tablespace of MAIN_FILE_ID, the others are listed in its superblock
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Tablespace {
+    pub file_id: file_id_t,
+    pub name: String,
+    // the file of the tablespace
+    pub path: String,
+}
+
+impl Tablespace {
+    // the file of a tablespace created in the directory
+    pub fn path_in(location: &str, name: &str) -> String {
+        format!("{}/{}.tbs", location.trim_end_matches('/'), name)
+    }
+}
+
+/// TablespaceStore, the page store of a database of several files.
+/// A page id is the address of the page in its file, see `PageAddr`, the i/o of a page goes to the store
+/// of its file with the page number as the page id, so each file is a store of its own with its pages from 0.
+/// A run of the disk scheduler is in one file, since the ids of two files are far apart
+#[derive(Debug)]
+pub struct TablespaceStore {
+    files: RwLock<HashMap<file_id_t, Arc<dyn PageStore>>>,
+    page_size: usize,
+}
+
+impl TablespaceStore {
+    pub fn new(main: Arc<dyn PageStore>) -> Self {
+        let page_size = main.get_page_size();
+        Self {
+            files: RwLock::new(HashMap::from([(MAIN_FILE_ID, main)])),
+            page_size,
+        }
+    }
+
+    // the pages of the file are read and written from then on
+    pub fn add_file(&self, file_id: file_id_t, store: Arc<dyn PageStore>) -> Result<(), String> {
+        if store.get_page_size() != self.page_size {
+            return Err(format!("the file {} has page size {}, the database has page size {}", file_id, store.get_page_size(), self.page_size));
+        }
+        let mut files = self.files.write().unwrap();
+        if files.contains_key(&file_id) {
+            return Err(format!("the file {} is open already", file_id));
+        }
+        files.insert(file_id, store);
+        Ok(())
+    }
+
+    pub fn get_file(&self, file_id: file_id_t) -> Option<Arc<dyn PageStore>> {
+        self.files.read().unwrap().get(&file_id).cloned()
+    }
+
+    pub fn get_file_ids(&self) -> Vec<file_id_t> {
+        let mut file_ids: Vec<file_id_t> = self.files.read().unwrap().keys().cloned().collect();
+        file_ids.sort();
+        file_ids
+    }
+
+    pub fn get_filesize_of(&self, file_id: file_id_t) -> Option<u64> {
+        self.get_file(file_id).map(|store| store.get_filesize())
+    }
+
+    // the store of the file of the run of num_pages pages from page_id on, and the page number of it
+    fn route(&self, page_id: page_id_t, num_pages: usize) -> Result<(Arc<dyn PageStore>, page_id_t), String> {
+        let addr = PageAddr::of(page_id);
+        if num_pages > 1 && PageAddr::file_of(page_id + num_pages as page_id_t - 1) != addr.file_id {
+            return Err(format!("Error: the pages from {} on cross the end of file {}", page_id, addr.file_id));
+        }
+        match self.get_file(addr.file_id) {
+            Some(store) => Ok((store, addr.page_no)),
+            None => Err(format!("Error: page {} is in file {} which is not open", page_id, addr.file_id)),
+        }
+    }
+}
+
+impl PageStore for TablespaceStore {
+    fn read_page(&self, page_id: page_id_t, buf: &mut Vec<u8>) -> Result<(), String> {
+        let (store, page_no) = self.route(page_id, 1)?;
+        store.read_page(page_no, buf)
+    }
+
+    fn write_page(&self, page_id: page_id_t, page_data: &Vec<u8>) -> Result<(), String> {
+        let (store, page_no) = self.route(page_id, 1)?;
+        store.write_page(page_no, page_data)
+    }
+
+    fn read_pages(&self, page_id: page_id_t, bufs: &mut [&mut Vec<u8>]) -> Result<(), String> {
+        let (store, page_no) = self.route(page_id, bufs.len())?;
+        store.read_pages(page_no, bufs)
+    }
+
+    fn write_pages(&self, page_id: page_id_t, pages_data: &[&Vec<u8>]) -> Result<(), String> {
+        let (store, page_no) = self.route(page_id, pages_data.len())?;
+        store.write_pages(page_no, pages_data)
+    }
+
+    // the runs of each file go to its store as one batch, the results are put back in the order of the runs
+    fn submit(&self, ios: &mut [PageIo]) -> Vec<Result<(), String>> {
+        let mut results: Vec<Result<(), String>> = vec![Ok(()); ios.len()];
+        let mut runs_of_file: BTreeMap<file_id_t, Vec<usize>> = BTreeMap::new();
+        for (i, io) in ios.iter().enumerate() {
+            match self.route(io.page_id(), io.num_pages()) {
+                Ok(_) => runs_of_file.entry(PageAddr::file_of(io.page_id())).or_default().push(i),
+                Err(err) => results[i] = Err(err),
+            }
+        }
+
+        for (file_id, runs) in runs_of_file {
+            let store = self.get_file(file_id).unwrap();
+            let mut file_ios: Vec<PageIo> = runs.iter().map(|i| match &mut ios[*i] {
+                PageIo::Read(page_id, bufs) => PageIo::Read(PageAddr::of(*page_id).page_no, std::mem::take(bufs)),
+                PageIo::Write(page_id, pages_data) => PageIo::Write(PageAddr::of(*page_id).page_no, std::mem::take(pages_data)),
+            }).collect();
+            let file_results = store.submit(&mut file_ios);
+            for ((i, file_io), result) in runs.iter().zip(file_ios).zip(file_results) {
+                match (&mut ios[*i], file_io) {
+                    (PageIo::Read(_, bufs), PageIo::Read(_, file_bufs)) => *bufs = file_bufs,
+                    (PageIo::Write(_, pages_data), PageIo::Write(_, file_pages_data)) => *pages_data = file_pages_data,
+                    _ => unreachable!(),
+                }
+                results[*i] = result;
+            }
+        }
+        results
+    }
+
+    fn sync(&self) -> Result<(), String> {
+        let files: Vec<Arc<dyn PageStore>> = self.files.read().unwrap().values().cloned().collect();
+        for store in files {
+            store.sync()?;
+        }
+        Ok(())
+    }
+
+    // the size of the database file, see `get_filesize_of` for the others
+    fn get_filesize(&self) -> u64 {
+        self.get_filesize_of(MAIN_FILE_ID).unwrap()
+    }
+
+    fn get_page_size(&self) -> usize {
+        self.page_size
+    }
+
+    fn contains_page(&self, page_id: page_id_t) -> bool {
+        match self.route(page_id, 1) {
+            Ok((store, page_no)) => store.contains_page(page_no),
+            Err(_) => false,
+        }
+    }
+}
+
+
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use crate::{common::{config::{MAIN_FILE_ID, PAGE_SIZE}, page_addr::PageAddr}, storage::page_based::disk::{disk_manager::{PageIo, PageStore}, memory_page_store::MemoryPageStore}};
+
+    use super::TablespaceStore;
+
+    #[test]
+    fn test_route() {
+        let main = Arc::new(MemoryPageStore::new());
+        let other = Arc::new(MemoryPageStore::new());
+        let store = TablespaceStore::new(main.clone());
+        store.add_file(2, other.clone()).unwrap();
+        assert_eq!(true, store.add_file(2, Arc::new(MemoryPageStore::new())).is_err());
+        assert_eq!(true, store.add_file(3, Arc::new(MemoryPageStore::new_with_page_size(8192))).is_err());
+
+        let page_size = PAGE_SIZE as usize;
+        let pages: Vec<Vec<u8>> = (0..3).map(|i| vec![i as u8 + 1; page_size]).collect();
+        let (page0, page1) = (PageAddr::new(MAIN_FILE_ID, 1).to_page_id(), PageAddr::new(2, 4).to_page_id());
+        store.write_pages(page0, &[&pages[0]]).unwrap();
+        store.write_pages(page1, &[&pages[1], &pages[2]]).unwrap();
+        // each file has its own pages
+        assert_eq!((2, 6), (main.num_pages(), other.num_pages()));
+        assert_eq!(2 * page_size as u64, store.get_filesize());
+        assert_eq!(Some(6 * page_size as u64), store.get_filesize_of(2));
+        assert_eq!((true, true, false), (store.contains_page(page1 + 1), store.contains_page(page0), store.contains_page(page1 + 2)));
+        assert_eq!(true, store.write_page(PageAddr::new(5, 0).to_page_id(), &pages[0]).is_err());
+
+        // a batch over both files, a run of a file which is not open fails alone
+        let mut bufs: Vec<Vec<u8>> = (0..4).map(|_| vec![0; page_size]).collect();
+        let (head, tail) = bufs.split_at_mut(2);
+        let (buf2, buf3) = tail.split_at_mut(1);
+        let mut ios = vec![
+            PageIo::Read(page1, head.iter_mut().collect()),
+            PageIo::Read(PageAddr::new(5, 0).to_page_id(), vec![&mut buf3[0]]),
+            PageIo::Read(page0, vec![&mut buf2[0]]),
+        ];
+        let results = store.submit(&mut ios);
+        assert_eq!((true, true, true), (results[0].is_ok(), results[1].is_err(), results[2].is_ok()));
+        drop(ios);
+        assert_eq!((&pages[1][8..], &pages[2][8..], &pages[0][8..]), (&bufs[0][8..], &bufs[1][8..], &bufs[2][8..]));
+    }
+}
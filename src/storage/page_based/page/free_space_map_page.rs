@@ -0,0 +1,124 @@
+#![allow(warnings)]
+
+use crate::common::config::{lsn_t, page_id_t, HEADER_PAGE_ID, INVALID_LSN};
+
+use super::page::COMMON_PAGE_HEADER_SIZE;
+
+// an entry of a page which is not allocated, a zeroed map page has all of its pages free
+pub const FSM_FREE_PAGE: u16 = 0;
+// the first map page is after the superblock, the next ones are `entries_per_page` pages apart
+pub const FSM_FIRST_PAGE_ID: page_id_t = HEADER_PAGE_ID + 1;
+
+/// A page of the free space map, an entry for each page it covers,
+/// FSM_FREE_PAGE if the page is not allocated, otherwise the free bytes of it plus 1.
+/// The allocations are logged, the free bytes are hints which the table heaps correct when they are wrong.
+/// How many pages a map page covers depends on the page size of the file, so it is passed in.
+/// The page ids here are the page numbers in the file of the map, see `PageAddr`
+#[repr(C)]
+pub struct FreeSpaceMapPage {
+    // must be the first, see `Page::get_lsn`
+    page_lsn: lsn_t,
+    // set by the page store, see `Page::set_checksum`
+    checksum: u32,
+    entries: [u16; 0],
+}
+
+
+impl FreeSpaceMapPage {
+
+    // how many pages a map page covers, the map page is the first one of them
+    pub fn entries_per_page(page_size: usize) -> usize {
+        (page_size - COMMON_PAGE_HEADER_SIZE) / size_of::<u16>()
+    }
+
+    // the map page covering the page
+    pub fn map_page_of(page_id: page_id_t, page_size: usize) -> page_id_t {
+        let entries_per_page = Self::entries_per_page(page_size) as page_id_t;
+        FSM_FIRST_PAGE_ID + (page_id - FSM_FIRST_PAGE_ID) / entries_per_page * entries_per_page
+    }
+
+    pub fn is_map_page(page_id: page_id_t, page_size: usize) -> bool {
+        page_id >= FSM_FIRST_PAGE_ID && Self::map_page_of(page_id, page_size) == page_id
+    }
+
+    pub fn encode(free_bytes: usize) -> u16 {
+        free_bytes.min(u16::MAX as usize - 1) as u16 + 1
+    }
+
+    // the free bytes of an allocated page
+    pub fn decode(entry: u16) -> Option<usize> {
+        match entry {
+            FSM_FREE_PAGE => None,
+            entry => Some(entry as usize - 1),
+        }
+    }
+
+    pub fn get_lsn(&self) -> lsn_t {
+        self.page_lsn
+    }
+
+    pub fn set_lsn(&mut self, lsn: lsn_t) {
+        if lsn != INVALID_LSN {
+            self.page_lsn = lsn;
+        }
+    }
+
+    pub fn get_entries(&self, page_size: usize) -> &[u16] {
+        unsafe {
+            std::slice::from_raw_parts(self.entries.as_ptr(), Self::entries_per_page(page_size))
+        }
+    }
+
+    pub fn get_entry(&self, page_id: page_id_t, page_size: usize) -> u16 {
+        self.get_entries(page_size)[Self::index_of(page_id, page_size)]
+    }
+
+    pub fn set_entry(&mut self, page_id: page_id_t, entry: u16, page_size: usize) {
+        let index = Self::index_of(page_id, page_size);
+        let entries = unsafe {
+            std::slice::from_raw_parts_mut(self.entries.as_mut_ptr(), Self::entries_per_page(page_size))
+        };
+        entries[index] = entry;
+    }
+
+    fn index_of(page_id: page_id_t, page_size: usize) -> usize {
+        (page_id - Self::map_page_of(page_id, page_size)) as usize
+    }
+}
+
+
+
+#[cfg(test)]
+mod tests {
+    use crate::common::config::{page_id_t, MAX_PAGE_SIZE, PAGE_SIZE};
+
+    use super::{FreeSpaceMapPage, FSM_FIRST_PAGE_ID, FSM_FREE_PAGE};
+
+    #[test]
+    fn test_entries() {
+        let page_size = PAGE_SIZE as usize;
+        let (n, first) = (FreeSpaceMapPage::entries_per_page(page_size) as page_id_t, FSM_FIRST_PAGE_ID);
+        assert_eq!(first, FreeSpaceMapPage::map_page_of(first + n - 1, page_size));
+        assert_eq!(first + n, FreeSpaceMapPage::map_page_of(first + n + 5, page_size));
+        assert_eq!(true, FreeSpaceMapPage::is_map_page(first + 2 * n, page_size));
+        assert_eq!(false, FreeSpaceMapPage::is_map_page(first + 2 * n + 1, page_size));
+        // a bigger page covers more pages
+        assert_eq!(first, FreeSpaceMapPage::map_page_of(first + n + 5, MAX_PAGE_SIZE));
+
+        let mut buf = vec![0u8; page_size];
+        let map_page = unsafe { &mut *(buf.as_mut_ptr() as *mut FreeSpaceMapPage) };
+        assert_eq!(FSM_FREE_PAGE, map_page.get_entry(first + n - 1, page_size));
+        map_page.set_entry(first + n - 1, FreeSpaceMapPage::encode(100), page_size);
+        assert_eq!(Some(100), FreeSpaceMapPage::decode(map_page.get_entry(first + n - 1, page_size)));
+
+        // the last pages of the first two map pages take the same slot, each in its own map page
+        assert_ne!(FreeSpaceMapPage::map_page_of(first + n - 1, page_size), FreeSpaceMapPage::map_page_of(first + 2 * n - 1, page_size));
+        let mut next_buf = vec![0u8; page_size];
+        let next_map_page = unsafe { &mut *(next_buf.as_mut_ptr() as *mut FreeSpaceMapPage) };
+        next_map_page.set_entry(first + 2 * n - 1, FreeSpaceMapPage::encode(200), page_size);
+        assert_eq!(Some(200), FreeSpaceMapPage::decode(next_map_page.get_entry(first + 2 * n - 1, page_size)));
+        assert_eq!(Some(100), FreeSpaceMapPage::decode(map_page.get_entry(first + n - 1, page_size)));
+        assert_eq!(Some(0), FreeSpaceMapPage::decode(FreeSpaceMapPage::encode(0)));
+        assert_eq!(None, FreeSpaceMapPage::decode(FSM_FREE_PAGE));
+    }
+}
@@ -1,3 +1,8 @@
 pub mod page;
 pub mod page_guard;
-pub mod table_page;
\ No newline at end of file
+pub mod table_page;
+pub mod sequence_page;
+pub mod free_space_map_page;
+pub mod superblock_page;
+
+pub mod overflow_page;
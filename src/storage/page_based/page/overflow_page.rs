@@ -0,0 +1,54 @@
+#![allow(warnings)]
+
+use crate::common::config::{lsn_t, page_id_t, INVALID_LSN, INVALID_PAGE_ID};
+
+/// A page of a chain holding a large value out of its tuple, see `Toaster`.
+/// The bytes of the value are cut into the pages of the chain in order
+#[repr(C)]
+pub struct OverflowPage {
+    // the overflow pages are written out before the tuple pointing to them is logged, they are not logged
+    page_lsn: lsn_t,
+    // set by the page store, see `Page::set_checksum`
+    checksum: u32,
+    next_page_id: page_id_t,
+    // the bytes of the value on this page
+    data_len: u32,
+    data: [u8; 0],
+}
+
+
+impl OverflowPage {
+
+    pub fn init(&mut self) {
+        self.page_lsn = INVALID_LSN;
+        self.next_page_id = INVALID_PAGE_ID;
+        self.data_len = 0;
+    }
+
+    // the bytes of a value a page holds
+    pub fn capacity(page_size: usize) -> usize {
+        page_size - size_of::<Self>()
+    }
+
+    pub fn get_next_page_id(&self) -> page_id_t {
+        self.next_page_id
+    }
+
+    pub fn set_next_page_id(&mut self, next_page_id: page_id_t) {
+        self.next_page_id = next_page_id;
+    }
+
+    pub fn get_data(&self) -> &[u8] {
+        unsafe {
+            std::slice::from_raw_parts(self.data.as_ptr(), self.data_len as usize)
+        }
+    }
+
+    pub fn set_data(&mut self, data: &[u8], page_size: usize) {
+        assert!(data.len() <= Self::capacity(page_size), "{} bytes do not fit in an overflow page", data.len());
+        self.data_len = data.len() as u32;
+        unsafe {
+            std::slice::from_raw_parts_mut(self.data.as_mut_ptr(), data.len()).copy_from_slice(data);
+        }
+    }
+}
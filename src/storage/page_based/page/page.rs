@@ -2,13 +2,24 @@
 
 use std::sync::{RwLock, RwLockReadGuard, RwLockWriteGuard};
 
-use crate::common::config::{page_id_t, INVALID_PAGE_ID, PAGE_SIZE};
+use crate::common::{config::{lsn_t, page_id_t, INVALID_LSN, INVALID_PAGE_ID, MAX_PAGE_SIZE, MIN_PAGE_SIZE, PAGE_SIZE}, crc32c::{crc32c, crc32c_append}};
+
+// every kind of page starts with the lsn of the last logged change on it (pageLSN)
+pub const PAGE_LSN_OFFSET: usize = 0;
+// then the crc32c of the page, set when the page is written out and checked when it is read in
+pub const PAGE_CHECKSUM_OFFSET: usize = 4;
+// the header every kind of page starts with, the data of a page go after it
+pub const COMMON_PAGE_HEADER_SIZE: usize = 8;
+// a page failing the checksum, or read short, gives an error starting with it
+pub const PAGE_CORRUPTED: &str = "page corrupted";
 
 #[derive(Debug)]
 pub struct Page {
     pub page_id: page_id_t,
     pub pin_count: i32,
     pub is_dirty: bool,
+    // the first lsn whose change may not be on disk yet (recLSN), taken when the page was read or written last time
+    pub rec_lsn: lsn_t,
     pub data: Vec<u8>,
     pub rwlatch: RwLock<()>,
 }
@@ -25,12 +36,18 @@ impl PartialEq for Page {
 
 impl Page {
     pub fn new() -> Self {
+        Self::new_with_size(PAGE_SIZE as usize)
+    }
+
+    // a frame for the pages of a store, see `PageStore::get_page_size`
+    pub fn new_with_size(page_size: usize) -> Self {
         let mut data = Vec::new();
-        data.resize(PAGE_SIZE as usize, 0);
+        data.resize(page_size, 0);
         Self {
             page_id: INVALID_PAGE_ID,
             pin_count: 0,
             is_dirty: false,
+            rec_lsn: INVALID_LSN,
             data: data,
             rwlatch: RwLock::new(()),
         }
@@ -40,6 +57,10 @@ impl Page {
         self.page_id
     }
 
+    pub fn is_valid_size(page_size: usize) -> bool {
+        page_size.is_power_of_two() && (MIN_PAGE_SIZE..=MAX_PAGE_SIZE).contains(&page_size)
+    }
+
     pub fn get_pincount(&self) -> i32 {
         self.pin_count
     }
@@ -48,6 +69,39 @@ impl Page {
         self.is_dirty
     }
 
+    pub fn get_lsn(&self) -> lsn_t {
+        lsn_t::from_ne_bytes(self.data[PAGE_LSN_OFFSET..PAGE_LSN_OFFSET + size_of::<lsn_t>()].try_into().unwrap())
+    }
+
+    pub fn set_lsn(&mut self, lsn: lsn_t) {
+        self.data[PAGE_LSN_OFFSET..PAGE_LSN_OFFSET + size_of::<lsn_t>()].copy_from_slice(&lsn.to_ne_bytes());
+    }
+
+    // the checksum covers the page id too, so a page written to the place of another one is caught
+    pub fn compute_checksum(page_id: page_id_t, data: &[u8]) -> u32 {
+        let crc = crc32c(&page_id.to_le_bytes());
+        let crc = crc32c_append(crc, &data[..PAGE_CHECKSUM_OFFSET]);
+        crc32c_append(crc, &data[COMMON_PAGE_HEADER_SIZE..])
+    }
+
+    pub fn set_checksum(page_id: page_id_t, data: &mut [u8]) {
+        let checksum = Self::compute_checksum(page_id, data);
+        data[PAGE_CHECKSUM_OFFSET..PAGE_CHECKSUM_OFFSET + size_of::<u32>()].copy_from_slice(&checksum.to_le_bytes());
+    }
+
+    // a page of zeros was never written, e.g. a hole of the file
+    pub fn verify_checksum(page_id: page_id_t, data: &[u8]) -> Result<(), String> {
+        if !Self::is_valid_size(data.len()) {
+            return Err(format!("{}: short read of page {}, {} bytes", PAGE_CORRUPTED, page_id, data.len()));
+        }
+        let stored = u32::from_le_bytes(data[PAGE_CHECKSUM_OFFSET..PAGE_CHECKSUM_OFFSET + size_of::<u32>()].try_into().unwrap());
+        let computed = Self::compute_checksum(page_id, data);
+        if stored != computed && data.iter().any(|byte| *byte != 0) {
+            return Err(format!("{}: checksum of page {} is {:#010x}, expected {:#010x}", PAGE_CORRUPTED, page_id, stored, computed));
+        }
+        Ok(())
+    }
+
     // return the actual data, not include the header
     pub fn get_data(&self) -> &Vec<u8>{
         self.data.as_ref()
@@ -119,6 +173,7 @@ mod tests {
             page_id: 1,
             pin_count: 0,
             is_dirty: false,
+            rec_lsn: INVALID_LSN,
             data: buf,
             rwlatch: RwLock::new(()),
         };
@@ -127,4 +182,20 @@ mod tests {
         println!("{:#?}", another);
     }
 
+    #[test]
+    fn test_checksum() {
+        let mut page = Page::new();
+        assert_eq!(true, Page::verify_checksum(3, page.get_data()).is_ok());
+
+        page.get_mut_data()[COMMON_PAGE_HEADER_SIZE..COMMON_PAGE_HEADER_SIZE + 5].copy_from_slice("hello".as_bytes());
+        assert_eq!(true, Page::verify_checksum(3, page.get_data()).unwrap_err().starts_with(PAGE_CORRUPTED));
+        Page::set_checksum(3, page.get_mut_data());
+        assert_eq!(true, Page::verify_checksum(3, page.get_data()).is_ok());
+        // the lsn is covered, the checksum of another page does not match
+        assert_eq!(true, Page::verify_checksum(4, page.get_data()).is_err());
+        page.set_lsn(7);
+        assert_eq!(true, Page::verify_checksum(3, page.get_data()).is_err());
+        assert_eq!(true, Page::verify_checksum(3, &page.get_data()[..100]).unwrap_err().starts_with(PAGE_CORRUPTED));
+    }
+
 }
\ No newline at end of file
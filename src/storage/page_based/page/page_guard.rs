@@ -145,6 +145,15 @@ impl<'a> WritePageGuard<'a> {
         self.page_guard.is_dirty = true;
         unsafe { &mut *(self.page_guard.get_mut_data().map(|p| p.as_mut_ptr() as *mut T).unwrap()) }
     }
+
+    // reading through a write guard does not make the page dirty
+    pub fn get_as<T>(&self) -> &'a T {
+        self.page_guard.get_as()
+    }
+
+    pub fn get_pid(&self) -> Option<page_id_t> {
+        self.page_guard.get_pid()
+    }
 }
 
 impl<'a> Drop for WritePageGuard<'a> {
@@ -159,7 +168,7 @@ impl<'a> Drop for WritePageGuard<'a> {
 mod tests {
     use std::sync::Arc;
 
-    use crate::{storage::page_based::disk::{disk_manager::DiskManager, log_manager::LogManager}, buffer::buffer_pool_manager::BufferPoolManager};
+    use crate::{storage::page_based::disk::{log_manager::LogManager, memory_page_store::MemoryPageStore}, buffer::buffer_pool_manager::BufferPoolManager};
 
     use super::PageGuard;
 
@@ -167,7 +176,7 @@ mod tests {
 
     fn create_bpm(pool_size: usize, k: usize) -> BufferPoolManager {
         // setup
-        let disk_manager = DiskManager::new("test.db").unwrap();
+        let disk_manager = MemoryPageStore::new();
         let bpm = BufferPoolManager::new(pool_size, Arc::new(disk_manager), 
             k, Arc::new(LogManager::new()));
         bpm
@@ -0,0 +1,72 @@
+#![allow(warnings)]
+
+use crate::common::config::{lsn_t, INVALID_LSN};
+
+const SEQUENCE_PAGE_MAGIC: u32 = 0x5345_5131;
+
+/// A page holding the state of one sequence.
+/// `last_value` is the largest value that may have been handed out,
+/// the values after it are never returned before it is moved forward on this page.
+#[repr(C)]
+pub struct SequencePage {
+    // the sequence page is flushed on every change instead of being logged, see `Page::get_lsn`
+    page_lsn: lsn_t,
+    // set by the page store, see `Page::set_checksum`
+    checksum: u32,
+    magic: u32,
+    start_value: i32,
+    increment: i32,
+    min_value: i32,
+    max_value: i32,
+    last_value: i32,
+    // whether last_value has been handed out, false until the first nextval
+    is_called: u8,
+}
+
+
+impl SequencePage {
+
+    pub fn init(&mut self, start_value: i32, increment: i32, min_value: i32, max_value: i32) {
+        self.page_lsn = INVALID_LSN;
+        self.magic = SEQUENCE_PAGE_MAGIC;
+        self.start_value = start_value;
+        self.increment = increment;
+        self.min_value = min_value;
+        self.max_value = max_value;
+        self.last_value = start_value;
+        self.is_called = 0;
+    }
+
+    pub fn is_valid(&self) -> bool {
+        self.magic == SEQUENCE_PAGE_MAGIC
+    }
+
+    pub fn get_start_value(&self) -> i32 {
+        self.start_value
+    }
+
+    pub fn get_increment(&self) -> i32 {
+        self.increment
+    }
+
+    pub fn get_min_value(&self) -> i32 {
+        self.min_value
+    }
+
+    pub fn get_max_value(&self) -> i32 {
+        self.max_value
+    }
+
+    pub fn get_last_value(&self) -> i32 {
+        self.last_value
+    }
+
+    pub fn is_called(&self) -> bool {
+        self.is_called != 0
+    }
+
+    pub fn set_last_value(&mut self, last_value: i32) {
+        self.last_value = last_value;
+        self.is_called = 1;
+    }
+}
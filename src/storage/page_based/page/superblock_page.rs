@@ -0,0 +1,242 @@
+#![allow(warnings)]
+
+use crate::{common::config::{file_id_t, lsn_t, page_id_t, INVALID_LSN, INVALID_PAGE_ID, MAIN_FILE_ID, MAX_FILE_ID, MAX_PAGE_SIZE, MIN_PAGE_SIZE}, storage::page_based::disk::tablespace::Tablespace};
+
+use super::page::Page;
+
+// the first bytes of the data of a database file
+pub const SUPERBLOCK_MAGIC: [u8; 8] = *b"rustdb\0\0";
+// the format of the files written by this build, a file of a newer one is not opened.
+// 2 has the file id and the tablespaces, they are zeros in a file of 1, which is a database file without any.
+// 3 has the slotted table pages, the table pages of the older ones are not read.
+// 4 has the 64 bit page ids, in the pages, the log and the double write buffer
+pub const FORMAT_VERSION: u32 = 4;
+pub const MIN_FORMAT_VERSION: u32 = 4;
+// the longest name and path of a tablespace
+pub const MAX_TABLESPACE_NAME_LEN: usize = 63;
+pub const MAX_TABLESPACE_PATH_LEN: usize = 189;
+// as many as fit in the smallest page
+pub const MAX_TABLESPACES: usize = (MIN_PAGE_SIZE - SUPERBLOCK_HEADER_SIZE) / size_of::<TablespaceEntry>();
+const SUPERBLOCK_HEADER_SIZE: usize = 40;
+
+// a tablespace in the superblock of the database file
+#[repr(C)]
+#[derive(Clone, Copy)]
+struct TablespaceEntry {
+    file_id: file_id_t,
+    name_len: u8,
+    path_len: u8,
+    name: [u8; MAX_TABLESPACE_NAME_LEN],
+    path: [u8; MAX_TABLESPACE_PATH_LEN],
+}
+
+/// SuperblockPage, the page at `HEADER_PAGE_ID` of a database file.
+/// It tells a database file apart and has the format version and the page size the file is written with,
+/// they are checked before any other page is read. It fits in the smallest page, so it can be read
+/// from the front of the file before the page size is known.
+/// Each file of a tablespace starts with one too, with the id of the file, the one of the database file
+/// lists the tablespaces, so they are found and opened with it.
+/// Like a sequence page it is written out on every change instead of being logged
+#[repr(C)]
+pub struct SuperblockPage {
+    // must be the first, see `Page::get_lsn`
+    page_lsn: lsn_t,
+    // set by the page store, see `Page::set_checksum`
+    checksum: u32,
+    magic: [u8; 8],
+    version: u32,
+    page_size: u32,
+    // the first page of the catalog, INVALID_PAGE_ID while the catalog is kept in memory
+    catalog_root: page_id_t,
+    // MAIN_FILE_ID for the database file
+    file_id: file_id_t,
+    num_tablespaces: u16,
+    tablespaces: [TablespaceEntry; 0],
+}
+
+
+impl SuperblockPage {
+
+    pub fn init(&mut self, page_size: usize) {
+        self.init_file(page_size, MAIN_FILE_ID);
+    }
+
+    // the superblock of the file of a tablespace
+    pub fn init_file(&mut self, page_size: usize, file_id: file_id_t) {
+        self.page_lsn = INVALID_LSN;
+        self.magic = SUPERBLOCK_MAGIC;
+        self.version = FORMAT_VERSION;
+        self.page_size = page_size as u32;
+        self.catalog_root = INVALID_PAGE_ID;
+        self.file_id = file_id;
+        self.num_tablespaces = 0;
+    }
+
+    // a page size a database may be created with
+    pub fn check_page_size(page_size: usize) -> Result<(), String> {
+        match Page::is_valid_size(page_size) {
+            true => Ok(()),
+            false => Err(format!("invalid page size {}, it must be a power of two from {} to {}", page_size, MIN_PAGE_SIZE, MAX_PAGE_SIZE)),
+        }
+    }
+
+    // the file is opened with the page size only if it is written with it, in a format this build knows
+    pub fn validate(&self, page_size: usize) -> Result<(), String> {
+        if self.magic != SUPERBLOCK_MAGIC {
+            return Err(format!("not a database file, the magic bytes are {:02x?}", self.magic));
+        }
+        if self.version > FORMAT_VERSION || self.version < MIN_FORMAT_VERSION {
+            return Err(format!("the file has format version {}, this build reads versions {} to {}", self.version, MIN_FORMAT_VERSION, FORMAT_VERSION));
+        }
+        if self.page_size as usize != page_size {
+            return Err(format!("the file has page size {}, it can not be opened with page size {}", self.page_size, page_size));
+        }
+        Ok(())
+    }
+
+    // the same, and the file must be the one of the tablespace
+    pub fn validate_file(&self, page_size: usize, file_id: file_id_t) -> Result<(), String> {
+        self.validate(page_size)?;
+        match self.file_id == file_id {
+            true => Ok(()),
+            false => Err(format!("the file has file id {}, {} is expected", self.file_id, file_id)),
+        }
+    }
+
+    pub fn get_version(&self) -> u32 {
+        self.version
+    }
+
+    pub fn get_page_size(&self) -> usize {
+        self.page_size as usize
+    }
+
+    pub fn get_catalog_root(&self) -> page_id_t {
+        self.catalog_root
+    }
+
+    pub fn set_catalog_root(&mut self, catalog_root: page_id_t) {
+        self.catalog_root = catalog_root;
+    }
+
+    pub fn get_file_id(&self) -> file_id_t {
+        self.file_id
+    }
+
+    pub fn get_tablespaces(&self) -> Vec<Tablespace> {
+        self.entries().iter().map(|entry| Tablespace {
+            file_id: entry.file_id,
+            name: String::from_utf8_lossy(&entry.name[..entry.name_len as usize]).to_string(),
+            path: String::from_utf8_lossy(&entry.path[..entry.path_len as usize]).to_string(),
+        }).collect()
+    }
+
+    // the name and the file id must not be taken, the id of the database file is not one of a tablespace
+    pub fn add_tablespace(&mut self, tablespace: &Tablespace) -> Result<(), String> {
+        let (name, path) = (tablespace.name.as_bytes(), tablespace.path.as_bytes());
+        if name.is_empty() || name.len() > MAX_TABLESPACE_NAME_LEN {
+            return Err(format!("invalid tablespace name \"{}\", it must have 1 to {} bytes", tablespace.name, MAX_TABLESPACE_NAME_LEN));
+        }
+        if path.len() > MAX_TABLESPACE_PATH_LEN {
+            return Err(format!("the path of tablespace \"{}\" is longer than {} bytes", tablespace.name, MAX_TABLESPACE_PATH_LEN));
+        }
+        if tablespace.file_id == MAIN_FILE_ID || tablespace.file_id > MAX_FILE_ID {
+            return Err(format!("invalid file id {} of tablespace \"{}\"", tablespace.file_id, tablespace.name));
+        }
+        if self.num_tablespaces as usize >= MAX_TABLESPACES {
+            return Err(format!("too many tablespaces, at most {}", MAX_TABLESPACES));
+        }
+        if self.get_tablespaces().iter().any(|other| other.name == tablespace.name || other.file_id == tablespace.file_id) {
+            return Err(format!("tablespace \"{}\" already exists", tablespace.name));
+        }
+
+        let mut entry = TablespaceEntry {
+            file_id: tablespace.file_id,
+            name_len: name.len() as u8,
+            path_len: path.len() as u8,
+            name: [0; MAX_TABLESPACE_NAME_LEN],
+            path: [0; MAX_TABLESPACE_PATH_LEN],
+        };
+        entry.name[..name.len()].copy_from_slice(name);
+        entry.path[..path.len()].copy_from_slice(path);
+        unsafe {
+            *self.tablespaces.as_mut_ptr().add(self.num_tablespaces as usize) = entry;
+        }
+        self.num_tablespaces += 1;
+        Ok(())
+    }
+
+    fn entries(&self) -> &[TablespaceEntry] {
+        let len = (self.num_tablespaces as usize).min(MAX_TABLESPACES);
+        unsafe {
+            std::slice::from_raw_parts(self.tablespaces.as_ptr(), len)
+        }
+    }
+}
+
+
+
+#[cfg(test)]
+mod tests {
+    use crate::common::config::{INVALID_PAGE_ID, MAX_PAGE_SIZE, MIN_PAGE_SIZE, PAGE_SIZE};
+
+    use crate::storage::page_based::disk::tablespace::Tablespace;
+
+    use super::{SuperblockPage, FORMAT_VERSION, MAX_TABLESPACES, SUPERBLOCK_HEADER_SIZE};
+
+    #[test]
+    fn test_validate() {
+        let mut buf = vec![0u8; MIN_PAGE_SIZE];
+        let superblock = unsafe { &mut *(buf.as_mut_ptr() as *mut SuperblockPage) };
+        assert_eq!(true, superblock.validate(PAGE_SIZE as usize).unwrap_err().starts_with("not a database file"));
+
+        superblock.init(16384);
+        assert_eq!((FORMAT_VERSION, 16384, INVALID_PAGE_ID), (superblock.get_version(), superblock.get_page_size(), superblock.get_catalog_root()));
+        assert_eq!(Ok(()), superblock.validate(16384));
+        assert_eq!(true, superblock.validate(4096).is_err());
+        superblock.version = FORMAT_VERSION + 1;
+        assert_eq!(true, superblock.validate(16384).is_err());
+        // the table pages of a file of 2 are laid out otherwise
+        superblock.version = 2;
+        assert_eq!(true, superblock.validate(16384).is_err());
+
+        for page_size in [MIN_PAGE_SIZE, 8192, MAX_PAGE_SIZE] {
+            assert_eq!(Ok(()), SuperblockPage::check_page_size(page_size));
+        }
+        for page_size in [0, 2048, 6000, 2 * MAX_PAGE_SIZE] {
+            assert_eq!(true, SuperblockPage::check_page_size(page_size).is_err());
+        }
+    }
+
+    #[test]
+    fn test_tablespaces() {
+        assert_eq!(SUPERBLOCK_HEADER_SIZE, std::mem::size_of::<SuperblockPage>());
+        assert_eq!(15, MAX_TABLESPACES);
+
+        let mut buf = vec![0u8; MIN_PAGE_SIZE];
+        let superblock = unsafe { &mut *(buf.as_mut_ptr() as *mut SuperblockPage) };
+        superblock.init(MIN_PAGE_SIZE);
+        assert_eq!((0, Vec::new()), (superblock.get_file_id(), superblock.get_tablespaces()));
+
+        let tablespace = |file_id, name: &str| Tablespace { file_id, name: name.to_string(), path: format!("/mnt/{}/{}.tbs", name, name) };
+        superblock.add_tablespace(&tablespace(1, "fast")).unwrap();
+        superblock.add_tablespace(&tablespace(2, "archive")).unwrap();
+        assert_eq!(vec![tablespace(1, "fast"), tablespace(2, "archive")], superblock.get_tablespaces());
+        // the name and the file id are taken, and the database file is no tablespace
+        assert_eq!(true, superblock.add_tablespace(&tablespace(3, "fast")).is_err());
+        assert_eq!(true, superblock.add_tablespace(&tablespace(2, "other")).is_err());
+        assert_eq!(true, superblock.add_tablespace(&tablespace(0, "other")).is_err());
+        assert_eq!(true, superblock.add_tablespace(&tablespace(3, &"x".repeat(64))).is_err());
+        for file_id in 3..=MAX_TABLESPACES as u16 {
+            superblock.add_tablespace(&tablespace(file_id, &format!("ts{}", file_id))).unwrap();
+        }
+        assert_eq!(true, superblock.add_tablespace(&tablespace(100, "full")).is_err());
+        assert_eq!(MAX_TABLESPACES, superblock.get_tablespaces().len());
+
+        // the file of a tablespace knows its id
+        superblock.init_file(MIN_PAGE_SIZE, 2);
+        assert_eq!(Ok(()), superblock.validate_file(MIN_PAGE_SIZE, 2));
+        assert_eq!(true, superblock.validate_file(MIN_PAGE_SIZE, 1).is_err());
+        assert_eq!(Vec::<Tablespace>::new(), superblock.get_tablespaces());
+    }
+}
@@ -1,40 +1,96 @@
 #![allow(warnings)]
 
-use crate::{common::{config::{page_id_t, PAGE_SIZE, INVALID_PAGE_ID}, rid::RID}, storage::page_based::table::tuple::{Tuple, TupleMeta}};
+use crate::{common::{config::{file_id_t, lsn_t, page_id_t, INVALID_LSN, INVALID_PAGE_ID, INVALID_TXN_ID}, page_addr::PageAddr, rid::RID}, storage::page_based::table::tuple::{Tuple, TupleMeta}};
 
 
 
+// the kinds of a slot
+// no tuple, the slot of a vacuumed one, a new tuple takes it
+const SLOT_EMPTY: u8 = 0;
+const SLOT_NORMAL: u8 = 1;
+// the tuple is moved to another page, the slot keeps its meta and the rid it is at, see `Slot`
+const SLOT_FORWARD: u8 = 2;
+// a tuple moved here, its data starts with the rid of its slot, which the scans and the readers go through
+const SLOT_MOVED: u8 = 3;
 
-/// | offset | tuple length  | meta|
-pub type TupleInfo = (u16, u16, TupleMeta);
-const TUPLE_INFO_SIZE: usize = size_of::<TupleInfo>();
-const PAGE_HEADER_SIZE: usize = size_of::<TablePage>();
-// how many tuple infos can be satisfied in a page
-const TUPLE_INFO_MAX_LEN: usize = (PAGE_SIZE as usize - PAGE_HEADER_SIZE) / TUPLE_INFO_SIZE;
+/// a slot of the directory, where the data of its tuple is and how long it is.
+/// A forwarding slot has no data, the file id, the offset and the length are the address and the slot
+/// the tuple is moved to, the offset is the page number in the file then
+#[derive(Debug, Clone)]
+#[repr(C)]
+struct Slot {
+    offset: u32,
+    len: u16,
+    file_id: file_id_t,
+    kind: u8,
+    meta: TupleMeta,
+}
 
+const SLOT_SIZE: usize = size_of::<Slot>();
+const PAGE_HEADER_SIZE: usize = size_of::<TablePage>();
+// the rid of its slot before the data of a moved tuple
+const MOVED_HEADER_SIZE: usize = size_of::<page_id_t>() + size_of::<u16>();
+
+/// TablePage, a slotted page. The directory of the slots grows from the header and the data of the tuples
+/// grows from the end of the page down to the free pointer, the bytes in between are the free space.
+/// A slot stays where it is as long as its tuple is there, so a rid is the page and the slot of the tuple.
+/// A tuple shrinks in place and grows at the free pointer, the room left by it and by the vacuumed tuples
+/// is taken back by compacting the data when a tuple does not fit in the bytes in between.
+/// A vacuumed slot is taken by the next tuple inserted, the empty ones at the end are dropped
 #[repr(C)]
 pub struct TablePage {
     page_start: [u8; 0],
+    // must be the first, see `Page::get_lsn`
+    page_lsn: lsn_t,
+    // set by the page store, see `Page::set_checksum`
+    checksum: u32,
     next_page_id: page_id_t,
+    // the page size of the file, the tuples are stored from the end of the page
+    page_size: u32,
+    // where the data of the tuples starts
+    free_pointer: u32,
+    // the bytes the tuples take, the holes between them are free space too
+    tuple_bytes: u32,
     num_tuples: u16,
     num_deleted_tuples: u16,
-    tuple_infos: [TupleInfo; 0]
+    slots: [Slot; 0]
 }
 
 
 
 impl TablePage {
-    
-    pub fn init(&mut self) {
+
+    pub fn init(&mut self, page_size: usize) {
+        self.page_lsn = INVALID_LSN;
         self.next_page_id = INVALID_PAGE_ID;
+        self.page_size = page_size as u32;
+        self.free_pointer = page_size as u32;
+        self.tuple_bytes = 0;
         self.num_tuples = 0;
         self.num_deleted_tuples = 0;
     }
 
+    pub fn get_lsn(&self) -> lsn_t {
+        self.page_lsn
+    }
+
+    // an unlogged change comes with an invalid lsn, the page keeps the lsn of the last logged one
+    pub fn set_lsn(&mut self, lsn: lsn_t) {
+        if lsn != INVALID_LSN {
+            self.page_lsn = lsn;
+        }
+    }
+
+    // the slots of the page, the empty ones among them too
     pub fn get_num_tuples(&self) -> u16 {
         self.num_tuples
     }
 
+    // the deleted tuples not vacuumed yet
+    pub fn get_num_deleted_tuples(&self) -> u16 {
+        self.num_deleted_tuples
+    }
+
     pub fn get_next_page_id(&self) -> page_id_t {
         self.next_page_id
     }
@@ -43,35 +99,31 @@ impl TablePage {
         self.next_page_id = next_pid;
     }
 
+    // the slot a new tuple takes, the first empty one or a new one
+    pub fn get_next_slot(&self) -> u16 {
+        self.get_slots().iter()
+            .position(|slot| slot.kind == SLOT_EMPTY)
+            .map_or(self.num_tuples, |sid| sid as u16)
+    }
 
     /// return slot id if has enough space
     /// otherwise, return None
     pub fn insert_tuple(&mut self, meta: &TupleMeta, tuple: &Tuple) -> Option<u16> {
-        let insert_pos = self.get_next_offset(meta, tuple);
-        match insert_pos {
-            None => None,
-            Some(write_pos) => {
-                // write bytes
-                let write_pos = write_pos as usize;
-                let bytes = tuple.get_data();
-                // transfer [u8; 0] -> [u8; 4096]
-
-                // println!("page_start: {:p}", self.page_start.as_mut_ptr());
-                let page_start = self.get_page_slice_mut();
-                page_start[write_pos..write_pos+bytes.len()].copy_from_slice(&bytes);
-
-                // write meta
-                let slot_idx = self.num_tuples;
-                let tuple_infos = self.get_tuple_info_slice_mut();
-                tuple_infos[slot_idx as usize] = (
-                    write_pos as u16,
-                    tuple.get_length() as u16,
-                    meta.clone(),
-                );
-                self.num_tuples += 1;
-                Some(slot_idx)
-            }
-        }
+        self.insert_tuple_at(self.get_next_slot(), meta, tuple)
+    }
+
+    // insert into the slot, which must be empty or the next new one, e.g. the one logged by the table heap
+    pub fn insert_tuple_at(&mut self, sid: u16, meta: &TupleMeta, tuple: &Tuple) -> Option<u16> {
+        self.place(sid, SLOT_NORMAL, meta, &tuple.get_data())
+    }
+
+    // insert a tuple moved from the slot `from` of another page
+    pub fn insert_moved_at(&mut self, sid: u16, from: &RID, tuple: &Tuple) -> Option<u16> {
+        let mut data = Vec::with_capacity(MOVED_HEADER_SIZE + tuple.get_length() as usize);
+        data.extend_from_slice(&from.pid.to_le_bytes());
+        data.extend_from_slice(&from.sid.to_le_bytes());
+        data.extend_from_slice(&tuple.get_data());
+        self.place(sid, SLOT_MOVED, &TupleMeta::new(INVALID_TXN_ID, INVALID_TXN_ID, false), &data)
     }
 
     // update tuple meta by rid
@@ -79,99 +131,284 @@ impl TablePage {
         if rid.sid >= self.num_tuples {
             return Err(format!("Slot idx is out of range"));
         }
-        let slot_idx = rid.sid as usize;
-        let num_tuples = self.num_tuples;
-        let infos = self.get_tuple_info_slice();
-        let (off, len, meta) = infos[slot_idx].clone();
-        if !meta.is_deleted && meta.is_deleted {
-            self.num_deleted_tuples = num_tuples + 1;
+        let slot = &mut self.get_slots_mut()[rid.sid as usize];
+        let counted = slot.kind == SLOT_NORMAL || slot.kind == SLOT_FORWARD;
+        let old_deleted = slot.meta.is_deleted;
+        slot.meta = meta.clone();
+        if counted && !old_deleted && meta.is_deleted {
+            self.num_deleted_tuples += 1;
+        } else if counted && old_deleted && !meta.is_deleted {
+            self.num_deleted_tuples -= 1;
+        }
+        Ok(())
+    }
+
+    // whether the tuple at the rid can be replaced by the new one on this page
+    pub fn has_room_for_update(&self, rid: &RID, tuple: &Tuple) -> bool {
+        let Some(slot) = self.get_slots().get(rid.sid as usize) else {
+            return false;
+        };
+        let header = match slot.kind {
+            SLOT_NORMAL => 0,
+            SLOT_MOVED => MOVED_HEADER_SIZE,
+            _ => return false,
+        };
+        let new_len = header + tuple.get_length() as usize;
+        new_len <= slot.len as usize + self.get_free_space()
+    }
+
+    // replace the data of the tuple at the rid, the meta stays. a shorter one is written in place,
+    // a longer one at the free pointer, the page is compacted first when the bytes in between are too few
+    pub fn update_tuple(&mut self, rid: &RID, tuple: &Tuple) -> Result<(), String> {
+        if !self.has_room_for_update(rid, tuple) {
+            return Err(format!("can not update the tuple at {:?}, the page has no room for {} bytes", rid, tuple.get_length()));
+        }
+        let Slot { offset, len, kind, .. } = self.get_slots()[rid.sid as usize].clone();
+        let (offset, len) = (offset as usize, len as usize);
+        let mut data = match kind {
+            SLOT_MOVED => self.get_page_slice()[offset..offset + MOVED_HEADER_SIZE].to_vec(),
+            _ => Vec::new(),
+        };
+        data.extend_from_slice(&tuple.get_data());
+
+        let offset = match data.len() <= len {
+            true => {
+                self.get_page_slice_mut()[offset..offset + data.len()].copy_from_slice(&data);
+                offset as u32
+            },
+            false => {
+                // the old bytes are free now, a compaction leaves them out
+                self.get_slots_mut()[rid.sid as usize].len = 0;
+                if self.get_contiguous_free_space(0) < data.len() {
+                    self.compact();
+                }
+                self.write_data(&data)
+            },
+        };
+        self.tuple_bytes = self.tuple_bytes - len as u32 + data.len() as u32;
+        let slot = &mut self.get_slots_mut()[rid.sid as usize];
+        slot.offset = offset;
+        slot.len = data.len() as u16;
+        Ok(())
+    }
+
+    // the tuple at the rid is moved to `to`, its slot keeps the meta and forwards to it.
+    // a slot forwarding already is pointed to the new place
+    pub fn set_forward(&mut self, rid: &RID, to: &RID) -> Result<(), String> {
+        let Some(slot) = self.get_slots().get(rid.sid as usize).cloned() else {
+            return Err(format!("Slot idx is out of range"));
+        };
+        match slot.kind {
+            SLOT_NORMAL => self.tuple_bytes -= slot.len as u32,
+            SLOT_FORWARD => {},
+            _ => return Err(format!("the slot of {:?} has no tuple to forward", rid)),
+        }
+        let slot = &mut self.get_slots_mut()[rid.sid as usize];
+        let addr = PageAddr::of(to.pid);
+        slot.kind = SLOT_FORWARD;
+        slot.file_id = addr.file_id;
+        slot.offset = addr.page_no as u32;
+        slot.len = to.sid;
+        Ok(())
+    }
+
+    // where the tuple at the rid is moved to
+    pub fn get_forward(&self, rid: &RID) -> Option<RID> {
+        match self.get_slots().get(rid.sid as usize) {
+            Some(slot) if slot.kind == SLOT_FORWARD => Some(RID {
+                pid: PageAddr::new(slot.file_id, slot.offset as page_id_t).to_page_id(),
+                sid: slot.len,
+            }),
+            _ => None,
+        }
+    }
+
+    // the rid a tuple moved here is read through
+    pub fn get_moved_from(&self, rid: &RID) -> Option<RID> {
+        match self.get_slots().get(rid.sid as usize) {
+            Some(slot) if slot.kind == SLOT_MOVED => {
+                let at = slot.offset as usize;
+                let header = &self.get_page_slice()[at..at + MOVED_HEADER_SIZE];
+                Some(RID {
+                    pid: page_id_t::from_le_bytes(header[..size_of::<page_id_t>()].try_into().unwrap()),
+                    sid: u16::from_le_bytes(header[size_of::<page_id_t>()..].try_into().unwrap()),
+                })
+            },
+            _ => None,
         }
+    }
 
-        let infos = self.get_tuple_info_slice_mut();
-        infos[slot_idx] = (off, len, meta);
+    // remove the deleted tuples of the slots and the moved ones, then compact the page.
+    // the slots of the others stay where they are, so their rids do not change, and the empty ones
+    // are taken by the next tuples inserted. the empty slots at the end of the directory are dropped
+    pub fn vacuum(&mut self, slots: &[u16]) -> Result<(), String> {
+        let mut num_removed = 0;
+        for sid in slots {
+            let Some(slot) = self.get_slots().get(*sid as usize).cloned() else {
+                return Err(format!("Slot idx is out of range"));
+            };
+            match slot.kind {
+                SLOT_EMPTY => continue,
+                SLOT_NORMAL | SLOT_FORWARD if !slot.meta.is_deleted => {
+                    return Err(format!("the tuple at slot {} is not deleted, can not vacuum it", sid));
+                },
+                SLOT_NORMAL | SLOT_FORWARD => num_removed += 1,
+                _ => {},
+            }
+            if slot.kind != SLOT_FORWARD {
+                self.tuple_bytes -= slot.len as u32;
+            }
+            let slot = &mut self.get_slots_mut()[*sid as usize];
+            slot.kind = SLOT_EMPTY;
+            slot.len = 0;
+        }
+        while self.num_tuples > 0 && self.get_slots()[self.num_tuples as usize - 1].kind == SLOT_EMPTY {
+            self.num_tuples -= 1;
+        }
+        self.num_deleted_tuples -= num_removed;
+        self.compact();
         Ok(())
     }
 
-    pub fn update_tuple_meta_inplace(&mut self, meta: &TupleMeta, tuple: &Tuple, rid: &RID) {
-        todo!()
+    pub fn is_vacuumed(&self, rid: &RID) -> bool {
+        rid.sid < self.num_tuples && self.get_slots()[rid.sid as usize].kind == SLOT_EMPTY
     }
 
-    // returns the tuple in phisical page at the rid
+    // returns the tuple in phisical page at the rid, a moved tuple without the rid it is read through
     pub fn get_tuple(&self, rid: &RID) -> Result<(TupleMeta, Tuple), String> {
         if rid.sid >= self.num_tuples {
             return Err(format!("slot id out of range"));
         }
 
-        let infos = self.get_tuple_info_slice();
-        let (off, len, meta) = infos[rid.sid as usize].clone(); 
-        let (off, len) = (off as usize, len as usize);
+        let Slot { offset, len, kind, meta, .. } = self.get_slots()[rid.sid as usize].clone();
+        let (off, len) = match kind {
+            SLOT_EMPTY => return Err(format!("the tuple at {:?} is vacuumed", rid)),
+            SLOT_FORWARD => return Err(format!("the tuple at {:?} is moved to {:?}", rid, self.get_forward(rid).unwrap())),
+            SLOT_MOVED => (offset as usize + MOVED_HEADER_SIZE, len as usize - MOVED_HEADER_SIZE),
+            _ => (offset as usize, len as usize),
+        };
         let page = self.get_page_slice();
 
-        Ok((meta, Tuple::deserialize(&Vec::from(&page[off..off+len]))?))
+        let mut tuple = Tuple::deserialize(&Vec::from(&page[off..off+len]))?;
+        tuple.set_rid(*rid);
+        Ok((meta, tuple))
     }
 
     pub fn get_tuple_meta(&self, rid:  &RID) -> Result<TupleMeta, String> {
         if rid.sid >= self.num_tuples {
             return Err(format!("slot id out of range"));
         }
+        Ok(self.get_slots()[rid.sid as usize].meta.clone())
+    }
+
+    // the bytes a tuple may take after a compaction, a new tuple takes its length and a slot of them
+    pub fn get_free_space(&self) -> usize {
+        self.page_size as usize - PAGE_HEADER_SIZE - self.num_tuples as usize * SLOT_SIZE - self.tuple_bytes as usize
+    }
+
+    // the free space of an empty page, the most a tuple may take
+    pub fn get_max_free_space(page_size: usize) -> usize {
+        page_size - PAGE_HEADER_SIZE
+    }
 
-        let infos = self.get_tuple_info_slice();
-        let (_, _, meta) = infos[rid.sid as usize].clone();
-        Ok(meta)
+    // the free space a tuple needs on a page
+    pub fn get_space_needed(tuple: &Tuple) -> usize {
+        tuple.get_length() as usize + SLOT_SIZE
+    }
+
+    // the same for a tuple moved to the page, see `insert_moved_at`
+    pub fn get_space_needed_to_move(tuple: &Tuple) -> usize {
+        Self::get_space_needed(tuple) + MOVED_HEADER_SIZE
     }
 
     /// ================== private methods ===============
+    // how many slots can be satisfied in a page
+    fn get_max_num_tuples(&self) -> usize {
+        (self.page_size as usize - PAGE_HEADER_SIZE) / SLOT_SIZE
+    }
+
     fn get_page_slice(&self) -> &[u8] {
         unsafe {
-            std::slice::from_raw_parts(self.page_start.as_ptr(), PAGE_SIZE as usize)
+            std::slice::from_raw_parts(self.page_start.as_ptr(), self.page_size as usize)
         }
     }
 
-    fn get_tuple_info_slice(&self) -> &[TupleInfo] {
+    fn get_slots(&self) -> &[Slot] {
         unsafe {
-            std::slice::from_raw_parts(self.tuple_infos.as_ptr(), TUPLE_INFO_MAX_LEN)
+            std::slice::from_raw_parts(self.slots.as_ptr(), self.num_tuples as usize)
         }
     }
+
     fn get_page_slice_mut(&mut self) -> &mut [u8] {
         unsafe {
-            std::slice::from_raw_parts_mut(self.page_start.as_mut_ptr(), PAGE_SIZE as usize)
+            std::slice::from_raw_parts_mut(self.page_start.as_mut_ptr(), self.page_size as usize)
         }
     }
 
-    fn get_tuple_info_slice_mut(&mut self) -> &mut [TupleInfo] {
+    fn get_slots_mut(&mut self) -> &mut [Slot] {
         unsafe {
-            std::slice::from_raw_parts_mut(self.tuple_infos.as_mut_ptr(), TUPLE_INFO_MAX_LEN)
+            std::slice::from_raw_parts_mut(self.slots.as_mut_ptr(), self.num_tuples as usize)
         }
     }
-    fn get_slot_offset(&self, slot_idx: u16) -> u16 {
-        // return the offset of tuple in page 
-        if slot_idx >= self.num_tuples {
-            panic!("Slot idx {} is greater than num {}", slot_idx, self.num_tuples);
-        }
-        let tuple_infos = unsafe {
-            std::slice::from_raw_parts(self.tuple_infos.as_ptr(), TUPLE_INFO_MAX_LEN)
-        };
-        return tuple_infos[slot_idx as usize].0;
+
+    // the bytes between the directory and the free pointer, with `new_slots` more slots
+    fn get_contiguous_free_space(&self, new_slots: usize) -> usize {
+        (self.free_pointer as usize).saturating_sub(PAGE_HEADER_SIZE + (self.num_tuples as usize + new_slots) * SLOT_SIZE)
     }
 
-    /// return the tuple store position
-    pub fn get_next_offset(&self, meta: &TupleMeta, tuple: &Tuple) -> Option<u16> {
-        let mut tuple_end_offset = {
-            if self.num_tuples > 0 {
-                self.get_slot_offset(self.num_tuples - 1) as i32
-            } else {
-                PAGE_SIZE
-            }
+    // put the data of a new tuple into the slot, None if the page has no room for it
+    fn place(&mut self, sid: u16, kind: u8, meta: &TupleMeta, data: &[u8]) -> Option<u16> {
+        let new_slots = match sid == self.num_tuples {
+            true => 1,
+            false if (sid as usize) < self.num_tuples as usize && self.get_slots()[sid as usize].kind == SLOT_EMPTY => 0,
+            false => return None,
         };
+        if new_slots == 1 && self.num_tuples as usize >= self.get_max_num_tuples() {
+            return None;
+        }
+        if data.len() + new_slots * SLOT_SIZE > self.get_free_space() {
+            return None;
+        }
+        if self.get_contiguous_free_space(new_slots) < data.len() {
+            self.compact();
+        }
 
-        tuple_end_offset = tuple_end_offset - tuple.get_length() as i32;
-        let slot_end_offset = PAGE_HEADER_SIZE + 
-                            (self.num_tuples + 1) as usize * TUPLE_INFO_SIZE;
+        let offset = self.write_data(data);
+        self.num_tuples += new_slots as u16;
+        self.get_slots_mut()[sid as usize] = Slot { offset, len: data.len() as u16, file_id: 0, kind, meta: meta.clone() };
+        self.tuple_bytes += data.len() as u32;
+        if kind == SLOT_NORMAL && meta.is_deleted {
+            self.num_deleted_tuples += 1;
+        }
+        Some(sid)
+    }
 
-        if tuple_end_offset < (slot_end_offset as i32) {
-            return None;
+    // write the bytes below the free pointer, which must have room for them, returns where they start
+    fn write_data(&mut self, data: &[u8]) -> u32 {
+        let offset = self.free_pointer as usize - data.len();
+        self.get_page_slice_mut()[offset..offset + data.len()].copy_from_slice(data);
+        self.free_pointer = offset as u32;
+        offset as u32
+    }
+
+    // move the data of the tuples to the end of the page, so the free space is between the directory and the data.
+    // the one at the highest offset goes first, so each one moves up or stays
+    fn compact(&mut self) {
+        let mut order: Vec<(u32, usize)> = self.get_slots().iter().enumerate()
+            .filter(|(_, slot)| slot.kind == SLOT_NORMAL || slot.kind == SLOT_MOVED)
+            .map(|(sid, slot)| (slot.offset, sid))
+            .collect();
+        order.sort_by(|a, b| b.cmp(a));
+
+        let mut tuple_end = self.page_size as usize;
+        for (offset, sid) in order {
+            let len = self.get_slots()[sid].len as usize;
+            let new_offset = tuple_end - len;
+            self.get_page_slice_mut().copy_within(offset as usize..offset as usize + len, new_offset);
+            self.get_slots_mut()[sid].offset = new_offset as u32;
+            tuple_end = new_offset;
         }
-        Some(tuple_end_offset as u16)
+        self.free_pointer = tuple_end as u32;
     }
 }
 
@@ -179,7 +416,7 @@ impl TablePage {
 
 #[cfg(test)]
 mod tests {
-    use crate::{common::rid::RID, storage::page_based::{page::table_page::PAGE_HEADER_SIZE, table::tuple::{Tuple, TupleMeta}}};
+    use crate::{common::{page_addr::PageAddr, rid::RID}, storage::page_based::{page::table_page::{PAGE_HEADER_SIZE, SLOT_SIZE}, table::tuple::{Tuple, TupleMeta}}};
 
     use super::TablePage;
 
@@ -191,9 +428,10 @@ mod tests {
 
     #[test]
     fn sample_insert_test() {
-        let mut buf: [u8; 4096] = [0; 4096];
+        let mut buf = vec![0u8; 4096];
 
         let t_page = unsafe { &mut *(buf.as_mut_ptr() as *mut TablePage) };
+        t_page.init(buf.len());
 
         let meta = TupleMeta {
             insert_txn_id: -1,
@@ -217,9 +455,9 @@ mod tests {
 
     #[test]
     fn insert_get_test() {
-        let mut buf: [u8; 4096] = [0; 4096];
+        let mut buf = vec![0u8; 4096];
         let table_page = unsafe { &mut *(buf.as_mut_ptr() as *mut TablePage) };
-
+        table_page.init(buf.len());
 
         let meta = TupleMeta {
             insert_txn_id: -12,
@@ -244,4 +482,104 @@ mod tests {
         }
     }
 
-}
\ No newline at end of file
+    #[test]
+    fn vacuum_test() {
+        let mut buf = vec![0u8; 4096];
+        let table_page = unsafe { &mut *(buf.as_mut_ptr() as *mut TablePage) };
+        table_page.init(buf.len());
+
+        let meta = TupleMeta::new(1, -1, false);
+        let tuples: Vec<Tuple> = (1..=4u8).map(|i| Tuple::deserialize(&vec![i; 100 * i as usize]).unwrap()).collect();
+        for tuple in tuples.iter() {
+            table_page.insert_tuple(&meta, tuple).unwrap();
+        }
+        let rid = |sid: u16| RID { pid: 0, sid };
+        let free_space = table_page.get_free_space();
+        assert_eq!(true, table_page.vacuum(&[1]).is_err());
+
+        let deleted = TupleMeta::new(1, 2, true);
+        table_page.update_tuple_meta(&deleted, &rid(1)).unwrap();
+        table_page.update_tuple_meta(&deleted, &rid(3)).unwrap();
+        assert_eq!(2, table_page.get_num_deleted_tuples());
+        table_page.vacuum(&[1, 3]).unwrap();
+        assert_eq!(0, table_page.get_num_deleted_tuples());
+        // the empty slot at the end is dropped, the one in the middle stays
+        assert_eq!(3, table_page.get_num_tuples());
+        assert_eq!(free_space + 600 + SLOT_SIZE, table_page.get_free_space());
+
+        // the others keep their slots
+        assert_eq!((true, true), (table_page.is_vacuumed(&rid(1)), table_page.get_tuple(&rid(1)).is_err()));
+        assert_eq!(tuples[0].get_data(), table_page.get_tuple(&rid(0)).unwrap().1.get_data());
+        assert_eq!(tuples[2].get_data(), table_page.get_tuple(&rid(2)).unwrap().1.get_data());
+        // again is a no-op, a new tuple takes the empty slot
+        table_page.vacuum(&[1]).unwrap();
+        assert_eq!(free_space + 600 + SLOT_SIZE, table_page.get_free_space());
+        assert_eq!(Some(1), table_page.insert_tuple(&meta, &tuples[3]));
+        assert_eq!(tuples[3].get_data(), table_page.get_tuple(&rid(1)).unwrap().1.get_data());
+        assert_eq!(Some(3), table_page.insert_tuple(&meta, &tuples[1]));
+        assert_eq!(free_space, table_page.get_free_space());
+
+        // all of them gone, the page is as empty as a new one
+        for sid in 0..4 {
+            table_page.update_tuple_meta(&deleted, &rid(sid)).unwrap();
+        }
+        table_page.vacuum(&[0, 1, 2, 3]).unwrap();
+        assert_eq!((0, TablePage::get_max_free_space(4096)), (table_page.get_num_tuples(), table_page.get_free_space()));
+    }
+
+    #[test]
+    fn update_test() {
+        let mut buf = vec![0u8; 4096];
+        let table_page = unsafe { &mut *(buf.as_mut_ptr() as *mut TablePage) };
+        table_page.init(buf.len());
+
+        let meta = TupleMeta::new(1, -1, false);
+        let build = |byte: u8, len: usize| Tuple::deserialize(&vec![byte; len]).unwrap();
+        let rid = |sid: u16| RID { pid: 0, sid };
+        for i in 0..3 {
+            table_page.insert_tuple(&meta, &build(i, 1000)).unwrap();
+        }
+        let free_space = table_page.get_free_space();
+
+        // a shorter one stays in place, a longer one takes the room left
+        table_page.update_tuple(&rid(0), &build(9, 400)).unwrap();
+        assert_eq!(free_space + 600, table_page.get_free_space());
+        assert_eq!(false, table_page.has_room_for_update(&rid(1), &build(8, 1000 + free_space + 601)));
+        assert_eq!(true, table_page.update_tuple(&rid(1), &build(8, 1000 + free_space + 601)).is_err());
+        // it only fits after the hole left by the first one is taken back
+        table_page.update_tuple(&rid(1), &build(8, 1000 + free_space + 600)).unwrap();
+        assert_eq!(0, table_page.get_free_space());
+        assert_eq!(build(9, 400).get_data(), table_page.get_tuple(&rid(0)).unwrap().1.get_data());
+        assert_eq!(build(8, 1000 + free_space + 600).get_data(), table_page.get_tuple(&rid(1)).unwrap().1.get_data());
+        assert_eq!(build(2, 1000).get_data(), table_page.get_tuple(&rid(2)).unwrap().1.get_data());
+        assert_eq!(meta, table_page.get_tuple(&rid(1)).unwrap().0);
+
+        // the tuple of the last slot moves to another page, the slot keeps the meta and forwards to it
+        let to = RID { pid: 7, sid: 3 };
+        table_page.set_forward(&rid(2), &to).unwrap();
+        assert_eq!(1000, table_page.get_free_space());
+        assert_eq!((Some(to), None), (table_page.get_forward(&rid(2)), table_page.get_forward(&rid(1))));
+        assert_eq!(true, table_page.get_tuple(&rid(2)).is_err());
+        assert_eq!(meta, table_page.get_tuple_meta(&rid(2)).unwrap());
+        // it may move again, to a page of a tablespace far into its file
+        let far = RID { pid: PageAddr::new(2, 1 << 31).to_page_id(), sid: 5 };
+        table_page.set_forward(&rid(2), &far).unwrap();
+        assert_eq!((Some(far), 1000), (table_page.get_forward(&rid(2)), table_page.get_free_space()));
+        table_page.set_forward(&rid(2), &to).unwrap();
+
+        // a tuple moved here keeps the rid it is read through, its update keeps it too
+        let from = RID { pid: 5, sid: 11 };
+        let sid = table_page.insert_moved_at(table_page.get_next_slot(), &from, &build(6, 300)).unwrap();
+        assert_eq!((3, Some(from)), (sid, table_page.get_moved_from(&rid(sid))));
+        assert_eq!(1000 - TablePage::get_space_needed_to_move(&build(6, 300)), table_page.get_free_space());
+        table_page.update_tuple(&rid(sid), &build(5, 500)).unwrap();
+        assert_eq!((Some(from), build(5, 500).get_data()), (table_page.get_moved_from(&rid(sid)), table_page.get_tuple(&rid(sid)).unwrap().1.get_data()));
+
+        // the moved one is removed without a delete, the forwarding one once it is deleted
+        table_page.vacuum(&[sid]).unwrap();
+        assert_eq!(true, table_page.vacuum(&[2]).is_err());
+        table_page.update_tuple_meta(&TupleMeta::new(1, 2, true), &rid(2)).unwrap();
+        table_page.vacuum(&[2]).unwrap();
+        assert_eq!((2, 1000 + SLOT_SIZE), (table_page.get_num_tuples(), table_page.get_free_space()));
+    }
+}
@@ -1,3 +1,4 @@
 pub mod tuple;
 pub mod table_heap;
-pub mod table_iter;
\ No newline at end of file
+pub mod table_iter;
+pub mod toast;
@@ -1,10 +1,10 @@
 #![allow(warnings)]
 
-use std::sync::{Mutex, Arc};
+use std::{collections::{BTreeSet, HashMap}, sync::{Mutex, Arc}};
 
-use crate::{buffer::buffer_pool_manager::BufferPoolManager, common::{config::page_id_t, rid::RID}, storage::page_based::page::table_page::TablePage};
+use crate::{catalog::schema::Schema, buffer::{access_strategy::{AccessIntent, BufferAccessStrategy}, buffer_pool_manager::BufferPoolManager}, common::{config::{file_id_t, lsn_t, page_id_t, INVALID_LSN, INVALID_PAGE_ID, INVALID_TXN_ID, MAIN_FILE_ID}, rid::RID}, storage::page_based::{disk::{log_manager::LogManager, log_record::{LogRecord, LogRecordBody}}, page::{page_guard::WritePageGuard, table_page::TablePage}}, transaction::transaction::TransactionRef};
 
-use super::{tuple::{TupleMeta, Tuple}, table_iter::TableIter};
+use super::{tuple::{TupleMeta, Tuple}, table_iter::TableIter, toast::Toaster};
 
 
 
@@ -17,102 +17,492 @@ pub type BufferPoolManagerRef = Arc<BufferPoolManager>;
 struct TableHeapState {
     first_page_id: page_id_t,
     last_page_id: page_id_t,
+    // the pages with room for more tuples by their free bytes, the least room that fits is taken first
+    pages_with_space: BTreeSet<(usize, page_id_t)>,
+    free_space: HashMap<page_id_t, usize>,
+    // the pages with deleted tuples, the ones a vacuum of the heap goes through
+    pages_to_vacuum: BTreeSet<page_id_t>,
 }
 
 pub type TableHeapRef = Arc<TableHeap>;
 
+/// TableHeap, the pages of a table linked one by one.
+/// The changes made by a transaction are logged before they are applied to the page,
+/// and the page takes the lsn of the record, the ones without a transaction are not logged.
+/// A tuple goes to a page of the heap with room for it, a new page is linked at the end only when there is none,
+/// the free bytes of the pages are recorded in the free space map of the pool too.
+/// A deleted tuple stays on its page until a vacuum removes it once no transaction sees it,
+/// the page is compacted then and its slots stay, so the rids of the other tuples do not change.
+/// A tuple replaced by a longer one which its page has no room for is moved to another page,
+/// its slot forwards to it and it is read through the slot, so its rid stays too.
+/// All of the pages are in the file of the tablespace the heap is created in.
+/// With a toaster the large values of a tuple are kept in overflow pages, the tuples are read back with the values
 #[derive(Debug)]
 pub struct TableHeap {
     bpm: BufferPoolManagerRef,
+    file_id: file_id_t,
+    log_mgr: Arc<LogManager>,
+    toaster: Option<Toaster>,
     state: Mutex<TableHeapState>,
 }
 
 
 impl TableHeap {
     // create a new table heap
-    pub fn new(bpm: BufferPoolManagerRef) -> Self {
-        match bpm.new_page_guarded() {
+    pub fn new(bpm: BufferPoolManagerRef) -> Result<Self, String> {
+        Self::new_in(bpm, MAIN_FILE_ID)
+    }
+
+    // create a new table heap in the file of a tablespace
+    pub fn new_in(bpm: BufferPoolManagerRef, file_id: file_id_t) -> Result<Self, String> {
+        match bpm.new_page_guarded_in(file_id) {
             None => {
-                panic!("New a null page, check the bpm");
+                Err(format!("can not allocate the first page of the table heap, bpm err"))
             },
             Some(mut page_guard) => {
                 let pid = page_guard.get_pid().unwrap();
 
+                // the first page is logged out of any transaction, the redo starts the table heap from it
+                let log_mgr = bpm.get_log_manager();
+                let mut record = LogRecord::new(INVALID_TXN_ID, INVALID_LSN, LogRecordBody::NewPage { prev_page_id: INVALID_PAGE_ID, page_id: pid });
+                let lsn = log_mgr.append_log_record(&mut record);
+
                 let table_page = page_guard.get_mut_as::<TablePage>();
-                table_page.init();
+                table_page.init(bpm.get_page_size());
+                table_page.set_lsn(lsn);
                 let state = TableHeapState {
                     first_page_id: pid,
-                    last_page_id: pid
+                    last_page_id: pid,
+                    pages_with_space: BTreeSet::new(),
+                    free_space: HashMap::new(),
+                    pages_to_vacuum: BTreeSet::new(),
                 };
-                TableHeap {
+                let table_heap = TableHeap {
                     bpm: bpm.clone(),
+                    file_id,
+                    log_mgr,
+                    toaster: None,
                     state: Mutex::new(state)  
+                };
+                table_heap.record_free_space(&mut table_heap.state.lock().unwrap(), pid, table_page.get_free_space())?;
+                Ok(table_heap)
+            }
+        }
+    }
+
+    // open the table heap of the file from its first page, after a restart.
+    // the free bytes of the pages and the pages with deleted tuples are taken from the pages of it
+    pub fn open_in(bpm: BufferPoolManagerRef, file_id: file_id_t, first_page_id: page_id_t) -> Result<Self, String> {
+        let state = TableHeapState {
+            first_page_id,
+            last_page_id: first_page_id,
+            pages_with_space: BTreeSet::new(),
+            free_space: HashMap::new(),
+            pages_to_vacuum: BTreeSet::new(),
+        };
+        let table_heap = TableHeap {
+            bpm: bpm.clone(),
+            file_id,
+            log_mgr: bpm.get_log_manager(),
+            toaster: None,
+            state: Mutex::new(state),
+        };
+        {
+            let mut lock_ = table_heap.state.lock().unwrap();
+            let mut pid = first_page_id;
+            while pid != INVALID_PAGE_ID {
+                let page_guard = bpm.fetch_page_read(pid)
+                    .ok_or(format!("can not fetch page {} of the table heap, bpm err", pid))?;
+                let table_page = page_guard.get_as::<TablePage>();
+                table_heap.record_free_space(&mut lock_, pid, table_page.get_free_space())?;
+                if table_page.get_num_deleted_tuples() > 0 {
+                    lock_.pages_to_vacuum.insert(pid);
                 }
+                lock_.last_page_id = pid;
+                pid = table_page.get_next_page_id();
             }
         }
+        Ok(table_heap)
+    }
+
+    // toast the tuples of the schema, see `Toaster`
+    pub fn with_toaster(mut self, schema: Schema, compression: bool) -> Self {
+        self.toaster = Some(Toaster::new(self.bpm.clone(), self.file_id, schema, compression));
+        self
+    }
+
+    pub fn insert_tuple(&self, meta: &TupleMeta, tuple: &Tuple, txn: Option<&TransactionRef>) -> Result<RID, String> {
+        self.insert_tuple_with(meta, tuple, txn, &BufferAccessStrategy::new(AccessIntent::Normal), |_| Ok(()))
     }
 
-    pub fn insert_tuple(&self, meta: &TupleMeta, tuple: &Tuple) -> Option<RID> {
+    // insert with the pages fetched by the strategy, a bulk write keeps the pages it fills in its ring.
+    // `lock_rid` locks the rid before the tuple is on the page, so no one reaches the tuple before its inserter locks it.
+    // the slot is a new one or one of a vacuumed version, which no running transaction sees, so no one else has its lock.
+    // it runs under the heap mutex and the latch of the page, it must not wait, see `LockManager::lock_row_nowait`
+    pub fn insert_tuple_with(&self, meta: &TupleMeta, tuple: &Tuple, txn: Option<&TransactionRef>, strategy: &BufferAccessStrategy,
+        lock_rid: impl FnOnce(RID) -> Result<(), String>) -> Result<RID, String> {
+        // the large values go to the overflow pages first
+        let toasted;
+        let tuple = match &self.toaster {
+            None => tuple,
+            Some(toaster) => {
+                toasted = toaster.toast(tuple)?;
+                &toasted
+            },
+        };
+        let space_needed = TablePage::get_space_needed(tuple);
+        // an empty page can not take it
+        if space_needed > TablePage::get_max_free_space(self.bpm.get_page_size()) {
+            return Err(format!("tuple of {} bytes does not fit in a page", space_needed));
+        }
+
         let mut lock_ = self.state.lock().unwrap();
+        let mut page_guard = self.fetch_page_with_space(&mut lock_, space_needed, &[], txn, strategy)?;
+
+        let pid = page_guard.get_pid().unwrap();
+        let table_page = page_guard.get_mut_as::<TablePage>();
+        // the slot of a vacuumed tuple is taken first, the redo takes the one logged
+        let rid = RID { pid, sid: table_page.get_next_slot() };
+        lock_rid(rid)?;
+        let lsn = self.append_log(txn, LogRecordBody::Insert { rid, meta: meta.clone(), tuple: tuple.clone() });
+        let slot_id = table_page.insert_tuple_at(rid.sid, meta, tuple);
+        table_page.set_lsn(lsn);
+        self.record_free_space(&mut lock_, pid, table_page.get_free_space())?;
+
+        Ok(RID {
+            pid,
+            sid: slot_id.unwrap(),
+        })
+    }
 
-        let mut last_page_guard;
-        let mut last_pid = lock_.last_page_id;
+    // a page with room for the tuple but the excluded ones, a new page is linked at the end when there is none
+    fn fetch_page_with_space(&self, lock_: &mut TableHeapState, space_needed: usize, exclude: &[page_id_t], txn: Option<&TransactionRef>, strategy: &BufferAccessStrategy) -> Result<WritePageGuard<'_>, String> {
         loop {
-            let page_guard = self.bpm.fetch_page_write(lock_.last_page_id);
-            let mut page_guard = page_guard.expect("can not fetch page, bpm err");
-            let page = page_guard.get_mut_as::<TablePage>();
+            let Some((_, pid)) = lock_.pages_with_space.range((space_needed, page_id_t::MIN)..)
+                .find(|(_, pid)| !exclude.contains(pid)).cloned() else {
+                //here, means no page has enough space, create a new page
+                self.append_page(lock_, txn, strategy)?;
+                continue;
+            };
+            let page_guard = self.bpm.fetch_page_write_with(pid, strategy)
+                .ok_or(format!("can not fetch page {}, bpm err", pid))?;
+            let free_space = page_guard.get_as::<TablePage>().get_free_space();
+            if free_space >= space_needed {
+                return Ok(page_guard);
+            }
+            // the recorded space is out of date
+            self.record_free_space(lock_, pid, free_space)?;
+        }
+    }
+
+    // link a new page after the last one
+    fn append_page(&self, lock_: &mut TableHeapState, txn: Option<&TransactionRef>, strategy: &BufferAccessStrategy) -> Result<(), String> {
+        let mut last_page_guard = self.bpm.fetch_page_write_with(lock_.last_page_id, strategy)
+            .ok_or(format!("can not fetch page {}, bpm err", lock_.last_page_id))?;
+        let mut new_page = self.bpm.new_page_guarded_in_with(self.file_id, strategy)
+            .ok_or(format!("can not allocate a page for the table heap, bpm err"))?;
+        let new_pid = new_page.get_pid().unwrap();
+
+        let lsn = self.append_log(txn, LogRecordBody::NewPage { prev_page_id: lock_.last_page_id, page_id: new_pid });
+        let new_table_page = new_page.get_mut_as::<TablePage>();
+        new_table_page.init(self.bpm.get_page_size());
+        new_table_page.set_lsn(lsn);
+        let last_table_page = last_page_guard.get_mut_as::<TablePage>();
+        last_table_page.set_next_page_id(new_pid);
+        last_table_page.set_lsn(lsn);
+        lock_.last_page_id = new_pid;
+        self.record_free_space(lock_, new_pid, new_table_page.get_free_space())
+    }
+
+    // a page without room for any tuple is not kept in the set
+    fn record_free_space(&self, lock_: &mut TableHeapState, pid: page_id_t, free_space: usize) -> Result<(), String> {
+        if let Some(old_space) = lock_.free_space.insert(pid, free_space) {
+            lock_.pages_with_space.remove(&(old_space, pid));
+        }
+        if free_space > TablePage::get_space_needed(&Tuple::new()) {
+            lock_.pages_with_space.insert((free_space, pid));
+        }
+        self.bpm.update_free_space(pid, free_space)
+    }
 
-            let slot_off = page.get_next_offset(meta, tuple);
+    // replace the tuple at the rid, it keeps its meta. it is for a version no other transaction sees, which goes
+    // with the undo of its insert, since the update is redone but never undone. the tuple grows on its page while
+    // there is room, otherwise it is moved to another page and its slot forwards to it, so the rid stays.
+    // returns the replaced tuple and the new one as they are stored, the overflow chains of the replaced one are
+    // kept until it can not be put back any more, see `free_replaced`
+    pub fn update_tuple(&self, rid: &RID, tuple: &Tuple, txn: Option<&TransactionRef>) -> Result<(Tuple, Tuple), String> {
+        let toasted;
+        let tuple = match &self.toaster {
+            None => tuple,
+            Some(toaster) => {
+                toasted = toaster.toast(tuple)?;
+                &toasted
+            },
+        };
 
-            if None != slot_off {
-                last_page_guard = page_guard;
-                last_pid = lock_.last_page_id;
-                break;
+        let mut lock_ = self.state.lock().unwrap();
+        // where the tuple is now, its slot or the page it is moved to already
+        let (from, old_tuple) = {
+            let mut page_guard = self.bpm.fetch_page_write(rid.pid)
+                .ok_or(format!("can not fetch page {}, bpm err", rid.pid))?;
+            let from = page_guard.get_as::<TablePage>().get_forward(rid).unwrap_or(*rid);
+            let mut from_guard = match from.pid == rid.pid {
+                true => None,
+                false => Some(self.bpm.fetch_page_write(from.pid).ok_or(format!("can not fetch page {}, bpm err", from.pid))?),
+            };
+            let from_page = from_guard.as_mut().unwrap_or(&mut page_guard).get_mut_as::<TablePage>();
+            let (_, old_tuple) = from_page.get_tuple(&from)?;
+            if from_page.has_room_for_update(&from, tuple) {
+                let lsn = self.append_log(txn, LogRecordBody::UpdateTuple { rid: from, tuple: tuple.clone() });
+                from_page.update_tuple(&from, tuple)?;
+                from_page.set_lsn(lsn);
+                self.record_free_space(&mut lock_, from.pid, from_page.get_free_space())?;
+                return Ok((old_tuple, tuple.clone()));
             }
-            // check if tuple is too large, means zero record, and slot_off is invalid
-            assert!(page.get_num_tuples() != 0, "tuple is too large, can not insert");
-            //here, means space is not enough, create a new page
-            let new_page = self.bpm.new_page_guarded();
-            if new_page.is_none() {
-                panic!("can not allocate new page");
+            (from, old_tuple)
+        };
+
+        let space_needed = TablePage::get_space_needed_to_move(tuple);
+        if space_needed > TablePage::get_max_free_space(self.bpm.get_page_size()) {
+            return Err(format!("can not move the tuple at {:?}, an empty page has no room for {} bytes", rid, tuple.get_length()));
+        }
+        // the pages are fetched again once the new one is found, a new page is linked after the last one which may be them
+        let mut to_guard = self.fetch_page_with_space(&mut lock_, space_needed, &[rid.pid, from.pid], txn, &BufferAccessStrategy::new(AccessIntent::Normal))?;
+        let to = RID { pid: to_guard.get_pid().unwrap(), sid: to_guard.get_as::<TablePage>().get_next_slot() };
+        let mut page_guard = self.bpm.fetch_page_write(rid.pid)
+            .ok_or(format!("can not fetch page {}, bpm err", rid.pid))?;
+        let mut from_guard = match from == *rid {
+            true => None,
+            false => Some(self.bpm.fetch_page_write(from.pid).ok_or(format!("can not fetch page {}, bpm err", from.pid))?),
+        };
+
+        // in the order of the redo, the copy moved before is removed first
+        let lsn = self.append_log(txn, LogRecordBody::MoveTuple { rid: *rid, from, to, tuple: tuple.clone() });
+        if let Some(from_guard) = from_guard.as_mut() {
+            let from_page = from_guard.get_mut_as::<TablePage>();
+            from_page.vacuum(&[from.sid])?;
+            from_page.set_lsn(lsn);
+            self.record_free_space(&mut lock_, from.pid, from_page.get_free_space())?;
+        }
+        let table_page = page_guard.get_mut_as::<TablePage>();
+        table_page.set_forward(rid, &to)?;
+        table_page.set_lsn(lsn);
+        self.record_free_space(&mut lock_, rid.pid, table_page.get_free_space())?;
+        let to_page = to_guard.get_mut_as::<TablePage>();
+        to_page.insert_moved_at(to.sid, rid, tuple).expect("the page has room for the tuple");
+        to_page.set_lsn(lsn);
+        self.record_free_space(&mut lock_, to.pid, to_page.get_free_space())?;
+        Ok((old_tuple, tuple.clone()))
+    }
+
+    // the overflow pages of the values the new tuple does not point to any more are given back,
+    // both are stored tuples. it is called once the transaction of the update commits, or with the two swapped
+    // once the update is rolled back
+    pub fn free_replaced(&self, old_tuple: &Tuple, tuple: &Tuple) -> Result<(), String> {
+        match &self.toaster {
+            None => Ok(()),
+            Some(toaster) => toaster.free_chains(old_tuple, Some(tuple)),
+        }
+    }
+
+    pub fn update_meta(&self, meta: &TupleMeta, rid: &RID, txn: Option<&TransactionRef>) -> Result<(), String> {
+        let mut page_guard = self.bpm.fetch_page_write(rid.pid)
+            .ok_or(format!("can not fetch page {}, bpm err", rid.pid))?;
+        let table_page = page_guard.get_mut_as::<TablePage>();
+        let old_meta = table_page.get_tuple_meta(rid)?;
+        let body = match meta.is_deleted && !old_meta.is_deleted {
+            true => LogRecordBody::MarkDelete { rid: *rid, old_meta, new_meta: meta.clone() },
+            false => LogRecordBody::Update { rid: *rid, old_meta, new_meta: meta.clone() },
+        };
+        let lsn = self.append_log(txn, body);
+        table_page.update_tuple_meta(meta, rid)?;
+        table_page.set_lsn(lsn);
+        drop(page_guard);
+        self.note_deleted(meta, rid);
+        Ok(())
+    }
+
+    // undo a change of the transaction, logged as a compensation record,
+    // `undo_next_lsn` is the lsn of the next change of the transaction to undo
+    pub fn compensate_meta(&self, meta: &TupleMeta, rid: &RID, txn: &TransactionRef, undo_next_lsn: lsn_t) -> Result<(), String> {
+        let mut page_guard = self.bpm.fetch_page_write(rid.pid)
+            .ok_or(format!("can not fetch page {}, bpm err", rid.pid))?;
+        let table_page = page_guard.get_mut_as::<TablePage>();
+        let old_meta = table_page.get_tuple_meta(rid)?;
+        let lsn = self.append_log(Some(txn), LogRecordBody::Clr { rid: *rid, old_meta, new_meta: meta.clone(), undo_next_lsn });
+        table_page.update_tuple_meta(meta, rid)?;
+        table_page.set_lsn(lsn);
+        drop(page_guard);
+        self.note_deleted(meta, rid);
+        Ok(())
+    }
+
+    // the page of a deleted tuple is vacuumed next time
+    fn note_deleted(&self, meta: &TupleMeta, rid: &RID) {
+        if meta.is_deleted {
+            self.state.lock().unwrap().pages_to_vacuum.insert(rid.pid);
+        }
+    }
+
+    // remove the dead tuples, `is_dead` tells the ones no transaction sees any more. a full vacuum goes through
+    // all of the pages, otherwise only the ones with tuples deleted since the last vacuum. returns the tuples removed
+    pub fn vacuum(&self, full: bool, is_dead: &dyn Fn(&TupleMeta) -> bool) -> Result<usize, String> {
+        let mut num_removed = 0;
+        if full {
+            let mut pid = self.get_first_page_id();
+            while pid != INVALID_PAGE_ID {
+                let (removed, next_page_id) = self.vacuum_page(pid, is_dead)?;
+                num_removed += removed;
+                pid = next_page_id;
             }
-            let new_page = new_page.unwrap();
+            return Ok(num_removed);
+        }
 
-            page.set_next_page_id(new_page.get_pid()?);
-            lock_.last_page_id = new_page.get_pid()?;
-        }   
-        
-        let last_table_page = last_page_guard.get_mut_as::<TablePage>();
+        let pages: Vec<page_id_t> = self.state.lock().unwrap().pages_to_vacuum.iter().cloned().collect();
+        for pid in pages {
+            num_removed += self.vacuum_page(pid, is_dead)?.0;
+        }
+        Ok(num_removed)
+    }
 
-        let slot_id = last_table_page.insert_tuple(meta, tuple);       
+    // returns the tuples removed and the next page
+    fn vacuum_page(&self, pid: page_id_t, is_dead: &dyn Fn(&TupleMeta) -> bool) -> Result<(usize, page_id_t), String> {
+        // the heap first and then the page, as an insert does
+        let mut lock_ = self.state.lock().unwrap();
+        let mut page_guard = self.bpm.fetch_page_write(pid)
+            .ok_or(format!("can not fetch page {}, bpm err", pid))?;
+        let table_page = page_guard.get_mut_as::<TablePage>();
+        let next_page_id = table_page.get_next_page_id();
+        let mut slots = Vec::new();
+        let mut dead_tuples = Vec::new();
+        let mut forwarded = Vec::new();
+        let num_tuples = match table_page.get_num_deleted_tuples() {
+            0 => 0,
+            _ => table_page.get_num_tuples(),
+        };
+        for sid in 0..num_tuples {
+            let rid = RID { pid, sid };
+            // a moved tuple goes with the slot it is read through
+            if table_page.is_vacuumed(&rid) || table_page.get_moved_from(&rid).is_some() {
+                continue;
+            }
+            let meta = table_page.get_tuple_meta(&rid)?;
+            if !meta.is_deleted || !is_dead(&meta) {
+                continue;
+            }
+            slots.push(sid);
+            match table_page.get_forward(&rid) {
+                Some(to) => forwarded.push((rid, to)),
+                None => dead_tuples.push(table_page.get_tuple(&rid)?.1),
+            }
+        }
 
-        Some(RID {
-            pid: last_pid,
-            sid: slot_id.unwrap(),
-        })
+        if !slots.is_empty() {
+            // out of any transaction, the redo compacts the page again and it is never undone
+            let mut record = LogRecord::new(INVALID_TXN_ID, INVALID_LSN, LogRecordBody::Vacuum { page_id: pid, slots: slots.clone() });
+            let lsn = self.log_mgr.append_log_record(&mut record);
+            table_page.vacuum(&slots)?;
+            table_page.set_lsn(lsn);
+            self.record_free_space(&mut lock_, pid, table_page.get_free_space())?;
+        }
+        // the tuples deleted but still seen by someone are left for the next time
+        if table_page.get_num_deleted_tuples() == 0 {
+            lock_.pages_to_vacuum.remove(&pid);
+        }
+        drop(page_guard);
+
+        // then the copies the removed slots forward to, a crash in between leaves a copy no slot forwards to,
+        // which is never read
+        for (rid, to) in forwarded {
+            let mut page_guard = self.bpm.fetch_page_write(to.pid)
+                .ok_or(format!("can not fetch page {}, bpm err", to.pid))?;
+            let table_page = page_guard.get_mut_as::<TablePage>();
+            if table_page.get_moved_from(&to) != Some(rid) {
+                continue;
+            }
+            dead_tuples.push(table_page.get_tuple(&to)?.1);
+            let mut record = LogRecord::new(INVALID_TXN_ID, INVALID_LSN, LogRecordBody::Vacuum { page_id: to.pid, slots: vec![to.sid] });
+            let lsn = self.log_mgr.append_log_record(&mut record);
+            table_page.vacuum(&[to.sid])?;
+            table_page.set_lsn(lsn);
+            self.record_free_space(&mut lock_, to.pid, table_page.get_free_space())?;
+        }
+        drop(lock_);
+
+        // the values of the tuples removed are not pointed to any more
+        if let Some(toaster) = &self.toaster {
+            for tuple in dead_tuples.iter() {
+                toaster.free_chains(tuple, None)?;
+            }
+        }
+        Ok((slots.len(), next_page_id))
     }
 
-    pub fn update_meta(&self, rid: &RID) {
-        todo!()
+    // append the record to the chain of the transaction, returns its lsn
+    fn append_log(&self, txn: Option<&TransactionRef>, body: LogRecordBody) -> lsn_t {
+        let Some(txn) = txn else {
+            return INVALID_LSN;
+        };
+        let mut record = LogRecord::new(txn.get_txn_id(), txn.get_prev_lsn(), body);
+        let lsn = self.log_mgr.append_log_record(&mut record);
+        if lsn != INVALID_LSN {
+            txn.set_prev_lsn(lsn);
+        }
+        lsn
     }
 
-    pub fn get_tuple(&self, rid: &RID) -> (TupleMeta, Tuple) {
-        let page_guard = self.bpm.fetch_page_read(rid.pid).unwrap();
-        let table_page = page_guard.get_as::<TablePage>();
-        match table_page.get_tuple(rid) {
-            Err(err) => {
-                eprintln!("{}", err);
-                panic!("{}", err);
-            },
-            Ok(tuple_pair) => tuple_pair,
+    // the tuple at rid, a vacuumed one is an error, see `read_tuple` for the ones which may be gone
+    pub fn get_tuple(&self, rid: &RID) -> Result<(TupleMeta, Tuple), String> {
+        self.read_tuple(rid)?.ok_or_else(|| format!("the tuple at {:?} is vacuumed", rid))
+    }
+
+    // the tuple with its values, None once a vacuum removed it. a moved one is read through its slot and takes the rid of it.
+    // the values are read back while the page the tuple is on is latched, a vacuum gives the overflow pages
+    // of a tuple back only after the tuple is off its page
+    pub fn read_tuple(&self, rid: &RID) -> Result<Option<(TupleMeta, Tuple)>, String> {
+        loop {
+            let page_guard = self.bpm.try_fetch_page_read(rid.pid)?;
+            let table_page = page_guard.get_as::<TablePage>();
+            if rid.sid >= table_page.get_num_tuples() || table_page.is_vacuumed(rid) {
+                return Ok(None);
+            }
+            let Some(to) = table_page.get_forward(rid) else {
+                let (meta, tuple) = table_page.get_tuple(rid)?;
+                return Ok(Some((meta, self.detoast(tuple)?)));
+            };
+            let meta = table_page.get_tuple_meta(rid)?;
+            drop(page_guard);
+
+            // the tuple may be moved again before its page is read, the slot there holds another one then
+            let page_guard = self.bpm.try_fetch_page_read(to.pid)?;
+            let table_page = page_guard.get_as::<TablePage>();
+            if table_page.get_moved_from(&to) == Some(*rid) {
+                let (_, mut tuple) = table_page.get_tuple(&to)?;
+                tuple.set_rid(*rid);
+                return Ok(Some((meta, self.detoast(tuple)?)));
+            }
         }
     }
 
-    pub fn get_meta(&self, rid: &RID) -> TupleMeta {
-        todo!()
+    // the tuple read from a page with its values, with the page still latched
+    pub(crate) fn detoast(&self, tuple: Tuple) -> Result<Tuple, String> {
+        match &self.toaster {
+            None => Ok(tuple),
+            Some(toaster) => toaster.detoast(&tuple),
+        }
+    }
+
+    pub fn get_meta(&self, rid: &RID) -> Result<TupleMeta, String> {
+        let page_guard = self.bpm.try_fetch_page_read(rid.pid)?;
+        page_guard.get_as::<TablePage>().get_tuple_meta(rid)
     }
 
     pub fn make_iterator(self: Arc<Self>) -> TableIter {
+        self.make_iterator_with(BufferAccessStrategy::new(AccessIntent::Normal))
+    }
+
+    // the iterator reads the pages by the strategy, e.g. a sequential scan through a ring
+    pub fn make_iterator_with(self: Arc<Self>, strategy: BufferAccessStrategy) -> TableIter {
         let _lock = self.state.lock().unwrap();
         let last_pid = _lock.last_page_id;
 
@@ -122,10 +512,19 @@ impl TableHeap {
 
         TableIter::new(self.clone(), 
             RID {pid: _lock.first_page_id, sid: 0},
-            RID { pid: last_pid, sid: table_page.get_num_tuples()} 
+            RID { pid: last_pid, sid: table_page.get_num_tuples()},
+            strategy
         )
     }
 
+    pub fn get_first_page_id(&self) -> page_id_t {
+        self.state.lock().unwrap().first_page_id
+    }
+
+    pub fn get_file_id(&self) -> file_id_t {
+        self.file_id
+    }
+
     pub fn get_bpm(&self) -> BufferPoolManagerRef {
         self.bpm.clone()
     }
@@ -145,13 +544,15 @@ mod test {
 
     use rand::Rng;
 
-    use crate::{buffer::buffer_pool_manager::BufferPoolManager, storage::page_based::{disk::{disk_manager::DiskManager, log_manager::LogManager}, table::tuple::{TupleMeta, Tuple}}, catalog::{schema::Schema, column::Column}, common::config::INVALID_TXN_ID, typedef::{type_id::TypeId, value::Value}};
+    use std::{fs, os::unix::fs::FileExt};
+
+    use crate::{buffer::{access_strategy::{AccessIntent, BufferAccessStrategy}, buffer_pool_manager::BufferPoolManager}, storage::page_based::{disk::{disk_manager::DiskManager, log_manager::LogManager, log_record::LogRecordBody, memory_page_store::MemoryPageStore}, page::{page::PAGE_CORRUPTED, table_page::TablePage}, table::tuple::{TupleMeta, Tuple}}, catalog::{schema::Schema, column::Column}, common::{config::{page_id_t, INVALID_PAGE_ID, INVALID_TXN_ID, PAGE_SIZE, READ_AHEAD_PAGES}, rid::RID}, transaction::transaction::{IsolationLevel, Transaction}, typedef::{type_id::TypeId, value::Value}};
 
     use super::TableHeap;
     
     fn create_bpm(pool_size: usize, k: usize) -> BufferPoolManager {
         // setup
-        let disk_manager = DiskManager::new("test.db").unwrap();
+        let disk_manager = MemoryPageStore::new();
         let bpm = BufferPoolManager::new(pool_size, Arc::new(disk_manager), 
             k, Arc::new(LogManager::new()));
         bpm
@@ -160,7 +561,7 @@ mod test {
     fn create_table_heap() -> TableHeap {
         let bpm = create_bpm(20, 3);
 
-        TableHeap::new(Arc::new(bpm))
+        TableHeap::new(Arc::new(bpm)).unwrap()
     }
 
     fn create_schema() -> Schema {
@@ -224,7 +625,7 @@ mod test {
 
         for i in 0..10000 {
             let tuple = generate_tuple(&schema);
-            table_heap.insert_tuple(&meta, &tuple);
+            table_heap.insert_tuple(&meta, &tuple, None).unwrap();
         }
 
         println!("{:.2?}", start.elapsed());
@@ -237,4 +638,279 @@ mod test {
         //     println!("{:#?}", tuple.to_string(&schema));
         // }
     }
+
+    #[test]
+    fn read_ahead_test() {
+        let _ = fs::remove_file("test_read_ahead.db");
+        let disk_manager = Arc::new(DiskManager::new("test_read_ahead.db").unwrap());
+        let bpm = Arc::new(BufferPoolManager::new(16, disk_manager, 2, Arc::new(LogManager::new())));
+        let table_heap = Arc::new(TableHeap::new(bpm.clone()).unwrap());
+        let schema = create_schema();
+        let meta = TupleMeta::new(INVALID_TXN_ID, INVALID_TXN_ID, false);
+
+        // the pages of the table are 0, 1, 2 ... and most of them are out of the pool
+        let tuples: Vec<_> = (0..3000).map(|_| generate_tuple(&schema)).collect();
+        let rids: Vec<_> = tuples.iter().map(|tuple| table_heap.insert_tuple(&meta, tuple, None).unwrap()).collect();
+        let last_pid = rids.last().unwrap().pid;
+        assert_eq!(true, last_pid > 32);
+
+        let mut iter = table_heap.clone().make_iterator_with(BufferAccessStrategy::new(AccessIntent::SequentialScan));
+        let mut scanned = Vec::new();
+        while let Some(res) = iter.next() {
+            let (_, tuple) = res.unwrap();
+            // the scan has seen pages 0, 1, 2, so the next ones are read ahead
+            if tuple.get_rid() == (RID { pid: 3, sid: 0 }) {
+                let normal = BufferAccessStrategy::new(AccessIntent::Normal);
+                assert_eq!(false, bpm.prefetch_page(3 + READ_AHEAD_PAGES as page_id_t - 1, &normal));
+            }
+            scanned.push(tuple);
+        }
+        assert_eq!(tuples.len(), scanned.len());
+        // a small tuple may go back to a page with room, the scan is in the order of the rids
+        let mut expected: Vec<_> = rids.iter().zip(tuples.iter()).collect();
+        expected.sort_by_key(|(rid, _)| (rid.pid, rid.sid));
+        for (tuple, (rid, expected)) in scanned.iter().zip(expected.iter()) {
+            assert_eq!(expected.get_data(), tuple.get_data());
+            assert_eq!(**rid, tuple.get_rid());
+        }
+        let _ = fs::remove_file("test_read_ahead.db");
+    }
+
+    #[test]
+    fn corrupted_page_test() {
+        let _ = fs::remove_file("test_corrupted_heap.db");
+        let disk_manager = Arc::new(DiskManager::new("test_corrupted_heap.db").unwrap());
+        let bpm = Arc::new(BufferPoolManager::new(1, disk_manager, 2, Arc::new(LogManager::new())));
+        let table_heap = TableHeap::new(bpm.clone()).unwrap();
+        let schema = create_schema();
+        let meta = TupleMeta::new(INVALID_TXN_ID, INVALID_TXN_ID, false);
+        let rid = table_heap.insert_tuple(&meta, &generate_tuple(&schema), None).unwrap();
+        bpm.flush_all_pages();
+        // the page of the tuple is pushed out of the pool, then a byte of it is changed on disk
+        let page = bpm.new_page().unwrap();
+        bpm.unpin_page(page.get_pid(), false);
+        let file = fs::OpenOptions::new().write(true).open("test_corrupted_heap.db").unwrap();
+        file.write_all_at(&[0xff], (rid.pid as u64 + 1) * PAGE_SIZE as u64 - 1).unwrap();
+
+        // the reads fail with the error instead of a panic
+        assert_eq!(true, table_heap.get_meta(&rid).unwrap_err().starts_with(PAGE_CORRUPTED));
+        assert_eq!(true, table_heap.get_tuple(&rid).unwrap_err().starts_with(PAGE_CORRUPTED));
+        let _ = fs::remove_file("test_corrupted_heap.db");
+    }
+
+    #[test]
+    fn free_space_test() {
+        let bpm = Arc::new(create_bpm(20, 3));
+        bpm.open_free_space_map(0).unwrap();
+        let table_heap = TableHeap::new(bpm.clone()).unwrap();
+        let schema = Schema::new(&vec![Column::new_varchar("a", TypeId::VARCHAR, 2000)]);
+        let meta = TupleMeta::new(INVALID_TXN_ID, INVALID_TXN_ID, false);
+        let build = |len: usize| Tuple::build(&vec![Value::new_varchar(TypeId::VARCHAR, &"x".repeat(len))], &schema);
+
+        // a page takes only one of the big tuples, the rest of it is left for the small ones
+        let big_rids: Vec<RID> = (0..3).map(|_| table_heap.insert_tuple(&meta, &build(2500), None).unwrap()).collect();
+        let page_ids: Vec<page_id_t> = big_rids.iter().map(|rid| rid.pid).collect();
+        assert_eq!(3, page_ids.iter().collect::<std::collections::HashSet<_>>().len());
+        let free_space = bpm.get_free_space(page_ids[0]).unwrap();
+        assert_eq!(true, free_space > 1000 && free_space < 2000);
+
+        let small_rid = table_heap.insert_tuple(&meta, &build(100), None).unwrap();
+        assert_eq!(true, page_ids.contains(&small_rid.pid));
+        assert_eq!(Some(free_space - TablePage::get_space_needed(&build(100))), bpm.get_free_space(small_rid.pid));
+        // the one with the least room that fits is taken
+        let medium_rid = table_heap.insert_tuple(&meta, &build(1000), None).unwrap();
+        assert_eq!(small_rid.pid, medium_rid.pid);
+        // it has no room left for another one, so a page with more is taken
+        let rid = table_heap.insert_tuple(&meta, &build(1000), None).unwrap();
+        assert_ne!(small_rid.pid, rid.pid);
+        assert_eq!(true, page_ids.contains(&rid.pid));
+    }
+
+    #[test]
+    fn toast_test() {
+        let schema = Schema::new(&vec![Column::new("a", TypeId::INTEGER), Column::new("b", TypeId::TEXT),
+            Column::new_varchar("c", TypeId::VARCHAR, 16), Column::new("d", TypeId::BLOB)]);
+        let meta = TupleMeta::new(INVALID_TXN_ID, INVALID_TXN_ID, false);
+        let mut rand_eng = rand::thread_rng();
+        let noise: Vec<u8> = (0..9000).map(|_| rand_eng.gen()).collect();
+        let tuple = Tuple::build(&vec![Value::new_integer(TypeId::INTEGER, 7), Value::new_varchar(TypeId::VARCHAR, &"abcdefgh".repeat(2000)),
+            Value::new_varchar(TypeId::VARCHAR, "short"), Value::new_blob(&noise)], &schema);
+
+        // without a toaster an empty page can not take it
+        let table_heap = create_table_heap();
+        let err = table_heap.insert_tuple(&meta, &tuple, None).err().unwrap();
+        assert_eq!(true, err.contains("does not fit in a page"), "{}", err);
+
+        for compression in [true, false] {
+            let bpm = Arc::new(create_bpm(20, 3));
+            let table_heap = Arc::new(TableHeap::new(bpm.clone()).unwrap().with_toaster(schema.clone(), compression));
+            let rids: Vec<RID> = (0..3).map(|_| table_heap.insert_tuple(&meta, &tuple, None).unwrap()).collect();
+            // the values are pointers on the page, the tuples share a page
+            assert_eq!(rids[0].pid, rids[2].pid);
+            let (_, stored) = bpm.fetch_page_read(rids[0].pid).unwrap().get_as::<TablePage>().get_tuple(&rids[0]).unwrap();
+            assert_eq!(true, stored.get_length() < 100);
+            assert_eq!("7", stored.get_value(&schema, 0).to_string());
+            assert_eq!("short", stored.get_value(&schema, 2).to_string());
+
+            for rid in rids.iter() {
+                let (_, read) = table_heap.get_tuple(rid).unwrap();
+                assert_eq!(tuple.get_data(), read.get_data());
+                assert_eq!(*rid, read.get_rid());
+            }
+            let scanned: Vec<_> = table_heap.clone().make_iterator().map(|res| res.unwrap().1).collect();
+            assert_eq!(3, scanned.len());
+            assert_eq!(noise, scanned[2].get_value(&schema, 3).get_data());
+        }
+
+        // a tuple compressed small enough stays on its page
+        let bpm = Arc::new(create_bpm(20, 3));
+        let table_heap = TableHeap::new(bpm.clone()).unwrap().with_toaster(schema.clone(), true);
+        let tuple = Tuple::build(&vec![Value::new_integer(TypeId::INTEGER, 1), Value::new_varchar(TypeId::VARCHAR, &"abcdefgh".repeat(500)),
+            Value::new_null(TypeId::VARCHAR), Value::new_null(TypeId::BLOB)], &schema);
+        let rid = table_heap.insert_tuple(&meta, &tuple, None).unwrap();
+        let (_, stored) = bpm.fetch_page_read(rid.pid).unwrap().get_as::<TablePage>().get_tuple(&rid).unwrap();
+        assert_eq!(true, stored.get_length() < 1024 && stored.get_length() > 100);
+        assert_eq!(tuple.get_data(), table_heap.get_tuple(&rid).unwrap().1.get_data());
+    }
+
+    #[test]
+    fn vacuum_test() -> Result<(), String> {
+        let bpm = Arc::new(create_bpm(20, 3));
+        bpm.open_free_space_map(0).unwrap();
+        let schema = Schema::new(&vec![Column::new("a", TypeId::INTEGER), Column::new("b", TypeId::TEXT)]);
+        let table_heap = Arc::new(TableHeap::new(bpm.clone()).unwrap().with_toaster(schema.clone(), false));
+        let meta = TupleMeta::new(INVALID_TXN_ID, INVALID_TXN_ID, false);
+        let build = |a: i32, len: usize| Tuple::build(&vec![Value::new_integer(TypeId::INTEGER, a), Value::new_varchar(TypeId::VARCHAR, &"x".repeat(len))], &schema);
+
+        let rids: Vec<RID> = (0..10).map(|i| table_heap.insert_tuple(&meta, &build(i, 200), None).unwrap()).collect();
+        // its value is in 3 overflow pages
+        let big_rid = table_heap.insert_tuple(&meta, &build(10, 10000), None).unwrap();
+        let pid = rids[0].pid;
+        let free_space = bpm.get_free_space(pid).unwrap();
+
+        // a deleted tuple still seen by someone stays
+        let deleted = TupleMeta::new(INVALID_TXN_ID, 5, true);
+        for rid in rids.iter().step_by(2).chain([&big_rid]) {
+            table_heap.update_meta(&deleted, rid, None)?;
+        }
+        assert_eq!(0, table_heap.vacuum(false, &|_| false)?);
+        assert_eq!(Some(free_space), bpm.get_free_space(pid));
+
+        let overflow_pid = big_rid.pid + 1;
+        assert_eq!(true, bpm.get_free_space(overflow_pid).is_some());
+        assert_eq!(6, table_heap.vacuum(false, &|_| true)?);
+        assert_eq!(true, bpm.get_free_space(pid).unwrap() > free_space + 5 * 200);
+        // the overflow pages are given back
+        assert_eq!(None, bpm.get_free_space(overflow_pid));
+        // nothing is left to vacuum, the pages done are not visited again
+        assert_eq!(0, table_heap.vacuum(false, &|_| true)?);
+        assert_eq!(0, table_heap.vacuum(true, &|_| true)?);
+
+        // the others keep their rids, the scan skips the slots removed
+        for rid in rids.iter().skip(1).step_by(2) {
+            assert_eq!(build(rid.sid as i32, 200).get_data(), table_heap.get_tuple(rid).unwrap().1.get_data());
+        }
+        let scanned: Vec<RID> = table_heap.clone().make_iterator().map(|res| res.unwrap().1.get_rid()).collect();
+        assert_eq!(rids.iter().skip(1).step_by(2).cloned().collect::<Vec<RID>>(), scanned);
+
+        // the room and the first slot removed are taken by the new tuples
+        let rid = table_heap.insert_tuple(&meta, &build(11, 800), None).unwrap();
+        assert_eq!((pid, 0), (rid.pid, rid.sid));
+        Ok(())
+    }
+
+    #[test]
+    fn update_tuple_test() -> Result<(), String> {
+        let bpm = Arc::new(create_bpm(20, 3));
+        bpm.open_free_space_map(0).unwrap();
+        let table_heap = Arc::new(TableHeap::new(bpm.clone()).unwrap());
+        let schema = Schema::new(&vec![Column::new("a", TypeId::INTEGER), Column::new_varchar("b", TypeId::VARCHAR, 4000)]);
+        let meta = TupleMeta::new(INVALID_TXN_ID, INVALID_TXN_ID, false);
+        let build = |a: i32, len: usize| Tuple::build(&vec![Value::new_integer(TypeId::INTEGER, a), Value::new_varchar(TypeId::VARCHAR, &"x".repeat(len))], &schema);
+        let forward_of = |rid: &RID| bpm.fetch_page_read(rid.pid).unwrap().get_as::<TablePage>().get_forward(rid);
+
+        // 3 of them fill a page
+        let rids: Vec<RID> = (0..4).map(|i| table_heap.insert_tuple(&meta, &build(i, 1000), None).unwrap()).collect();
+        let pid = rids[0].pid;
+        assert_eq!((pid, pid, true), (rids[1].pid, rids[2].pid, rids[3].pid != pid));
+
+        // a shorter one and then a longer one stay on the page
+        table_heap.update_tuple(&rids[0], &build(10, 500), None)?;
+        table_heap.update_tuple(&rids[1], &build(11, 1400), None)?;
+        assert_eq!((None, None), (forward_of(&rids[0]), forward_of(&rids[1])));
+        assert_eq!(build(11, 1400).get_data(), table_heap.get_tuple(&rids[1]).unwrap().1.get_data());
+
+        // one the page has no room for moves, the rid stays
+        table_heap.update_tuple(&rids[2], &build(12, 2500), None)?;
+        let to = forward_of(&rids[2]).unwrap();
+        assert_eq!(true, to.pid != pid);
+        let (read_meta, read) = table_heap.get_tuple(&rids[2]).unwrap();
+        assert_eq!((meta.clone(), build(12, 2500).get_data(), rids[2]), (read_meta, read.get_data(), read.get_rid()));
+
+        // the page it moved to is filled up, so it moves on from there and the copy left is removed
+        let filler = table_heap.insert_tuple(&meta, &build(20, bpm.get_free_space(to.pid).unwrap() - 100), None).unwrap();
+        assert_eq!(to.pid, filler.pid);
+        table_heap.update_tuple(&rids[2], &build(13, 2800), None)?;
+        let to2 = forward_of(&rids[2]).unwrap();
+        assert_eq!(true, to2.pid != to.pid && to2.pid != pid);
+        assert_eq!(None, bpm.fetch_page_read(to.pid).unwrap().get_as::<TablePage>().get_moved_from(&to));
+        // an update of the moved one stays where it is
+        table_heap.update_tuple(&rids[2], &build(14, 2000), None)?;
+        assert_eq!(Some(to2), forward_of(&rids[2]));
+        assert_eq!(build(14, 2000).get_data(), table_heap.get_tuple(&rids[2]).unwrap().1.get_data());
+
+        // the scan reads it through its slot once
+        let scanned: Vec<RID> = table_heap.clone().make_iterator().map(|res| res.unwrap().1.get_rid()).collect();
+        let mut expected = vec![rids[0], rids[1], rids[2], rids[3], filler];
+        expected.sort_by_key(|rid| (rid.pid, rid.sid));
+        assert_eq!(expected, scanned);
+
+        // the vacuum removes the copy with the slot
+        table_heap.update_meta(&TupleMeta::new(INVALID_TXN_ID, 5, true), &rids[2], None)?;
+        assert_eq!(1, table_heap.vacuum(false, &|_| true)?);
+        assert_eq!(Some(TablePage::get_max_free_space(bpm.get_page_size())), bpm.get_free_space(to2.pid));
+        assert_eq!(4, table_heap.clone().make_iterator().count());
+        Ok(())
+    }
+
+    #[test]
+    fn wal_test() -> Result<(), String> {
+        let _ = fs::remove_file("test_wal.log");
+        let disk_manager = Arc::new(DiskManager::new("test_wal.db")?);
+        let log_mgr = Arc::new(LogManager::open("test_wal.log")?);
+        let bpm = Arc::new(BufferPoolManager::new(4, disk_manager, 2, log_mgr.clone()));
+        let table_heap = TableHeap::new(bpm.clone()).unwrap();
+        let txn = Arc::new(Transaction::new(0, 0, IsolationLevel::SnapshotIsolation));
+        let schema = create_schema();
+
+        let meta = TupleMeta::new(0, INVALID_TXN_ID, false);
+        let rid = table_heap.insert_tuple(&meta, &generate_tuple(&schema), Some(&txn)).unwrap();
+        table_heap.update_meta(&TupleMeta::new(0, 0, true), &rid, Some(&txn))?;
+
+        // the page takes the lsn of its last change, the records are still in the log buffer
+        let lsn = txn.get_prev_lsn();
+        assert_eq!(lsn, bpm.fetch_page_read(rid.pid).unwrap().get_as::<TablePage>().get_lsn());
+        assert!(log_mgr.get_persistent_lsn() < lsn);
+
+        // the log goes to disk before the page
+        assert!(bpm.flush_page(rid.pid));
+        assert_eq!(lsn, log_mgr.get_persistent_lsn());
+        let records = log_mgr.read_log()?;
+        assert!(matches!(records[0].body, LogRecordBody::NewPage { prev_page_id: INVALID_PAGE_ID, .. }));
+        assert!(matches!(records[1].body, LogRecordBody::Insert { .. }));
+        assert!(matches!(records[2].body, LogRecordBody::MarkDelete { .. }));
+        assert_eq!(records[1].lsn, records[2].prev_lsn);
+
+        // the dirty pages evicted carry the log with them
+        for _ in 0..1000 {
+            table_heap.insert_tuple(&meta, &generate_tuple(&schema), Some(&txn)).unwrap();
+        }
+        assert!(log_mgr.get_persistent_lsn() > lsn);
+        log_mgr.flush_all()?;
+        let num_new_pages = log_mgr.read_log()?.iter()
+            .filter(|record| matches!(record.body, LogRecordBody::NewPage { .. }))
+            .count();
+        assert!(num_new_pages > 4);
+        Ok(())
+    }
 }
@@ -1,56 +1,127 @@
 
 use std::sync::Arc;
 
-use crate::{common::{rid::RID, config::INVALID_PAGE_ID}, storage::page_based::page::table_page::TablePage};
+use crate::{buffer::access_strategy::{AccessIntent, BufferAccessStrategy}, common::{rid::RID, config::{page_id_t, INVALID_PAGE_ID, READ_AHEAD_PAGES}}, storage::page_based::page::table_page::TablePage};
 
 use super::{table_heap::{TableHeap, TableHeapRef}, tuple::{Tuple, TupleMeta}};
 
+// the pages in a row with the same stride of ids before a sequential scan reads ahead
+const READ_AHEAD_TRIGGER: usize = 2;
+
 pub struct TableIter {
     table_heap: TableHeapRef,
     cur_rid: RID,
     end_rid: RID,
+    strategy: BufferAccessStrategy,
+
+    // for the read-ahead, the last page, the stride of the ids of the chain so far and how many times it is seen
+    last_pid: page_id_t,
+    stride: page_id_t,
+    num_strides: usize,
+    // the last page read ahead
+    prefetched_pid: page_id_t,
 }
 impl TableIter {
 
-    pub fn new(table_heap: TableHeapRef, cur: RID, end: RID) -> Self {
+    pub fn new(table_heap: TableHeapRef, cur: RID, end: RID, strategy: BufferAccessStrategy) -> Self {
         Self {
-            table_heap: table_heap, cur_rid: cur, end_rid: end
+            table_heap: table_heap, cur_rid: cur, end_rid: end, strategy,
+            last_pid: INVALID_PAGE_ID, stride: 0, num_strides: 0, prefetched_pid: INVALID_PAGE_ID,
+        }
+    }
+
+    // the next pages of a chain are only known when its pages are read, but the pages of a table
+    // mostly come one after another. so when a scan sees the same stride of page ids a few times,
+    // the pages on that stride up to the last one of the table are read ahead in the background
+    fn read_ahead(&mut self, pid: page_id_t) {
+        if self.strategy.get_intent() != AccessIntent::SequentialScan {
+            return;
+        }
+        if self.last_pid != INVALID_PAGE_ID {
+            let stride = pid - self.last_pid;
+            if stride == self.stride {
+                self.num_strides += 1;
+            } else {
+                self.stride = stride;
+                self.num_strides = 1;
+                self.prefetched_pid = pid;
+            }
+        }
+        self.last_pid = pid;
+        if self.num_strides < READ_AHEAD_TRIGGER || self.stride <= 0 {
+            return;
+        }
+
+        let bpm = self.table_heap.get_bpm();
+        let until = (pid + self.stride * READ_AHEAD_PAGES as page_id_t).min(self.end_rid.pid);
+        let mut next = self.prefetched_pid.max(pid) + self.stride;
+        while next <= until {
+            bpm.prefetch_page(next, &self.strategy);
+            self.prefetched_pid = next;
+            next += self.stride;
         }
     }
 }
 
 impl Iterator for TableIter {
-    type Item = (TupleMeta, Tuple);
+    type Item = Result<(TupleMeta, Tuple), String>;
 
     fn next(&mut self) -> Option<Self::Item> {
-        match self.cur_rid.pid {
-            INVALID_PAGE_ID => None,
-            pid => {
-                if self.cur_rid == self.end_rid {
-                    return None;
-                }
+        loop {
+            let pid = self.cur_rid.pid;
+            if pid == INVALID_PAGE_ID || self.cur_rid == self.end_rid {
+                return None;
+            }
 
-                let bpm = self.table_heap.get_bpm();
-                let page_guard = bpm.fetch_page_read(pid).unwrap();
-                let table_page = page_guard.get_as::<TablePage>();
+            if self.cur_rid.sid == 0 {
+                self.read_ahead(pid);
+            }
+            let bpm = self.table_heap.get_bpm();
+            let page_guard = match bpm.try_fetch_page_read_with(pid, &self.strategy) {
+                Err(err) => {
+                    self.cur_rid = RID { pid: INVALID_PAGE_ID, sid: 0 };
+                    return Some(Err(err));
+                },
+                Ok(page_guard) => page_guard,
+            };
+            let table_page = page_guard.get_as::<TablePage>();
 
-                let cur_tuple = self.table_heap.get_tuple(&self.cur_rid);
-                
-                if self.cur_rid.sid + 1 < table_page.get_num_tuples() {
-                    self.cur_rid.sid += 1;
+            // read from the page at hand, fetching it again would be a normal access.
+            // the slot of a tuple removed by vacuum is skipped, so are the ones dropped since the scan started.
+            // a moved tuple is read through the slot forwarding to it, in the order of the rids.
+            // the values of a tuple are read back before the page is released, a vacuum may free them afterwards
+            let rid = self.cur_rid;
+            let forward = table_page.get_forward(&rid);
+            let cur_tuple = match rid.sid >= table_page.get_num_tuples() || table_page.is_vacuumed(&rid)
+                || table_page.get_moved_from(&rid).is_some() || forward.is_some() {
+                true => None,
+                false => Some(table_page.get_tuple(&rid)
+                    .and_then(|(meta, tuple)| Ok((meta, self.table_heap.detoast(tuple)?)))),
+            };
+
+            if self.cur_rid.sid + 1 < table_page.get_num_tuples() {
+                self.cur_rid.sid += 1;
+            } else {
+                // turn to next page
+                if self.cur_rid.pid == self.end_rid.pid {
+                    self.cur_rid = RID { pid: INVALID_PAGE_ID, sid: 0 }
                 } else {
-                    // turn to next page
-                    if self.cur_rid.pid == self.end_rid.pid {
-                        self.cur_rid = RID { pid: INVALID_PAGE_ID, sid: 0 }
-                    } else {
-                        self.cur_rid = RID { pid: pid + 1, sid: 0  };
+                    self.cur_rid = RID { pid: table_page.get_next_page_id(), sid: 0  };
 
-                    }
                 }
+            }
 
-                Some(cur_tuple)
+            drop(page_guard);
+            // a moved tuple vacuumed in the meantime is skipped as well
+            if forward.is_some() {
+                match self.table_heap.read_tuple(&rid).transpose() {
+                    None => continue,
+                    res => return res,
+                }
+            }
+            if cur_tuple.is_some() {
+                return cur_tuple;
             }
-        }        
-        
+        }
     }
-}
\ No newline at end of file
+}
@@ -0,0 +1,259 @@
+#![allow(warnings)]
+
+use std::collections::HashMap;
+
+use crate::{catalog::schema::Schema, common::{config::{file_id_t, page_id_t, INVALID_PAGE_ID}, lz}, storage::page_based::page::{overflow_page::OverflowPage, page_guard::PageGuard}, typedef::limits::DB_VALUE_NULL};
+
+use super::{table_heap::BufferPoolManagerRef, tuple::Tuple};
+
+// the flags in the length word of a stored variable length value, the low bits are the bytes after the word.
+// the null word has all of the bits set, so it is checked first
+pub const TOAST_EXTERNAL: u32 = 1 << 31;
+pub const TOAST_COMPRESSED: u32 = 1 << 30;
+pub const TOAST_LEN_MASK: u32 = TOAST_COMPRESSED - 1;
+
+// a pointer to a chain, the first page of it, the bytes in the chain and the bytes of the value
+const POINTER_SIZE: usize = size_of::<page_id_t>() + 8;
+// a value shorter than it is left in the tuple
+const TOAST_MIN_VALUE_SIZE: usize = 32;
+
+/// Toaster, the out of line storage of the large values of a table heap, like the TOAST of PostgreSQL.
+/// A tuple longer than a quarter of a page has its largest values compressed first, then moved to chains
+/// of overflow pages in the file of the heap, until it is short enough. The length word of a value tells
+/// a plain one, `[len][bytes]`, from a compressed one, `[COMPRESSED|len][raw len][lz bytes]`, and from a
+/// pointer, `[EXTERNAL(|COMPRESSED)|16][first page][len in the chain][raw len]`.
+/// The tuples on the pages and in the log keep the pointers, they are read back through `detoast`
+#[derive(Debug)]
+pub struct Toaster {
+    bpm: BufferPoolManagerRef,
+    file_id: file_id_t,
+    schema: Schema,
+    compression: bool,
+}
+
+impl Toaster {
+    pub fn new(bpm: BufferPoolManagerRef, file_id: file_id_t, schema: Schema, compression: bool) -> Self {
+        Self { bpm, file_id, schema, compression }
+    }
+
+    // a tuple longer than it is toasted
+    fn get_threshold(&self) -> usize {
+        self.bpm.get_page_size() / 4
+    }
+
+    fn read_word(bytes: &[u8], at: usize) -> u32 {
+        u32::from_le_bytes(bytes[at..at + 4].try_into().unwrap())
+    }
+
+    // the first page of the chain a pointer points to
+    fn read_first_page_id(pointer: &[u8]) -> page_id_t {
+        page_id_t::from_le_bytes(pointer[4..4 + size_of::<page_id_t>()].try_into().unwrap())
+    }
+
+    // the tuple as it is stored, the values already toasted are left as they are
+    pub fn toast(&self, tuple: &Tuple) -> Result<Tuple, String> {
+        let threshold = self.get_threshold();
+        let mut length = tuple.get_length();
+        if length <= threshold {
+            return Ok(tuple.clone());
+        }
+
+        // the plain values worth toasting, the largest first
+        let mut candidates: Vec<(usize, usize)> = self.schema.get_uninlined_inds().iter()
+            .map(|idx| (*idx as usize, tuple.get_stored_field(&self.schema, *idx as usize)))
+            .filter(|(_, field)| {
+                let word = Self::read_word(field, 0);
+                word != DB_VALUE_NULL && word & (TOAST_EXTERNAL | TOAST_COMPRESSED) == 0 && field.len() - 4 >= TOAST_MIN_VALUE_SIZE
+            })
+            .map(|(idx, field)| (field.len(), idx))
+            .collect();
+        candidates.sort_by(|a, b| b.cmp(a));
+
+        // compress them in place first, a value which does not get smaller is left plain
+        let mut fields: HashMap<usize, Vec<u8>> = HashMap::new();
+        if self.compression {
+            for (len, idx) in candidates.iter() {
+                if length <= threshold {
+                    break;
+                }
+                let raw = &tuple.get_stored_field(&self.schema, *idx)[4..];
+                let Some(compressed) = lz::compress(raw) else {
+                    continue;
+                };
+                let mut field = Vec::with_capacity(8 + compressed.len());
+                field.extend_from_slice(&(TOAST_COMPRESSED | (4 + compressed.len()) as u32).to_le_bytes());
+                field.extend_from_slice(&(raw.len() as u32).to_le_bytes());
+                field.extend_from_slice(&compressed);
+                if field.len() < *len {
+                    length -= len - field.len();
+                    fields.insert(*idx, field);
+                }
+            }
+        }
+
+        // then move the largest ones out of line
+        let mut moving: Vec<(usize, usize)> = candidates.iter()
+            .map(|(len, idx)| (fields.get(idx).map(|field| field.len()).unwrap_or(*len), *idx))
+            .filter(|(len, _)| *len > 4 + POINTER_SIZE)
+            .collect();
+        moving.sort_by(|a, b| b.cmp(a));
+        for (len, idx) in moving {
+            if length <= threshold {
+                break;
+            }
+            let pointer = {
+                let field = match fields.get(&idx) {
+                    Some(field) => &field[..],
+                    None => tuple.get_stored_field(&self.schema, idx),
+                };
+                let (flags, raw_len, data) = match Self::read_word(field, 0) & TOAST_COMPRESSED {
+                    0 => (0, field.len() - 4, &field[4..]),
+                    _ => (TOAST_COMPRESSED, Self::read_word(field, 4) as usize, &field[8..]),
+                };
+                let first_page_id = self.write_chain(data)?;
+                let mut pointer = Vec::with_capacity(4 + POINTER_SIZE);
+                pointer.extend_from_slice(&(TOAST_EXTERNAL | flags | POINTER_SIZE as u32).to_le_bytes());
+                pointer.extend_from_slice(&first_page_id.to_le_bytes());
+                pointer.extend_from_slice(&(data.len() as u32).to_le_bytes());
+                pointer.extend_from_slice(&(raw_len as u32).to_le_bytes());
+                pointer
+            };
+            length -= len - pointer.len();
+            fields.insert(idx, pointer);
+        }
+
+        Ok(tuple.with_stored_fields(&self.schema, &fields))
+    }
+
+    // the tuple with its values plain again, it keeps its rid
+    pub fn detoast(&self, tuple: &Tuple) -> Result<Tuple, String> {
+        let mut fields: HashMap<usize, Vec<u8>> = HashMap::new();
+        for idx in self.schema.get_uninlined_inds() {
+            let idx = *idx as usize;
+            let field = tuple.get_stored_field(&self.schema, idx);
+            let word = Self::read_word(field, 0);
+            if word == DB_VALUE_NULL || word & (TOAST_EXTERNAL | TOAST_COMPRESSED) == 0 {
+                continue;
+            }
+
+            let (raw_len, mut raw) = match word & TOAST_EXTERNAL {
+                0 => (Self::read_word(field, 4) as usize, field[8..].to_vec()),
+                _ => {
+                    let first_page_id = Self::read_first_page_id(field);
+                    let stored_len = Self::read_word(field, POINTER_SIZE - 4) as usize;
+                    (Self::read_word(field, POINTER_SIZE) as usize, self.read_chain(first_page_id, stored_len)?)
+                },
+            };
+            if word & TOAST_COMPRESSED != 0 {
+                raw = lz::decompress(&raw, raw_len)?;
+            }
+            let mut plain = Vec::with_capacity(4 + raw.len());
+            plain.extend_from_slice(&(raw.len() as u32).to_le_bytes());
+            plain.extend_from_slice(&raw);
+            fields.insert(idx, plain);
+        }
+
+        match fields.is_empty() {
+            true => Ok(tuple.clone()),
+            false => Ok(tuple.with_stored_fields(&self.schema, &fields)),
+        }
+    }
+
+    // give the overflow pages the stored tuple points to back to the free space map, once the tuple is vacuumed
+    // or replaced. the chains the replacing one still points to are kept
+    pub fn free_chains(&self, tuple: &Tuple, kept: Option<&Tuple>) -> Result<(), String> {
+        for idx in self.schema.get_uninlined_inds() {
+            let field = tuple.get_stored_field(&self.schema, *idx as usize);
+            let word = Self::read_word(field, 0);
+            if word == DB_VALUE_NULL || word & TOAST_EXTERNAL == 0 {
+                continue;
+            }
+            if kept.is_some_and(|kept| kept.get_stored_field(&self.schema, *idx as usize) == field) {
+                continue;
+            }
+            let mut pid = Self::read_first_page_id(field);
+            while pid != INVALID_PAGE_ID {
+                let next_page_id = self.bpm.fetch_page_read(pid)
+                    .ok_or(format!("can not fetch page {}, bpm err", pid))?
+                    .get_as::<OverflowPage>().get_next_page_id();
+                // nobody reads the chain of a tuple which is gone, a pin on it is a bug rather than a wait
+                if !self.bpm.delete_page(pid)? {
+                    return Err(format!("Error: overflow page {} is pinned, it can not be freed", pid));
+                }
+                pid = next_page_id;
+            }
+        }
+        Ok(())
+    }
+
+    // write the bytes to a new chain of overflow pages, returns its first page.
+    // a page is written out once the next one is linked to it, so the chain is on disk before the tuple is logged.
+    // the pages of a chain which can not be written whole are given back
+    fn write_chain(&self, data: &[u8]) -> Result<page_id_t, String> {
+        let mut pids = Vec::new();
+        let res = self.write_pages(data, &mut pids);
+        if res.is_err() {
+            for pid in pids {
+                let _ = self.bpm.delete_page(pid);
+            }
+        }
+        res
+    }
+
+    fn write_pages(&self, data: &[u8], pids: &mut Vec<page_id_t>) -> Result<page_id_t, String> {
+        let page_size = self.bpm.get_page_size();
+        let mut first_page_id = INVALID_PAGE_ID;
+        let mut prev: Option<PageGuard> = None;
+        for chunk in data.chunks(OverflowPage::capacity(page_size)) {
+            let Some(mut page_guard) = self.bpm.new_page_guarded_in(self.file_id) else {
+                return Err(format!("Error: can not allocate an overflow page in file {}", self.file_id));
+            };
+            let pid = page_guard.get_pid().unwrap();
+            pids.push(pid);
+            let overflow_page = page_guard.get_mut_as::<OverflowPage>();
+            overflow_page.init();
+            overflow_page.set_data(chunk, page_size);
+
+            match prev.take() {
+                None => first_page_id = pid,
+                Some(mut prev_guard) => {
+                    prev_guard.get_mut_as::<OverflowPage>().set_next_page_id(pid);
+                    self.write_out(prev_guard)?;
+                },
+            }
+            prev = Some(page_guard);
+        }
+        if let Some(prev_guard) = prev {
+            self.write_out(prev_guard)?;
+        }
+        Ok(first_page_id)
+    }
+
+    // flushed while it is pinned, so it is still in the pool, the guard holds no latch to wait for
+    fn write_out(&self, page_guard: PageGuard) -> Result<(), String> {
+        let pid = page_guard.get_pid().unwrap();
+        match self.bpm.flush_page(pid) {
+            true => Ok(()),
+            false => Err(format!("Error: can not write overflow page {}", pid)),
+        }
+    }
+
+    fn read_chain(&self, first_page_id: page_id_t, len: usize) -> Result<Vec<u8>, String> {
+        let mut data = Vec::with_capacity(len);
+        let mut pid = first_page_id;
+        while data.len() < len {
+            if pid == INVALID_PAGE_ID {
+                return Err(format!("Error: the chain from page {} ends after {} of {} bytes", first_page_id, data.len(), len));
+            }
+            let page_guard = self.bpm.fetch_page_read(pid)
+                .ok_or(format!("can not fetch page {}, bpm err", pid))?;
+            let overflow_page = page_guard.get_as::<OverflowPage>();
+            data.extend_from_slice(overflow_page.get_data());
+            pid = overflow_page.get_next_page_id();
+        }
+        match data.len() == len {
+            true => Ok(data),
+            false => Err(format!("Error: the chain from page {} has {} bytes, {} expected", first_page_id, data.len(), len)),
+        }
+    }
+}
@@ -1,11 +1,15 @@
 #![allow(warnings)]
 
-use crate::{catalog::schema::Schema, common::{config::txn_id_t, rid::RID}, typedef::{type_id::TypeId, value::{ Value}}};
+use std::collections::HashMap;
+
+use crate::{catalog::schema::Schema, common::{config::txn_id_t, rid::RID}, typedef::{limits::DB_VALUE_NULL, type_id::TypeId, value::{ Value}}};
+
+use super::toast::{TOAST_COMPRESSED, TOAST_EXTERNAL, TOAST_LEN_MASK};
 
 /// Version 0.1
 /// tuple meta diffs from different concret transaction theory 
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct TupleMeta {
     // insert txn_id/ts
     pub insert_txn_id: txn_id_t,
@@ -25,7 +29,7 @@ impl TupleMeta {
 
 
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct Tuple {
     //TODO
     rid: RID,
@@ -49,7 +53,7 @@ impl Tuple {
         let mut total_len = schema.get_len();
         for unlined_idx in schema.get_uninlined_inds() {
             let idx = *unlined_idx as usize;
-            // get data length
+            // get data length, null value has a zero length
             let len = values[idx].get_length();
 
             // store length itself, and data length
@@ -69,18 +73,27 @@ impl Tuple {
                     panic!("{}", err);
                 },
                 Ok(col) => {
+                    // a null value may come without a concrete type, give it the column's one
+                    let null_val;
+                    let value = if values[i].is_null() {
+                        null_val = Value::new_null(col.get_type());
+                        &null_val
+                    } else {
+                        &values[i]
+                    };
+
                     if !col.is_inlined() {
                         let off_ptr = col.get_offset() as usize;
                         // varchar type, writes where to store data
                         buf[off_ptr..off_ptr+size_of::<u32>()].copy_from_slice(&(offset as u32).to_ne_bytes());
 
                         // ser_bytes, [data]
-                        let ser_bytes = Value::serialize(&values[i]);
+                        let ser_bytes = Value::serialize(value);
                         // write val serials
                         buf[offset..offset+ser_bytes.len()].copy_from_slice(&ser_bytes);
                         offset += ser_bytes.len();
                     } else {
-                        let bytes = Value::serialize(&values[i]);
+                        let bytes = Value::serialize(value);
                         let col_off = col.get_offset() as usize;
                         let fixed_len = col.get_fixed_len() as usize;
                         buf[col_off..col_off+fixed_len].copy_from_slice(&bytes);
@@ -95,8 +108,12 @@ impl Tuple {
         }
     }
 
-    pub fn get_key(schema: &Schema, key_inds: &Vec<i32>, key_schema: &Schema) -> Self {
-        todo!()
+    // build a key tuple from the columns at `key_inds`, laid out by `key_schema`
+    pub fn get_key(&self, schema: &Schema, key_inds: &Vec<usize>, key_schema: &Schema) -> Self {
+        let values = key_inds.iter()
+            .map(|idx| self.get_value(schema, *idx))
+            .collect::<Vec<Value>>();
+        Tuple::build(&values, key_schema)
     }
 
     pub fn get_value(&self, schema: &Schema, col_index: usize) -> Value {
@@ -113,12 +130,19 @@ impl Tuple {
         let column = column.ok().unwrap();
                 // depend on different type
         match column.get_type() {
-            TypeId::VARCHAR => {
+            TypeId::VARCHAR | TypeId::TEXT | TypeId::BLOB => {
                 let off_ptr = column.get_offset() as usize; 
                 let off = unsafe { *(self.data[off_ptr..off_ptr+4].as_ptr() as *const u32) } as usize;
                 let len_bytes = &self.data[off..off+4];
                 // can't just use u32 pointer, if needs 4 byte aligned
-                let str_len = u32::from_le_bytes(len_bytes.try_into().unwrap()) as usize;
+                let str_len = u32::from_le_bytes(len_bytes.try_into().unwrap());
+                if DB_VALUE_NULL == str_len {
+                    return Value::new_null(column.get_type());
+                }
+                if str_len & (TOAST_EXTERNAL | TOAST_COMPRESSED) != 0 {
+                    panic!("Error: the value of column {} is toasted, detoast the tuple first", column.get_name());
+                }
+                let str_len = str_len as usize;
                 // deserialize need [len, data]
                 let res = Value::deserialize(&self.data[off..off+4+str_len].to_vec(), column.get_type());
                 
@@ -136,6 +160,35 @@ impl Tuple {
         }
     }
 
+    // the stored bytes of a variable length column, the length word and the bytes after it, see `Toaster`
+    pub fn get_stored_field(&self, schema: &Schema, col_index: usize) -> &[u8] {
+        let column = schema.get_column(col_index).unwrap();
+        assert!(!column.is_inlined(), "column {} is not of variable length", column.get_name());
+        let off_ptr = column.get_offset() as usize;
+        let off = u32::from_le_bytes(self.data[off_ptr..off_ptr+4].try_into().unwrap()) as usize;
+        let word = u32::from_le_bytes(self.data[off..off+4].try_into().unwrap());
+        if word == DB_VALUE_NULL {
+            return &self.data[off..off+4];
+        }
+        &self.data[off..off+4+(word & TOAST_LEN_MASK) as usize]
+    }
+
+    // the tuple with the stored bytes of some variable length columns replaced, the others are copied
+    pub fn with_stored_fields(&self, schema: &Schema, fields: &HashMap<usize, Vec<u8>>) -> Tuple {
+        let mut data = self.data[..schema.get_len() as usize].to_vec();
+        for idx in schema.get_uninlined_inds() {
+            let idx = *idx as usize;
+            let offset = data.len() as u32;
+            let off_ptr = schema.get_column(idx).unwrap().get_offset() as usize;
+            data[off_ptr..off_ptr+4].copy_from_slice(&offset.to_ne_bytes());
+            match fields.get(&idx) {
+                Some(field) => data.extend_from_slice(field),
+                None => data.extend_from_slice(self.get_stored_field(schema, idx)),
+            }
+        }
+        Tuple { rid: self.rid, data }
+    }
+
     fn resolve_value_result(&self, res: Result<Value, String>) -> Value {
         match res.is_ok() {
             true => {
@@ -163,6 +216,11 @@ impl Tuple {
         self.rid
     }
 
+    #[inline]
+    pub fn set_rid(&mut self, rid: RID) {
+        self.rid = rid;
+    }
+
     // ================================ static method ===============
     pub fn deserialize(bytes: &Vec<u8>) -> Result<Tuple, String> {
         let tuple = Tuple {
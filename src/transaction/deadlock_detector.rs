@@ -0,0 +1,38 @@
+#![allow(warnings)]
+
+use std::{sync::{atomic::{AtomicBool, Ordering}, Arc}, thread::{self, JoinHandle}, time::Duration};
+
+use super::lock_manager::LockManagerRef;
+
+
+/// A background thread which runs the cycle detection of the lock manager at an interval,
+/// it stops when the detector is dropped.
+#[derive(Debug)]
+pub struct DeadlockDetector {
+    handler: Option<JoinHandle<()>>,
+    stop: Arc<AtomicBool>,
+}
+
+impl DeadlockDetector {
+    pub fn new(lock_mgr: LockManagerRef, interval: Duration) -> Self {
+        let stop = Arc::new(AtomicBool::new(false));
+        let stop_flag = stop.clone();
+        let handler = thread::spawn(move || {
+            while !stop_flag.load(Ordering::Relaxed) {
+                thread::sleep(interval);
+                lock_mgr.run_cycle_detection();
+            }
+        });
+
+        Self { handler: Some(handler), stop }
+    }
+}
+
+impl Drop for DeadlockDetector {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::Relaxed);
+        if let Some(handler) = self.handler.take() {
+            let _ = handler.join();
+        }
+    }
+}
@@ -1,16 +1,736 @@
-use std::sync::Arc;
+#![allow(warnings)]
 
+use std::{collections::{HashMap, HashSet}, fmt::Display, hash::Hash, sync::{Arc, Condvar, Mutex}};
 
+use crate::common::{config::{table_id_t, txn_id_t, INVALID_TXN_ID}, rid::RID};
+
+use super::transaction::{IsolationLevel, TransactionRef, TransactionState};
+
+
+// the error of a transaction aborted by the lock manager starts with it
+pub const TXN_ABORTED: &str = "transaction aborted";
+
+// how the lock manager deals with deadlocks, the older transaction has the smaller txn id.
+// Detection: a background thread finds the cycles of the waits-for graph, the youngest one of a cycle is aborted.
+// WoundWait: an older requester aborts the younger holders, a younger requester waits.
+// WaitDie: an older requester waits, a younger requester aborts itself.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DeadlockPolicy {
+    Detection,
+    WoundWait,
+    WaitDie,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LockMode {
+    Shared,
+    Exclusive,
+    IntentionShared,
+    IntentionExclusive,
+    SharedIntentionExclusive,
+}
+
+impl LockMode {
+    pub fn is_compatible(&self, other: &LockMode) -> bool {
+        match (self, other) {
+            (Self::IntentionShared, Self::Exclusive) | (Self::Exclusive, Self::IntentionShared) => false,
+            (Self::IntentionShared, _) | (_, Self::IntentionShared) => true,
+            (Self::IntentionExclusive, Self::IntentionExclusive) => true,
+            (Self::Shared, Self::Shared) => true,
+            _ => false,
+        }
+    }
+
+    // holding `self` grants everything `other` does
+    pub fn covers(&self, other: &LockMode) -> bool {
+        match (self, other) {
+            (Self::Exclusive, _) => true,
+            (Self::SharedIntentionExclusive, Self::Exclusive) => false,
+            (Self::SharedIntentionExclusive, _) => true,
+            (Self::Shared, Self::Shared | Self::IntentionShared) => true,
+            (Self::IntentionExclusive, Self::IntentionExclusive | Self::IntentionShared) => true,
+            (Self::IntentionShared, Self::IntentionShared) => true,
+            _ => false,
+        }
+    }
+
+    // IS -> S/X/IX/SIX, S -> X/SIX, IX -> X/SIX, SIX -> X
+    fn can_upgrade_to(&self, other: &LockMode) -> bool {
+        match (self, other) {
+            (Self::IntentionShared, _) => true,
+            (Self::Shared | Self::IntentionExclusive, Self::Exclusive | Self::SharedIntentionExclusive) => true,
+            (Self::SharedIntentionExclusive, Self::Exclusive) => true,
+            _ => false,
+        }
+    }
+
+    // the lock a mixed S + IX request ends up with, used by the upgrades out of S and IX
+    fn combine(&self, other: &LockMode) -> LockMode {
+        match (self, other) {
+            (Self::Shared, Self::IntentionExclusive) | (Self::IntentionExclusive, Self::Shared) => Self::SharedIntentionExclusive,
+            _ => *other,
+        }
+    }
+}
+
+impl Display for LockMode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Shared => f.write_str("S"),
+            Self::Exclusive => f.write_str("X"),
+            Self::IntentionShared => f.write_str("IS"),
+            Self::IntentionExclusive => f.write_str("IX"),
+            Self::SharedIntentionExclusive => f.write_str("SIX"),
+        }
+    }
+}
 
-pub type LockManagerRef = Arc<LockManager>;
 
 #[derive(Debug)]
+struct LockRequest {
+    txn: TransactionRef,
+    mode: LockMode,
+    granted: bool,
+}
 
-pub struct LockManager {}
+#[derive(Debug)]
+struct LockRequestQueue_ {
+    requests: Vec<LockRequest>,
+    // the txn which is upgrading its lock, only one upgrade at a time
+    upgrading: txn_id_t,
+}
+
+#[derive(Debug)]
+struct LockRequestQueue {
+    state: Mutex<LockRequestQueue_>,
+    cv: Condvar,
+}
+
+impl LockRequestQueue {
+    fn new() -> Self {
+        Self {
+            state: Mutex::new(LockRequestQueue_ { requests: Vec::new(), upgrading: INVALID_TXN_ID }),
+            cv: Condvar::new(),
+        }
+    }
+}
+
+
+pub type LockManagerRef = Arc<LockManager>;
 
+/// Table locks (IS, IX, S, SIX, X) and row locks (S, X) for the strict two-phase locking.
+///
+/// Each table or row has a FIFO queue of requests, a request is granted when it is compatible with
+/// the granted ones and all the requests ahead of it are granted, the waiters sleep on the condvar of the queue.
+/// An upgrade goes ahead of the waiting requests. The granted locks are recorded in the lock sets of the transaction,
+/// they are released together when the transaction ends (see `unlock_all`).
+/// The waiters may deadlock, see `DeadlockPolicy`.
+#[derive(Debug)]
+pub struct LockManager {
+    policy: DeadlockPolicy,
+    table_lock_map: Mutex<HashMap<table_id_t, Arc<LockRequestQueue>>>,
+    row_lock_map: Mutex<HashMap<RID, Arc<LockRequestQueue>>>,
+}
 
 impl LockManager {
     pub fn new() -> Self {
-        Self {}
+        Self::with_policy(DeadlockPolicy::Detection)
+    }
+
+    pub fn with_policy(policy: DeadlockPolicy) -> Self {
+        Self {
+            policy,
+            table_lock_map: Mutex::new(HashMap::new()),
+            row_lock_map: Mutex::new(HashMap::new()),
+        }
+    }
+
+    pub fn lock_table(&self, txn: &TransactionRef, mode: LockMode, table_oid: table_id_t) -> Result<(), String> {
+        Self::check_isolation_level(txn, mode)?;
+        let queue = Self::get_queue(&self.table_lock_map, table_oid);
+        let held = txn.get_table_lock(table_oid);
+        let granted = self.acquire(&queue, txn, mode, held, true)?;
+        txn.set_table_lock(table_oid, Some(granted));
+        Ok(())
+    }
+
+    pub fn unlock_table(&self, txn: &TransactionRef, table_oid: table_id_t) -> Result<(), String> {
+        if txn.get_table_lock(table_oid).is_none() {
+            return Err(format!("transaction {} does not hold a lock on table {}", txn.get_txn_id(), table_oid));
+        }
+        if txn.get_locked_rows().iter().any(|(row_table, _)| *row_table == table_oid) {
+            return Err(format!("transaction {} still holds locks on the rows of table {}", txn.get_txn_id(), table_oid));
+        }
+
+        Self::release(&self.table_lock_map, table_oid, txn);
+        txn.set_table_lock(table_oid, None);
+        Ok(())
+    }
+
+    // a shared row lock needs any lock on the table, an exclusive one needs IX, SIX or X
+    pub fn lock_row(&self, txn: &TransactionRef, mode: LockMode, table_oid: table_id_t, rid: RID) -> Result<(), String> {
+        self.lock_row_with(txn, mode, table_oid, rid, true)
+    }
+
+    // fails instead of waiting, for the rows which no one else can lock yet, see `TableHeap::insert_tuple_with`
+    pub fn lock_row_nowait(&self, txn: &TransactionRef, mode: LockMode, table_oid: table_id_t, rid: RID) -> Result<(), String> {
+        self.lock_row_with(txn, mode, table_oid, rid, false)
+    }
+
+    fn lock_row_with(&self, txn: &TransactionRef, mode: LockMode, table_oid: table_id_t, rid: RID, wait: bool) -> Result<(), String> {
+        if mode != LockMode::Shared && mode != LockMode::Exclusive {
+            return Err(format!("intention lock {} is not allowed on rows", mode));
+        }
+        let table_lock_present = match (mode, txn.get_table_lock(table_oid)) {
+            (_, None) => false,
+            (LockMode::Shared, Some(_)) => true,
+            (LockMode::Exclusive, Some(table_mode)) => table_mode.covers(&LockMode::IntentionExclusive),
+            _ => false,
+        };
+        if !table_lock_present {
+            return Err(format!("transaction {} locks a row of table {} without the table lock", txn.get_txn_id(), table_oid));
+        }
+        Self::check_isolation_level(txn, mode)?;
+
+        let queue = Self::get_queue(&self.row_lock_map, rid);
+        let held = txn.get_row_lock(&rid);
+        let granted = self.acquire(&queue, txn, mode, held, wait)?;
+        txn.set_row_lock(table_oid, rid, Some(granted));
+        Ok(())
+    }
+
+    pub fn unlock_row(&self, txn: &TransactionRef, table_oid: table_id_t, rid: RID) -> Result<(), String> {
+        if txn.get_row_lock(&rid).is_none() {
+            return Err(format!("transaction {} does not hold a lock on row {:?}", txn.get_txn_id(), rid));
+        }
+
+        Self::release(&self.row_lock_map, rid, txn);
+        txn.set_row_lock(table_oid, rid, None);
+        Ok(())
+    }
+
+    // release all the locks of the transaction, the rows go before the tables
+    pub fn unlock_all(&self, txn: &TransactionRef) {
+        for (table_oid, rid) in txn.get_locked_rows() {
+            Self::release(&self.row_lock_map, rid, txn);
+            txn.set_row_lock(table_oid, rid, None);
+        }
+        for table_oid in txn.get_locked_tables() {
+            Self::release(&self.table_lock_map, table_oid, txn);
+            txn.set_table_lock(table_oid, None);
+        }
+    }
+
+    // read uncommitted reads without locks, only the exclusive ones are taken for the writes
+    fn check_isolation_level(txn: &TransactionRef, mode: LockMode) -> Result<(), String> {
+        let shared = matches!(mode, LockMode::Shared | LockMode::IntentionShared | LockMode::SharedIntentionExclusive);
+        if shared && txn.get_isolation_level() == IsolationLevel::ReadUncommitted {
+            return Err(format!("transaction {} can not take lock {} under {}", txn.get_txn_id(), mode, IsolationLevel::ReadUncommitted));
+        }
+        Ok(())
+    }
+
+    fn get_queue<K: Eq + Hash + Copy>(lock_map: &Mutex<HashMap<K, Arc<LockRequestQueue>>>, key: K) -> Arc<LockRequestQueue> {
+        lock_map.lock().unwrap()
+            .entry(key)
+            .or_insert_with(|| Arc::new(LockRequestQueue::new()))
+            .clone()
+    }
+
+    pub fn get_policy(&self) -> DeadlockPolicy {
+        self.policy
+    }
+
+    // the edges (waiter, blocker) of the waits-for graph, see `get_blockers`
+    pub fn get_waits_for_edges(&self) -> Vec<(txn_id_t, txn_id_t)> {
+        let mut edges = HashSet::new();
+        for queue in self.get_all_queues() {
+            let state = queue.state.lock().unwrap();
+            for waiter in state.requests.iter().filter(|req| !req.granted) {
+                let waiter_id = waiter.txn.get_txn_id();
+                for blocker in Self::get_blockers(&state, waiter_id, &waiter.mode) {
+                    edges.insert((waiter_id, blocker.get_txn_id()));
+                }
+            }
+        }
+        let mut edges: Vec<(txn_id_t, txn_id_t)> = edges.into_iter().collect();
+        edges.sort();
+        edges
+    }
+
+    // abort the youngest transaction of each cycle until the graph has no cycle, returns the victims
+    pub fn run_cycle_detection(&self) -> Vec<txn_id_t> {
+        let mut waiters = HashMap::new();
+        for queue in self.get_all_queues() {
+            let state = queue.state.lock().unwrap();
+            for req in state.requests.iter().filter(|req| !req.granted) {
+                waiters.insert(req.txn.get_txn_id(), req.txn.clone());
+            }
+        }
+
+        let mut graph: HashMap<txn_id_t, Vec<txn_id_t>> = HashMap::new();
+        for (waiter, holder) in self.get_waits_for_edges() {
+            graph.entry(waiter).or_default().push(holder);
+        }
+
+        let mut victims = Vec::new();
+        while let Some(cycle) = Self::find_cycle(&graph) {
+            let victim = *cycle.iter().max().unwrap();
+            // only a waiting transaction can be in a cycle
+            if let Some(txn) = waiters.get(&victim) {
+                txn.set_aborted();
+            }
+            graph.remove(&victim);
+            for holders in graph.values_mut() {
+                holders.retain(|holder| *holder != victim);
+            }
+            victims.push(victim);
+        }
+
+        if !victims.is_empty() {
+            self.notify_all_queues();
+        }
+        victims
+    }
+
+    // dfs from the smallest txn id, the neighbors are visited in order, so the result is deterministic
+    fn find_cycle(graph: &HashMap<txn_id_t, Vec<txn_id_t>>) -> Option<Vec<txn_id_t>> {
+        fn dfs(graph: &HashMap<txn_id_t, Vec<txn_id_t>>, node: txn_id_t, path: &mut Vec<txn_id_t>, visited: &mut HashSet<txn_id_t>) -> Option<Vec<txn_id_t>> {
+            if let Some(pos) = path.iter().position(|n| *n == node) {
+                return Some(path[pos..].to_vec());
+            }
+            if !visited.insert(node) {
+                return None;
+            }
+            path.push(node);
+            let mut neighbors = graph.get(&node).cloned().unwrap_or_default();
+            neighbors.sort();
+            for next in neighbors {
+                if let Some(cycle) = dfs(graph, next, path, visited) {
+                    return Some(cycle);
+                }
+            }
+            path.pop();
+            None
+        }
+
+        let mut nodes: Vec<txn_id_t> = graph.keys().cloned().collect();
+        nodes.sort();
+        let mut visited = HashSet::new();
+        for node in nodes {
+            if let Some(cycle) = dfs(graph, node, &mut Vec::new(), &mut visited) {
+                return Some(cycle);
+            }
+        }
+        None
+    }
+
+    fn get_all_queues(&self) -> Vec<Arc<LockRequestQueue>> {
+        let mut queues: Vec<Arc<LockRequestQueue>> = self.table_lock_map.lock().unwrap().values().cloned().collect();
+        queues.extend(self.row_lock_map.lock().unwrap().values().cloned());
+        queues
+    }
+
+    // wake up the waiters, an aborted one leaves its queue
+    fn notify_all_queues(&self) {
+        for queue in self.get_all_queues() {
+            queue.cv.notify_all();
+        }
+    }
+
+    fn aborted_error(txn_id: txn_id_t) -> String {
+        format!("{}: transaction {} is aborted to avoid a deadlock", TXN_ABORTED, txn_id)
+    }
+
+    // wait until the lock is granted, returns the granted mode. without `wait` a request which is not granted
+    // at once is taken back
+    fn acquire(&self, queue: &LockRequestQueue, txn: &TransactionRef, mode: LockMode, held: Option<LockMode>, wait: bool) -> Result<LockMode, String> {
+        match txn.get_state() {
+            TransactionState::Running => {},
+            TransactionState::Aborted => {
+                return Err(Self::aborted_error(txn.get_txn_id()));
+            },
+            TransactionState::Committed => {
+                return Err(format!("transaction {} is not running", txn.get_txn_id()));
+            },
+        }
+        let txn_id = txn.get_txn_id();
+
+        let mut state = queue.state.lock().unwrap();
+        let mut mode = mode;
+        match held {
+            Some(held) if held.covers(&mode) => {
+                return Ok(held);
+            },
+            Some(held) => {
+                let upgrade_to = held.combine(&mode);
+                if !held.can_upgrade_to(&upgrade_to) {
+                    return Err(format!("transaction {} can not upgrade lock {} to {}", txn_id, held, upgrade_to));
+                }
+                if state.upgrading != INVALID_TXN_ID {
+                    return Err(format!("transaction {} upgrades a lock while another upgrade is in progress", txn_id));
+                }
+
+                // drop the granted request, the new one waits ahead of the other waiting requests
+                state.requests.retain(|req| req.txn.get_txn_id() != txn_id);
+                let pos = state.requests.iter().position(|req| !req.granted).unwrap_or(state.requests.len());
+                state.requests.insert(pos, LockRequest { txn: txn.clone(), mode: upgrade_to, granted: false });
+                state.upgrading = txn_id;
+                mode = upgrade_to;
+            },
+            None => {
+                state.requests.push(LockRequest { txn: txn.clone(), mode, granted: false });
+            }
+        }
+
+        loop {
+            if txn.get_state() == TransactionState::Aborted {
+                Self::withdraw(&mut state, txn, held);
+                queue.cv.notify_all();
+                return Err(Self::aborted_error(txn_id));
+            }
+
+            if Self::grantable(&state, txn_id, &mode) {
+                let req = state.requests.iter_mut().find(|req| req.txn.get_txn_id() == txn_id).unwrap();
+                req.granted = true;
+                if state.upgrading == txn_id {
+                    state.upgrading = INVALID_TXN_ID;
+                }
+                // the compatible requests behind it may go now
+                queue.cv.notify_all();
+                return Ok(mode);
+            }
+
+            if !wait {
+                Self::withdraw(&mut state, txn, held);
+                queue.cv.notify_all();
+                return Err(format!("transaction {} could not obtain lock {} without waiting", txn_id, mode));
+            }
+
+            let blockers = Self::get_blockers(&state, txn_id, &mode);
+            match self.policy {
+                DeadlockPolicy::Detection => {},
+                DeadlockPolicy::WoundWait => {
+                    let mut wounded = false;
+                    for blocker in blockers.iter().filter(|blocker| blocker.get_txn_id() > txn_id) {
+                        if blocker.get_state() == TransactionState::Running {
+                            blocker.set_aborted();
+                            wounded = true;
+                        }
+                    }
+                    // a wounded transaction may wait in another queue
+                    if wounded {
+                        drop(state);
+                        self.notify_all_queues();
+                        state = queue.state.lock().unwrap();
+                        continue;
+                    }
+                },
+                DeadlockPolicy::WaitDie => {
+                    if blockers.iter().any(|blocker| blocker.get_txn_id() < txn_id) {
+                        txn.set_aborted();
+                        continue;
+                    }
+                },
+            }
+            state = queue.cv.wait(state).unwrap();
+        }
+    }
+
+    // take the waiting request out of the queue. the lock held before an upgrade is granted again,
+    // the lock sets of the transaction still have it, it goes with the others in `unlock_all`
+    fn withdraw(state: &mut LockRequestQueue_, txn: &TransactionRef, held: Option<LockMode>) {
+        let txn_id = txn.get_txn_id();
+        state.requests.retain(|req| req.txn.get_txn_id() != txn_id);
+        if state.upgrading == txn_id {
+            state.upgrading = INVALID_TXN_ID;
+            let pos = state.requests.iter().position(|req| !req.granted).unwrap_or(state.requests.len());
+            state.requests.insert(pos, LockRequest { txn: txn.clone(), mode: held.unwrap(), granted: true });
+        }
+    }
+
+    // the transactions a waiting request waits for, the granted incompatible ones and,
+    // since the grants are FIFO, the waiting ones ahead of it
+    fn get_blockers(state: &LockRequestQueue_, txn_id: txn_id_t, mode: &LockMode) -> Vec<TransactionRef> {
+        let pos = state.requests.iter().position(|req| req.txn.get_txn_id() == txn_id).unwrap();
+        state.requests.iter().enumerate()
+            .filter(|(idx, req)| req.txn.get_txn_id() != txn_id
+                && ((req.granted && !req.mode.is_compatible(mode)) || (!req.granted && *idx < pos)))
+            .map(|(_, req)| req.txn.clone())
+            .collect()
+    }
+
+    // FIFO, a request is granted after all the requests ahead of it
+    fn grantable(state: &LockRequestQueue_, txn_id: txn_id_t, mode: &LockMode) -> bool {
+        let pos = state.requests.iter().position(|req| req.txn.get_txn_id() == txn_id).unwrap();
+        let compatible = state.requests.iter()
+            .all(|req| !req.granted || req.txn.get_txn_id() == txn_id || req.mode.is_compatible(mode));
+        compatible && state.requests[..pos].iter().all(|req| req.granted)
+    }
+
+    // an empty queue is dropped from the map, unless another thread has it at hand, e.g. one which is about
+    // to put its request in. the map is locked before the queue, so no one gets it meanwhile
+    fn release<K: Eq + Hash + Copy>(lock_map: &Mutex<HashMap<K, Arc<LockRequestQueue>>>, key: K, txn: &TransactionRef) {
+        let mut lock_map = lock_map.lock().unwrap();
+        let Some(queue) = lock_map.get(&key).cloned() else { return; };
+        let mut state = queue.state.lock().unwrap();
+        state.requests.retain(|req| req.txn.get_txn_id() != txn.get_txn_id());
+        queue.cv.notify_all();
+        if state.requests.is_empty() && Arc::strong_count(&queue) == 2 {
+            drop(state);
+            lock_map.remove(&key);
+        }
+    }
+}
+
+
+
+
+#[cfg(test)]
+mod tests {
+    use std::{sync::{atomic::{AtomicBool, Ordering}, Arc}, thread, time::Duration};
+
+    use crate::{common::rid::RID, transaction::{deadlock_detector::DeadlockDetector, transaction::{IsolationLevel, Transaction, TransactionRef, TransactionState}}};
+
+    use super::{DeadlockPolicy, LockManager, LockMode, TXN_ABORTED};
+
+    fn new_txn(txn_id: i32) -> TransactionRef {
+        Arc::new(Transaction::new(txn_id, 0, IsolationLevel::RepeatableRead))
+    }
+
+    fn lock_rid(lock_mgr: &LockManager, txn: &TransactionRef, rid: RID) -> Result<(), String> {
+        lock_mgr.lock_table(txn, LockMode::IntentionExclusive, 0)?;
+        lock_mgr.lock_row(txn, LockMode::Exclusive, 0, rid)
+    }
+
+    #[test]
+    fn compatibility_test() {
+        use LockMode::*;
+        let modes = [IntentionShared, IntentionExclusive, Shared, SharedIntentionExclusive, Exclusive];
+        let expected = [
+            [true, true, true, true, false],
+            [true, true, false, false, false],
+            [true, false, true, false, false],
+            [true, false, false, false, false],
+            [false, false, false, false, false],
+        ];
+        for i in 0..modes.len() {
+            for j in 0..modes.len() {
+                assert_eq!(expected[i][j], modes[i].is_compatible(&modes[j]), "{} {}", modes[i], modes[j]);
+            }
+        }
+    }
+
+    #[test]
+    fn lock_wait_test() -> Result<(), String> {
+        let lock_mgr = Arc::new(LockManager::new());
+        let t1 = Arc::new(Transaction::new(1, 0, IsolationLevel::RepeatableRead));
+        let t2 = Arc::new(Transaction::new(2, 0, IsolationLevel::RepeatableRead));
+        let rid = RID { pid: 1, sid: 0 };
+
+        // a row lock needs the table lock
+        assert!(lock_mgr.lock_row(&t1, LockMode::Exclusive, 0, rid).is_err());
+        lock_mgr.lock_table(&t1, LockMode::IntentionShared, 0)?;
+        assert!(lock_mgr.lock_row(&t1, LockMode::Exclusive, 0, rid).is_err());
+
+        // IS -> IX, S -> X
+        lock_mgr.lock_table(&t1, LockMode::IntentionExclusive, 0)?;
+        lock_mgr.lock_row(&t1, LockMode::Shared, 0, rid)?;
+        lock_mgr.lock_row(&t1, LockMode::Exclusive, 0, rid)?;
+        assert_eq!(Some(LockMode::Exclusive), t1.get_row_lock(&rid));
+        assert!(lock_mgr.unlock_table(&t1, 0).is_err());
+        lock_mgr.lock_table(&t2, LockMode::IntentionExclusive, 0)?;
+        assert!(lock_mgr.lock_row_nowait(&t2, LockMode::Exclusive, 0, rid).is_err());
+        assert_eq!(None, t2.get_row_lock(&rid));
+        lock_mgr.unlock_table(&t2, 0)?;
+
+        // t2 waits for the exclusive lock of t1
+        let granted = Arc::new(AtomicBool::new(false));
+        let handle = {
+            let (lock_mgr, t2, granted) = (lock_mgr.clone(), t2.clone(), granted.clone());
+            thread::spawn(move || {
+                lock_mgr.lock_table(&t2, LockMode::IntentionShared, 0).unwrap();
+                lock_mgr.lock_row(&t2, LockMode::Shared, 0, rid).unwrap();
+                granted.store(true, Ordering::SeqCst);
+            })
+        };
+        thread::sleep(Duration::from_millis(50));
+        assert!(!granted.load(Ordering::SeqCst));
+
+        lock_mgr.unlock_all(&t1);
+        handle.join().unwrap();
+        assert!(granted.load(Ordering::SeqCst));
+        assert_eq!(Some(LockMode::Shared), t2.get_row_lock(&rid));
+        assert!(t1.get_table_lock(0).is_none());
+
+        // S and SIX go along with the IS of t2
+        lock_mgr.unlock_row(&t2, 0, rid)?;
+        lock_mgr.lock_table(&t1, LockMode::Shared, 0)?;
+        lock_mgr.lock_table(&t1, LockMode::IntentionExclusive, 0)?;
+        assert_eq!(Some(LockMode::SharedIntentionExclusive), t1.get_table_lock(0));
+        lock_mgr.unlock_all(&t1);
+        lock_mgr.unlock_all(&t2);
+
+        // the queues go with the last request
+        assert!(lock_mgr.table_lock_map.lock().unwrap().is_empty());
+        assert!(lock_mgr.row_lock_map.lock().unwrap().is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn deadlock_detection_test() -> Result<(), String> {
+        let lock_mgr = Arc::new(LockManager::new());
+        let detector = DeadlockDetector::new(lock_mgr.clone(), Duration::from_millis(20));
+        let (t1, t2) = (new_txn(1), new_txn(2));
+        let (r1, r2) = (RID { pid: 1, sid: 0 }, RID { pid: 1, sid: 1 });
+        lock_rid(&lock_mgr, &t1, r1)?;
+        lock_rid(&lock_mgr, &t2, r2)?;
+
+        let handle = {
+            let (lock_mgr, t1) = (lock_mgr.clone(), t1.clone());
+            thread::spawn(move || lock_rid(&lock_mgr, &t1, r2))
+        };
+        thread::sleep(Duration::from_millis(10));
+        assert_eq!(vec![(1, 2)], lock_mgr.get_waits_for_edges());
+
+        // t2 closes the cycle, the younger one is the victim
+        let res = lock_rid(&lock_mgr, &t2, r1);
+        assert!(res.unwrap_err().starts_with(TXN_ABORTED));
+        assert_eq!(TransactionState::Aborted, t2.get_state());
+        lock_mgr.unlock_all(&t2);
+
+        handle.join().unwrap()?;
+        assert_eq!(Some(LockMode::Exclusive), t1.get_row_lock(&r2));
+        assert!(lock_mgr.get_waits_for_edges().is_empty());
+        lock_mgr.unlock_all(&t1);
+        drop(detector);
+        Ok(())
+    }
+
+    #[test]
+    fn queued_deadlock_test() -> Result<(), String> {
+        let lock_mgr = Arc::new(LockManager::new());
+        let detector = DeadlockDetector::new(lock_mgr.clone(), Duration::from_millis(20));
+        let (t1, t2, t3) = (new_txn(1), new_txn(2), new_txn(3));
+        let (a, b) = (RID { pid: 1, sid: 0 }, RID { pid: 1, sid: 1 });
+        for txn in [&t1, &t2, &t3] {
+            lock_mgr.lock_table(txn, LockMode::IntentionExclusive, 0)?;
+        }
+        lock_mgr.lock_row(&t1, LockMode::Shared, 0, a)?;
+        lock_mgr.lock_row(&t3, LockMode::Exclusive, 0, b)?;
+
+        // t2 waits for the S of t1 on a, t1 waits for the X of t3 on b
+        let handle2 = {
+            let (lock_mgr, t2) = (lock_mgr.clone(), t2.clone());
+            thread::spawn(move || lock_mgr.lock_row(&t2, LockMode::Exclusive, 0, a))
+        };
+        thread::sleep(Duration::from_millis(10));
+        let handle1 = {
+            let (lock_mgr, t1) = (lock_mgr.clone(), t1.clone());
+            thread::spawn(move || lock_mgr.lock_row(&t1, LockMode::Exclusive, 0, b))
+        };
+        thread::sleep(Duration::from_millis(10));
+        assert_eq!(vec![(1, 3), (2, 1)], lock_mgr.get_waits_for_edges());
+
+        // the S of t3 goes along with the one of t1, but it is queued behind t2, so the cycle is t3 -> t2 -> t1 -> t3
+        let res = lock_mgr.lock_row(&t3, LockMode::Shared, 0, a);
+        assert!(res.unwrap_err().starts_with(TXN_ABORTED));
+        lock_mgr.unlock_all(&t3);
+
+        handle1.join().unwrap()?;
+        lock_mgr.unlock_all(&t1);
+        handle2.join().unwrap()?;
+        assert_eq!(Some(LockMode::Exclusive), t2.get_row_lock(&a));
+        lock_mgr.unlock_all(&t2);
+        drop(detector);
+        Ok(())
+    }
+
+    #[test]
+    fn upgrade_abort_test() -> Result<(), String> {
+        let lock_mgr = Arc::new(LockManager::with_policy(DeadlockPolicy::WaitDie));
+        let (t1, t2) = (new_txn(1), new_txn(2));
+        let rid = RID { pid: 1, sid: 0 };
+        for txn in [&t1, &t2] {
+            lock_mgr.lock_table(txn, LockMode::IntentionExclusive, 0)?;
+            lock_mgr.lock_row(txn, LockMode::Shared, 0, rid)?;
+        }
+
+        // the upgrade of the younger one dies, it still holds its S
+        assert!(lock_mgr.lock_row(&t2, LockMode::Exclusive, 0, rid).unwrap_err().starts_with(TXN_ABORTED));
+        assert_eq!(Some(LockMode::Shared), t2.get_row_lock(&rid));
+        let handle = {
+            let (lock_mgr, t1) = (lock_mgr.clone(), t1.clone());
+            thread::spawn(move || lock_mgr.lock_row(&t1, LockMode::Exclusive, 0, rid))
+        };
+        thread::sleep(Duration::from_millis(20));
+        assert_eq!(Some(LockMode::Shared), t1.get_row_lock(&rid));
+
+        lock_mgr.unlock_all(&t2);
+        handle.join().unwrap()?;
+        assert_eq!(Some(LockMode::Exclusive), t1.get_row_lock(&rid));
+        lock_mgr.unlock_all(&t1);
+        Ok(())
+    }
+
+    #[test]
+    fn wound_wait_test() -> Result<(), String> {
+        let lock_mgr = Arc::new(LockManager::with_policy(DeadlockPolicy::WoundWait));
+        let (t1, t2, t3) = (new_txn(1), new_txn(2), new_txn(3));
+        let rid = RID { pid: 1, sid: 0 };
+
+        // the younger requester waits
+        lock_rid(&lock_mgr, &t2, rid)?;
+        let handle = {
+            let (lock_mgr, t3) = (lock_mgr.clone(), t3.clone());
+            thread::spawn(move || lock_rid(&lock_mgr, &t3, rid))
+        };
+        thread::sleep(Duration::from_millis(20));
+        assert_eq!(TransactionState::Running, t3.get_state());
+
+        // the older requester wounds the younger holder and waiter
+        let handle1 = {
+            let (lock_mgr, t1) = (lock_mgr.clone(), t1.clone());
+            thread::spawn(move || lock_rid(&lock_mgr, &t1, rid))
+        };
+        thread::sleep(Duration::from_millis(20));
+        assert_eq!(TransactionState::Aborted, t2.get_state());
+        assert!(handle.join().unwrap().unwrap_err().starts_with(TXN_ABORTED));
+        lock_mgr.unlock_all(&t3);
+        assert!(lock_rid(&lock_mgr, &t2, RID { pid: 1, sid: 1 }).is_err());
+        lock_mgr.unlock_all(&t2);
+
+        handle1.join().unwrap()?;
+        assert_eq!(Some(LockMode::Exclusive), t1.get_row_lock(&rid));
+        lock_mgr.unlock_all(&t1);
+        Ok(())
+    }
+
+    #[test]
+    fn wait_die_test() -> Result<(), String> {
+        let lock_mgr = Arc::new(LockManager::with_policy(DeadlockPolicy::WaitDie));
+        let (t1, t2) = (new_txn(1), new_txn(2));
+        let rid = RID { pid: 1, sid: 0 };
+
+        // the younger requester dies
+        lock_rid(&lock_mgr, &t1, rid)?;
+        assert!(lock_rid(&lock_mgr, &t2, rid).unwrap_err().starts_with(TXN_ABORTED));
+        assert_eq!(TransactionState::Aborted, t2.get_state());
+        lock_mgr.unlock_all(&t2);
+        lock_mgr.unlock_all(&t1);
+
+        // the older requester waits
+        let t3 = new_txn(3);
+        lock_rid(&lock_mgr, &t3, rid)?;
+        let handle = {
+            let (lock_mgr, t1) = (lock_mgr.clone(), t1.clone());
+            thread::spawn(move || lock_rid(&lock_mgr, &t1, rid))
+        };
+        thread::sleep(Duration::from_millis(20));
+        assert_eq!(TransactionState::Running, t1.get_state());
+        lock_mgr.unlock_all(&t3);
+        handle.join().unwrap()?;
+        lock_mgr.unlock_all(&t1);
+        Ok(())
     }
-}
\ No newline at end of file
+}
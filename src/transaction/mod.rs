@@ -1,2 +1,5 @@
 pub mod lock_manager;
-pub mod transaction;
\ No newline at end of file
+pub mod transaction;
+pub mod transaction_manager;
+pub mod deadlock_detector;
+pub mod vacuum_manager;
@@ -1,14 +1,98 @@
-use std::sync::Arc;
+use std::{collections::HashMap, fmt::Display, sync::{Arc, Mutex}};
 
-use crate::common::config::txn_id_t;
+use crate::{common::{config::{lsn_t, table_id_t, timestamp_t, txn_id_t, INVALID_LSN, INVALID_TS}, rid::RID}, storage::page_based::table::tuple::Tuple};
+
+use super::lock_manager::LockMode;
 
 
 pub type TransactionRef = Arc<Transaction>;
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TransactionState {
+    Running,
+    Committed,
+    Aborted,
+}
+
+// the snapshot isolation reads its snapshot without locks, the other levels read the latest versions:
+// READ UNCOMMITTED reads the uncommitted ones without locks too,
+// READ COMMITTED takes a shared lock on a row only while reading it,
+// REPEATABLE READ keeps the shared row locks to the end, new rows (phantoms) may still show up,
+// SERIALIZABLE locks the whole table against the phantoms
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IsolationLevel {
+    ReadUncommitted,
+    ReadCommitted,
+    RepeatableRead,
+    Serializable,
+    SnapshotIsolation,
+}
+
+impl Display for IsolationLevel {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
+            Self::ReadUncommitted => "READ UNCOMMITTED",
+            Self::ReadCommitted => "READ COMMITTED",
+            Self::RepeatableRead => "REPEATABLE READ",
+            Self::Serializable => "SERIALIZABLE",
+            Self::SnapshotIsolation => "SNAPSHOT",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WriteType {
+    Insert,
+    Delete,
+    Update,
+}
+
+// a version written by the transaction, an update writes a Delete of the old version and an Insert of the new one.
+// a version the transaction inserted itself is updated in place instead, see `ConstraintChecker::update_tuple`.
+// `lsn` is the one of its log record
+#[derive(Debug, Clone)]
+pub struct WriteRecord {
+    pub table_oid: table_id_t,
+    pub rid: RID,
+    pub wtype: WriteType,
+    pub lsn: lsn_t,
+    // (replaced, new) of an Update, as they are stored
+    pub tuples: Option<(Tuple, Tuple)>,
+}
+
+impl WriteRecord {
+    pub fn new(table_oid: table_id_t, rid: RID, wtype: WriteType, lsn: lsn_t) -> Self {
+        Self { table_oid, rid, wtype, lsn, tuples: None }
+    }
+
+    pub fn new_update(table_oid: table_id_t, rid: RID, tuples: (Tuple, Tuple), lsn: lsn_t) -> Self {
+        Self { table_oid, rid, wtype: WriteType::Update, lsn, tuples: Some(tuples) }
+    }
+}
+
+#[derive(Debug)]
+struct Transaction_ {
+    state: TransactionState,
+    isolation_level: IsolationLevel,
+    commit_ts: timestamp_t,
+    // the lsn of the last log record of the transaction
+    prev_lsn: lsn_t,
+    write_set: Vec<WriteRecord>,
+    // (name, length of the write set when the savepoint is made)
+    savepoints: Vec<(String, usize)>,
+    // the locks granted by the lock manager
+    table_locks: HashMap<table_id_t, LockMode>,
+    row_locks: HashMap<RID, (table_id_t, LockMode)>,
+}
 
-#[derive(Debug, Eq)]
+/// A transaction reads the snapshot of its read timestamp,
+/// the versions written by the transactions committed after it are invisible.
+#[derive(Debug)]
 pub struct Transaction {
     txn_id: txn_id_t,
+    read_ts: timestamp_t,
+    state: Mutex<Transaction_>,
 }
 
 impl PartialEq for Transaction {
@@ -17,8 +101,140 @@ impl PartialEq for Transaction {
     }
 }
 
+impl Eq for Transaction {}
+
 impl Transaction {
-    pub fn new() -> Self {
-        Self { txn_id: 0 }
+    pub fn new(txn_id: txn_id_t, read_ts: timestamp_t, isolation_level: IsolationLevel) -> Self {
+        Self { 
+            txn_id, 
+            read_ts,
+            state: Mutex::new(Transaction_ { 
+                state: TransactionState::Running, 
+                isolation_level,
+                commit_ts: INVALID_TS,
+                prev_lsn: INVALID_LSN,
+                write_set: Vec::new(),
+                savepoints: Vec::new(),
+                table_locks: HashMap::new(),
+                row_locks: HashMap::new(),
+            }),
+        }
+    }
+
+    pub fn get_txn_id(&self) -> txn_id_t {
+        self.txn_id
+    }
+
+    pub fn get_read_ts(&self) -> timestamp_t {
+        self.read_ts
+    }
+
+    pub fn get_isolation_level(&self) -> IsolationLevel {
+        self.state.lock().unwrap().isolation_level
+    }
+
+    // SET TRANSACTION, only before the transaction reads or writes anything
+    pub(crate) fn set_isolation_level(&self, isolation_level: IsolationLevel) {
+        self.state.lock().unwrap().isolation_level = isolation_level;
+    }
+
+    pub fn get_state(&self) -> TransactionState {
+        self.state.lock().unwrap().state
+    }
+
+    pub fn get_commit_ts(&self) -> timestamp_t {
+        self.state.lock().unwrap().commit_ts
+    }
+
+    pub fn get_prev_lsn(&self) -> lsn_t {
+        self.state.lock().unwrap().prev_lsn
+    }
+
+    pub fn set_prev_lsn(&self, lsn: lsn_t) {
+        self.state.lock().unwrap().prev_lsn = lsn;
+    }
+
+    // the lsn of the last record in the write set, the undo goes on from it
+    pub fn get_last_write_lsn(&self) -> lsn_t {
+        self.state.lock().unwrap().write_set.last().map(|record| record.lsn).unwrap_or(INVALID_LSN)
+    }
+
+    pub fn append_write_record(&self, record: WriteRecord) {
+        self.state.lock().unwrap().write_set.push(record);
+    }
+
+    pub fn get_write_set_len(&self) -> usize {
+        self.state.lock().unwrap().write_set.len()
+    }
+
+    // remove the records written after the first `len` ones, the newest comes first
+    pub(crate) fn take_write_records(&self, len: usize) -> Vec<WriteRecord> {
+        let mut state = self.state.lock().unwrap();
+        let len = len.min(state.write_set.len());
+        state.write_set.drain(len..).rev().collect()
+    }
+
+    // a savepoint with the same name hides the older one
+    pub fn add_savepoint(&self, name: &str) {
+        let mut state = self.state.lock().unwrap();
+        let len = state.write_set.len();
+        state.savepoints.push((name.to_owned(), len));
+    }
+
+    // drop the savepoints made after `name`, returns the length of the write set at `name`.
+    // the savepoint itself is kept when `keep` is set, as ROLLBACK TO does
+    pub(crate) fn pop_savepoints(&self, name: &str, keep: bool) -> Result<usize, String> {
+        let mut state = self.state.lock().unwrap();
+        let Some(pos) = state.savepoints.iter().rposition(|(sp_name, _)| sp_name == name) else {
+            return Err(format!("savepoint \"{}\" does not exist", name));
+        };
+        let len = state.savepoints[pos].1;
+        state.savepoints.truncate(if keep { pos + 1 } else { pos });
+        Ok(len)
+    }
+
+    pub fn get_table_lock(&self, table_oid: table_id_t) -> Option<LockMode> {
+        self.state.lock().unwrap().table_locks.get(&table_oid).cloned()
+    }
+
+    pub fn get_row_lock(&self, rid: &RID) -> Option<LockMode> {
+        self.state.lock().unwrap().row_locks.get(rid).map(|(_, mode)| *mode)
+    }
+
+    pub fn get_locked_tables(&self) -> Vec<table_id_t> {
+        self.state.lock().unwrap().table_locks.keys().cloned().collect()
+    }
+
+    pub fn get_locked_rows(&self) -> Vec<(table_id_t, RID)> {
+        self.state.lock().unwrap().row_locks.iter().map(|(rid, (table_oid, _))| (*table_oid, *rid)).collect()
+    }
+
+    // the lock sets are kept by the lock manager
+    pub(crate) fn set_table_lock(&self, table_oid: table_id_t, mode: Option<LockMode>) {
+        let mut state = self.state.lock().unwrap();
+        match mode {
+            Some(mode) => { state.table_locks.insert(table_oid, mode); },
+            None => { state.table_locks.remove(&table_oid); },
+        }
+    }
+
+    pub(crate) fn set_row_lock(&self, table_oid: table_id_t, rid: RID, mode: Option<LockMode>) {
+        let mut state = self.state.lock().unwrap();
+        match mode {
+            Some(mode) => { state.row_locks.insert(rid, (table_oid, mode)); },
+            None => { state.row_locks.remove(&rid); },
+        }
+    }
+
+    pub(crate) fn set_committed(&self, commit_ts: timestamp_t) {
+        let mut state = self.state.lock().unwrap();
+        state.state = TransactionState::Committed;
+        state.commit_ts = commit_ts;
+        state.write_set.clear();
+        state.savepoints.clear();
+    }
+
+    pub(crate) fn set_aborted(&self) {
+        self.state.lock().unwrap().state = TransactionState::Aborted;
     }
-}
\ No newline at end of file
+}
@@ -0,0 +1,525 @@
+#![allow(warnings)]
+
+use std::{collections::{HashMap, VecDeque}, sync::{atomic::{AtomicI32, Ordering}, Arc, Mutex, RwLock}};
+
+use crate::{catalog::catalog::CataLog, common::config::{lsn_t, timestamp_t, txn_id_t, INVALID_LSN, INVALID_TXN_ID}, storage::page_based::{disk::{log_manager::LogManager, log_record::{LogRecord, LogRecordBody}}, table::tuple::TupleMeta}};
+
+use super::{lock_manager::LockManagerRef, transaction::{IsolationLevel, Transaction, TransactionRef, TransactionState, WriteRecord, WriteType}};
+
+
+#[derive(Debug)]
+struct TransactionManager_ {
+    // the commit timestamp of the last committed transaction
+    last_commit_ts: timestamp_t,
+    // the transactions without a COMMIT/ABORT record and the lsns of their BEGIN records
+    active_txns: HashMap<txn_id_t, lsn_t>,
+    // the ended transactions still in `txn_map`, with their commit ts or the last one when they are aborted
+    ended_txns: VecDeque<(timestamp_t, txn_id_t)>,
+}
+
+// a transaction in `txn_map`, it is kept as its end once it commits or aborts
+#[derive(Debug, Clone)]
+enum TxnEntry {
+    Live(TransactionRef),
+    Ended { state: TransactionState, commit_ts: timestamp_t },
+}
+
+pub type TransactionManagerRef = Arc<TransactionManager>;
+
+/// Hands out the txn ids and the read/commit timestamps, and decides which version of a tuple a transaction sees.
+///
+/// A version is tagged with the txn ids which insert and delete it (see `TupleMeta`),
+/// an update deletes the old version and inserts a new one, so the versions of a row stay in the table heap
+/// and a scan picks the one visible to it. Under the snapshot isolation readers take no locks, they never block writers,
+/// the other isolation levels read the latest versions and rely on the lock manager (see `IsolationLevel`).
+/// The tuples written without a transaction (`INVALID_TXN_ID`) are treated as committed at the beginning,
+/// so are the ones of the transactions before a restart, the recovery rolled back the unfinished ones among them.
+/// The locks of a transaction are released when it commits or aborts.
+/// A commit returns after its log record is on disk, the undo of an abort or a rollback is logged as compensation records.
+/// The transactions without a COMMIT/ABORT record are tracked for the checkpoints (see `CheckpointManager`).
+/// An ended transaction is forgotten once every snapshot sees its end, it is taken as committed at the beginning then,
+/// like the ones before a restart. The versions of an aborted one are dead by then, its rollback deleted them.
+#[derive(Debug)]
+pub struct TransactionManager {
+    lock_mgr: LockManagerRef,
+    log_mgr: Arc<LogManager>,
+    next_txn_id: AtomicI32,
+    // the ids before it are of the transactions before the restart
+    first_txn_id: txn_id_t,
+    txn_map: RwLock<HashMap<txn_id_t, TxnEntry>>,
+    state: Mutex<TransactionManager_>,
+}
+
+impl TransactionManager {
+    pub fn new(lock_mgr: LockManagerRef, log_mgr: Arc<LogManager>) -> Self {
+        Self {
+            lock_mgr,
+            log_mgr,
+            next_txn_id: AtomicI32::new(0),
+            first_txn_id: 0,
+            txn_map: RwLock::new(HashMap::new()),
+            state: Mutex::new(TransactionManager_ { last_commit_ts: 0, active_txns: HashMap::new(), ended_txns: VecDeque::new() }),
+        }
+    }
+
+    // the ids go on after the ones in the log, see `LogRecovery::get_next_txn_id`
+    pub fn with_next_txn_id(mut self, next_txn_id: txn_id_t) -> Self {
+        self.next_txn_id = AtomicI32::new(next_txn_id);
+        self.first_txn_id = next_txn_id;
+        self
+    }
+
+    pub fn get_next_txn_id(&self) -> txn_id_t {
+        self.next_txn_id.load(Ordering::SeqCst)
+    }
+
+    // the snapshot of a new transaction contains all the transactions committed so far
+    pub fn begin(&self, isolation_level: IsolationLevel) -> TransactionRef {
+        let mut state = self.state.lock().unwrap();
+        let txn_id = self.next_txn_id.fetch_add(1, Ordering::SeqCst);
+        let txn = Arc::new(Transaction::new(txn_id, state.last_commit_ts, isolation_level));
+        self.txn_map.write().unwrap().insert(txn_id, TxnEntry::Live(txn.clone()));
+        let lsn = self.append_log(&txn, LogRecordBody::Begin);
+        state.active_txns.insert(txn_id, lsn);
+        txn
+    }
+
+    // the index entries of the versions deleted by the transaction go once the deletes are committed.
+    // a transaction aborted by others, e.g. wounded, is rolled back instead and its locks are released
+    pub fn commit(&self, txn: &TransactionRef, catalog: &CataLog) -> Result<(), String> {
+        match txn.get_state() {
+            TransactionState::Running => {},
+            TransactionState::Aborted => {
+                self.abort(txn, catalog)?;
+                return Err(format!("transaction {} is aborted, it is rolled back", txn.get_txn_id()));
+            },
+            TransactionState::Committed => {
+                return Err(format!("transaction {} is not running", txn.get_txn_id()));
+            },
+        }
+
+        // the deleted versions are read before the commit is logged, the transaction stays running
+        // when one of them can not be read, and it is rolled back by its abort
+        let records = txn.take_write_records(0);
+        let mut deleted = Vec::new();
+        let mut failed = None;
+        for record in records.iter().filter(|record| record.wtype == WriteType::Delete) {
+            let Some(table_info) = catalog.get_table_byid(record.table_oid) else { continue; };
+            match table_info.table_heap.get_tuple(&record.rid) {
+                Ok((_, tuple)) => deleted.push((table_info, tuple, record.rid)),
+                Err(err) => {
+                    failed = Some(err);
+                    break;
+                },
+            }
+        }
+        if let Some(err) = failed {
+            for record in records.into_iter().rev() {
+                txn.append_write_record(record);
+            }
+            return Err(format!("transaction {} can not commit, {}", txn.get_txn_id(), err));
+        }
+
+        // a read-only transaction does not wait for the log
+        let wrote = !records.is_empty();
+        let lsn = self.append_log_end(txn, LogRecordBody::Commit);
+        if wrote {
+            self.log_mgr.flush(lsn)?;
+        }
+
+        // the deleted versions are not removed by a vacuum before their entries are gone
+        for (table_info, tuple, rid) in deleted {
+            catalog.delete_index_entries(&table_info.table_name, &tuple, rid);
+        }
+        let mut replaced = Vec::new();
+        for record in records {
+            let Some(table_info) = catalog.get_table_byid(record.table_oid) else { continue; };
+            if let (WriteType::Update, Some(tuples)) = (record.wtype, record.tuples) {
+                replaced.push((table_info.table_heap.clone(), tuples));
+            }
+        }
+
+        // the commit ts and the state are set together, a new snapshot never sees a half committed transaction
+        {
+            let mut state = self.state.lock().unwrap();
+            let commit_ts = state.last_commit_ts + 1;
+            txn.set_committed(commit_ts);
+            state.last_commit_ts = commit_ts;
+        }
+        self.lock_mgr.unlock_all(txn);
+        self.end(txn);
+
+        // the values replaced by the updates in place can not be put back now, their overflow pages are given back
+        for (table_heap, (old_tuple, tuple)) in replaced {
+            table_heap.free_replaced(&old_tuple, &tuple)
+                .map_err(|err| format!("transaction {} is committed, but its replaced values are not freed, {}", txn.get_txn_id(), err))?;
+        }
+        Ok(())
+    }
+
+    // undo the writes of the transaction, the versions of an aborted transaction are invisible to everyone
+    pub fn abort(&self, txn: &TransactionRef, catalog: &CataLog) -> Result<(), String> {
+        if txn.get_state() == TransactionState::Committed {
+            return Err(format!("transaction {} is committed already", txn.get_txn_id()));
+        }
+        // the transaction may be aborted by others already, e.g. a deadlock victim, its writes are still there
+        self.rollback_to(txn, 0, catalog)?;
+        self.append_log_end(txn, LogRecordBody::Abort);
+        txn.set_aborted();
+        self.lock_mgr.unlock_all(txn);
+        self.end(txn);
+        Ok(())
+    }
+
+    pub fn rollback_to_savepoint(&self, txn: &TransactionRef, name: &str, catalog: &CataLog) -> Result<(), String> {
+        let len = txn.pop_savepoints(name, true)?;
+        self.rollback_to(txn, len, catalog)
+    }
+
+    pub fn release_savepoint(&self, txn: &TransactionRef, name: &str) -> Result<(), String> {
+        txn.pop_savepoints(name, false)?;
+        Ok(())
+    }
+
+    // undo the writes after the first `len` ones of the write set, the newest first
+    pub fn rollback_to(&self, txn: &TransactionRef, len: usize, catalog: &CataLog) -> Result<(), String> {
+        if txn.get_state() == TransactionState::Committed {
+            return Err(format!("transaction {} is committed already", txn.get_txn_id()));
+        }
+        let records = txn.take_write_records(len);
+        // once a write is undone, the next one to undo is the write before it
+        let undo_end_lsn = txn.get_last_write_lsn();
+        for (idx, record) in records.iter().enumerate() {
+            let undo_next_lsn = records.get(idx + 1).map(|next| next.lsn).unwrap_or(undo_end_lsn);
+            self.undo(txn, record, undo_next_lsn, catalog)?;
+        }
+        Ok(())
+    }
+
+    fn undo(&self, txn: &TransactionRef, record: &WriteRecord, undo_next_lsn: lsn_t, catalog: &CataLog) -> Result<(), String> {
+        let Some(table_info) = catalog.get_table_byid(record.table_oid) else {
+            return Err(format!("table {} of the write record not found", record.table_oid));
+        };
+        let (mut meta, tuple) = table_info.table_heap.get_tuple(&record.rid)?;
+        match record.wtype {
+            // the version is deleted by the transaction itself, so it is invisible to the transaction too
+            WriteType::Insert => {
+                meta.is_deleted = true;
+                meta.delete_txn_id = txn.get_txn_id();
+                table_info.table_heap.compensate_meta(&meta, &record.rid, txn, undo_next_lsn)?;
+                catalog.delete_index_entries(&table_info.table_name, &tuple, record.rid);
+            },
+            // the index entries are kept until the delete commits
+            WriteType::Delete => {
+                meta.is_deleted = false;
+                meta.delete_txn_id = INVALID_TXN_ID;
+                table_info.table_heap.compensate_meta(&meta, &record.rid, txn, undo_next_lsn)?;
+            },
+            // the replaced tuple is put back by another update, which goes with the undo of the insert as well
+            WriteType::Update => {
+                let Some((old_tuple, new_tuple)) = &record.tuples else {
+                    return Err(format!("the update of {:?} has no tuples to put back", record.rid));
+                };
+                catalog.delete_index_entries(&table_info.table_name, &tuple, record.rid);
+                table_info.table_heap.update_tuple(&record.rid, old_tuple, Some(txn))?;
+                table_info.table_heap.free_replaced(new_tuple, old_tuple)?;
+                let (_, old_tuple) = table_info.table_heap.get_tuple(&record.rid)?;
+                catalog.insert_index_entries(&table_info.table_name, &old_tuple, record.rid);
+            },
+        }
+        Ok(())
+    }
+
+    fn append_log(&self, txn: &TransactionRef, body: LogRecordBody) -> lsn_t {
+        let mut record = LogRecord::new(txn.get_txn_id(), txn.get_prev_lsn(), body);
+        let lsn = self.log_mgr.append_log_record(&mut record);
+        if lsn != INVALID_LSN {
+            txn.set_prev_lsn(lsn);
+        }
+        lsn
+    }
+
+    // the COMMIT/ABORT record is written together with the removal from the active ones,
+    // so a checkpoint never takes a transaction ended before it as active
+    fn append_log_end(&self, txn: &TransactionRef, body: LogRecordBody) -> lsn_t {
+        let mut state = self.state.lock().unwrap();
+        state.active_txns.remove(&txn.get_txn_id());
+        self.append_log(txn, body)
+    }
+
+    // keep the transaction as its end, and forget the ended ones every snapshot sees as ended, see `get_oldest_read_ts`
+    fn end(&self, txn: &TransactionRef) {
+        let mut state = self.state.lock().unwrap();
+        let commit_ts = match txn.get_state() {
+            TransactionState::Committed => txn.get_commit_ts(),
+            _ => state.last_commit_ts,
+        };
+        state.ended_txns.push_back((commit_ts, txn.get_txn_id()));
+        let oldest_read_ts = Self::oldest_read_ts(&state, &self.txn_map.read().unwrap());
+
+        let mut txn_map = self.txn_map.write().unwrap();
+        txn_map.insert(txn.get_txn_id(), TxnEntry::Ended { state: txn.get_state(), commit_ts });
+        while let Some((commit_ts, txn_id)) = state.ended_txns.front().cloned() {
+            if commit_ts > oldest_read_ts {
+                break;
+            }
+            txn_map.remove(&txn_id);
+            state.ended_txns.pop_front();
+        }
+    }
+
+    // the active transaction table of a checkpoint, the running transactions and their last lsns
+    pub fn get_active_txns(&self) -> Vec<(txn_id_t, lsn_t)> {
+        let state = self.state.lock().unwrap();
+        let txn_map = self.txn_map.read().unwrap();
+        state.active_txns.keys()
+            .filter_map(|txn_id| match txn_map.get(txn_id) {
+                Some(TxnEntry::Live(txn)) => Some((*txn_id, txn.get_prev_lsn())),
+                _ => None,
+            })
+            .collect()
+    }
+
+    // the first lsn the undo of the running transactions may go back to
+    pub fn get_oldest_active_lsn(&self) -> Option<lsn_t> {
+        self.state.lock().unwrap().active_txns.values().min().cloned()
+    }
+
+    pub fn get_txn(&self, txn_id: txn_id_t) -> Option<TransactionRef> {
+        match self.txn_map.read().unwrap().get(&txn_id) {
+            Some(TxnEntry::Live(txn)) => Some(txn.clone()),
+            _ => None,
+        }
+    }
+
+    // the state and the commit ts of a transaction, the forgotten ones and the ones before the restart
+    // are committed at the beginning
+    fn get_end(&self, txn_id: txn_id_t) -> (TransactionState, timestamp_t) {
+        match self.txn_map.read().unwrap().get(&txn_id) {
+            Some(TxnEntry::Live(txn)) => (txn.get_state(), txn.get_commit_ts()),
+            Some(TxnEntry::Ended { state, commit_ts }) => (*state, *commit_ts),
+            None => (TransactionState::Committed, 0),
+        }
+    }
+
+    pub fn get_last_commit_ts(&self) -> timestamp_t {
+        self.state.lock().unwrap().last_commit_ts
+    }
+
+    // the oldest snapshot of the running transactions, a version deleted by a transaction committed
+    // at or before it is seen by none of them, nor by the ones to come
+    pub fn get_oldest_read_ts(&self) -> timestamp_t {
+        let state = self.state.lock().unwrap();
+        Self::oldest_read_ts(&state, &self.txn_map.read().unwrap())
+    }
+
+    fn oldest_read_ts(state: &TransactionManager_, txn_map: &HashMap<txn_id_t, TxnEntry>) -> timestamp_t {
+        state.active_txns.keys()
+            .filter_map(|txn_id| match txn_map.get(txn_id) {
+                Some(TxnEntry::Live(txn)) => Some(txn.get_read_ts()),
+                _ => None,
+            })
+            .min()
+            .unwrap_or(state.last_commit_ts)
+    }
+
+    // whether the version can be removed by a vacuum: it is deleted by a transaction committed before `oldest_read_ts`
+    // (see `get_oldest_read_ts`), or its insert is undone by the rollback. the delete of a running transaction may be undone yet
+    pub fn is_dead(&self, meta: &TupleMeta, oldest_read_ts: timestamp_t) -> bool {
+        if !meta.is_deleted {
+            return false;
+        }
+        if meta.delete_txn_id == INVALID_TXN_ID || self.is_recovered(meta.delete_txn_id) {
+            return true;
+        }
+        if self.state.lock().unwrap().active_txns.contains_key(&meta.delete_txn_id) {
+            return false;
+        }
+        match self.get_end(meta.delete_txn_id) {
+            (TransactionState::Committed, commit_ts) => commit_ts <= oldest_read_ts,
+            (TransactionState::Aborted, _) => meta.insert_txn_id == meta.delete_txn_id,
+            _ => false,
+        }
+    }
+
+    pub fn is_visible(&self, meta: &TupleMeta, txn: &Transaction) -> bool {
+        if !self.is_committed_for(meta.insert_txn_id, txn) {
+            return false;
+        }
+        meta.delete_txn_id == INVALID_TXN_ID || !self.is_committed_for(meta.delete_txn_id, txn)
+    }
+
+    // a version can be deleted or updated by `txn` only when no other live transaction did it,
+    // the first updater wins
+    pub fn check_write(&self, meta: &TupleMeta, txn: &Transaction) -> Result<(), String> {
+        if meta.delete_txn_id == INVALID_TXN_ID || meta.delete_txn_id == txn.get_txn_id()
+            || self.is_aborted(meta.delete_txn_id) {
+            return Ok(());
+        }
+        Err(format!("could not serialize access due to concurrent update"))
+    }
+
+    pub fn is_committed(&self, txn_id: txn_id_t) -> bool {
+        txn_id == INVALID_TXN_ID || self.get_end(txn_id).0 == TransactionState::Committed
+    }
+
+    pub fn is_aborted(&self, txn_id: txn_id_t) -> bool {
+        txn_id != INVALID_TXN_ID && self.get_end(txn_id).0 == TransactionState::Aborted
+    }
+
+    // whether the writes of `txn_id` are seen by `txn`: the snapshot isolation sees the ones committed before it began,
+    // read uncommitted sees the ones not aborted, the others see the committed ones
+    fn is_committed_for(&self, txn_id: txn_id_t, txn: &Transaction) -> bool {
+        if txn_id == INVALID_TXN_ID || txn_id == txn.get_txn_id() || self.is_recovered(txn_id) {
+            return true;
+        }
+        let (state, commit_ts) = self.get_end(txn_id);
+        match txn.get_isolation_level() {
+            IsolationLevel::SnapshotIsolation => state == TransactionState::Committed && commit_ts <= txn.get_read_ts(),
+            IsolationLevel::ReadUncommitted => state != TransactionState::Aborted,
+            _ => state == TransactionState::Committed,
+        }
+    }
+
+    // a transaction before the restart, it is over and taken as committed at the beginning
+    fn is_recovered(&self, txn_id: txn_id_t) -> bool {
+        txn_id < self.first_txn_id
+    }
+}
+
+
+
+
+#[cfg(test)]
+mod tests {
+    use std::{sync::Arc, thread, time::Duration};
+
+    use crate::{buffer::buffer_pool_manager::BufferPoolManager, catalog::catalog::CataLog, common::{config::INVALID_TXN_ID, rid::RID}, storage::page_based::{disk::{log_manager::LogManager, memory_page_store::MemoryPageStore}, table::tuple::TupleMeta}, transaction::lock_manager::{DeadlockPolicy, LockManager, LockMode}};
+
+    use super::TransactionManager;
+    use crate::transaction::transaction::{IsolationLevel, TransactionState};
+
+    #[test]
+    fn visibility_test() -> Result<(), String> {
+        let disk_mgr = Arc::new(MemoryPageStore::new());
+        let log_mgr = Arc::new(LogManager::new());
+        let bpm = Arc::new(BufferPoolManager::new(16, disk_mgr, 2, log_mgr.clone()));
+        let catalog = CataLog::new(bpm, Arc::new(LockManager::new()), log_mgr.clone());
+
+        let txn_mgr = TransactionManager::new(Arc::new(LockManager::new()), log_mgr.clone());
+        let t1 = txn_mgr.begin(IsolationLevel::SnapshotIsolation);
+        let t2 = txn_mgr.begin(IsolationLevel::SnapshotIsolation);
+
+        // the own writes are visible, the uncommitted ones of others are not
+        let inserted = TupleMeta::new(t1.get_txn_id(), INVALID_TXN_ID, false);
+        assert!(txn_mgr.is_visible(&inserted, &t1));
+        assert!(!txn_mgr.is_visible(&inserted, &t2));
+
+        let deleted = TupleMeta::new(INVALID_TXN_ID, t1.get_txn_id(), true);
+        assert!(!txn_mgr.is_visible(&deleted, &t1));
+        assert!(txn_mgr.is_visible(&deleted, &t2));
+        assert!(txn_mgr.check_write(&deleted, &t2).is_err());
+
+        // t2 keeps its snapshot after t1 commits
+        txn_mgr.commit(&t1, &catalog)?;
+        let t3 = txn_mgr.begin(IsolationLevel::SnapshotIsolation);
+        assert!(!txn_mgr.is_visible(&inserted, &t2));
+        assert!(txn_mgr.is_visible(&inserted, &t3));
+        assert!(!txn_mgr.is_visible(&deleted, &t3));
+        assert!(txn_mgr.check_write(&deleted, &t2).is_err());
+
+        // the writes of an aborted transaction are discarded
+        let t4 = txn_mgr.begin(IsolationLevel::SnapshotIsolation);
+        let deleted = TupleMeta::new(INVALID_TXN_ID, t4.get_txn_id(), true);
+        let inserted = TupleMeta::new(t4.get_txn_id(), INVALID_TXN_ID, false);
+        txn_mgr.abort(&t4, &catalog)?;
+        assert!(txn_mgr.is_visible(&deleted, &t3));
+        assert!(!txn_mgr.is_visible(&inserted, &t3));
+        assert!(txn_mgr.check_write(&deleted, &t3).is_ok());
+        assert!(txn_mgr.abort(&t1, &catalog).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn forget_ended_test() -> Result<(), String> {
+        let disk_mgr = Arc::new(MemoryPageStore::new());
+        let log_mgr = Arc::new(LogManager::new());
+        let bpm = Arc::new(BufferPoolManager::new(16, disk_mgr, 2, log_mgr.clone()));
+        let catalog = CataLog::new(bpm, Arc::new(LockManager::new()), log_mgr.clone());
+        let txn_mgr = TransactionManager::new(Arc::new(LockManager::new()), log_mgr.clone());
+
+        // the commit is kept while the snapshot of the reader is older than it
+        let (t1, reader) = (txn_mgr.begin(IsolationLevel::SnapshotIsolation), txn_mgr.begin(IsolationLevel::SnapshotIsolation));
+        let inserted = TupleMeta::new(t1.get_txn_id(), INVALID_TXN_ID, false);
+        txn_mgr.commit(&t1, &catalog)?;
+        assert_eq!(2, txn_mgr.txn_map.read().unwrap().len());
+        assert!(!txn_mgr.is_visible(&inserted, &reader));
+
+        // then both are forgotten, and t1 is committed at the beginning
+        txn_mgr.commit(&reader, &catalog)?;
+        assert!(txn_mgr.txn_map.read().unwrap().is_empty());
+        let t3 = txn_mgr.begin(IsolationLevel::SnapshotIsolation);
+        assert!(txn_mgr.is_visible(&inserted, &t3));
+
+        // the rollback deleted the versions of an aborted one
+        let t4 = txn_mgr.begin(IsolationLevel::SnapshotIsolation);
+        let undone = TupleMeta::new(t4.get_txn_id(), t4.get_txn_id(), true);
+        txn_mgr.abort(&t4, &catalog)?;
+        txn_mgr.commit(&t3, &catalog)?;
+        assert!(txn_mgr.txn_map.read().unwrap().is_empty());
+        assert!(txn_mgr.is_dead(&undone, txn_mgr.get_oldest_read_ts()));
+        Ok(())
+    }
+
+    #[test]
+    fn restart_visibility_test() {
+        let log_mgr = Arc::new(LogManager::new());
+        let txn_mgr = TransactionManager::new(Arc::new(LockManager::new()), log_mgr).with_next_txn_id(5);
+        let txn = txn_mgr.begin(IsolationLevel::SnapshotIsolation);
+        assert_eq!(5, txn.get_txn_id());
+
+        // the transactions before the restart are over, their writes are committed at the beginning
+        let inserted = TupleMeta::new(3, INVALID_TXN_ID, false);
+        let deleted = TupleMeta::new(2, 3, true);
+        assert!(txn_mgr.is_visible(&inserted, &txn));
+        assert!(!txn_mgr.is_visible(&deleted, &txn));
+        assert!(txn_mgr.is_committed(3));
+        assert!(txn_mgr.is_dead(&deleted, txn_mgr.get_oldest_read_ts()));
+    }
+
+    #[test]
+    fn wounded_commit_test() -> Result<(), String> {
+        let disk_mgr = Arc::new(MemoryPageStore::new());
+        let log_mgr = Arc::new(LogManager::new());
+        let bpm = Arc::new(BufferPoolManager::new(16, disk_mgr, 2, log_mgr.clone()));
+        let lock_mgr = Arc::new(LockManager::with_policy(DeadlockPolicy::WoundWait));
+        let catalog = CataLog::new(bpm, lock_mgr.clone(), log_mgr.clone());
+        let txn_mgr = TransactionManager::new(lock_mgr.clone(), log_mgr.clone());
+        let rid = RID { pid: 1, sid: 0 };
+
+        let older = txn_mgr.begin(IsolationLevel::RepeatableRead);
+        let younger = txn_mgr.begin(IsolationLevel::RepeatableRead);
+        lock_mgr.lock_table(&younger, LockMode::IntentionExclusive, 0)?;
+        lock_mgr.lock_row(&younger, LockMode::Exclusive, 0, rid)?;
+
+        // the older one wounds the younger one and waits for its lock
+        let waiter = {
+            let (lock_mgr, older) = (lock_mgr.clone(), older.clone());
+            thread::spawn(move || -> Result<(), String> {
+                lock_mgr.lock_table(&older, LockMode::IntentionExclusive, 0)?;
+                lock_mgr.lock_row(&older, LockMode::Exclusive, 0, rid)
+            })
+        };
+        while younger.get_state() != TransactionState::Aborted {
+            thread::sleep(Duration::from_millis(1));
+        }
+
+        // the commit of the wounded one rolls it back, and the older one gets the lock
+        assert!(txn_mgr.commit(&younger, &catalog).is_err());
+        waiter.join().unwrap()?;
+        assert_eq!(Some(LockMode::Exclusive), older.get_row_lock(&rid));
+        assert!(younger.get_locked_rows().is_empty());
+        assert!(txn_mgr.get_active_txns().iter().all(|(txn_id, _)| *txn_id != younger.get_txn_id()));
+        txn_mgr.commit(&older, &catalog)
+    }
+}
@@ -0,0 +1,86 @@
+#![allow(warnings)]
+
+use std::{sync::{mpsc::{self, RecvTimeoutError}, Arc, Mutex}, thread::{self, JoinHandle}, time::Duration};
+
+use crate::storage::page_based::table::table_heap::{TableHeap, TableHeapRef};
+
+use super::transaction_manager::TransactionManagerRef;
+
+
+/// Removes the dead versions from the table heaps, see `TableHeap::vacuum`.
+/// A version is dead when no running transaction sees it and no rollback brings it back (see `TransactionManager::is_dead`),
+/// so a long running snapshot keeps the versions deleted after it began.
+#[derive(Debug)]
+pub struct VacuumManager {
+    txn_mgr: TransactionManagerRef,
+    // the heaps the vacuum worker goes through
+    table_heaps: Mutex<Vec<TableHeapRef>>,
+}
+
+impl VacuumManager {
+    pub fn new(txn_mgr: TransactionManagerRef) -> Self {
+        Self { txn_mgr, table_heaps: Mutex::new(Vec::new()) }
+    }
+
+    pub fn register(&self, table_heap: TableHeapRef) {
+        self.table_heaps.lock().unwrap().push(table_heap);
+    }
+
+    // vacuum the heap, a full one goes through all of its pages. returns the tuples removed
+    pub fn vacuum(&self, table_heap: &TableHeap, full: bool) -> Result<usize, String> {
+        let oldest_read_ts = self.txn_mgr.get_oldest_read_ts();
+        table_heap.vacuum(full, &|meta| self.txn_mgr.is_dead(meta, oldest_read_ts))
+    }
+
+    // the pages with deleted tuples of the heaps registered
+    pub fn vacuum_all(&self) -> Result<usize, String> {
+        let table_heaps = self.table_heaps.lock().unwrap().clone();
+        let mut num_removed = 0;
+        for table_heap in table_heaps {
+            num_removed += self.vacuum(&table_heap, false)?;
+        }
+        Ok(num_removed)
+    }
+}
+
+
+/// A background thread which vacuums the heaps registered at an interval, it stops when dropped.
+/// The error of a failed vacuum is kept until one succeeds, see `get_error`
+#[derive(Debug)]
+pub struct VacuumWorker {
+    handler: Option<JoinHandle<()>>,
+    // dropped to wake up and stop the thread
+    stop: Option<mpsc::Sender<()>>,
+    error: Arc<Mutex<Option<String>>>,
+}
+
+impl VacuumWorker {
+    pub fn new(vacuum_mgr: Arc<VacuumManager>, interval: Duration) -> Self {
+        let (stop, receiver) = mpsc::channel::<()>();
+        let error = Arc::new(Mutex::new(None));
+        let handler = {
+            let error = error.clone();
+            thread::spawn(move || {
+                while let Err(RecvTimeoutError::Timeout) = receiver.recv_timeout(interval) {
+                    *error.lock().unwrap() = vacuum_mgr.vacuum_all().err();
+                }
+            })
+        };
+
+        Self { handler: Some(handler), stop: Some(stop), error }
+    }
+
+    // the error of the last vacuum, None when it went through
+    pub fn get_error(&self) -> Option<String> {
+        self.error.lock().unwrap().clone()
+    }
+}
+
+impl Drop for VacuumWorker {
+    fn drop(&mut self) {
+        self.stop.take();
+        if let Some(handler) = self.handler.take() {
+            let _ = handler.join();
+        }
+    }
+}
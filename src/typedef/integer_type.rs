@@ -1,5 +1,6 @@
 #![allow(warnings)]
 
+use super::limits::DB_INT32_NULL;
 use super::type_id;
 use super::type_trait::*;
 use super::value::*;
@@ -60,6 +61,10 @@ impl MathOp for IntegerType {
 /// Use default implementation is ok
 impl Type for IntegerType {
     fn serialize_value(val: &Value) -> Vec<u8> {
+        // null is stored as a sentinel, so the column keeps its fixed length
+        if val.is_null() {
+            return DB_INT32_NULL.to_ne_bytes().to_vec();
+        }
         return val.get_data()
     }
 
@@ -67,6 +72,9 @@ impl Type for IntegerType {
         assert_eq!(size_of::<i32>(), bytes.len());
         let bytes = unsafe { *(bytes.as_ptr() as *const [u8; 4]) };
         let val = i32::from_ne_bytes(bytes);
+        if DB_INT32_NULL == val {
+            return Value::new_null(type_id::TypeId::INTEGER);
+        }
 
         Value::new_integer(type_id::TypeId::INTEGER, val)
     }
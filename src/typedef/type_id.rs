@@ -5,6 +5,9 @@ pub enum TypeId {
     INTEGER,
     VARCHAR,
     TIMESTAMP,
+    // the strings and the bytes of any length, a large one is kept out of the page of its tuple
+    TEXT,
+    BLOB,
 }
 
 impl TypeId {
@@ -13,6 +16,8 @@ impl TypeId {
             Self::BOOLEAN => "bool".to_owned(),
             Self::INTEGER => "integer".to_owned(),
             Self::VARCHAR => "varchar".to_owned(),
+            Self::TEXT => "text".to_owned(),
+            Self::BLOB => "blob".to_owned(),
             _ => {
                 panic!("Not support type")
             }
@@ -20,6 +25,18 @@ impl TypeId {
     }
 }
 
+impl TypeId {
+    // the value is stored after the fixed part of the tuple, with its length
+    pub fn is_variable_length(&self) -> bool {
+        matches!(self, Self::VARCHAR | Self::TEXT | Self::BLOB)
+    }
+
+    // a value of the type may be stored in a column of `target`, a string goes to a text or a blob as it is
+    pub fn is_coercible_to(&self, target: TypeId) -> bool {
+        *self == target || (*self == Self::VARCHAR && target.is_variable_length())
+    }
+}
+
 impl From <u8> for TypeId {
     fn from(value: u8) -> Self {
         match value {
@@ -28,6 +45,8 @@ impl From <u8> for TypeId {
             2 => Self::INTEGER,
             3 => Self::VARCHAR,
             4 => Self::TIMESTAMP,
+            5 => Self::TEXT,
+            6 => Self::BLOB,
             _ => {
                 panic!("Not implemented type id")
             }
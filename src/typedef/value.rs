@@ -13,11 +13,14 @@ macro_rules! generate_compare_func {
     ($($func: ident), *) => {
         $(
             pub fn $func(&self, other: &Value) -> CmpBool {
+                if self.is_null() || other.is_null() {
+                    return CmpBool::CmpNull;
+                }
                 match self.type_id {
                     TypeId::INTEGER => {
                         IntegerType::$func(self, other)
                     },
-                    TypeId::VARCHAR => {
+                    TypeId::VARCHAR | TypeId::TEXT | TypeId::BLOB => {
                         VarcharType::$func(self, other)
                     },
                     _ => {
@@ -65,6 +68,10 @@ impl Value {
         Value::new(type_id, bytes)
     }
 
+    pub fn new_blob(bytes: &[u8]) -> Self {
+        Value::new(TypeId::BLOB, bytes)
+    }
+
     ///========================================= method of struct =====================
     // transfer data to another type with no check
     pub fn as_mut_ptr<T>(&mut self) -> &mut T {
@@ -134,6 +141,12 @@ impl Value {
                 }
                 return str[..10].to_owned();
             },
+            TypeId::TEXT => String::from_utf8_lossy(self.val.as_ref().unwrap()).to_string(),
+            // in hex like a bytea of PostgreSQL
+            TypeId::BLOB => {
+                let hex: String = self.val.as_ref().unwrap().iter().map(|byte| format!("{:02x}", byte)).collect();
+                format!("\\x{}", hex)
+            },
             _ => {
                 panic!("Not support yet.");
             }
@@ -154,7 +167,7 @@ impl Value {
     // ======================== static method =========================
     pub fn serialize(val: &Value) -> Vec<u8> {
         match val.get_type() {
-            TypeId::VARCHAR => {
+            TypeId::VARCHAR | TypeId::TEXT | TypeId::BLOB => {
                 VarcharType::serialize_value(val)
             },
             TypeId::INTEGER => {
@@ -170,6 +183,14 @@ impl Value {
             TypeId::VARCHAR => {
                 Ok(VarcharType::deserialize_value(bytes))
             },
+            // laid out as a varchar
+            TypeId::TEXT | TypeId::BLOB => {
+                let val = VarcharType::deserialize_value(bytes);
+                match val.is_null() {
+                    true => Ok(Value::new_null(type_id)),
+                    false => Ok(Value::new(type_id, &val.get_data())),
+                }
+            },
             TypeId::INTEGER => {
                 Ok(IntegerType::deserialize_value(bytes))
             },
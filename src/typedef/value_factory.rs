@@ -14,6 +14,11 @@ impl ValueFactory {
         Value::new_varchar(TypeId::VARCHAR, val)
     }
 
+    #[inline]
+    pub fn get_blob_value(val: &[u8]) -> Value {
+        Value::new_blob(val)
+    }
+
     #[inline]
     pub fn get_null_value(t: TypeId) -> Value {
         Value::new_null(t)
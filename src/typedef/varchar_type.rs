@@ -1,6 +1,6 @@
 use crate::typedef::type_id;
 
-use super::{type_trait::*, value::Value};
+use super::{limits::DB_VALUE_NULL, type_trait::*, value::Value};
 
 pub struct VarcharType {
 }
@@ -12,6 +12,10 @@ impl Compare for VarcharType {
 
 impl Type for VarcharType {
     fn serialize_value(val: &Value) -> Vec<u8> {
+        // a null varchar only keeps the length field, marked as DB_VALUE_NULL
+        if val.is_null() {
+            return DB_VALUE_NULL.to_ne_bytes().to_vec();
+        }
         let data = val.get_data();
         let len = data.len() as u32;
         let mut len_bytes = len.to_ne_bytes().to_vec();
@@ -24,6 +28,9 @@ impl Type for VarcharType {
         assert!(bytes.len() >= u32_size);
         
         let len = unsafe { *(bytes.as_ptr() as *const u32) };
+        if DB_VALUE_NULL == len {
+            return Value::new_null(type_id::TypeId::VARCHAR);
+        }
         assert_eq!(bytes.len() - u32_size, len as usize);
         Value::new_varchar_with_bytes(type_id::TypeId::VARCHAR, &bytes[u32_size..])
     }